@@ -0,0 +1,26 @@
+//! Print an Argon2 hash for the `password_hash` setting of the `[auth]` section.
+//!
+//! Usage: `cargo run -p gateway_server --example hash_password` and type the
+//! password on stdin, so it doesn't end up in the shell history.
+
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHasher, SaltString};
+use argon2::Argon2;
+use std::io::{self, BufRead};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    eprintln!("Password:");
+    let mut password = String::new();
+    io::stdin().lock().read_line(&mut password)?;
+    let password = password.trim_end_matches(['\r', '\n']);
+    if password.is_empty() {
+        return Err("password must not be empty".into());
+    }
+
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| e.to_string())?;
+    println!("{hash}");
+    Ok(())
+}
@@ -0,0 +1,168 @@
+use crate::tags::structures::{Quality, TagValue};
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+/// How urgently an alarm needs attention.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Severity {
+    Info,
+    #[default]
+    Warning,
+    Critical,
+}
+
+/// Alarm limits of a tag. Limits left `None` are not checked.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AlarmLimits {
+    /// An alarm is active while the value is above this limit.
+    #[serde(default)]
+    pub high: Option<f64>,
+    /// An alarm is active while the value is below this limit.
+    #[serde(default)]
+    pub low: Option<f64>,
+    /// An alarm is active while the value changes faster than this many units
+    /// per second, in either direction.
+    #[serde(default)]
+    pub rate_of_change: Option<f64>,
+    /// Severity of the alarms raised for these limits.
+    #[serde(default)]
+    pub severity: Severity,
+}
+
+impl AlarmLimits {
+    /// Whether no limit is configured.
+    pub fn is_empty(&self) -> bool {
+        self.high.is_none() && self.low.is_none() && self.rate_of_change.is_none()
+    }
+}
+
+/// Which limit an alarm is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum AlarmKind {
+    High,
+    Low,
+    RateOfChange,
+}
+
+/// An alarm that is currently raised.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActiveAlarm {
+    pub path: String,
+    pub kind: AlarmKind,
+    pub severity: Severity,
+    /// The limit the value crossed.
+    pub limit: f64,
+    /// Latest value seen while the alarm is active. For rate-of-change alarms
+    /// this is the rate in units per second.
+    pub value: f64,
+    /// Timestamp (Unix ms) of the value that raised the alarm.
+    pub since: u64,
+}
+
+/// Tracks which tags are outside their alarm limits.
+#[derive(Debug, Default)]
+pub struct AlarmEngine {
+    active: DashMap<(String, AlarmKind), ActiveAlarm>,
+}
+
+impl AlarmEngine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check a newly stored value against the tag's limits, raising the alarms
+    /// it has entered and clearing the ones it has returned from. `previous` is
+    /// the value it replaces, used for the rate of change. Values that aren't
+    /// Good or numeric leave the alarm state unchanged.
+    pub fn evaluate(
+        &self,
+        path: &str,
+        limits: &AlarmLimits,
+        previous: &TagValue,
+        value: &TagValue,
+    ) {
+        if limits.is_empty() || value.quality != Quality::Good {
+            return;
+        }
+        let Some(reading) = value.value.as_f64() else {
+            return;
+        };
+
+        // (kind, limit, measured value, whether the limit is exceeded)
+        let mut checks = Vec::with_capacity(3);
+        if let Some(high) = limits.high {
+            checks.push((AlarmKind::High, high, reading, reading > high));
+        }
+        if let Some(low) = limits.low {
+            checks.push((AlarmKind::Low, low, reading, reading < low));
+        }
+        // Without a usable previous sample the rate alarm keeps its state
+        let rate = rate_of_change(previous, value);
+        if let (Some(limit), Some(rate)) = (limits.rate_of_change, rate) {
+            checks.push((AlarmKind::RateOfChange, limit, rate, rate > limit));
+        }
+
+        for (kind, limit, measured, exceeded) in checks {
+            let key = (path.to_string(), kind);
+            if !exceeded {
+                if self.active.remove(&key).is_some() {
+                    info!("{:?} alarm on '{}' cleared at {}", kind, path, measured);
+                }
+                continue;
+            }
+            match self.active.entry(key) {
+                Entry::Occupied(mut alarm) => alarm.get_mut().value = measured,
+                Entry::Vacant(slot) => {
+                    warn!(
+                        "{:?} alarm on '{}': {} is beyond the limit of {}",
+                        kind, path, measured, limit
+                    );
+                    slot.insert(ActiveAlarm {
+                        path: path.to_string(),
+                        kind,
+                        severity: limits.severity,
+                        limit,
+                        value: measured,
+                        since: value.timestamp,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Drop the alarms of a tag, e.g. when it is removed or its limits change.
+    pub fn clear_tag(&self, path: &str) {
+        // Called on every registration, so look the kinds up instead of scanning
+        let mut key = (path.to_string(), AlarmKind::High);
+        for kind in [AlarmKind::High, AlarmKind::Low, AlarmKind::RateOfChange] {
+            key.1 = kind;
+            self.active.remove(&key);
+        }
+    }
+
+    /// Drop all alarms.
+    pub fn clear(&self) {
+        self.active.clear();
+    }
+
+    /// All active alarms, ordered by tag path.
+    pub fn active(&self) -> Vec<ActiveAlarm> {
+        let mut alarms: Vec<ActiveAlarm> =
+            self.active.iter().map(|alarm| alarm.value().clone()).collect();
+        alarms.sort_by(|a, b| (&a.path, a.kind).cmp(&(&b.path, b.kind)));
+        alarms
+    }
+}
+
+/// Absolute change per second from `previous` to `current`. `None` when the
+/// previous sample isn't a Good number or isn't older than the current one.
+fn rate_of_change(previous: &TagValue, current: &TagValue) -> Option<f64> {
+    if previous.quality != Quality::Good || previous.timestamp >= current.timestamp {
+        return None;
+    }
+    let delta = current.value.as_f64()? - previous.value.as_f64()?;
+    let seconds = (current.timestamp - previous.timestamp) as f64 / 1000.0;
+    Some(delta.abs() / seconds)
+}
@@ -0,0 +1,163 @@
+use crate::api::rest::ApiError;
+use argon2::password_hash::{PasswordHash, PasswordVerifier};
+use argon2::Argon2;
+use axum::body::Body;
+use axum::http::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+use axum::http::{HeaderValue, Request, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashSet;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use tower_http::auth::{AsyncAuthorizeRequest, AsyncRequireAuthorizationLayer};
+
+/// Credentials required by the HTTP API.
+#[derive(Clone, PartialEq, Deserialize, Serialize)]
+pub struct AuthConfig {
+    /// Set to `false` to serve the API without authentication. For development only.
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub username: String,
+    /// Argon2 hash in PHC format, as printed by `cargo run --example hash_password`.
+    #[serde(default)]
+    pub password_hash: String,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+// Keep the hash out of logs
+impl fmt::Debug for AuthConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuthConfig")
+            .field("enabled", &self.enabled)
+            .field("username", &self.username)
+            .field("password_hash", &"<redacted>")
+            .finish()
+    }
+}
+
+impl AuthConfig {
+    /// Check that an enabled section has a username and a well-formed hash.
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.username.is_empty() {
+            return Err("Auth username must not be empty".to_string());
+        }
+        PasswordHash::new(&self.password_hash)
+            .map(|_| ())
+            .map_err(|e| format!("Auth password_hash is not a valid PHC string: {}", e))
+    }
+}
+
+/// Checks HTTP Basic credentials against the configured username and password hash.
+#[derive(Clone)]
+pub struct BasicAuth {
+    username: String,
+    password_hash: String,
+    /// Decoded `username:password` pairs that already passed. Argon2 is
+    /// deliberately slow, so each pair is only verified once. Only correct
+    /// credentials are kept, however the header was spelled.
+    verified: Arc<Mutex<HashSet<String>>>,
+}
+
+impl BasicAuth {
+    pub fn new(username: String, password_hash: String) -> Self {
+        Self {
+            username,
+            password_hash,
+            verified: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// The `username:password` of a Basic authorization header.
+    fn decode(header: &str) -> Option<String> {
+        let encoded = header.strip_prefix("Basic ")?;
+        let decoded = STANDARD.decode(encoded.trim()).ok()?;
+        String::from_utf8(decoded).ok()
+    }
+
+    async fn is_authorized(&self, header: Option<String>) -> bool {
+        let Some(credentials) = header.as_deref().and_then(Self::decode) else {
+            return false;
+        };
+        if self.verified.lock().unwrap().contains(&credentials) {
+            return true;
+        }
+        // Hashing takes tens of milliseconds; keep it off the runtime's workers
+        let this = self.clone();
+        let checked = credentials.clone();
+        let authorized = tokio::task::spawn_blocking(move || this.check_credentials(&checked))
+            .await
+            .unwrap_or(false);
+        if authorized {
+            self.verified.lock().unwrap().insert(credentials);
+        }
+        authorized
+    }
+
+    fn check_credentials(&self, credentials: &str) -> bool {
+        let Some((username, password)) = credentials.split_once(':') else {
+            return false;
+        };
+        let Ok(hash) = PasswordHash::new(&self.password_hash) else {
+            return false;
+        };
+        username == self.username
+            && Argon2::default()
+                .verify_password(password.as_bytes(), &hash)
+                .is_ok()
+    }
+}
+
+fn unauthorized() -> Response {
+    let error = ApiError::new("unauthorized", "Invalid or missing credentials");
+    let mut response = (StatusCode::UNAUTHORIZED, Json(json!({ "error": error }))).into_response();
+    response.headers_mut().insert(
+        WWW_AUTHENTICATE,
+        HeaderValue::from_static("Basic realm=\"forgeio\""),
+    );
+    response
+}
+
+impl<B: Send + 'static> AsyncAuthorizeRequest<B> for BasicAuth {
+    type RequestBody = B;
+    type ResponseBody = Body;
+    type Future = Pin<Box<dyn Future<Output = Result<Request<B>, Response>> + Send>>;
+
+    fn authorize(&mut self, request: Request<B>) -> Self::Future {
+        let header = request
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let auth = self.clone();
+        Box::pin(async move {
+            if auth.is_authorized(header).await {
+                Ok(request)
+            } else {
+                Err(unauthorized())
+            }
+        })
+    }
+}
+
+/// Layer enforcing the configured credentials, or `None` when auth is disabled.
+pub fn auth_layer(config: &AuthConfig) -> Option<AsyncRequireAuthorizationLayer<BasicAuth>> {
+    config.enabled.then(|| {
+        AsyncRequireAuthorizationLayer::new(BasicAuth::new(
+            config.username.clone(),
+            config.password_hash.clone(),
+        ))
+    })
+}
@@ -0,0 +1,75 @@
+use axum::http::{HeaderName, HeaderValue, Method};
+use serde::{Deserialize, Serialize};
+use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, Any, CorsLayer};
+
+/// Cross-origin access to the API for front-ends served from another origin.
+/// Without this section only same-origin requests are possible.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct CorsConfig {
+    /// Origins such as `http://localhost:5173`, or `"*"` for any origin.
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+    #[serde(default = "default_allowed_methods")]
+    pub allowed_methods: Vec<String>,
+    /// Request headers a browser may send, or `"*"` for any header.
+    #[serde(default = "default_allowed_headers")]
+    pub allowed_headers: Vec<String>,
+}
+
+fn default_allowed_methods() -> Vec<String> {
+    ["GET", "POST", "PUT", "PATCH"].map(String::from).to_vec()
+}
+
+fn default_allowed_headers() -> Vec<String> {
+    ["authorization", "content-type"].map(String::from).to_vec()
+}
+
+impl CorsConfig {
+    /// Check that every origin, method and header is well-formed.
+    pub fn validate(&self) -> Result<(), String> {
+        self.layer().map(|_| ())
+    }
+
+    /// Build the layer answering preflight requests and adding CORS headers.
+    pub fn layer(&self) -> Result<CorsLayer, String> {
+        let origins = if self.allowed_origins.iter().any(|o| o == "*") {
+            AllowOrigin::from(Any)
+        } else {
+            let origins = self
+                .allowed_origins
+                .iter()
+                .map(|origin| {
+                    HeaderValue::from_str(origin)
+                        .map_err(|_| format!("Invalid CORS origin '{}'", origin))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            AllowOrigin::list(origins)
+        };
+        let methods = self
+            .allowed_methods
+            .iter()
+            .map(|method| {
+                Method::from_bytes(method.to_ascii_uppercase().as_bytes())
+                    .map_err(|_| format!("Invalid CORS method '{}'", method))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let headers = if self.allowed_headers.iter().any(|h| h == "*") {
+            AllowHeaders::from(Any)
+        } else {
+            let headers = self
+                .allowed_headers
+                .iter()
+                .map(|header| {
+                    HeaderName::from_bytes(header.as_bytes())
+                        .map_err(|_| format!("Invalid CORS header '{}'", header))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            AllowHeaders::list(headers)
+        };
+
+        Ok(CorsLayer::new()
+            .allow_origin(origins)
+            .allow_methods(AllowMethods::list(methods))
+            .allow_headers(headers))
+    }
+}
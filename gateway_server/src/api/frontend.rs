@@ -0,0 +1,40 @@
+use axum::http::{StatusCode, Uri};
+use axum::response::{Html, IntoResponse};
+use axum::Router;
+use std::path::Path;
+use tower_http::services::{ServeDir, ServeFile};
+use tracing::warn;
+
+/// Shown in place of the web UI when it hasn't been built or installed.
+const PLACEHOLDER_PAGE: &str = "<!doctype html>
+<html>
+<head><meta charset=\"utf-8\"><title>ForgeIO Gateway</title></head>
+<body>
+<h1>ForgeIO Gateway is running</h1>
+<p>The web UI is not installed on this server. The REST API is available under <code>/api</code>,
+e.g. <a href=\"/api/health\">/api/health</a>.</p>
+</body>
+</html>
+";
+
+/// Serve the built web UI from `dir` for every route the API doesn't handle,
+/// falling back to its `index.html` for client-side routing. Without `dir`
+/// (e.g. a headless deployment) a placeholder page is served instead.
+pub fn with_frontend(app: Router, dir: &Path) -> Router {
+    if dir.is_dir() {
+        let index = ServeFile::new(dir.join("index.html"));
+        app.fallback_service(ServeDir::new(dir).not_found_service(index))
+    } else {
+        warn!("Web UI directory {:?} not found, serving the API only.", dir);
+        app.fallback(placeholder)
+    }
+}
+
+async fn placeholder(uri: Uri) -> impl IntoResponse {
+    let status = if uri.path() == "/" {
+        StatusCode::OK
+    } else {
+        StatusCode::NOT_FOUND
+    };
+    (status, Html(PLACEHOLDER_PAGE))
+}
@@ -1,2 +1,6 @@
+pub mod auth; // HTTP Basic auth from the [auth] config section
+pub mod cors; // CORS from the [cors] config section
+pub mod frontend; // Static web UI, or a placeholder when it isn't installed
 pub mod rest; // Axum REST endpoints
+pub mod server; // Serving the API until shutdown
 pub mod websocket; // WebSocket handling
@@ -1,45 +1,173 @@
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
+    async_trait,
+    extract::{FromRequest, Path, Query, Request, State},
+    http::{header, StatusCode},
+    response::sse::{Event, KeepAlive, Sse},
     response::IntoResponse,
-    routing::get,
+    routing::{get, patch, post},
     Json, Router,
 };
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::convert::Infallible;
 use std::sync::Arc;
 use tokio::sync::RwLock;
+use tokio_stream::wrappers::WatchStream;
+use tokio_stream::{Stream, StreamExt, StreamMap};
+use tower_http::compression::CompressionLayer;
 use tracing::{info, warn, error};
 
-use crate::drivers::opcua::OpcUaDriver;
-use crate::drivers::traits::OpcDriver;
+use crate::drivers::breaker::{BreakerState, CircuitBreakers};
+use crate::drivers::scan_stats::{ScanStatistics, ScanStats};
+use crate::drivers::opcua::{
+    parse_attribute, BrowseChild, BrowseFilter, BrowsedNode, DiscoveredTag, OpcUaDriver,
+    DEFAULT_DEBUG_ATTRIBUTES,
+};
+use crate::drivers::health::{begin_reconnect, reconnect, ConnectionState, ConnectionStates};
+use crate::drivers::traits::{DeviceDriver, DriverError, TagRequest, WriteStatus};
+use crate::drivers::DriverMap;
 use crate::tags::engine::TagEngine;
+use crate::tags::structures::{Quality, Tag, TagMetadata, TagValue, ValueVariant};
+use crate::config::reload::{ConfigReloader, ReloadError};
 use crate::config::settings::Settings;
+use crate::api::websocket::stream_logs;
+use crate::logging::LogStream;
 
 #[derive(Clone)]
 pub struct SharedAppState {
     pub tag_engine: Arc<TagEngine>,
-    pub driver_count: usize,
     pub start_time: tokio::time::Instant,
     pub settings: Arc<RwLock<Settings>>,
-    pub drivers: Arc<HashMap<String, Arc<dyn OpcDriver + Send + Sync>>>,
+    pub drivers: Arc<DriverMap>,
+    /// Connection state of each driver, kept up to date by the health monitor.
+    pub connection_states: Arc<ConnectionStates>,
+    /// Circuit breaker of each driver, opened by the poller on repeated failures.
+    pub breakers: Arc<CircuitBreakers>,
+    /// Scan durations and failures of each driver, recorded by the poller.
+    pub scan_stats: Arc<ScanStatistics>,
+    /// Saves and applies configuration updates to the running drivers and tags.
+    pub reloader: ConfigReloader,
+    /// Log lines for `/api/logs/ws` viewers.
+    pub logs: LogStream,
 }
 
 #[derive(Deserialize)]
 pub struct BrowseQuery {
     #[serde(default = "default_node_id")]
     node_id: String,
+    /// Reference type to follow, by name (e.g. `Organizes`) or node id.
+    /// All hierarchical references when omitted.
+    ref_type: Option<String>,
+    /// Comma-separated node classes to list, e.g. `Variable`. All when omitted.
+    node_class: Option<String>,
 }
 
 fn default_node_id() -> String {
     "ns=0;i=85".to_string() // Root Objects folder
 }
 
+#[derive(Deserialize)]
+pub struct BrowseTreeQuery {
+    #[serde(default = "default_node_id")]
+    node_id: String,
+    #[serde(default = "default_max_depth")]
+    max_depth: usize,
+}
+
+fn default_max_depth() -> usize {
+    3
+}
+
+#[derive(Deserialize)]
+pub struct AttributesQuery {
+    node_id: String,
+    /// Comma-separated attribute names, e.g. `Value,DataType`. A set of common
+    /// attributes when omitted.
+    attributes: Option<String>,
+}
+
+/// Error reported by every endpoint as `{ "error": { "kind": ..., "message": ... } }`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiError {
+    /// Machine-readable category, e.g. `not_found` or `not_connected`.
+    pub kind: &'static str,
+    pub message: String,
+}
+
+impl ApiError {
+    pub fn new(kind: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    /// Status code and error for a failed driver operation.
+    pub fn from_driver(e: &DriverError) -> (StatusCode, Self) {
+        let (status, kind) = match e {
+            DriverError::NotConnected => (StatusCode::SERVICE_UNAVAILABLE, "not_connected"),
+            DriverError::InvalidNodeId(_) => (StatusCode::BAD_REQUEST, "invalid_node_id"),
+            DriverError::InvalidValue(_) => (StatusCode::BAD_REQUEST, "invalid_value"),
+            DriverError::Timeout => (StatusCode::GATEWAY_TIMEOUT, "timeout"),
+            DriverError::Protocol(_) => (StatusCode::BAD_GATEWAY, "protocol"),
+            DriverError::Io(_) => (StatusCode::BAD_GATEWAY, "io"),
+            DriverError::Config(_) => (StatusCode::INTERNAL_SERVER_ERROR, "config"),
+        };
+        (status, Self::new(kind, e.to_string()))
+    }
+}
+
+/// JSON request body. Unlike axum's `Json`, a body that can't be used is
+/// answered with an [`ApiError`] naming the field that failed, e.g.
+/// `{ "error": { "kind": "invalid_json", "message": ... }, "path": "devices[0].id" }`.
+pub struct ApiJson<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for ApiJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, Json<serde_json::Value>);
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        // Content type and JSON syntax are checked by axum; its message is kept
+        let Json(value) = Json::<serde_json::Value>::from_request(req, state)
+            .await
+            .map_err(|rejection| {
+                let error = ApiError::new("invalid_json", rejection.body_text());
+                (rejection.status(), error_body(error))
+            })?;
+        serde_path_to_error::deserialize(value).map(ApiJson).map_err(|e| {
+            let path = e.path().to_string();
+            let error = ApiError::new("invalid_json", format!("{}: {}", path, e.inner()));
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(json!({ "error": error, "path": path })),
+            )
+        })
+    }
+}
+
+/// JSON body for endpoints that have no response type of their own.
+fn error_body(error: ApiError) -> Json<serde_json::Value> {
+    Json(json!({ "error": error }))
+}
+
 #[derive(Serialize)]
 pub struct BrowseResponse {
     pub node_id: String,
-    pub children: Vec<String>,
-    pub error: Option<String>,
+    pub children: Vec<BrowseChild>,
+    pub error: Option<ApiError>,
+}
+
+#[derive(Serialize)]
+pub struct BrowseTreeResponse {
+    pub node_id: String,
+    pub nodes: Vec<BrowsedNode>,
+    pub error: Option<ApiError>,
 }
 
 #[derive(Serialize)]
@@ -50,8 +178,70 @@ pub struct DiscoverResponse {
 #[derive(Serialize)]
 pub struct TagDiscoveryResponse {
     pub driver_id: String,
-    pub tags: Vec<String>,
-    pub error: Option<String>,
+    pub tags: Vec<DiscoveredTag>,
+    pub error: Option<ApiError>,
+}
+
+#[derive(Deserialize)]
+pub struct CallMethodRequest {
+    pub object_id: String,
+    pub method_id: String,
+    /// Input arguments as JSON bools, numbers, strings or nulls.
+    #[serde(default)]
+    pub args: Vec<serde_json::Value>,
+}
+
+#[derive(Serialize)]
+pub struct CallMethodResponse {
+    pub driver_id: String,
+    pub outputs: Vec<ValueVariant>,
+    pub error: Option<ApiError>,
+}
+
+#[derive(Serialize)]
+pub struct AttributesResponse {
+    pub driver_id: String,
+    pub node_id: String,
+    pub attributes: Vec<AttributeReading>,
+    pub error: Option<ApiError>,
+}
+
+/// One attribute of a node as the server returned it.
+#[derive(Serialize)]
+pub struct AttributeReading {
+    pub attribute: String,
+    #[serde(flatten)]
+    pub value: TagValue,
+}
+
+#[derive(Serialize)]
+pub struct ReconnectResponse {
+    pub driver_id: String,
+    /// Connection state after the reconnect, `None` for an unknown driver.
+    pub state: Option<ConnectionState>,
+    pub connected: bool,
+    pub error: Option<ApiError>,
+}
+
+#[derive(Serialize)]
+pub struct DriverStatsResponse {
+    pub driver_id: String,
+    #[serde(flatten)]
+    pub stats: ScanStats,
+}
+
+/// OpenAPI description of every route in [`create_api_routes`]. Update it
+/// along with the router.
+const OPENAPI_DOCUMENT: &str = include_str!("openapi.json");
+
+/// Body of `GET /api/health`.
+#[derive(Serialize)]
+pub struct HealthResponse {
+    /// `ok` when every driver is connected, `degraded` when only non-critical
+    /// ones are not, `unavailable` when a critical one is not.
+    pub status: &'static str,
+    /// Whether each driver is connected, by device id.
+    pub drivers: HashMap<String, bool>,
 }
 
 #[derive(Serialize)]
@@ -60,6 +250,7 @@ pub struct DriverInfo {
     pub name: String,
     pub address: String,
     pub connected: bool,
+    pub state: ConnectionState,
     pub driver_type: String,
 }
 
@@ -69,16 +260,17 @@ async fn discover_opcua_tags(
 ) -> impl IntoResponse {
     info!("Discovering OPC UA tags for driver: {}", driver_id);
     
-    let driver = match state.drivers.get(&driver_id) {
+    let driver = match state.drivers.get(&driver_id).map(|d| Arc::clone(d.value())) {
         Some(driver) => driver,
         None => {
             warn!("Driver not found: {}", driver_id);
+            let error = ApiError::new("not_found", format!("Driver '{}' not found", driver_id));
             return (
                 StatusCode::NOT_FOUND,
                 Json(TagDiscoveryResponse {
                     driver_id,
                     tags: vec![],
-                    error: Some("Driver not found".to_string()),
+                    error: Some(error),
                 }),
             );
         }
@@ -103,12 +295,13 @@ async fn discover_opcua_tags(
                 }
                 Err(e) => {
                     error!("Failed to discover tags for driver {}: {}", driver_id, e);
+                    let (status, error) = ApiError::from_driver(&e);
                     (
-                        StatusCode::INTERNAL_SERVER_ERROR,
+                        status,
                         Json(TagDiscoveryResponse {
                             driver_id,
                             tags: vec![],
-                            error: Some(e.to_string()),
+                            error: Some(error),
                         }),
                     )
                 }
@@ -116,23 +309,648 @@ async fn discover_opcua_tags(
         }
         None => {
             warn!("Driver '{}' is not an OPC UA driver", driver_id);
+            let error = ApiError::new(
+                "not_opcua",
+                format!("Driver '{}' is not an OPC UA driver", driver_id),
+            );
             (
                 StatusCode::BAD_REQUEST,
                 Json(TagDiscoveryResponse {
                     driver_id,
                     tags: vec![],
-                    error: Some("Driver is not an OPC UA driver".to_string()),
+                    error: Some(error),
                 }),
             )
         }
     }
 }
 
+#[derive(Deserialize)]
+pub struct TagsQuery {
+    /// Optional glob pattern (`*`, `?`) matched against tag paths.
+    pattern: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct TagListQuery {
+    /// Optional glob pattern (`*`, `?`) matched against tag paths.
+    pattern: Option<String>,
+    /// Only return tags served by this driver.
+    driver_id: Option<String>,
+    /// Number of matching tags to skip.
+    #[serde(default)]
+    offset: usize,
+    /// Maximum number of tags to return. All remaining tags when omitted.
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct TagPage {
+    /// Number of tags matching the filters, before pagination.
+    pub total: usize,
+    pub items: Vec<Tag>,
+}
+
+#[derive(Deserialize)]
+pub struct TagTreeQuery {
+    /// Folder to list, e.g. `Plant1/`. The root when omitted.
+    #[serde(default)]
+    prefix: String,
+}
+
+async fn get_tag_tree(
+    State(state): State<SharedAppState>,
+    Query(params): Query<TagTreeQuery>,
+) -> impl IntoResponse {
+    Json(state.tag_engine.list_children(&params.prefix))
+}
+
+async fn get_tags(
+    State(state): State<SharedAppState>,
+    Query(params): Query<TagListQuery>,
+) -> impl IntoResponse {
+    let (total, items) = state.tag_engine.query_tags_page(
+        params.pattern.as_deref(),
+        params.driver_id.as_deref(),
+        params.offset,
+        params.limit.unwrap_or(usize::MAX),
+    );
+    Json(TagPage { total, items })
+}
+
+#[derive(Deserialize)]
+pub struct ChangedSinceQuery {
+    /// Unix time in milliseconds; only tags with a newer value are returned.
+    ts: u64,
+}
+
+/// Tags whose value changed after `ts`, for refreshing a view without
+/// fetching every tag again.
+async fn get_tags_changed_since(
+    State(state): State<SharedAppState>,
+    Query(params): Query<ChangedSinceQuery>,
+) -> impl IntoResponse {
+    Json(state.tag_engine.changed_since(params.ts))
+}
+
+#[derive(Deserialize)]
+pub struct WriteTagRequest {
+    /// Plain JSON value to write: a bool, number, string or null.
+    pub value: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+pub struct BatchWriteRequest {
+    pub writes: Vec<BatchWrite>,
+}
+
+/// One write of a [`BatchWriteRequest`].
+#[derive(Deserialize)]
+pub struct BatchWrite {
+    pub path: String,
+    /// Plain JSON value to write: a bool, number, string or null.
+    pub value: serde_json::Value,
+}
+
+/// Outcome of one write of a batch.
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum BatchWriteResult {
+    /// Sent to the device, which accepted or rejected it.
+    Written(WriteStatus),
+    /// Not sent, e.g. because the tag is not writable.
+    Rejected { error: ApiError },
+}
+
+#[derive(Deserialize)]
+pub struct WriteQuery {
+    /// Read the value back after a successful write and compare it.
+    #[serde(default)]
+    verify: bool,
+}
+
+/// Outcome of a write, with the read-back check when `?verify=true` was given.
+#[derive(Serialize)]
+pub struct WriteResult {
+    #[serde(flatten)]
+    pub status: WriteStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verify: Option<WriteVerification>,
+}
+
+/// The value read back from the device after a write.
+#[derive(Serialize)]
+pub struct WriteVerification {
+    /// `None` if the value could not be read back.
+    pub value: Option<ValueVariant>,
+    pub quality: Quality,
+    /// Whether the device holds the value that was written. `false` if it
+    /// changed the value, e.g. clamped it to a limit, or the read failed.
+    pub matches: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Read `address` back after writing `written` to it. Both values are in the
+/// tag's engineering units; the raw value read is scaled with `metadata`.
+async fn verify_write(
+    driver: &(dyn DeviceDriver + Send + Sync),
+    address: &str,
+    metadata: &TagMetadata,
+    written: &ValueVariant,
+) -> WriteVerification {
+    let request = [TagRequest { address: address.to_string() }];
+    let failed = |error: String| WriteVerification {
+        value: None,
+        quality: Quality::Bad,
+        matches: false,
+        error: Some(error),
+    };
+    match driver.read_tags(&request).await.map(|mut values| values.remove(address)) {
+        Ok(Some(read)) => {
+            let value = metadata.scale_raw(read.value);
+            WriteVerification {
+                matches: read.quality == Quality::Good && same_value(written, &value),
+                value: Some(value),
+                quality: read.quality,
+                error: None,
+            }
+        }
+        Ok(None) => failed("Driver returned no value for the tag".to_string()),
+        Err(e) => failed(e.to_string()),
+    }
+}
+
+/// Compare a written value with the one read back. Floats may come back at
+/// single precision, so they only have to agree to that precision.
+fn same_value(written: &ValueVariant, read: &ValueVariant) -> bool {
+    match (written, read.coerce_to(written)) {
+        (ValueVariant::Float(a), Ok(ValueVariant::Float(b))) => {
+            // Also matches equal infinities and NaN read back as NaN
+            *a == b
+                || (a.is_nan() && b.is_nan())
+                || (a - b).abs() <= f64::from(f32::EPSILON) * a.abs().max(1.0)
+        }
+        (written, Ok(read)) => *written == read,
+        (_, Err(_)) => false,
+    }
+}
+
+/// Convert a plain JSON scalar into a tag value. Arrays and objects are not supported.
+fn json_to_value_variant(value: serde_json::Value) -> Option<ValueVariant> {
+    match value {
+        serde_json::Value::Null => Some(ValueVariant::Null),
+        serde_json::Value::Bool(b) => Some(ValueVariant::Bool(b)),
+        serde_json::Value::Number(n) => n
+            .as_i64()
+            .map(ValueVariant::Int)
+            .or_else(|| n.as_u64().map(ValueVariant::UInt))
+            .or_else(|| n.as_f64().map(ValueVariant::Float)),
+        serde_json::Value::String(s) => Some(ValueVariant::String(s)),
+        serde_json::Value::Array(_) | serde_json::Value::Object(_) => None,
+    }
+}
+
+/// Payload of each `tag` event on the SSE stream.
+#[derive(Serialize)]
+pub struct TagUpdateEvent<'a> {
+    pub path: &'a str,
+    pub value: &'a TagValue,
+}
+
+/// Stream tag updates as Server-Sent Events. Each selected tag's current value is
+/// sent first, followed by every change. Tags registered after the stream was
+/// opened are not included.
+async fn stream_tags(
+    State(state): State<SharedAppState>,
+    Query(params): Query<TagsQuery>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let paths = match params.pattern {
+        Some(pattern) => state
+            .tag_engine
+            .query_tags(&pattern)
+            .into_iter()
+            .map(|tag| tag.path.into())
+            .collect(),
+        None => state.tag_engine.get_all_tag_paths(),
+    };
+
+    let mut updates = StreamMap::new();
+    for path in paths {
+        // WatchStream yields the current value first, which gives us the initial snapshot
+        let receiver = state.tag_engine.subscribe(&path);
+        updates.insert(path, WatchStream::new(receiver));
+    }
+
+    let events = updates.map(|(path, value)| {
+        let event = Event::default()
+            .event("tag")
+            .json_data(TagUpdateEvent { path: &path, value: &value })
+            .unwrap_or_else(|e| Event::default().event("error").data(e.to_string()));
+        Ok(event)
+    });
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+/// Stream quality transitions of all tags as Server-Sent Events named `quality`.
+/// Events a slow client falls too far behind on are skipped and counted.
+async fn stream_quality_events(
+    State(state): State<SharedAppState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let events = state
+        .tag_engine
+        .quality_events()
+        .into_stream()
+        .map(|event| {
+            let event = Event::default()
+                .event("quality")
+                .json_data(&event)
+                .unwrap_or_else(|e| Event::default().event("error").data(e.to_string()));
+            Ok(event)
+        });
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+/// Look up a tag for writing and convert `value` to the type it holds, e.g.
+/// `5` to a Float tag as 5.0. Unknown and read-only tags, aliases and values
+/// that don't convert are rejected. The value is returned as the driver takes it,
+/// i.e. mapped back to the raw range for scaled tags (see
+/// [`TagMetadata::unscale`]).
+fn prepare_write(
+    engine: &TagEngine,
+    tag_path: &str,
+    value: serde_json::Value,
+) -> Result<(Tag, ValueVariant), (StatusCode, ApiError)> {
+    let tag = engine.read_tag_full(tag_path).ok_or_else(|| {
+        let error = ApiError::new("not_found", format!("Tag '{}' not found", tag_path));
+        (StatusCode::NOT_FOUND, error)
+    })?;
+    if !tag.metadata.writable {
+        warn!("Rejected write to read-only tag '{}'", tag_path);
+        let error = ApiError::new("forbidden", format!("Tag '{}' is not writable", tag_path));
+        return Err((StatusCode::FORBIDDEN, error));
+    }
+    // Aliases have no driver of their own to write through
+    if let Some(source) = &tag.metadata.alias_of {
+        let error = ApiError::new(
+            "invalid_value",
+            format!("Tag '{}' is an alias of '{1}'; write to '{1}' instead", tag_path, source),
+        );
+        return Err((StatusCode::BAD_REQUEST, error));
+    }
+    let value = json_to_value_variant(value).ok_or_else(|| {
+        let error = ApiError::new("invalid_value", "Value must be a bool, number, string or null");
+        (StatusCode::BAD_REQUEST, error)
+    })?;
+    match value.coerce_to(&tag.value.value) {
+        Ok(value) => {
+            let raw = tag.metadata.unscale(value);
+            Ok((tag, raw))
+        }
+        Err(e) => Err((StatusCode::BAD_REQUEST, ApiError::new("invalid_value", e.to_string()))),
+    }
+}
+
+/// Write a value to a tag through its driver. The tag path must be URL-encoded
+/// (e.g. `Plant1%2FTemperature`) since it usually contains slashes.
+/// Responds with the tag's [`WriteStatus`](crate::drivers::traits::WriteStatus) keyed by its path.
+async fn write_tag(
+    State(state): State<SharedAppState>,
+    Path(tag_path): Path<String>,
+    Query(params): Query<WriteQuery>,
+    ApiJson(request): ApiJson<WriteTagRequest>,
+) -> impl IntoResponse {
+    let (tag, value) = match prepare_write(&state.tag_engine, &tag_path, request.value) {
+        Ok(prepared) => prepared,
+        Err((status, error)) => return (status, error_body(error)),
+    };
+    let driver = match state.drivers.get(&tag.driver_id).map(|d| Arc::clone(d.value())) {
+        Some(driver) => driver,
+        None => {
+            return (
+                StatusCode::NOT_FOUND,
+                error_body(ApiError::new(
+                    "not_found",
+                    format!("Driver '{}' not found", tag.driver_id),
+                )),
+            );
+        }
+    };
+
+    info!("Writing {:?} to tag '{}' ({})", value, tag_path, tag.driver_address);
+    let request = HashMap::from([(tag.driver_address.clone(), TagValue::new(value, Quality::Good))]);
+    match driver.write_tags(request).await {
+        // Keyed by tag path; a write the device rejected still answers 200 with a Bad status
+        Ok(mut results) => match results.remove(&tag.driver_address) {
+            Some(mut status) => {
+                // Answered in engineering units, like the tag's value
+                status.value = tag.metadata.scale_raw(status.value);
+                let verify = if params.verify && status.quality == Quality::Good {
+                    let address = &tag.driver_address;
+                    let verification =
+                        verify_write(driver.as_ref(), address, &tag.metadata, &status.value).await;
+                    if !verification.matches {
+                        warn!(
+                            "Tag '{}' reads back {:?} after writing {:?}",
+                            tag_path, verification.value, status.value
+                        );
+                    }
+                    Some(verification)
+                } else {
+                    None
+                };
+                (StatusCode::OK, Json(json!({ tag_path: WriteResult { status, verify } })))
+            }
+            None => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                error_body(ApiError::new(
+                    "internal",
+                    "Driver returned no result for the tag",
+                )),
+            ),
+        },
+        Err(e) => {
+            error!("Failed to write tag '{}': {}", tag_path, e);
+            let (status, error) = ApiError::from_driver(&e);
+            (status, error_body(error))
+        }
+    }
+}
+
+/// Write several tags in one request, e.g. a recipe download. Each driver's
+/// tags are written with a single `write_tags` call. Tags that can't be
+/// written are rejected individually, so the response is `200` with an entry
+/// for every path: the [`WriteStatus`](crate::drivers::traits::WriteStatus)
+/// of the write, or the error that kept it from being sent.
+async fn write_tags(
+    State(state): State<SharedAppState>,
+    ApiJson(request): ApiJson<BatchWriteRequest>,
+) -> impl IntoResponse {
+    let mut results = BTreeMap::new();
+    // (path, tag, raw value) of the writes to send, by driver
+    let mut by_driver: BTreeMap<String, Vec<(String, Tag, ValueVariant)>> = BTreeMap::new();
+    // A path given twice is ambiguous, so none of its writes are sent
+    let mut seen = HashSet::new();
+    let duplicates: HashSet<String> = request
+        .writes
+        .iter()
+        .filter(|write| !seen.insert(write.path.as_str()))
+        .map(|write| write.path.clone())
+        .collect();
+    for write in request.writes {
+        if duplicates.contains(&write.path) {
+            let error = ApiError::new(
+                "invalid_value",
+                format!("Tag '{}' is written more than once", write.path),
+            );
+            results.insert(write.path, BatchWriteResult::Rejected { error });
+            continue;
+        }
+        match prepare_write(&state.tag_engine, &write.path, write.value) {
+            Ok((tag, value)) => {
+                let writes = by_driver.entry(tag.driver_id.clone()).or_default();
+                writes.push((write.path, tag, value));
+            }
+            Err((_, error)) => {
+                results.insert(write.path, BatchWriteResult::Rejected { error });
+            }
+        }
+    }
+
+    for (driver_id, writes) in by_driver {
+        // The driver takes one value per address, so tags sharing an address
+        // would overwrite each other; none of them are sent
+        let mut tags_at: HashMap<&str, usize> = HashMap::new();
+        for (_, tag, _) in &writes {
+            *tags_at.entry(tag.driver_address.as_str()).or_default() += 1;
+        }
+        let shared: HashSet<String> = tags_at
+            .into_iter()
+            .filter(|(_, count)| *count > 1)
+            .map(|(address, _)| address.to_string())
+            .collect();
+        let (shared, writes): (Vec<_>, Vec<_>) =
+            writes.into_iter().partition(|(_, tag, _)| shared.contains(&tag.driver_address));
+        for (path, tag, _) in shared {
+            let error = ApiError::new(
+                "invalid_value",
+                format!(
+                    "Tag '{}' shares address '{}' with another tag in the batch",
+                    path, tag.driver_address
+                ),
+            );
+            results.insert(path, BatchWriteResult::Rejected { error });
+        }
+        if writes.is_empty() {
+            continue;
+        }
+
+        let Some(driver) = state.drivers.get(&driver_id).map(|d| Arc::clone(d.value())) else {
+            let error = ApiError::new("not_found", format!("Driver '{}' not found", driver_id));
+            for (path, _, _) in writes {
+                results.insert(path, BatchWriteResult::Rejected { error: error.clone() });
+            }
+            continue;
+        };
+        info!("Writing {} tags through driver '{}'", writes.len(), driver_id);
+        let request = writes
+            .iter()
+            .map(|(_, tag, value)| {
+                (tag.driver_address.clone(), TagValue::new(value.clone(), Quality::Good))
+            })
+            .collect();
+        match driver.write_tags(request).await {
+            Ok(mut statuses) => {
+                for (path, tag, _) in writes {
+                    let result = match statuses.remove(&tag.driver_address) {
+                        Some(mut status) => {
+                            status.value = tag.metadata.scale_raw(status.value);
+                            BatchWriteResult::Written(status)
+                        }
+                        None => BatchWriteResult::Rejected {
+                            error: ApiError::new("internal", "Driver returned no result for the tag"),
+                        },
+                    };
+                    results.insert(path, result);
+                }
+            }
+            Err(e) => {
+                error!("Failed to write tags through driver '{}': {}", driver_id, e);
+                let (_, error) = ApiError::from_driver(&e);
+                for (path, _, _) in writes {
+                    results.insert(path, BatchWriteResult::Rejected { error: error.clone() });
+                }
+            }
+        }
+    }
+    Json(results)
+}
+
+/// List the alarms currently raised by tag limits, ordered by tag path.
+async fn get_alarms(State(state): State<SharedAppState>) -> impl IntoResponse {
+    Json(state.tag_engine.alarms().active())
+}
+
+#[derive(Deserialize)]
+pub struct UpdateTagRequest {
+    /// New poll rate in milliseconds, applied until the tag is re-registered.
+    pub poll_rate_ms: Option<u64>,
+    /// Pause (`false`) or resume (`true`) polling, applied until the tag is re-registered.
+    pub enabled: Option<bool>,
+}
+
+/// Change a tag's poll rate or pause its polling at runtime. Fields left out
+/// are kept. Responds with the updated tag.
+async fn update_tag(
+    State(state): State<SharedAppState>,
+    Path(tag_path): Path<String>,
+    ApiJson(request): ApiJson<UpdateTagRequest>,
+) -> impl IntoResponse {
+    if request.poll_rate_ms == Some(0) {
+        return (
+            StatusCode::BAD_REQUEST,
+            error_body(ApiError::new("invalid_value", "poll_rate_ms must be nonzero")),
+        );
+    }
+    if state.tag_engine.read_tag(&tag_path).is_none() {
+        return (
+            StatusCode::NOT_FOUND,
+            error_body(ApiError::new("not_found", format!("Tag '{}' not found", tag_path))),
+        );
+    }
+    if let Some(poll_rate_ms) = request.poll_rate_ms {
+        state.tag_engine.set_poll_rate(&tag_path, poll_rate_ms);
+        info!("Poll rate of '{}' set to {}ms", tag_path, poll_rate_ms);
+    }
+    if let Some(enabled) = request.enabled {
+        state.tag_engine.set_enabled(&tag_path, enabled);
+        info!("Polling of '{}' {}", tag_path, if enabled { "enabled" } else { "disabled" });
+    }
+    (StatusCode::OK, Json(json!(state.tag_engine.read_tag_full(&tag_path))))
+}
+
+async fn get_tag_stats(
+    State(state): State<SharedAppState>,
+    Path(tag_path): Path<String>,
+) -> impl IntoResponse {
+    match state.tag_engine.stats(&tag_path) {
+        Some(stats) => (StatusCode::OK, Json(json!(stats))),
+        None => (
+            StatusCode::NOT_FOUND,
+            error_body(ApiError::new("not_found", format!("Tag '{}' not found", tag_path))),
+        ),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    /// Maximum number of samples to return. All retained samples when omitted.
+    limit: Option<usize>,
+}
+
+async fn get_tag_history(
+    State(state): State<SharedAppState>,
+    Path(tag_path): Path<String>,
+    Query(params): Query<HistoryQuery>,
+) -> impl IntoResponse {
+    if state.tag_engine.read_tag(&tag_path).is_none() {
+        return (
+            StatusCode::NOT_FOUND,
+            error_body(ApiError::new("not_found", format!("Tag '{}' not found", tag_path))),
+        );
+    }
+    let samples = state
+        .tag_engine
+        .history(&tag_path, params.limit.unwrap_or(usize::MAX));
+    (StatusCode::OK, Json(json!(samples)))
+}
+
+async fn get_config(State(state): State<SharedAppState>) -> impl IntoResponse {
+    // Never hand out passwords, tokens or the password hash
+    Json(state.settings.read().await.redacted())
+}
+
+async fn update_config(
+    State(state): State<SharedAppState>,
+    ApiJson(mut new_cfg): ApiJson<Settings>,
+) -> impl IntoResponse {
+    // Clients only ever see redacted secrets, so a redacted one means "unchanged"
+    new_cfg.restore_secrets(&*state.settings.read().await);
+    if let Err(problems) = new_cfg.validate() {
+        warn!("Rejected configuration update: {}", problems.join("; "));
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "error": ApiError::new("invalid_config", "Invalid configuration"),
+                "problems": problems,
+            })),
+        );
+    }
+    match state.reloader.update(new_cfg).await {
+        Ok(diff) => (
+            StatusCode::OK,
+            Json(json!({ "status": "ok", "changes": diff.summary() })),
+        ),
+        // Nothing was changed and the previous config file is back in place
+        Err(e) => {
+            let (status, mut error) = match &e {
+                ReloadError::Io(_) => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    ApiError::new("internal", String::new()),
+                ),
+                ReloadError::Driver { error, .. } => ApiError::from_driver(error),
+            };
+            error.message = e.to_string();
+            warn!("Rejected configuration update: {}", error.message);
+            (status, error_body(error))
+        }
+    }
+}
+
+/// The API routes. Responses are gzip or brotli compressed for clients that
+/// send `Accept-Encoding`; the SSE streams and tiny bodies are left as they are.
 pub fn create_api_routes() -> Router<SharedAppState> {
     Router::new()
+        .route("/api/health", get(get_health))
+        .route("/api/stats", get(get_stats))
+        .route("/api/openapi.json", get(get_openapi))
+        .route("/tags", get(get_tags))
+        .route("/api/tags/stream", get(stream_tags))
+        .route("/api/tags/tree", get(get_tag_tree))
+        .route("/api/tags/changed-since", get(get_tags_changed_since))
+        .route("/api/events/quality", get(stream_quality_events))
+        .route("/api/alarms", get(get_alarms))
+        .route("/api/logs/ws", get(stream_logs))
+        .route("/api/tags/write", post(write_tags))
+        .route("/api/tags/:path", patch(update_tag))
+        .route("/api/tags/:path/write", post(write_tag))
+        .route("/api/tags/:path/stats", get(get_tag_stats))
+        .route("/api/tags/:path/history", get(get_tag_history))
+        .route("/api/config", get(get_config).put(update_config))
         .route("/api/opcua/browse/:driver_id", get(browse_opcua_tags))
+        .route("/api/opcua/browse-tree/:driver_id", get(browse_opcua_tree))
         .route("/api/opcua/discover", get(discover_opcua_drivers))
+        .route("/api/drivers/:driver_id/reconnect", post(reconnect_driver))
+        .route("/api/drivers/:driver_id/stats", get(get_driver_stats))
+        .route("/api/opcua/call/:driver_id", post(call_opcua_method))
+        .route("/api/opcua/attributes/:driver_id", get(read_opcua_attributes))
         .route("/api/opcua/discover-tags/:driver_id", get(discover_opcua_tags))
+        .layer(CompressionLayer::new())
+}
+
+/// Reject a malformed node id before any driver is asked, so it is answered
+/// with `400` without a round trip to the server.
+fn check_node_id(node_id: &str) -> Result<(), ApiError> {
+    match OpcUaDriver::parse_node_id(node_id) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(ApiError::new(
+            "invalid_node_id",
+            format!(
+                "Invalid NodeId '{}': expected [ns=<index>;]<i|s|g|b>=<identifier>, e.g. ns=2;s=Temperature",
+                node_id
+            ),
+        )),
+    }
 }
 
 async fn browse_opcua_tags(
@@ -141,8 +959,18 @@ async fn browse_opcua_tags(
     Query(params): Query<BrowseQuery>,
 ) -> impl IntoResponse {
     info!("Browsing OPC UA tags for driver: {}, node: {}", driver_id, params.node_id);
-    
-    let driver = match state.drivers.get(&driver_id) {
+
+    if let Err(error) = check_node_id(&params.node_id) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(BrowseResponse {
+                node_id: params.node_id,
+                children: vec![],
+                error: Some(error),
+            }),
+        );
+    }
+    let driver = match state.drivers.get(&driver_id).map(|d| Arc::clone(d.value())) {
         Some(driver) => driver,
         None => {
             warn!("Driver not found: {}", driver_id);
@@ -151,7 +979,24 @@ async fn browse_opcua_tags(
                 Json(BrowseResponse {
                     node_id: params.node_id,
                     children: vec![],
-                    error: Some(format!("Driver '{}' not found", driver_id)),
+                    error: Some(ApiError::new(
+                        "not_found",
+                        format!("Driver '{}' not found", driver_id),
+                    )),
+                }),
+            );
+        }
+    };
+
+    let filter = match BrowseFilter::parse(params.ref_type.as_deref(), params.node_class.as_deref()) {
+        Ok(filter) => filter,
+        Err(message) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(BrowseResponse {
+                    node_id: params.node_id,
+                    children: vec![],
+                    error: Some(ApiError::new("invalid_value", message)),
                 }),
             );
         }
@@ -162,7 +1007,7 @@ async fn browse_opcua_tags(
 
     match opcua_driver {
         Some(opcua) => {
-            match opcua.browse_node(&params.node_id).await {
+            match opcua.browse_node_filtered(&params.node_id, &filter).await {
                 Ok(children) => {
                     info!("Successfully browsed {} children for node {}", children.len(), params.node_id);
                     (
@@ -176,12 +1021,13 @@ async fn browse_opcua_tags(
                 }
                 Err(e) => {
                     error!("Failed to browse node {}: {}", params.node_id, e);
+                    let (status, error) = ApiError::from_driver(&e);
                     (
-                        StatusCode::INTERNAL_SERVER_ERROR,
+                        status,
                         Json(BrowseResponse {
                             node_id: params.node_id,
                             children: vec![],
-                            error: Some(e.to_string()),
+                            error: Some(error),
                         }),
                     )
                 }
@@ -194,30 +1040,337 @@ async fn browse_opcua_tags(
                 Json(BrowseResponse {
                     node_id: params.node_id,
                     children: vec![],
-                    error: Some(format!("Driver '{}' is not an OPC UA driver", driver_id)),
+                    error: Some(ApiError::new(
+                        "not_opcua",
+                        format!("Driver '{}' is not an OPC UA driver", driver_id),
+                    )),
+                }),
+            )
+        }
+    }
+}
+
+async fn browse_opcua_tree(
+    State(state): State<SharedAppState>,
+    Path(driver_id): Path<String>,
+    Query(params): Query<BrowseTreeQuery>,
+) -> impl IntoResponse {
+    info!(
+        "Browsing OPC UA tree for driver: {}, node: {}, depth: {}",
+        driver_id, params.node_id, params.max_depth
+    );
+
+    if let Err(error) = check_node_id(&params.node_id) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(BrowseTreeResponse {
+                node_id: params.node_id,
+                nodes: vec![],
+                error: Some(error),
+            }),
+        );
+    }
+    let driver = match state.drivers.get(&driver_id).map(|d| Arc::clone(d.value())) {
+        Some(driver) => driver,
+        None => {
+            warn!("Driver not found: {}", driver_id);
+            return (
+                StatusCode::NOT_FOUND,
+                Json(BrowseTreeResponse {
+                    node_id: params.node_id,
+                    nodes: vec![],
+                    error: Some(ApiError::new(
+                        "not_found",
+                        format!("Driver '{}' not found", driver_id),
+                    )),
+                }),
+            );
+        }
+    };
+
+    match driver.as_any().downcast_ref::<OpcUaDriver>() {
+        Some(opcua) => match opcua
+            .browse_node_recursive(&params.node_id, params.max_depth)
+            .await
+        {
+            Ok(nodes) => {
+                info!("Successfully browsed {} nodes below {}", nodes.len(), params.node_id);
+                (
+                    StatusCode::OK,
+                    Json(BrowseTreeResponse {
+                        node_id: params.node_id,
+                        nodes,
+                        error: None,
+                    }),
+                )
+            }
+            Err(e) => {
+                error!("Failed to browse tree below {}: {}", params.node_id, e);
+                let (status, error) = ApiError::from_driver(&e);
+                (
+                    status,
+                    Json(BrowseTreeResponse {
+                        node_id: params.node_id,
+                        nodes: vec![],
+                        error: Some(error),
+                    }),
+                )
+            }
+        },
+        None => {
+            warn!("Driver '{}' is not an OPC UA driver", driver_id);
+            (
+                StatusCode::BAD_REQUEST,
+                Json(BrowseTreeResponse {
+                    node_id: params.node_id,
+                    nodes: vec![],
+                    error: Some(ApiError::new(
+                        "not_opcua",
+                        format!("Driver '{}' is not an OPC UA driver", driver_id),
+                    )),
                 }),
             )
         }
     }
 }
 
+async fn call_opcua_method(
+    State(state): State<SharedAppState>,
+    Path(driver_id): Path<String>,
+    ApiJson(request): ApiJson<CallMethodRequest>,
+) -> impl IntoResponse {
+    let failed = |driver_id: String, status: StatusCode, error: ApiError| {
+        (
+            status,
+            Json(CallMethodResponse {
+                driver_id,
+                outputs: vec![],
+                error: Some(error),
+            }),
+        )
+    };
+
+    if let Err(error) = check_node_id(&request.object_id).and(check_node_id(&request.method_id)) {
+        return failed(driver_id, StatusCode::BAD_REQUEST, error);
+    }
+    let driver = match state.drivers.get(&driver_id).map(|d| Arc::clone(d.value())) {
+        Some(driver) => driver,
+        None => {
+            warn!("Driver not found: {}", driver_id);
+            let error = ApiError::new("not_found", format!("Driver '{}' not found", driver_id));
+            return failed(driver_id, StatusCode::NOT_FOUND, error);
+        }
+    };
+    let Some(opcua) = driver.as_any().downcast_ref::<OpcUaDriver>() else {
+        warn!("Driver '{}' is not an OPC UA driver", driver_id);
+        let error = ApiError::new(
+            "not_opcua",
+            format!("Driver '{}' is not an OPC UA driver", driver_id),
+        );
+        return failed(driver_id, StatusCode::BAD_REQUEST, error);
+    };
+    let args = match request
+        .args
+        .into_iter()
+        .map(json_to_value_variant)
+        .collect::<Option<Vec<_>>>()
+    {
+        Some(args) => args,
+        None => {
+            let error = ApiError::new(
+                "invalid_value",
+                "Arguments must be bools, numbers, strings or nulls",
+            );
+            return failed(driver_id, StatusCode::BAD_REQUEST, error);
+        }
+    };
+
+    info!(
+        "Calling method {} on {} through driver {}",
+        request.method_id, request.object_id, driver_id
+    );
+    match opcua.call_method(&request.object_id, &request.method_id, args).await {
+        Ok(outputs) => (
+            StatusCode::OK,
+            Json(CallMethodResponse {
+                driver_id,
+                outputs,
+                error: None,
+            }),
+        ),
+        Err(e) => {
+            error!("Failed to call method {}: {}", request.method_id, e);
+            let (status, error) = ApiError::from_driver(&e);
+            failed(driver_id, status, error)
+        }
+    }
+}
+
+async fn read_opcua_attributes(
+    State(state): State<SharedAppState>,
+    Path(driver_id): Path<String>,
+    Query(params): Query<AttributesQuery>,
+) -> impl IntoResponse {
+    let node_id = params.node_id;
+    let failed = |driver_id: String, node_id: String, status: StatusCode, error: ApiError| {
+        (
+            status,
+            Json(AttributesResponse {
+                driver_id,
+                node_id,
+                attributes: vec![],
+                error: Some(error),
+            }),
+        )
+    };
+
+    if let Err(error) = check_node_id(&node_id) {
+        return failed(driver_id, node_id, StatusCode::BAD_REQUEST, error);
+    }
+    let driver = match state.drivers.get(&driver_id).map(|d| Arc::clone(d.value())) {
+        Some(driver) => driver,
+        None => {
+            warn!("Driver not found: {}", driver_id);
+            let error = ApiError::new("not_found", format!("Driver '{}' not found", driver_id));
+            return failed(driver_id, node_id, StatusCode::NOT_FOUND, error);
+        }
+    };
+    let Some(opcua) = driver.as_any().downcast_ref::<OpcUaDriver>() else {
+        warn!("Driver '{}' is not an OPC UA driver", driver_id);
+        let error = ApiError::new(
+            "not_opcua",
+            format!("Driver '{}' is not an OPC UA driver", driver_id),
+        );
+        return failed(driver_id, node_id, StatusCode::BAD_REQUEST, error);
+    };
+    let attributes = match params.attributes.as_deref() {
+        Some(names) => {
+            match names
+                .split(',')
+                .map(|name| parse_attribute(name).ok_or(name))
+                .collect::<Result<Vec<_>, _>>()
+            {
+                Ok(attributes) => attributes,
+                Err(name) => {
+                    let error = ApiError::new(
+                        "invalid_value",
+                        format!("Unknown attribute '{}'", name.trim()),
+                    );
+                    return failed(driver_id, node_id, StatusCode::BAD_REQUEST, error);
+                }
+            }
+        }
+        None => DEFAULT_DEBUG_ATTRIBUTES.to_vec(),
+    };
+
+    match opcua.read_attribute_values(&node_id, &attributes).await {
+        Ok(values) => {
+            let attributes = attributes
+                .iter()
+                .zip(values)
+                .map(|(attribute, value)| AttributeReading {
+                    attribute: format!("{attribute:?}"),
+                    value,
+                })
+                .collect();
+            (
+                StatusCode::OK,
+                Json(AttributesResponse {
+                    driver_id,
+                    node_id,
+                    attributes,
+                    error: None,
+                }),
+            )
+        }
+        Err(e) => {
+            error!("Failed to read the attributes of {}: {}", node_id, e);
+            let (status, error) = ApiError::from_driver(&e);
+            failed(driver_id, node_id, status, error)
+        }
+    }
+}
+
+/// The OpenAPI 3 document describing this API.
+async fn get_openapi() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "application/json")], OPENAPI_DOCUMENT)
+}
+
+/// Uptime, tag counts and the connection and circuit breaker state of each driver.
+async fn get_stats(State(state): State<SharedAppState>) -> impl IntoResponse {
+    let tag_count = state.tag_engine.get_all_tag_paths().len();
+    let uptime = state.start_time.elapsed().as_secs();
+    let connections: HashMap<String, ConnectionState> = state
+        .connection_states
+        .iter()
+        .map(|entry| (entry.key().clone(), *entry.value()))
+        .collect();
+    // Drivers without a recorded failure are closed
+    let breakers: HashMap<String, BreakerState> = state
+        .drivers
+        .iter()
+        .map(|entry| (entry.key().clone(), state.breakers.state(entry.key())))
+        .collect();
+    Json(json!({
+        "uptime_seconds": uptime,
+        "tag_count": tag_count,
+        "tag_memory_bytes": state.tag_engine.approx_memory_bytes(),
+        "dropped_quality_events": state.tag_engine.dropped_quality_events(),
+        "driver_count": state.drivers.len(),
+        "connections": connections,
+        "circuit_breakers": breakers,
+    }))
+}
+
+/// Gateway health for load balancers and monitoring. Answers `503` while a
+/// device marked `critical` is disconnected, `200` otherwise.
+async fn get_health(State(state): State<SharedAppState>) -> impl IntoResponse {
+    let mut drivers = HashMap::new();
+    let mut critical_down = false;
+    for entry in state.drivers.iter() {
+        // As recorded by the connect and health tasks; never started counts as down
+        let connected = state
+            .connection_states
+            .get(entry.key())
+            .is_some_and(|s| *s == ConnectionState::Connected);
+        critical_down |= !connected && entry.value().config().critical;
+        drivers.insert(entry.key().clone(), connected);
+    }
+    let (status, code) = if critical_down {
+        ("unavailable", StatusCode::SERVICE_UNAVAILABLE)
+    } else if drivers.values().all(|&connected| connected) {
+        ("ok", StatusCode::OK)
+    } else {
+        ("degraded", StatusCode::OK)
+    };
+    (code, Json(HealthResponse { status, drivers }))
+}
+
 async fn discover_opcua_drivers(State(state): State<SharedAppState>) -> impl IntoResponse {
     info!("Discovering OPC UA drivers");
     
     let mut drivers_info = Vec::new();
     
-    for (id, driver) in state.drivers.iter() {
-        let connected = driver.check_status().await.is_ok();
+    for entry in state.drivers.iter() {
+        let (id, driver) = (entry.key().clone(), entry.value());
+        // Kept up to date by the connect and health tasks, so no driver is asked here.
+        // A driver without a recorded state was never started.
+        let connection = state
+            .connection_states
+            .get(&id)
+            .map(|s| *s)
+            .unwrap_or(ConnectionState::Disconnected);
         let config = driver.config();
         
         // Check if it's an OPC UA driver
         let is_opcua = driver.as_any().downcast_ref::<OpcUaDriver>().is_some();
         
         drivers_info.push(DriverInfo {
-            id: id.clone(),
+            id,
             name: config.name.clone(),
             address: config.address.clone(),
-            connected,
+            connected: connection == ConnectionState::Connected,
+            state: connection,
             driver_type: if is_opcua { "OPC UA".to_string() } else { "Unknown".to_string() },
         });
     }
@@ -225,3 +1378,63 @@ async fn discover_opcua_drivers(State(state): State<SharedAppState>) -> impl Int
     info!("Found {} drivers", drivers_info.len());
     (StatusCode::OK, Json(DiscoverResponse { drivers: drivers_info }))
 }
+
+/// Scan statistics of a driver, all zero until it has been polled.
+async fn get_driver_stats(
+    State(state): State<SharedAppState>,
+    Path(driver_id): Path<String>,
+) -> impl IntoResponse {
+    if !state.drivers.contains_key(&driver_id) {
+        return (
+            StatusCode::NOT_FOUND,
+            error_body(ApiError::new("not_found", format!("Driver '{}' not found", driver_id))),
+        );
+    }
+    let stats = state.scan_stats.get(&driver_id);
+    (StatusCode::OK, Json(json!(DriverStatsResponse { driver_id, stats })))
+}
+
+/// Disconnect a driver and connect it again, e.g. after a network problem was
+/// fixed. Answers `409` while a connect or reconnect of the driver is already running.
+async fn reconnect_driver(
+    State(state): State<SharedAppState>,
+    Path(driver_id): Path<String>,
+) -> impl IntoResponse {
+    let response = |driver_id: String, status: StatusCode, state: Option<ConnectionState>, error| {
+        (
+            status,
+            Json(ReconnectResponse {
+                driver_id,
+                state,
+                connected: state == Some(ConnectionState::Connected),
+                error,
+            }),
+        )
+    };
+    let driver = match state.drivers.get(&driver_id).map(|d| Arc::clone(d.value())) {
+        Some(driver) => driver,
+        None => {
+            let error = ApiError::new("not_found", format!("Driver '{}' not found", driver_id));
+            return response(driver_id, StatusCode::NOT_FOUND, None, Some(error));
+        }
+    };
+    if !begin_reconnect(&state.connection_states, &driver_id) {
+        let current = state.connection_states.get(&driver_id).map(|s| *s);
+        let error = ApiError::new(
+            "in_progress",
+            format!("Driver '{}' is already connecting", driver_id),
+        );
+        return response(driver_id, StatusCode::CONFLICT, current, Some(error));
+    }
+
+    info!("Reconnecting driver '{}' on request", driver_id);
+    let result = reconnect(&driver_id, driver.as_ref(), &state.connection_states).await;
+    let current = state.connection_states.get(&driver_id).map(|s| *s);
+    match result {
+        Ok(()) => response(driver_id, StatusCode::OK, current, None),
+        Err(e) => {
+            let (status, error) = ApiError::from_driver(&e);
+            response(driver_id, status, current, Some(error))
+        }
+    }
+}
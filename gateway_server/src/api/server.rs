@@ -0,0 +1,66 @@
+use axum::Router;
+use std::future::IntoFuture;
+use std::io;
+use tokio::net::TcpListener;
+use tokio::time::{sleep, Duration};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+/// Serve `app` on `listener` until `shutdown` fires, then stop accepting
+/// connections and let requests in flight finish. Connections still open after
+/// `drain_timeout`, such as tag streams, are dropped.
+pub async fn serve(
+    listener: TcpListener,
+    app: Router,
+    shutdown: CancellationToken,
+    drain_timeout: Duration,
+) -> io::Result<()> {
+    let server = axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown.clone().cancelled_owned())
+        .into_future();
+    let drain_deadline = async {
+        shutdown.cancelled().await;
+        sleep(drain_timeout).await;
+    };
+    tokio::select! {
+        result = server => result,
+        _ = drain_deadline => {
+            warn!("Connections still open after {:?}, closing them.", drain_timeout);
+            Ok(())
+        }
+    }
+}
+
+/// Cancel `shutdown` on Ctrl-C or, on Unix, SIGTERM.
+pub async fn cancel_on_signal(shutdown: CancellationToken) {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!("Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+        // Already shutting down for another reason
+        _ = shutdown.cancelled() => return,
+    }
+    info!("Shutdown signal received.");
+    shutdown.cancel();
+}
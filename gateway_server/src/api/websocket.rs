@@ -1,8 +1,45 @@
-// Placeholder for WebSocket handling
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::State;
+use axum::response::IntoResponse;
+use tokio::sync::broadcast::{self, error::RecvError};
 
-use tracing::info;
+use crate::api::rest::SharedAppState;
 
-pub async fn start_websocket_server() {
-    // TODO: Implement WebSocket server
-    info!("WebSocket server not implemented yet.");
+/// Stream forwarded log lines to the client, one text message per line.
+pub async fn stream_logs(
+    State(state): State<SharedAppState>,
+    ws: WebSocketUpgrade,
+) -> impl IntoResponse {
+    // Subscribe before upgrading so no line logged after the handshake is missed
+    let lines = state.logs.subscribe();
+    ws.on_upgrade(move |socket| send_logs(socket, lines))
+}
+
+async fn send_logs(mut socket: WebSocket, mut lines: broadcast::Receiver<String>) {
+    loop {
+        tokio::select! {
+            line = lines.recv() => match line {
+                Ok(line) => {
+                    let line = line.trim_end().to_string();
+                    if socket.send(Message::Text(line)).await.is_err() {
+                        break;
+                    }
+                }
+                // Tell the viewer rather than logging it, which would only add to the backlog
+                Err(RecvError::Lagged(skipped)) => {
+                    let notice = format!("... {skipped} log lines skipped");
+                    if socket.send(Message::Text(notice)).await.is_err() {
+                        break;
+                    }
+                }
+                Err(RecvError::Closed) => break,
+            },
+            // Only closing matters; anything else the client sends is ignored
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+    let _ = socket.close().await;
 }
@@ -1 +1,2 @@
 pub mod settings; // Loading and managing configuration
+pub mod reload; // Hot reload of the configuration file
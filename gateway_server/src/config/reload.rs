@@ -0,0 +1,269 @@
+use crate::config::settings::{Settings, SettingsDiff};
+use crate::drivers::health::{connect_started_driver, ConnectionState, ConnectionStates};
+use crate::drivers::traits::{DriverError, DeviceDriver};
+use crate::drivers::{build_driver, DriverMap};
+use crate::tags::engine::TagEngine;
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{mpsc, Mutex, Notify, RwLock};
+use tokio::time::{sleep, Duration};
+use tracing::{error, info, warn};
+
+/// How long to wait for a burst of file events (editors often write in several steps) to settle.
+const RELOAD_DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// Watch a config file for changes.
+/// The parent directory is watched so that editors which replace the file are still
+/// picked up. Keep the returned watcher alive for as long as events are wanted.
+pub fn watch_config_file(
+    config_path: &Path,
+) -> notify::Result<(notify::RecommendedWatcher, mpsc::Receiver<()>)> {
+    let (tx, rx) = mpsc::channel(1);
+    let file_name = config_path.file_name().map(|name| name.to_os_string());
+
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        let Ok(event) = res else { return };
+        if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+            return;
+        }
+        let touches_config = event
+            .paths
+            .iter()
+            .any(|path| path.file_name().map(|name| name.to_os_string()) == file_name);
+        if touches_config {
+            // A full channel already has a reload pending, so dropping the event is fine
+            let _ = tx.try_send(());
+        }
+    })?;
+
+    let dir = match config_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    Ok((watcher, rx))
+}
+
+/// Why a configuration change was not applied.
+#[derive(Debug)]
+pub enum ReloadError {
+    /// The config file could not be read or written.
+    Io(io::Error),
+    /// No driver could be built for a new or changed device, e.g. because its
+    /// `driver_type` is unknown. Nothing was changed.
+    Driver { device_id: String, error: DriverError },
+}
+
+impl fmt::Display for ReloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReloadError::Io(e) => write!(f, "Configuration file error: {}", e),
+            ReloadError::Driver { device_id, error } => {
+                write!(f, "Device '{}' could not be started: {}", device_id, error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReloadError {}
+
+type StartedDrivers = Vec<(String, Arc<dyn DeviceDriver + Send + Sync>)>;
+
+/// Applies configuration changes to the running gateway. Clones share their
+/// state, so the file watcher and the config API apply changes one at a time
+/// against the same running configuration.
+#[derive(Clone)]
+pub struct ConfigReloader {
+    config_path: PathBuf,
+    /// The configuration the drivers and tag engine currently reflect.
+    settings: Arc<RwLock<Settings>>,
+    tag_engine: Arc<TagEngine>,
+    drivers: Arc<DriverMap>,
+    /// Kept in step with `drivers` so the API never has to ask a driver directly.
+    connection_states: Arc<ConnectionStates>,
+    /// Signalled after every applied change so the poller can rebuild its groups.
+    changed: Arc<Notify>,
+    /// Held while a change is being applied.
+    applying: Arc<Mutex<()>>,
+}
+
+impl ConfigReloader {
+    pub fn new(
+        config_path: PathBuf,
+        settings: Arc<RwLock<Settings>>,
+        tag_engine: Arc<TagEngine>,
+        drivers: Arc<DriverMap>,
+        connection_states: Arc<ConnectionStates>,
+        changed: Arc<Notify>,
+    ) -> Self {
+        Self {
+            config_path,
+            settings,
+            tag_engine,
+            drivers,
+            connection_states,
+            changed,
+            applying: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Reload the config file every time `events` fires, until the sender is dropped.
+    pub async fn run(self, mut events: mpsc::Receiver<()>) {
+        while events.recv().await.is_some() {
+            sleep(RELOAD_DEBOUNCE).await;
+            while events.try_recv().is_ok() {}
+
+            // Load under the lock so a file rolled back by `update` is not applied
+            let _applying = self.applying.lock().await;
+            // A partially written or invalid file fails to load; keep the running config.
+            let result = match Settings::load(&self.config_path) {
+                Ok(new_settings) => self.apply_locked(new_settings).await,
+                Err(e) => {
+                    warn!(
+                        "Ignoring change to {:?}, configuration could not be loaded: {}",
+                        self.config_path, e
+                    );
+                    continue;
+                }
+            };
+            if let Err(e) = result {
+                warn!("Ignoring change to {:?}: {}", self.config_path, e);
+            }
+        }
+    }
+
+    /// Bring drivers and tags in line with `new_settings`. Either every change is
+    /// applied or, if a device's driver cannot be built, none is. New and changed
+    /// devices connect in the background, like at startup.
+    pub async fn apply(&self, new_settings: Settings) -> Result<SettingsDiff, ReloadError> {
+        let _applying = self.applying.lock().await;
+        self.apply_locked(new_settings).await
+    }
+
+    /// Write `new_settings` to the config file and apply them. The previous file
+    /// is put back if they cannot be applied. Values the running configuration
+    /// took from `FORGEIO_` environment variables are not written to the file
+    /// unless `new_settings` changes them (see [`Settings::keep_file_values`]).
+    pub async fn update(&self, new_settings: Settings) -> Result<SettingsDiff, ReloadError> {
+        let _applying = self.applying.lock().await;
+        let previous = match fs::read(&self.config_path) {
+            Ok(contents) => Some(contents),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(ReloadError::Io(e)),
+        };
+        let mut saved = new_settings.clone();
+        match Settings::load_file(&self.config_path) {
+            Ok(file) => saved.keep_file_values(&*self.settings.read().await, &file),
+            Err(e) if previous.is_some() => {
+                warn!("Could not read {:?} to keep overrides out of it: {}", self.config_path, e);
+            }
+            Err(_) => {}
+        }
+        saved.save(&self.config_path).map_err(ReloadError::Io)?;
+
+        let result = self.apply_locked(new_settings).await;
+        if result.is_err() {
+            let restored = match previous {
+                Some(contents) => fs::write(&self.config_path, contents),
+                None => fs::remove_file(&self.config_path),
+            };
+            if let Err(e) = restored {
+                error!("Failed to roll back {:?}: {}", self.config_path, e);
+            }
+        }
+        result
+    }
+
+    async fn apply_locked(&self, new_settings: Settings) -> Result<SettingsDiff, ReloadError> {
+        let diff = self.settings.read().await.diff(&new_settings);
+        if !diff.is_empty() {
+            info!(
+                "Applying configuration change: devices +{} -{} ~{}, tags +{} -{} ~{}",
+                diff.added_devices.len(),
+                diff.removed_devices.len(),
+                diff.changed_devices.len(),
+                diff.added_tags.len(),
+                diff.removed_tags.len(),
+                diff.changed_tags.len()
+            );
+        }
+
+        let started = self.build_drivers(&diff)?;
+        self.stop_drivers(&diff).await;
+        for (id, driver) in started {
+            info!("Driver '{}' started, connecting in the background.", id);
+            self.drivers.insert(id.clone(), Arc::clone(&driver));
+            self.connection_states.insert(id.clone(), ConnectionState::Connecting);
+            // An unreachable device must not hold the reload lock; the health
+            // monitor retries it once this first attempt fails
+            tokio::spawn(connect_started_driver(
+                id,
+                driver,
+                Arc::clone(&self.drivers),
+                Arc::clone(&self.connection_states),
+            ));
+        }
+        self.apply_tags(&diff);
+
+        *self.settings.write().await = new_settings;
+        if !diff.is_empty() {
+            self.changed.notify_one();
+        }
+        Ok(diff)
+    }
+
+    /// Build unconnected drivers for new and changed devices.
+    fn build_drivers(&self, diff: &SettingsDiff) -> Result<StartedDrivers, ReloadError> {
+        let mut started = StartedDrivers::new();
+        for config in diff.added_devices.iter().chain(&diff.changed_devices) {
+            match build_driver(config.clone()) {
+                Ok(driver) => started.push((config.id.clone(), driver)),
+                Err(error) => {
+                    error!("Failed to start driver '{}': {}", config.id, error);
+                    return Err(ReloadError::Driver {
+                        device_id: config.id.clone(),
+                        error,
+                    });
+                }
+            }
+        }
+        Ok(started)
+    }
+
+    async fn stop_drivers(&self, diff: &SettingsDiff) {
+        let stale = diff
+            .removed_devices
+            .iter()
+            .chain(diff.changed_devices.iter().map(|device| &device.id));
+        for id in stale {
+            self.connection_states.remove(id);
+            if let Some((_, driver)) = self.drivers.remove(id) {
+                if let Err(e) = driver.disconnect().await {
+                    warn!("Error disconnecting driver '{}': {}", id, e);
+                }
+                info!("Driver '{}' stopped.", id);
+            }
+        }
+    }
+
+    fn apply_tags(&self, diff: &SettingsDiff) {
+        for path in &diff.removed_tags {
+            self.tag_engine.unregister_tag(path);
+        }
+        for tag_config in diff.added_tags.iter().chain(&diff.changed_tags) {
+            if tag_config.is_alias() || self.drivers.contains_key(&tag_config.driver_id) {
+                self.tag_engine.register_tag(tag_config.to_tag());
+            } else {
+                self.tag_engine.unregister_tag(tag_config.path.as_str());
+                warn!(
+                    "Skipping tag '{}' because its driver '{}' was not found or failed to initialize.",
+                    tag_config.path, tag_config.driver_id
+                );
+            }
+        }
+    }
+}
@@ -1,47 +1,512 @@
-use crate::drivers::traits::OpcDriverConfig; // Reuse driver config for now
-use config::{Config, ConfigError, File};
+use crate::alarms::{AlarmLimits, Severity};
+use crate::api::auth::AuthConfig;
+use crate::api::cors::CorsConfig;
+use crate::drivers::traits::DriverConfig; // Reuse driver config for now
+use crate::historian::influx::InfluxConfig;
+use crate::integrations::mqtt::MqttConfig;
+use crate::logging::LoggingConfig;
+use crate::tags::path::TagPath;
+use crate::tags::structures::{enabled_by_default, Quality, Tag, TagMetadata, TagValue};
+use config::{Config, ConfigError, Environment, File, FileFormat};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::net::{SocketAddr, ToSocketAddrs};
 use std::path::Path;
 use std::fs;
 use std::io;
 use toml;
+use tracing::warn;
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub struct TagConfig {
-    pub path: String,           // Unique path for the tag (e.g., "Folder/Sub/MyTag")
+    pub path: TagPath,          // Unique path for the tag (e.g., "Folder/Sub/MyTag")
+    #[serde(default)]
     pub driver_id: String,      // ID of the driver this tag belongs to (must match a device ID)
+    #[serde(default)]
     pub address: String,        // Driver-specific address (e.g., OPC UA NodeId, Modbus register)
+    #[serde(default)]
     pub poll_rate_ms: u64, // How often to poll this tag in milliseconds
-                            // TODO: Add metadata, scaling, deadband etc. later
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool, // Disabled tags are not polled but keep their configuration
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias_of: Option<TagPath>, // Mirror this other tag's value instead of reading a device
+    #[serde(default)]
+    pub raw_low: Option<f64>, // Raw device value that maps to eng_low
+    #[serde(default)]
+    pub raw_high: Option<f64>, // Raw device value that maps to eng_high
+    #[serde(default)]
+    pub eng_low: Option<f64>, // Engineering value of raw_low; scaling needs all four bounds
+    #[serde(default)]
+    pub eng_high: Option<f64>, // Engineering value of raw_high
+    #[serde(default)]
+    pub deadband: Option<f64>, // Minimum numeric change required to store a new value
+    #[serde(default)]
+    pub writable: bool, // Whether the tag may be written through the API
+    #[serde(default)]
+    pub hold_last_value: bool, // Keep the last value, marked Bad, when a read fails
+    #[serde(default)]
+    pub alarm_high: Option<f64>, // Raise an alarm while the value is above this limit
+    #[serde(default)]
+    pub alarm_low: Option<f64>, // Raise an alarm while the value is below this limit
+    #[serde(default)]
+    pub alarm_rate_of_change: Option<f64>, // Raise an alarm while the value changes faster (units/s)
+    #[serde(default)]
+    pub alarm_severity: Severity, // Severity of the tag's limit alarms
+}
+
+impl TagConfig {
+    /// Whether the tag mirrors another tag rather than reading a device.
+    /// Aliases don't need `driver_id`, `address` or `poll_rate_ms`.
+    pub fn is_alias(&self) -> bool {
+        self.alias_of.is_some()
+    }
+
+    /// Build the initial engine tag for this config. Values start out Bad until first polled.
+    pub fn to_tag(&self) -> Tag {
+        let metadata = TagMetadata {
+            description: Some("Default description".to_string()),
+            eng_unit: Some("unit".to_string()),
+            eng_low: self.eng_low,
+            eng_high: self.eng_high,
+            raw_low: self.raw_low,
+            raw_high: self.raw_high,
+            deadband: self.deadband,
+            writable: self.writable,
+            hold_last_value: self.hold_last_value,
+            alarms: AlarmLimits {
+                high: self.alarm_high,
+                low: self.alarm_low,
+                rate_of_change: self.alarm_rate_of_change,
+                severity: self.alarm_severity,
+            },
+            alias_of: self.alias_of.clone(),
+        };
+
+        Tag {
+            path: self.path.clone(),
+            value: TagValue::bad(Quality::Bad), // Start with Bad quality
+            driver_id: self.driver_id.clone(),
+            driver_address: self.address.clone(),
+            poll_rate_ms: self.poll_rate_ms,
+            enabled: self.enabled,
+            metadata, // Basic metadata
+        }
+    }
+}
+
+/// Differences between two configurations, keyed by device id and tag path.
+/// Devices and tags present in both but with different settings are "changed".
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SettingsDiff {
+    pub added_devices: Vec<DriverConfig>,
+    pub removed_devices: Vec<String>,
+    pub changed_devices: Vec<DriverConfig>,
+    pub added_tags: Vec<TagConfig>,
+    pub removed_tags: Vec<String>,
+    pub changed_tags: Vec<TagConfig>,
+}
+
+impl SettingsDiff {
+    pub fn is_empty(&self) -> bool {
+        *self == SettingsDiff::default()
+    }
+
+    /// The device ids and tag paths involved, without device settings such as passwords.
+    pub fn summary(&self) -> ChangeSummary {
+        let device_ids = |devices: &[DriverConfig]| devices.iter().map(|d| d.id.clone()).collect();
+        let tag_paths = |tags: &[TagConfig]| tags.iter().map(|t| t.path.to_string()).collect();
+        ChangeSummary {
+            added_devices: device_ids(&self.added_devices),
+            removed_devices: self.removed_devices.clone(),
+            changed_devices: device_ids(&self.changed_devices),
+            added_tags: tag_paths(&self.added_tags),
+            removed_tags: self.removed_tags.clone(),
+            changed_tags: tag_paths(&self.changed_tags),
+        }
+    }
+}
+
+/// What a configuration change did, as device ids and tag paths.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct ChangeSummary {
+    pub added_devices: Vec<String>,
+    pub removed_devices: Vec<String>,
+    pub changed_devices: Vec<String>,
+    pub added_tags: Vec<String>,
+    pub removed_tags: Vec<String>,
+    pub changed_tags: Vec<String>,
+}
+
+/// Address the HTTP API listens on.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct ServerConfig {
+    /// IP address or host name to bind to.
+    #[serde(default = "default_host")]
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+}
+
+fn default_host() -> String {
+    "127.0.0.1".to_string()
+}
+
+fn default_port() -> u16 {
+    3000
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            host: default_host(),
+            port: default_port(),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Resolve `host` and `port` to the address to bind, preferring the first
+    /// address a host name resolves to.
+    pub fn socket_addr(&self) -> io::Result<SocketAddr> {
+        (self.host.as_str(), self.port)
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("Host '{}' did not resolve to an address", self.host),
+                )
+            })
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)] // Clone needed for passing around
 pub struct Settings {
-    // Maybe add general settings like server port, log level etc. later
-    // pub server_port: u16,
-    pub devices: Vec<OpcDriverConfig>, // A list of device configurations
+    #[serde(default)]
+    pub server: ServerConfig, // Listen address; read at startup only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<AuthConfig>, // API credentials; read at startup only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cors: Option<CorsConfig>, // Cross-origin API access; read at startup only
+    #[serde(default)]
+    pub logging: LoggingConfig, // Log level and format; read at startup only
+    pub devices: Vec<DriverConfig>, // A list of device configurations
     #[serde(default)] // Make tags optional in the config file
     pub tags: Vec<TagConfig>,       // A list of tag configurations
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub influx: Option<InfluxConfig>, // Historian output; read at startup only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mqtt: Option<MqttConfig>, // Publishing tag changes; read at startup only
+    /// How often the poller checks for due tags; derived from the poll rates if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub poll_tick_ms: Option<u64>, // Read at startup only
+}
+
+/// Stands in for secrets in configurations returned by the API.
+pub const REDACTED: &str = "***";
+
+fn redact(secret: &mut Option<String>) {
+    if secret.is_some() {
+        *secret = Some(REDACTED.to_string());
+    }
+}
+
+/// See [`Settings::keep_file_values`]. Objects are compared field by field,
+/// anything else as a whole; a missing field counts as null.
+fn keep_file_value(
+    new: &mut serde_json::Value,
+    running: &serde_json::Value,
+    file: &serde_json::Value,
+) {
+    if new == running {
+        if running != file {
+            *new = file.clone();
+        }
+        return;
+    }
+    if let serde_json::Value::Object(fields) = new {
+        let null = serde_json::Value::Null;
+        for (key, value) in fields.iter_mut() {
+            let running = running.get(key).unwrap_or(&null);
+            keep_file_value(value, running, file.get(key).unwrap_or(&null));
+        }
+    }
+}
+
+/// File formats the configuration can be stored in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Toml,
+    Yaml,
+    Json,
+}
+
+impl ConfigFormat {
+    /// Format of a config file by its extension: `.yaml`/`.yml`, `.json`, and
+    /// TOML for `.toml` or anything else.
+    pub fn from_path(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("yaml" | "yml") => ConfigFormat::Yaml,
+            Some("json") => ConfigFormat::Json,
+            _ => ConfigFormat::Toml,
+        }
+    }
+
+    fn file_format(self) -> FileFormat {
+        match self {
+            ConfigFormat::Toml => FileFormat::Toml,
+            ConfigFormat::Yaml => FileFormat::Yaml,
+            ConfigFormat::Json => FileFormat::Json,
+        }
+    }
 }
 
 impl Settings {
+    /// Load and validate the configuration, in the format given by the file's
+    /// extension (see [`ConfigFormat::from_path`]). Environment variables
+    /// prefixed `FORGEIO_` override file values, with `__` between nested keys:
+    /// `FORGEIO_SERVER__PORT=8080` sets `server.port`.
     pub fn load(config_path: &Path) -> Result<Self, ConfigError> {
+        let format = ConfigFormat::from_path(config_path).file_format();
         let s = Config::builder()
             // Start with defaults (optional)
             // .set_default("server_port", 3000)?
             // Add configuration file
-            .add_source(File::from(config_path))
-            // Add environment variables (optional, with prefix)
-            // .add_source(Environment::with_prefix("APP"))
+            .add_source(File::from(config_path).format(format))
+            // Override file values from the environment, e.g. FORGEIO_SERVER__PORT
+            .add_source(
+                Environment::with_prefix("FORGEIO")
+                    .prefix_separator("_")
+                    .separator("__"),
+            )
             .build()?;
 
         // Deserialize the entire configuration
-        s.try_deserialize()
+        let settings: Settings = s.try_deserialize()?;
+        settings
+            .validate()
+            .map_err(|problems| ConfigError::Message(problems.join("; ")))?;
+        Ok(settings)
+    }
+
+    /// Load the config file as written, without the `FORGEIO_` overrides and
+    /// without validating it.
+    pub fn load_file(config_path: &Path) -> Result<Self, ConfigError> {
+        let format = ConfigFormat::from_path(config_path).file_format();
+        Config::builder()
+            .add_source(File::from(config_path).format(format))
+            .build()?
+            .try_deserialize()
+    }
+
+    /// Check referential integrity: unique device ids and tag paths, every tag
+    /// pointing at a configured device, and nonzero poll rates. Alias tags must
+    /// point at a configured tag, without forming a cycle. The server host
+    /// must be an IP address or a resolvable host name, enabled auth needs
+    /// a username and password hash, and CORS entries and the log level must
+    /// be well-formed.
+    /// Returns every problem found rather than stopping at the first.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut problems = Vec::new();
+
+        if let Err(e) = self.server.socket_addr() {
+            problems.push(format!("Invalid server host '{}': {}", self.server.host, e));
+        }
+        if let Some(Err(e)) = self.auth.as_ref().map(AuthConfig::validate) {
+            problems.push(e);
+        }
+        if let Some(Err(e)) = self.cors.as_ref().map(CorsConfig::validate) {
+            problems.push(e);
+        }
+        if let Err(e) = self.logging.filter() {
+            problems.push(e);
+        }
+        if self.poll_tick_ms == Some(0) {
+            problems.push("poll_tick_ms must be nonzero".to_string());
+        }
+
+        let mut device_ids = HashSet::new();
+        for device in &self.devices {
+            if !device_ids.insert(device.id.as_str()) {
+                problems.push(format!("Duplicate device id '{}'", device.id));
+            }
+        }
+
+        let mut tag_paths = HashSet::new();
+        for tag in &self.tags {
+            if !tag_paths.insert(tag.path.as_str()) {
+                problems.push(format!("Duplicate tag path '{}'", tag.path));
+            }
+            if tag.is_alias() {
+                continue;
+            }
+            if !device_ids.contains(tag.driver_id.as_str()) {
+                problems.push(format!(
+                    "Tag '{}' references unknown device '{}'",
+                    tag.path, tag.driver_id
+                ));
+            }
+            if tag.poll_rate_ms == 0 {
+                problems.push(format!("Tag '{}' has a poll_rate_ms of 0", tag.path));
+            }
+            if tag.raw_low.is_some() && tag.raw_low == tag.raw_high {
+                problems.push(format!("Tag '{}' has equal raw_low and raw_high", tag.path));
+            }
+        }
+        problems.extend(self.alias_problems(&tag_paths));
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+
+    /// Aliases of unknown tags, and each cycle of aliases once.
+    fn alias_problems(&self, tag_paths: &HashSet<&str>) -> Vec<String> {
+        let alias_of: HashMap<&str, &str> = self
+            .tags
+            .iter()
+            .filter_map(|tag| Some((tag.path.as_str(), tag.alias_of.as_deref()?)))
+            .collect();
+        let mut problems = Vec::new();
+        let mut reported = HashSet::new();
+        for tag in &self.tags {
+            let Some(source) = tag.alias_of.as_deref() else {
+                continue;
+            };
+            if !tag_paths.contains(source) {
+                problems.push(format!(
+                    "Alias tag '{}' references unknown tag '{}'",
+                    tag.path, source
+                ));
+                continue;
+            }
+            // Follow the chain of aliases; coming back to this tag means a cycle
+            let mut chain = vec![tag.path.as_str()];
+            let mut next = Some(source);
+            while let Some(path) = next {
+                if path == tag.path.as_str() {
+                    if chain.iter().all(|path| reported.insert(*path)) {
+                        chain.push(path);
+                        problems.push(format!("Alias cycle: {}", chain.join(" -> ")));
+                    }
+                    break;
+                }
+                if chain.contains(&path) {
+                    break; // A cycle further down the chain, reported from its own tags
+                }
+                chain.push(path);
+                next = alias_of.get(path).copied();
+            }
+        }
+        problems
+    }
+
+    /// Compare this (running) configuration with `other` (the new one).
+    /// Entries in the returned lists keep the order of the config file.
+    pub fn diff(&self, other: &Settings) -> SettingsDiff {
+        let mut diff = SettingsDiff::default();
+
+        for device in &other.devices {
+            match self.devices.iter().find(|d| d.id == device.id) {
+                None => diff.added_devices.push(device.clone()),
+                Some(old) if old != device => diff.changed_devices.push(device.clone()),
+                Some(_) => {}
+            }
+        }
+        for device in &self.devices {
+            if !other.devices.iter().any(|d| d.id == device.id) {
+                diff.removed_devices.push(device.id.clone());
+            }
+        }
+
+        for tag in &other.tags {
+            match self.tags.iter().find(|t| t.path == tag.path) {
+                None => diff.added_tags.push(tag.clone()),
+                Some(old) if old != tag => diff.changed_tags.push(tag.clone()),
+                Some(_) => {}
+            }
+        }
+        for tag in &self.tags {
+            if !other.tags.iter().any(|t| t.path == tag.path) {
+                diff.removed_tags.push(tag.path.to_string());
+            }
+        }
+
+        diff
+    }
+
+    /// A copy safe to hand out through the API, with device passwords, the
+    /// historian token and the auth password hash replaced by [`REDACTED`].
+    pub fn redacted(&self) -> Settings {
+        let mut cfg = self.clone();
+        for device in &mut cfg.devices {
+            redact(&mut device.password);
+        }
+        if let Some(influx) = &mut cfg.influx {
+            redact(&mut influx.token);
+        }
+        if let Some(auth) = &mut cfg.auth {
+            auth.password_hash = REDACTED.to_string();
+        }
+        cfg
+    }
+
+    /// Put back the secrets a client left as [`REDACTED`], taking them from
+    /// `running`, so a config read through the API can be sent back unchanged.
+    pub fn restore_secrets(&mut self, running: &Settings) {
+        for device in &mut self.devices {
+            if device.password.as_deref() == Some(REDACTED) {
+                device.password = running
+                    .devices
+                    .iter()
+                    .find(|d| d.id == device.id)
+                    .and_then(|d| d.password.clone());
+            }
+        }
+        if let Some(influx) = &mut self.influx {
+            if influx.token.as_deref() == Some(REDACTED) {
+                influx.token = running.influx.as_ref().and_then(|i| i.token.clone());
+            }
+        }
+        if let (Some(auth), Some(old)) = (&mut self.auth, &running.auth) {
+            if auth.password_hash == REDACTED {
+                auth.password_hash = old.password_hash.clone();
+            }
+        }
+    }
+
+    /// Keep values that came from the environment out of a config about to be
+    /// saved: wherever this config repeats a `running` value that differs from
+    /// the config `file`, the file's value is put back. A config read through
+    /// the API and sent back unchanged thus saves as the file it was loaded
+    /// from, without env-only secrets or other `FORGEIO_` overrides.
+    pub fn keep_file_values(&mut self, running: &Settings, file: &Settings) {
+        let to_value = |settings: &Settings| serde_json::to_value(settings).ok();
+        let (Some(mut new), Some(running), Some(file)) =
+            (to_value(self), to_value(running), to_value(file))
+        else {
+            return;
+        };
+        keep_file_value(&mut new, &running, &file);
+        match serde_json::from_value(new) {
+            Ok(settings) => *self = settings,
+            Err(e) => warn!("Could not separate environment overrides from the config: {}", e),
+        }
     }
 
+    /// Write the configuration in the format given by the file's extension.
     pub fn save(&self, config_path: &Path) -> io::Result<()> {
-        let toml_string = toml::to_string_pretty(self)
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
-        fs::write(config_path, toml_string)
+        let contents = match ConfigFormat::from_path(config_path) {
+            ConfigFormat::Toml => toml::to_string_pretty(self).map_err(io::Error::other)?,
+            ConfigFormat::Yaml => serde_yaml::to_string(self).map_err(io::Error::other)?,
+            ConfigFormat::Json => serde_json::to_string_pretty(self).map_err(io::Error::other)?,
+        };
+        fs::write(config_path, contents)
     }
 }
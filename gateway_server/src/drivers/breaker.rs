@@ -0,0 +1,131 @@
+use dashmap::DashMap;
+use serde::Serialize;
+use tokio::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Consecutive failures after which a driver's breaker opens.
+pub const DEFAULT_FAILURE_THRESHOLD: u32 = 5;
+/// How long an open breaker stays open before a probe is let through.
+pub const DEFAULT_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// State of a driver's circuit breaker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    /// Requests go through.
+    Closed,
+    /// Too many consecutive failures; requests are skipped until the cooldown ends.
+    Open,
+    /// The cooldown ended and a single probe is in flight. Its outcome closes
+    /// or reopens the breaker.
+    HalfOpen,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Breaker {
+    state: BreakerState,
+    consecutive_failures: u32,
+    opened_at: Instant,
+}
+
+impl Breaker {
+    fn closed() -> Self {
+        Self {
+            state: BreakerState::Closed,
+            consecutive_failures: 0,
+            opened_at: Instant::now(),
+        }
+    }
+}
+
+/// Circuit breaker of each driver, keyed by device id. After
+/// `failure_threshold` consecutive failures a driver's breaker opens and
+/// [`allow`](Self::allow) refuses it for `cooldown`, so a device that is down
+/// is not retried on every poll. Drivers without an entry are closed.
+pub struct CircuitBreakers {
+    breakers: DashMap<String, Breaker>,
+    failure_threshold: u32,
+    cooldown: Duration,
+}
+
+impl Default for CircuitBreakers {
+    fn default() -> Self {
+        Self::new(DEFAULT_FAILURE_THRESHOLD, DEFAULT_COOLDOWN)
+    }
+}
+
+impl CircuitBreakers {
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            breakers: DashMap::new(),
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+        }
+    }
+
+    /// Whether a request to `id` may go ahead. Once the cooldown of an open
+    /// breaker has passed, the first caller gets through as the half-open probe
+    /// and everyone else is refused until it reports back. A probe that never
+    /// reports back is replaced after another cooldown.
+    pub fn allow(&self, id: &str) -> bool {
+        let Some(mut breaker) = self.breakers.get_mut(id) else {
+            return true;
+        };
+        match breaker.state {
+            BreakerState::Closed => true,
+            BreakerState::Open | BreakerState::HalfOpen
+                if breaker.opened_at.elapsed() >= self.cooldown =>
+            {
+                info!("Circuit breaker of driver '{}' half-open, probing.", id);
+                breaker.state = BreakerState::HalfOpen;
+                breaker.opened_at = Instant::now();
+                true
+            }
+            BreakerState::Open | BreakerState::HalfOpen => false,
+        }
+    }
+
+    /// Record a request to `id` that succeeded, closing its breaker.
+    pub fn record_success(&self, id: &str) {
+        if let Some((_, breaker)) = self.breakers.remove(id) {
+            if breaker.state != BreakerState::Closed {
+                info!("Circuit breaker of driver '{}' closed.", id);
+            }
+        }
+    }
+
+    /// Record a request to `id` that failed. Opens the breaker when the
+    /// threshold is reached or the half-open probe failed.
+    pub fn record_failure(&self, id: &str) {
+        let mut breaker = self
+            .breakers
+            .entry(id.to_string())
+            .or_insert_with(Breaker::closed);
+        breaker.consecutive_failures = breaker.consecutive_failures.saturating_add(1);
+        let open = match breaker.state {
+            BreakerState::Closed => breaker.consecutive_failures >= self.failure_threshold,
+            BreakerState::HalfOpen => true,
+            BreakerState::Open => false,
+        };
+        if open {
+            warn!(
+                "Circuit breaker of driver '{}' open after {} consecutive failures, retrying in {:?}.",
+                id, breaker.consecutive_failures, self.cooldown
+            );
+            breaker.state = BreakerState::Open;
+            breaker.opened_at = Instant::now();
+        }
+    }
+
+    /// Current state of the breaker of `id`.
+    pub fn state(&self, id: &str) -> BreakerState {
+        self.breakers
+            .get(id)
+            .map_or(BreakerState::Closed, |breaker| breaker.state)
+    }
+
+    /// Forget the breakers of drivers `keep` returns false for.
+    pub fn retain(&self, mut keep: impl FnMut(&str) -> bool) {
+        self.breakers.retain(|id, _| keep(id));
+    }
+}
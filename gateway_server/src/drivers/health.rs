@@ -0,0 +1,256 @@
+use crate::drivers::breaker::CircuitBreakers;
+use crate::drivers::traits::{DeviceDriver, DriverConfig, DriverResult};
+use crate::drivers::{build_driver, DriverMap};
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::task::JoinSet;
+use tokio::time::{interval, Duration, MissedTickBehavior};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+/// How often drivers are checked when no interval is configured.
+const DEFAULT_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Connection state of a driver as last seen by the [`HealthMonitor`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionState {
+    /// The first connection attempt after the driver was started is in progress.
+    Connecting,
+    Connected,
+    /// The connection was lost and `connect` is being retried.
+    Reconnecting,
+    /// The last reconnect attempt failed; it is tried again on the next check.
+    Disconnected,
+}
+
+/// Connection state of each driver, keyed by device id.
+pub type ConnectionStates = DashMap<String, ConnectionState>;
+
+/// Periodically checks every driver's connection and reconnects the ones that
+/// dropped, using each driver's own retry and backoff settings.
+pub struct HealthMonitor {
+    drivers: Arc<DriverMap>,
+    states: Arc<ConnectionStates>,
+    cancel: CancellationToken,
+    check_interval: Duration,
+    /// Drivers whose breaker is open are not reconnected until the cooldown ends.
+    breakers: Arc<CircuitBreakers>,
+}
+
+impl HealthMonitor {
+    pub fn new(
+        drivers: Arc<DriverMap>,
+        states: Arc<ConnectionStates>,
+        cancel: CancellationToken,
+    ) -> Self {
+        Self {
+            drivers,
+            states,
+            cancel,
+            check_interval: DEFAULT_CHECK_INTERVAL,
+            breakers: Arc::new(CircuitBreakers::default()),
+        }
+    }
+
+    /// Check the drivers at a different interval.
+    pub fn with_check_interval(mut self, check_interval: Duration) -> Self {
+        self.check_interval = check_interval;
+        self
+    }
+
+    /// Record reconnect outcomes in `breakers`, e.g. to share them with the poller.
+    pub fn with_circuit_breakers(mut self, breakers: Arc<CircuitBreakers>) -> Self {
+        self.breakers = breakers;
+        self
+    }
+
+    /// Check and reconnect drivers until the cancellation token fires.
+    pub async fn run(self) {
+        info!("Driver health monitor started.");
+        let mut ticker = interval(self.check_interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut reconnects = JoinSet::new();
+
+        loop {
+            tokio::select! {
+                biased;
+                _ = self.cancel.cancelled() => break,
+                _ = ticker.tick() => {}
+            }
+            while reconnects.try_join_next().is_some() {}
+
+            // Forget drivers removed by a configuration reload
+            self.states.retain(|id, _| self.drivers.contains_key(id));
+
+            let drivers: Vec<_> = self
+                .drivers
+                .iter()
+                .map(|entry| (entry.key().clone(), Arc::clone(entry.value())))
+                .collect();
+            for (id, driver) in drivers {
+                // A connect or reconnect for this driver is still retrying
+                if matches!(
+                    self.state(&id),
+                    Some(ConnectionState::Connecting | ConnectionState::Reconnecting)
+                ) {
+                    continue;
+                }
+                if driver.check_status().await.is_ok() {
+                    // Unless a reconnect was requested while the status was checked
+                    match self.states.entry(id) {
+                        Entry::Occupied(entry) if *entry.get() == ConnectionState::Reconnecting => {}
+                        entry => {
+                            entry.insert(ConnectionState::Connected);
+                        }
+                    }
+                    continue;
+                }
+
+                // Open breaker: a device that keeps refusing connections is only
+                // retried once the cooldown ends
+                if !self.breakers.allow(&id) {
+                    self.states.insert(id, ConnectionState::Disconnected);
+                    continue;
+                }
+                // Lost to a reconnect requested through the API
+                if !begin_reconnect(&self.states, &id) {
+                    continue;
+                }
+                warn!("Driver '{}' is disconnected, reconnecting.", id);
+                let states = Arc::clone(&self.states);
+                let breakers = Arc::clone(&self.breakers);
+                reconnects.spawn(async move {
+                    match reconnect(&id, driver.as_ref(), &states).await {
+                        Ok(()) => breakers.record_success(&id),
+                        Err(_) => breakers.record_failure(&id),
+                    }
+                });
+            }
+        }
+
+        reconnects.shutdown().await;
+        info!("Driver health monitor stopped.");
+    }
+
+    fn state(&self, id: &str) -> Option<ConnectionState> {
+        self.states.get(id).map(|state| *state)
+    }
+}
+
+/// Mark `id` as reconnecting. Returns `false`, leaving the state alone, if a
+/// connect or reconnect of the driver is already in progress.
+pub fn begin_reconnect(states: &ConnectionStates, id: &str) -> bool {
+    match states.entry(id.to_string()) {
+        Entry::Occupied(entry)
+            if matches!(
+                *entry.get(),
+                ConnectionState::Connecting | ConnectionState::Reconnecting
+            ) =>
+        {
+            false
+        }
+        entry => {
+            entry.insert(ConnectionState::Reconnecting);
+            true
+        }
+    }
+}
+
+/// Drop whatever is left of the driver's session and connect it again, recording
+/// the outcome in `states`. Call [`begin_reconnect`] first.
+pub async fn reconnect(
+    id: &str,
+    driver: &(dyn DeviceDriver + Send + Sync),
+    states: &ConnectionStates,
+) -> DriverResult<()> {
+    if let Err(e) = driver.disconnect().await {
+        warn!("Error cleaning up driver '{}' before reconnecting: {}", id, e);
+    }
+    let result = driver.connect().await;
+    let state = match &result {
+        Ok(()) => {
+            info!("Driver '{}' reconnected.", id);
+            ConnectionState::Connected
+        }
+        Err(e) => {
+            error!("Failed to reconnect driver '{}': {}", id, e);
+            ConnectionState::Disconnected
+        }
+    };
+    states.insert(id.to_string(), state);
+    result
+}
+
+/// Build a driver for every device and connect them in the background, so an
+/// unreachable device does not hold up startup. Drivers are added to `drivers`
+/// right away; one that fails to connect is left to the [`HealthMonitor`].
+/// Only an invalid driver configuration is an error. The returned connect
+/// tasks stop early once `cancel` fires.
+pub fn start_drivers(
+    devices: &[DriverConfig],
+    drivers: &Arc<DriverMap>,
+    states: &Arc<ConnectionStates>,
+    cancel: &CancellationToken,
+) -> Result<JoinSet<()>, String> {
+    let mut connects = JoinSet::new();
+    for config in devices {
+        info!("Initializing driver: {} ({})", config.name, config.id);
+        let driver = build_driver(config.clone())
+            .map_err(|e| format!("Failed to create driver '{}': {}", config.id, e))?;
+        drivers.insert(config.id.clone(), Arc::clone(&driver));
+        states.insert(config.id.clone(), ConnectionState::Connecting);
+
+        let connect = connect_started_driver(
+            config.id.clone(),
+            driver,
+            Arc::clone(drivers),
+            Arc::clone(states),
+        );
+        let cancel = cancel.clone();
+        connects.spawn(async move {
+            tokio::select! {
+                _ = cancel.cancelled() => {}
+                _ = connect => {}
+            }
+        });
+    }
+    Ok(connects)
+}
+
+/// First connection of a driver that was just added to `drivers` as
+/// [`ConnectionState::Connecting`]. The outcome is recorded in `states`; a
+/// failure is left to the [`HealthMonitor`]. If a configuration reload removed
+/// or replaced the driver in the meantime, it is disconnected again instead.
+pub async fn connect_started_driver(
+    id: String,
+    driver: Arc<dyn DeviceDriver + Send + Sync>,
+    drivers: Arc<DriverMap>,
+    states: Arc<ConnectionStates>,
+) {
+    let result = driver.connect().await;
+    let current = drivers
+        .get(&id)
+        .is_some_and(|entry| Arc::ptr_eq(entry.value(), &driver));
+    if !current {
+        if result.is_ok() {
+            if let Err(e) = driver.disconnect().await {
+                warn!("Error disconnecting replaced driver '{}': {}", id, e);
+            }
+        }
+        return;
+    }
+    let state = match result {
+        Ok(()) => {
+            info!("Driver '{}' connected.", id);
+            ConnectionState::Connected
+        }
+        Err(e) => {
+            error!("Failed to connect driver '{}', retrying in the background: {}", id, e);
+            ConnectionState::Disconnected
+        }
+    };
+    states.insert(id, state);
+}
@@ -0,0 +1,227 @@
+use crate::drivers::traits::{
+    DeviceDriver, DriverConfig, DriverError, DriverResult, TagRequest, WriteStatus,
+};
+use crate::tags::structures::{Quality, TagValue};
+use async_trait::async_trait;
+use dashmap::DashMap;
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use tokio::time::{sleep, Duration};
+
+/// In-memory [`DeviceDriver`] for tests, built with the `mock` feature.
+///
+/// Reads answer with the values set through [`set_value`](Self::set_value),
+/// and Bad for any other address. Writes are accepted and stored, so a read
+/// afterwards returns the written value, unless an error was set for the
+/// address with [`set_write_error`](Self::set_write_error). The driver starts
+/// out connected; while it is disconnected, reads, writes and status checks
+/// fail with [`DriverError::NotConnected`].
+pub struct MockDriver {
+    config: DriverConfig,
+    values: DashMap<String, TagValue>,
+    write_errors: DashMap<String, String>,
+    written: Mutex<Vec<(String, TagValue)>>,
+    latency: Mutex<Duration>,
+    /// Error every operation fails with while set, see [`set_failure`](Self::set_failure).
+    failure: Mutex<Option<fn() -> DriverError>>,
+    connected: AtomicBool,
+    connects: AtomicUsize,
+    reads: AtomicUsize,
+    writes: AtomicUsize,
+}
+
+impl MockDriver {
+    /// A connected mock device with id `id` and no values.
+    pub fn new(id: &str) -> Self {
+        Self::with_config(DriverConfig {
+            id: id.to_string(),
+            name: id.to_string(),
+            address: "mock://".to_string(),
+            scan_rate_ms: 1000,
+            driver_type: "mock".to_string(),
+            application_name: None,
+            application_uri: None,
+            session_name: None,
+            max_message_size: None,
+            max_chunk_count: None,
+            read_batch_size: None,
+            connect_retry_attempts: None,
+            connect_retry_delay_ms: None,
+            connect_retry_backoff: None,
+            connect_retry_jitter_ms: None,
+            connect_timeout_ms: None,
+            security_policy: None,
+            security_mode: None,
+            username: None,
+            password: None,
+            pki_dir: None,
+            trust_server_certs: None,
+            create_sample_keypair: None,
+            insecure: false,
+            max_concurrent_reads: None,
+            read_timeout_ms: None,
+            critical: false,
+        })
+    }
+
+    /// A connected mock device with the given configuration, e.g. to test read
+    /// limits or timeouts.
+    pub fn with_config(config: DriverConfig) -> Self {
+        Self {
+            config,
+            values: DashMap::new(),
+            write_errors: DashMap::new(),
+            written: Mutex::new(Vec::new()),
+            latency: Mutex::new(Duration::ZERO),
+            failure: Mutex::new(None),
+            connected: AtomicBool::new(true),
+            connects: AtomicUsize::new(0),
+            reads: AtomicUsize::new(0),
+            writes: AtomicUsize::new(0),
+        }
+    }
+
+    /// Answer reads of `address` with `value`.
+    pub fn set_value(&self, address: &str, value: TagValue) {
+        self.values.insert(address.to_string(), value);
+    }
+
+    /// Reject writes to `address` with `error`; `None` accepts them again.
+    pub fn set_write_error(&self, address: &str, error: Option<&str>) {
+        match error {
+            Some(error) => self.write_errors.insert(address.to_string(), error.to_string()),
+            None => self.write_errors.remove(address).map(|(_, error)| error),
+        };
+    }
+
+    /// Delay every read and write by `latency`.
+    pub fn set_latency(&self, latency: Duration) {
+        *self.latency.lock().unwrap() = latency;
+    }
+
+    /// Fail every connect, status check, read and write with the error `failure`
+    /// returns, until cleared with `None`.
+    pub fn set_failure(&self, failure: Option<fn() -> DriverError>) {
+        *self.failure.lock().unwrap() = failure;
+    }
+
+    /// Lose the connection, as if the device went away. [`connect`](DeviceDriver::connect)
+    /// restores it.
+    pub fn drop_connection(&self) {
+        self.connected.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::SeqCst)
+    }
+
+    /// Calls of `connect`, successful or not.
+    pub fn connect_count(&self) -> usize {
+        self.connects.load(Ordering::SeqCst)
+    }
+
+    /// Calls of `read_tags`, successful or not.
+    pub fn read_count(&self) -> usize {
+        self.reads.load(Ordering::SeqCst)
+    }
+
+    /// Calls of `write_tags`, successful or not.
+    pub fn write_count(&self) -> usize {
+        self.writes.load(Ordering::SeqCst)
+    }
+
+    /// Accepted writes in order, as `(address, value)`.
+    pub fn written(&self) -> Vec<(String, TagValue)> {
+        self.written.lock().unwrap().clone()
+    }
+
+    fn check_failure(&self) -> DriverResult<()> {
+        match *self.failure.lock().unwrap() {
+            Some(failure) => Err(failure()),
+            None => Ok(()),
+        }
+    }
+
+    fn check_connected(&self) -> DriverResult<()> {
+        self.check_failure()?;
+        if self.is_connected() {
+            Ok(())
+        } else {
+            Err(DriverError::NotConnected)
+        }
+    }
+
+    async fn delay(&self) {
+        let latency = *self.latency.lock().unwrap();
+        if !latency.is_zero() {
+            sleep(latency).await;
+        }
+    }
+}
+
+#[async_trait]
+impl DeviceDriver for MockDriver {
+    fn config(&self) -> &DriverConfig {
+        &self.config
+    }
+
+    async fn connect(&self) -> DriverResult<()> {
+        self.connects.fetch_add(1, Ordering::SeqCst);
+        self.check_failure()?;
+        self.connected.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    async fn disconnect(&self) -> DriverResult<()> {
+        self.connected.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+
+    async fn check_status(&self) -> DriverResult<()> {
+        self.check_connected()
+    }
+
+    async fn read_tags(&self, tags: &[TagRequest]) -> DriverResult<HashMap<String, TagValue>> {
+        self.reads.fetch_add(1, Ordering::SeqCst);
+        self.delay().await;
+        self.check_connected()?;
+        Ok(tags
+            .iter()
+            .map(|tag| {
+                let value = self
+                    .values
+                    .get(&tag.address)
+                    .map_or_else(|| TagValue::bad(Quality::Bad), |value| value.clone());
+                (tag.address.clone(), value)
+            })
+            .collect())
+    }
+
+    async fn write_tags(
+        &self,
+        tags: HashMap<String, TagValue>,
+    ) -> DriverResult<HashMap<String, WriteStatus>> {
+        self.writes.fetch_add(1, Ordering::SeqCst);
+        self.delay().await;
+        self.check_connected()?;
+        let mut results = HashMap::with_capacity(tags.len());
+        for (address, value) in tags {
+            let status = match self.write_errors.get(&address) {
+                Some(error) => WriteStatus::failed(value.value, error.as_str()),
+                None => {
+                    self.written.lock().unwrap().push((address.clone(), value.clone()));
+                    self.values.insert(address.clone(), value.clone());
+                    WriteStatus::good(value.value)
+                }
+            };
+            results.insert(address, status);
+        }
+        Ok(results)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
@@ -1,6 +1,32 @@
 pub mod traits;
 pub mod opcua;
+pub mod health;
+pub mod breaker;
+pub mod scan_stats;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
+mod session_pool;
 
 // Potentially declare specific driver implementations later
 // pub mod modbus;
 // pub mod s7;
+
+use dashmap::DashMap;
+use std::sync::Arc;
+use traits::{DriverError, DeviceDriver, DriverConfig, DriverResult};
+
+/// Running driver instances keyed by device id. Entries are added and removed
+/// at runtime when the configuration is reloaded.
+pub type DriverMap = DashMap<String, Arc<dyn DeviceDriver + Send + Sync>>;
+
+/// Construct the driver for a device based on its `driver_type`.
+/// The driver is returned unconnected.
+pub fn build_driver(config: DriverConfig) -> DriverResult<Arc<dyn DeviceDriver + Send + Sync>> {
+    match config.driver_type.as_str() {
+        "opcua" => Ok(Arc::new(opcua::OpcUaDriver::new(config)?)),
+        other => Err(DriverError::Config(format!(
+            "Unknown driver type '{}' for device '{}' (supported: opcua)",
+            other, config.id
+        ))),
+    }
+}
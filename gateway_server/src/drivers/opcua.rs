@@ -1,359 +1,1426 @@
-use crate::drivers::traits::{OpcDriver, OpcDriverConfig, OpcDriverResult, OpcTagRequest};
-use crate::tags::structures::{Quality, TagValue, ValueVariant};
+use crate::drivers::session_pool::{self, Lease, OpenedSession, SessionKey};
+use crate::drivers::traits::{
+    DriverError, DeviceDriver, DriverConfig, DriverResult, TagRequest, WriteStatus,
+};
+use crate::tags::path::TagPath;
+use crate::tags::structures::{CoerceError, Quality, Tag, TagMetadata, TagValue, ValueVariant};
 use async_trait::async_trait;
-use opcua::client::{Client, ClientBuilder, IdentityToken, Session};
+use base64::alphabet;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use base64::Engine;
+use dashmap::DashMap;
+use opcua::client::{ClientBuilder, DataChangeCallback, IdentityToken, Session};
+use opcua::crypto::SecurityPolicy;
 use opcua::types::{
-    AttributeId, BrowseDescription, BrowseDirection, BrowseResultMask, DataValue,
-    EndpointDescription, MessageSecurityMode, NodeId, QualifiedName, ReadValueId, ReferenceTypeId,
-    TimestampsToReturn, UAString, UserTokenPolicy, Variant,
+    Array, AttributeId, BrowseDescription, BrowseDirection, BrowseResultMask, ByteString, DataValue,
+    DateTime,
+    EndpointDescription, MessageSecurityMode, MonitoredItemCreateRequest, MonitoringMode,
+    MonitoringParameters, NodeClass, NodeId, NumericRange, QualifiedName,
+    ReadValueId, ReferenceDescription, ReferenceTypeId, StatusCode, TimestampsToReturn, UAString,
+    UserTokenPolicy, Variant, WriteValue,
 };
+use serde::Serialize;
 use std::any::Any;
-use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{BuildHasher, Hasher};
 use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
-use tokio::time::sleep;
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, timeout};
 use tracing::{info, warn};
 
+/// Capacity of the channel returned by [`OpcUaDriver::subscribe_tags`].
+const SUBSCRIPTION_CHANNEL_CAPACITY: usize = 1024;
+
+/// How deep [`OpcUaDriver::discover_tags`] walks below the Objects folder.
+const DISCOVERY_MAX_DEPTH: usize = 5;
+
+const OBJECTS_FOLDER_ID: &str = "i=85";
+
+/// Nodes per read request when neither `read_batch_size` nor `max_message_size` is set.
+const DEFAULT_READ_BATCH_SIZE: usize = 1000;
+/// Rough upper bound on the encoded size of one value in a read response, used to
+/// derive a batch size from `max_message_size`.
+const ESTIMATED_READ_VALUE_SIZE: usize = 256;
+
+/// Most parsed node ids kept per driver. Addresses beyond this are parsed on every use.
+const NODE_ID_CACHE_CAPACITY: usize = 10_000;
+
+/// `CurrentWrite` bit of the AccessLevel attribute.
+const ACCESS_LEVEL_CURRENT_WRITE: u8 = 0x02;
+
+/// OPC UA `DateTime` ticks (100 ns since 1601-01-01) at the Unix epoch.
+const UNIX_EPOCH_TICKS: i64 = 116_444_736_000_000_000;
+const TICKS_PER_MILLI: i64 = 10_000;
+
+/// Decodes the base64 of opaque (`b=`) node ids, with or without `=` padding.
+const OPAQUE_NODE_ID: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Reference types that can be given to [`BrowseFilter::parse`] by name.
+const REFERENCE_TYPES: [(&str, ReferenceTypeId); 8] = [
+    ("HierarchicalReferences", ReferenceTypeId::HierarchicalReferences),
+    ("HasChild", ReferenceTypeId::HasChild),
+    ("Aggregates", ReferenceTypeId::Aggregates),
+    ("Organizes", ReferenceTypeId::Organizes),
+    ("HasComponent", ReferenceTypeId::HasComponent),
+    ("HasProperty", ReferenceTypeId::HasProperty),
+    ("HasOrderedComponent", ReferenceTypeId::HasOrderedComponent),
+    ("HasNotifier", ReferenceTypeId::HasNotifier),
+];
+
+const NODE_CLASSES: [(&str, NodeClass); 8] = [
+    ("Object", NodeClass::Object),
+    ("Variable", NodeClass::Variable),
+    ("Method", NodeClass::Method),
+    ("ObjectType", NodeClass::ObjectType),
+    ("VariableType", NodeClass::VariableType),
+    ("ReferenceType", NodeClass::ReferenceType),
+    ("DataType", NodeClass::DataType),
+    ("View", NodeClass::View),
+];
+
+/// Which references a browse follows and which kinds of node it reports.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrowseFilter {
+    /// Reference type followed, including its subtypes.
+    pub reference_type: NodeId,
+    /// Bit mask of the node classes reported; `0` reports all of them.
+    pub node_class_mask: u32,
+}
+
+impl Default for BrowseFilter {
+    /// All hierarchical references to nodes of any class.
+    fn default() -> Self {
+        Self {
+            reference_type: ReferenceTypeId::HierarchicalReferences.into(),
+            node_class_mask: 0,
+        }
+    }
+}
+
+impl BrowseFilter {
+    /// Build a filter from a reference type, given by name (e.g. `Organizes`) or
+    /// node id, and a comma-separated list of node classes (e.g. `Variable`).
+    /// Either left out keeps the default.
+    pub fn parse(reference_type: Option<&str>, node_classes: Option<&str>) -> Result<Self, String> {
+        let mut filter = Self::default();
+        if let Some(name) = reference_type {
+            filter.reference_type = match REFERENCE_TYPES.iter().find(|(n, _)| *n == name) {
+                Some((_, id)) => (*id).into(),
+                None => NodeId::from_str(name)
+                    .map_err(|_| format!("Unknown reference type '{}'", name))?,
+            };
+        }
+        for name in node_classes.into_iter().flat_map(|list| list.split(',')) {
+            let name = name.trim();
+            let class = NODE_CLASSES
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, class)| *class)
+                .ok_or_else(|| format!("Unknown node class '{}'", name))?;
+            filter.node_class_mask |= class as u32;
+        }
+        Ok(filter)
+    }
+}
+
+/// A node directly below the browsed one, see [`OpcUaDriver::browse_node`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BrowseChild {
+    pub browse_name: String,
+    /// Full node id, e.g. `ns=2;s=Temperature`, to browse further or use as a tag address.
+    pub node_id: String,
+    /// Node class such as `Object` or `Variable`.
+    pub node_class: String,
+}
+
+impl From<&ReferenceDescription> for BrowseChild {
+    fn from(reference: &ReferenceDescription) -> Self {
+        Self {
+            browse_name: reference.browse_name.name.to_string(),
+            node_id: reference.node_id.node_id.to_string(),
+            node_class: format!("{:?}", reference.node_class),
+        }
+    }
+}
+
+/// A node found while walking the address space with
+/// [`OpcUaDriver::browse_node_recursive`].
+#[derive(Debug, Clone, Serialize)]
+pub struct BrowsedNode {
+    /// Full node id, e.g. `ns=2;s=Temperature`.
+    pub node_id: String,
+    pub browse_name: String,
+    /// Node class such as `Object` or `Variable`.
+    pub node_class: String,
+    /// Browse names from the starting node down to this node's parent, joined by `/`.
+    pub parent_path: String,
+}
+
+/// A data variable found by [`OpcUaDriver::discover_tags`].
+#[derive(Debug, Clone, Serialize)]
+pub struct DiscoveredTag {
+    /// Node id to use as the tag address.
+    pub node_id: String,
+    /// Suggested tag path built from the browse names below the Objects folder.
+    pub path: TagPath,
+    /// `ValueVariant` type detected from the node's current value.
+    pub data_type: String,
+    pub display_name: String,
+    pub description: Option<String>,
+    /// Whether the server allows writing the variable's value.
+    pub writable: bool,
+}
+
+impl DiscoveredTag {
+    /// Engine tag for this variable, with its description and writability taken
+    /// from the node. The value starts out Bad until first polled.
+    pub fn to_tag(&self, driver_id: &str, poll_rate_ms: u64) -> Tag {
+        Tag {
+            path: self.path.clone(),
+            value: TagValue::bad(Quality::Bad),
+            driver_id: driver_id.to_string(),
+            driver_address: self.node_id.clone(),
+            poll_rate_ms,
+            enabled: true,
+            metadata: TagMetadata {
+                description: self
+                    .description
+                    .clone()
+                    .or_else(|| Some(self.display_name.clone())),
+                eng_unit: None,
+                eng_low: None,
+                eng_high: None,
+                raw_low: None,
+                raw_high: None,
+                deadband: None,
+                writable: self.writable,
+                hold_last_value: false,
+                alarms: Default::default(),
+                alias_of: None,
+            },
+        }
+    }
+}
+
+/// Descriptive attributes of a node, read by [`OpcUaDriver::read_node_attributes`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NodeAttributes {
+    pub node_id: String,
+    pub display_name: String,
+    pub description: Option<String>,
+    /// Name of a standard data type such as `Double`, otherwise the data type's node id.
+    /// `None` for nodes without a data type, such as objects.
+    pub data_type: Option<String>,
+    /// Whether the AccessLevel attribute allows writing the current value.
+    pub writable: bool,
+}
+
+/// Attributes read for every node by [`OpcUaDriver::node_attributes`], in this order.
+pub const NODE_ATTRIBUTES: [AttributeId; 4] = [
+    AttributeId::DisplayName,
+    AttributeId::Description,
+    AttributeId::DataType,
+    AttributeId::AccessLevel,
+];
+
+/// Attributes listed by the attributes endpoint when none are asked for.
+pub const DEFAULT_DEBUG_ATTRIBUTES: [AttributeId; 9] = [
+    AttributeId::NodeId,
+    AttributeId::NodeClass,
+    AttributeId::BrowseName,
+    AttributeId::DisplayName,
+    AttributeId::Description,
+    AttributeId::Value,
+    AttributeId::DataType,
+    AttributeId::ValueRank,
+    AttributeId::AccessLevel,
+];
+
+/// Look up an attribute by name, e.g. `DataType` (case-insensitive), or by its
+/// numeric id.
+pub fn parse_attribute(name: &str) -> Option<AttributeId> {
+    let name = name.trim();
+    if let Ok(id) = name.parse::<u32>() {
+        return AttributeId::from_u32(id).ok();
+    }
+    (1..)
+        .map_while(|id| AttributeId::from_u32(id).ok())
+        .find(|attribute| format!("{attribute:?}").eq_ignore_ascii_case(name))
+}
+
+/// Whether an AccessLevel attribute value has the `CurrentWrite` bit set, i.e.
+/// clients may write the node's current value.
+pub fn access_level_writable(access_level: u8) -> bool {
+    access_level & ACCESS_LEVEL_CURRENT_WRITE != 0
+}
+
+/// Next value of a SplitMix64 sequence, enough to spread out retry delays.
+fn next_random(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// A parsed tag address.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeAddress {
+    pub node_id: NodeId,
+    /// The array elements to read; `NumericRange::None` for the whole value.
+    pub index_range: NumericRange,
+}
+
 pub struct OpcUaDriver {
-    config: OpcDriverConfig,
-    client: Mutex<Option<Client>>,
-    session: Mutex<Option<Arc<Session>>>,
-    event_loop: Mutex<Option<tokio::task::JoinHandle<opcua::types::StatusCode>>>,
+    config: DriverConfig,
+    /// This driver's share of the pooled session for its endpoint.
+    lease: Mutex<Option<Lease>>,
+    /// `true` while a session is established; flips to `false` on disconnect or
+    /// when the session event loop ends, which closes any subscription channels.
+    session_alive: Arc<watch::Sender<bool>>,
+    /// Copies the end of a shared session into `session_alive`.
+    alive_forwarder: Mutex<Option<JoinHandle<()>>>,
+    /// Parsed tag addresses, so polling doesn't reparse them every cycle.
+    node_ids: DashMap<String, NodeAddress>,
+    /// State of the generator behind `connect_retry_jitter_ms`, seeded
+    /// differently for every driver unless set with
+    /// [`with_retry_jitter_seed`](Self::with_retry_jitter_seed).
+    retry_jitter: Mutex<u64>,
 }
 
 impl OpcUaDriver {
-    pub fn new(config: OpcDriverConfig) -> Result<Self, Box<dyn std::error::Error>> {
+    pub fn new(config: DriverConfig) -> DriverResult<Self> {
         Ok(Self {
             config,
-            client: Mutex::new(None),
-            session: Mutex::new(None),
-            event_loop: Mutex::new(None),
+            lease: Mutex::new(None),
+            session_alive: Arc::new(watch::channel(false).0),
+            alive_forwarder: Mutex::new(None),
+            node_ids: DashMap::new(),
+            // Every RandomState has its own random keys
+            retry_jitter: Mutex::new(RandomState::new().build_hasher().finish()),
         })
     }
 
-    fn parse_node_id(
-        node_id_str: &str,
-    ) -> Result<NodeId, Box<dyn std::error::Error + Send + Sync>> {
-        NodeId::from_str(node_id_str)
-            .map_err(|e| format!("Invalid NodeId '{}': {e:?}", node_id_str).into())
+    /// Seed the retry jitter, making the delays reproducible.
+    pub fn with_retry_jitter_seed(self, seed: u64) -> Self {
+        *self.retry_jitter.lock().unwrap() = seed;
+        self
     }
 
-    fn data_value_to_tag_value(dv: &DataValue) -> TagValue {
-        let quality = match dv.status {
-            Some(status) => {
-                if status.is_good() {
-                    Quality::Good
-                } else {
-                    Quality::Bad
-                }
+    /// Delays slept between the attempts of the next connect: `connect_retry_delay_ms`
+    /// multiplied by `connect_retry_backoff` after each attempt, plus a random
+    /// extra of up to `connect_retry_jitter_ms`. One per retry; a zero delay
+    /// stays zero.
+    pub fn retry_delays(&self) -> Vec<Duration> {
+        let retries = self.config.connect_retry_attempts.unwrap_or(0);
+        let mut delay = self.config.connect_retry_delay_ms.unwrap_or(0);
+        let backoff = self.config.connect_retry_backoff.unwrap_or(2.0);
+        let jitter = self.config.connect_retry_jitter_ms.unwrap_or(0);
+        let mut state = self.retry_jitter.lock().unwrap();
+        (0..retries)
+            .map(|_| {
+                let extra = match (delay, jitter) {
+                    (0, _) | (_, 0) => 0,
+                    _ => next_random(&mut state) % (jitter + 1),
+                };
+                let current = Duration::from_millis(delay + extra);
+                delay = (delay as f64 * backoff) as u64;
+                current
+            })
+            .collect()
+    }
+
+    /// Number of nodes sent per `Read` request: `read_batch_size` if configured,
+    /// otherwise as many values as are estimated to fit in `max_message_size`.
+    pub fn read_batch_size(&self) -> usize {
+        let size = match (self.config.read_batch_size, self.config.max_message_size) {
+            (Some(size), _) => size,
+            (None, Some(max_message_size)) if max_message_size > 0 => {
+                max_message_size / ESTIMATED_READ_VALUE_SIZE
+            }
+            _ => DEFAULT_READ_BATCH_SIZE,
+        };
+        size.max(1)
+    }
+
+    /// Parse a node id: an optional `ns=<index>;` followed by one of
+    ///
+    /// - `i=85`, a numeric id
+    /// - `s=Temperature`, a string id, taken verbatim up to the end
+    /// - `g=72962b91-fa75-4ae6-8d28-b404dc7daf63`, a GUID in either case, with
+    ///   or without hyphens or braces
+    /// - `b=M/RbKBsRVkePCePcx24oRA==`, an opaque id as standard base64, where
+    ///   the `=` padding may be left out
+    ///
+    /// Node ids are displayed in the same forms, so browsed node ids parse back
+    /// to the same node.
+    pub fn parse_node_id(node_id_str: &str) -> DriverResult<NodeId> {
+        let invalid = || DriverError::InvalidNodeId(node_id_str.to_string());
+        let (namespace, identifier) = match node_id_str
+            .strip_prefix("ns=")
+            .and_then(|rest| rest.split_once(';'))
+        {
+            Some((namespace, identifier)) => {
+                (namespace.parse::<u16>().map_err(|_| invalid())?, identifier)
+            }
+            None => (0, node_id_str),
+        };
+        match identifier.strip_prefix("b=") {
+            // The types crate only accepts padded base64
+            Some(encoded) if !encoded.is_empty() => {
+                let bytes = OPAQUE_NODE_ID.decode(encoded).map_err(|_| invalid())?;
+                Ok(NodeId::new(namespace, ByteString::from(bytes)))
+            }
+            _ => NodeId::from_str(node_id_str).map_err(|_| invalid()),
+        }
+    }
+
+    /// Parse a tag address: a node id, optionally followed by the array elements
+    /// to read, e.g. `ns=2;s=Array[3]` or `ns=2;s=Array[0:5]`. A bracketed suffix
+    /// that isn't a valid index range is taken as part of the node id.
+    pub fn parse_address(address: &str) -> DriverResult<NodeAddress> {
+        let (node_id, index_range) = match address
+            .strip_suffix(']')
+            .and_then(|rest| rest.rsplit_once('['))
+            .and_then(|(node_id, range)| Some((node_id, NumericRange::from_str(range).ok()?)))
+        {
+            Some((node_id, range)) if range.has_range() => (node_id, range),
+            _ => (address, NumericRange::None),
+        };
+        let node_id = Self::parse_node_id(node_id)
+            .map_err(|_| DriverError::InvalidNodeId(address.to_string()))?;
+        Ok(NodeAddress { node_id, index_range })
+    }
+
+    /// Parse a tag address, reusing the result for addresses seen before.
+    /// Addresses never change meaning, so entries are never invalidated.
+    pub fn resolve(&self, address: &str) -> DriverResult<NodeAddress> {
+        if let Some(resolved) = self.node_ids.get(address) {
+            return Ok(resolved.clone());
+        }
+        let resolved = Self::parse_address(address)?;
+        if self.node_ids.len() < NODE_ID_CACHE_CAPACITY {
+            self.node_ids.insert(address.to_string(), resolved.clone());
+        }
+        Ok(resolved)
+    }
+
+    /// The node a tag address refers to, ignoring any index range.
+    pub fn node_id(&self, address: &str) -> DriverResult<NodeId> {
+        self.resolve(address).map(|resolved| resolved.node_id)
+    }
+
+    /// Number of addresses in the node id cache.
+    pub fn cached_node_ids(&self) -> usize {
+        self.node_ids.len()
+    }
+
+    fn security_policy(&self) -> DriverResult<SecurityPolicy> {
+        let name = self.config.security_policy.as_deref().unwrap_or("None");
+        match SecurityPolicy::from_str(name) {
+            Ok(SecurityPolicy::Unknown) | Err(_) => Err(DriverError::Config(format!(
+                "Unknown security policy '{}' (expected None, Basic128Rsa15, Basic256, \
+                 Basic256Sha256, Aes128Sha256RsaOaep or Aes256Sha256RsaPss)",
+                name
+            ))),
+            Ok(policy) => Ok(policy),
+        }
+    }
+
+    fn security_mode(&self) -> DriverResult<MessageSecurityMode> {
+        let name = self.config.security_mode.as_deref().unwrap_or("None");
+        match name.to_ascii_lowercase().as_str() {
+            "none" => Ok(MessageSecurityMode::None),
+            "sign" => Ok(MessageSecurityMode::Sign),
+            "signandencrypt" => Ok(MessageSecurityMode::SignAndEncrypt),
+            _ => Err(DriverError::Config(format!(
+                "Unknown security mode '{}' (expected None, Sign or SignAndEncrypt)",
+                name
+            ))),
+        }
+    }
+
+    /// Whether unknown server certificates are trusted rather than rejected.
+    /// Only `insecure` devices trust them unless `trust_server_certs` is set.
+    pub fn trusts_server_certs(&self) -> bool {
+        self.config.trust_server_certs.unwrap_or(self.config.insecure)
+    }
+
+    /// Whether a client keypair is generated when `pki_dir` has none. Only
+    /// `insecure` devices generate one unless `create_sample_keypair` is set.
+    pub fn creates_sample_keypair(&self) -> bool {
+        self.config.create_sample_keypair.unwrap_or(self.config.insecure)
+    }
+
+    /// Client builder for this device's sessions: its application identity,
+    /// message limits and certificate handling.
+    pub fn client_builder(&self) -> ClientBuilder {
+        let cfg = &self.config;
+        let builder = ClientBuilder::new()
+            .application_name(cfg.application_name.as_deref().unwrap_or("ForgeIO OPC UA Client"))
+            .application_uri(cfg.application_uri.as_deref().unwrap_or("urn:forgeio:client"))
+            .session_name(cfg.session_name.as_deref().unwrap_or("ForgeIOSession"))
+            .trust_server_certs(self.trusts_server_certs())
+            .create_sample_keypair(self.creates_sample_keypair())
+            .max_message_size(cfg.max_message_size.unwrap_or(0))
+            .max_chunk_count(cfg.max_chunk_count.unwrap_or(0));
+        match &cfg.pki_dir {
+            Some(pki_dir) => builder.pki_dir(pki_dir),
+            None => builder,
+        }
+    }
+
+    /// Identity token used when activating the session: a UserName token when
+    /// both `username` and `password` are configured, anonymous otherwise.
+    pub fn identity_token(&self) -> IdentityToken {
+        match (&self.config.username, &self.config.password) {
+            (Some(username), Some(password)) => {
+                IdentityToken::UserName(username.clone(), password.clone().into())
             }
-            None => Quality::Bad,
+            _ => IdentityToken::Anonymous,
+        }
+    }
+
+    /// Drivers with the same endpoint, security settings and credentials share
+    /// one session.
+    fn session_key(&self) -> DriverResult<SessionKey> {
+        Ok(SessionKey {
+            address: self.config.address.clone(),
+            security_policy: self.security_policy()?.to_str().to_string(),
+            security_mode: format!("{:?}", self.security_mode()?),
+            pki_dir: self.config.pki_dir.clone(),
+            trust_server_certs: self.trusts_server_certs(),
+            create_sample_keypair: self.creates_sample_keypair(),
+            username: self.config.username.clone(),
+            password: self.config.password.clone(),
+        })
+    }
+
+    fn session(&self) -> DriverResult<Arc<Session>> {
+        let lease = self.lease.lock().unwrap();
+        let lease = lease.as_ref().ok_or(DriverError::NotConnected)?;
+        Ok(Arc::clone(&lease.session))
+    }
+
+    /// Whether both drivers are connected through the same session.
+    pub fn shares_session_with(&self, other: &OpcUaDriver) -> bool {
+        match (self.session(), other.session()) {
+            (Ok(a), Ok(b)) => Arc::ptr_eq(&a, &b),
+            _ => false,
+        }
+    }
+
+    /// Number of connected drivers using this driver's session, including itself.
+    pub async fn session_users(&self) -> usize {
+        let lease = self.lease.lock().unwrap().clone();
+        match lease {
+            Some(lease) => session_pool::users(&lease).await,
+            None => 0,
+        }
+    }
+
+    /// Pair each read request with the value the server returned for it. A server
+    /// that returns fewer values than requested leaves the remaining addresses
+    /// `Bad` instead of silently dropping them; extra values are ignored.
+    pub fn read_results(
+        requests: &[TagRequest],
+        data_values: &[DataValue],
+    ) -> HashMap<String, TagValue> {
+        if data_values.len() != requests.len() {
+            warn!(
+                "OPC UA server returned {} values for {} reads",
+                data_values.len(),
+                requests.len()
+            );
+        }
+        requests
+            .iter()
+            .enumerate()
+            .map(|(i, request)| {
+                let value = match data_values.get(i) {
+                    Some(dv) => Self::data_value_to_tag_value(dv),
+                    None => TagValue::bad(Quality::Bad),
+                };
+                (request.address.clone(), value)
+            })
+            .collect()
+    }
+
+    /// Pair each written value with the status the server returned for it. A
+    /// server that returns fewer statuses than values leaves the remaining
+    /// addresses failed instead of silently dropping them.
+    pub fn write_results(
+        requested: Vec<(String, TagValue)>,
+        statuses: &[StatusCode],
+    ) -> HashMap<String, WriteStatus> {
+        if statuses.len() != requested.len() {
+            warn!(
+                "OPC UA server returned {} statuses for {} writes",
+                statuses.len(),
+                requested.len()
+            );
+        }
+        requested
+            .into_iter()
+            .enumerate()
+            .map(|(i, (address, value))| {
+                let status = match statuses.get(i) {
+                    Some(status) if status.is_good() => WriteStatus::good(value.value),
+                    Some(status) => {
+                        warn!("OPC UA write to '{}' failed: {}", address, status);
+                        WriteStatus::failed(value.value, status.to_string())
+                    }
+                    None => WriteStatus::failed(value.value, "no status returned by the server"),
+                };
+                (address, status)
+            })
+            .collect()
+    }
+
+    /// A single element read with an index range like `[3]` comes back as a
+    /// one-element array; unwrap it so the tag holds the element itself.
+    pub fn select_element(mut dv: DataValue, index_range: &NumericRange) -> DataValue {
+        if let (NumericRange::Index(_), Some(Variant::Array(array))) = (index_range, &mut dv.value) {
+            if array.values.len() == 1 {
+                dv.value = array.values.pop();
+            }
+        }
+        dv
+    }
+
+    /// Convert an OPC UA data value (value and status) into a tag value.
+    pub fn data_value_to_tag_value(dv: &DataValue) -> TagValue {
+        let quality = match dv.status {
+            Some(status) if status.is_good() => Quality::Good,
+            // Degraded but usable, e.g. UncertainLastUsableValue
+            Some(status) if status.is_uncertain() => Quality::Uncertain,
+            _ => Quality::Bad,
         };
 
         let value_variant = match &dv.value {
-            Some(variant) => match variant {
-                Variant::Boolean(b) => ValueVariant::Bool(*b),
-                Variant::SByte(i) => ValueVariant::Int(*i as i64),
-                Variant::Byte(u) => ValueVariant::UInt(*u as u64),
-                Variant::Int16(i) => ValueVariant::Int(*i as i64),
-                Variant::UInt16(u) => ValueVariant::UInt(*u as u64),
-                Variant::Int32(i) => ValueVariant::Int(*i as i64),
-                Variant::UInt32(u) => ValueVariant::UInt(*u as u64),
-                Variant::Int64(i) => ValueVariant::Int(*i),
-                Variant::UInt64(u) => ValueVariant::UInt(*u),
-                Variant::Float(f) => ValueVariant::Float(*f as f64),
-                Variant::Double(d) => ValueVariant::Float(*d),
-                Variant::String(s) => ValueVariant::String(s.to_string()),
-                Variant::LocalizedText(text) => ValueVariant::String(text.text.to_string()),
-                _ => ValueVariant::Null,
-            },
+            Some(variant) => Self::variant_to_value_variant(variant),
             None => ValueVariant::Null,
         };
 
-        TagValue::new(value_variant, quality)
+        // Prefer the time the device sampled the value, then the server's time
+        let timestamp = [&dv.source_timestamp, &dv.server_timestamp]
+            .into_iter()
+            .flatten()
+            .find(|ts| !ts.is_null())
+            .and_then(|ts| u64::try_from(ts.as_chrono().timestamp_millis()).ok());
+        match timestamp {
+            Some(ts) => TagValue::with_timestamp(value_variant, quality, ts),
+            None => TagValue::new(value_variant, quality),
+        }
     }
 
-    #[allow(dead_code)]
-    fn tag_value_to_variant(tv: &TagValue) -> Variant {
-        match &tv.value {
+    fn variant_to_value_variant(variant: &Variant) -> ValueVariant {
+        match variant {
+            Variant::Boolean(b) => ValueVariant::Bool(*b),
+            Variant::SByte(i) => ValueVariant::Int(*i as i64),
+            Variant::Byte(u) => ValueVariant::UInt(*u as u64),
+            Variant::Int16(i) => ValueVariant::Int(*i as i64),
+            Variant::UInt16(u) => ValueVariant::UInt(*u as u64),
+            Variant::Int32(i) => ValueVariant::Int(*i as i64),
+            Variant::UInt32(u) => ValueVariant::UInt(*u as u64),
+            Variant::Int64(i) => ValueVariant::Int(*i),
+            Variant::UInt64(u) => ValueVariant::UInt(*u),
+            Variant::Float(f) => ValueVariant::Float(*f as f64),
+            Variant::Double(d) => ValueVariant::Float(*d),
+            Variant::String(s) => ValueVariant::String(s.to_string()),
+            Variant::LocalizedText(text) => ValueVariant::String(text.text.to_string()),
+            Variant::NodeId(id) => ValueVariant::String(id.to_string()),
+            Variant::QualifiedName(name) => ValueVariant::String(name.to_string()),
+            Variant::DateTime(dt) => ValueVariant::DateTime(dt.as_chrono().timestamp_millis()),
+            Variant::Array(array) => ValueVariant::Array(
+                array.values.iter().map(Self::variant_to_value_variant).collect(),
+            ),
+            _ => ValueVariant::Null,
+        }
+    }
+
+    /// Convert a tag value into the OPC UA variant used when writing it.
+    pub fn tag_value_to_variant(tv: &TagValue) -> Result<Variant, CoerceError> {
+        Self::value_to_variant(&tv.value)
+    }
+
+    fn value_to_variant(value: &ValueVariant) -> Result<Variant, CoerceError> {
+        let invalid = |target| CoerceError { value: value.clone(), target };
+        let variant = match value {
             ValueVariant::Bool(b) => Variant::Boolean(*b),
-            ValueVariant::Int(i) => Variant::Int32(*i as i32),
-            ValueVariant::UInt(u) => Variant::UInt32(*u as u32),
+            // Integers are written as 32-bit; larger ones are refused, not truncated
+            ValueVariant::Int(i) => {
+                Variant::Int32(i32::try_from(*i).map_err(|_| invalid("Int32"))?)
+            }
+            ValueVariant::UInt(u) => {
+                Variant::UInt32(u32::try_from(*u).map_err(|_| invalid("UInt32"))?)
+            }
             ValueVariant::Float(f) => Variant::Double(*f),
             ValueVariant::String(s) => Variant::String(UAString::from(s.clone())),
-            _ => Variant::Empty,
-        }
+            ValueVariant::DateTime(ms) => Variant::from(DateTime::from(
+                ms.saturating_mul(TICKS_PER_MILLI).saturating_add(UNIX_EPOCH_TICKS),
+            )),
+            // Elements must all convert to the same OPC UA type, which an empty
+            // array doesn't tell
+            ValueVariant::Array(elements) => {
+                let values = elements
+                    .iter()
+                    .map(Self::value_to_variant)
+                    .collect::<Result<Vec<_>, _>>()?;
+                let value_type = values
+                    .first()
+                    .and_then(Variant::scalar_type_id)
+                    .ok_or_else(|| invalid("a non-empty Array"))?;
+                let array = Array::new(value_type, values)
+                    .map_err(|_| invalid("an Array of one element type"))?;
+                Variant::Array(Box::new(array))
+            }
+            ValueVariant::Null => Variant::Empty,
+        };
+        Ok(variant)
     }
 
-    pub async fn browse_node(&self, node_id_str: &str) -> OpcDriverResult<Vec<String>> {
-        let session = {
-            let guard = self.session.lock().unwrap();
-            guard.clone().ok_or("not connected")?
-        };
+    async fn browse_references(
+        &self,
+        node_id: NodeId,
+        filter: &BrowseFilter,
+    ) -> DriverResult<Vec<ReferenceDescription>> {
+        let session = self.session()?;
 
-        let node_id = Self::parse_node_id(node_id_str)?;
         let browse_desc = BrowseDescription {
             node_id,
             browse_direction: BrowseDirection::Forward,
-            reference_type_id: ReferenceTypeId::HierarchicalReferences.into(),
+            reference_type_id: filter.reference_type.clone(),
             include_subtypes: true,
-            node_class_mask: 0,
+            node_class_mask: filter.node_class_mask,
             result_mask: BrowseResultMask::All as u32,
         };
 
         let results = session
             .browse(&[browse_desc], 0, None)
             .await
-            .map_err(|e| format!("browse error: {e:?}"))?;
+            .map_err(|e| DriverError::Protocol(format!("browse error: {e:?}")))?;
 
-        let mut names = Vec::new();
-        if let Some(res) = results.get(0) {
-            if let Some(refs) = &res.references {
-                for reference in refs {
-                    names.push(reference.browse_name.name.to_string());
-                }
-            }
-        }
-        Ok(names)
+        Ok(results
+            .into_iter()
+            .next()
+            .and_then(|res| res.references)
+            .unwrap_or_default())
     }
 
-    pub async fn discover_tags(&self) -> OpcDriverResult<Vec<String>> {
-        // Start browsing from the Objects folder (ns=0;i=85)
-        let mut discovered_tags = Vec::new();
-        Box::pin(self.discover_tags_recursive("ns=0;i=85", &mut discovered_tags, 0, 3)).await?;
-        Ok(discovered_tags)
+    /// Read the DisplayName, Description, DataType and AccessLevel attributes of a node.
+    pub async fn read_node_attributes(&self, node_id: &str) -> DriverResult<NodeAttributes> {
+        let node_id = Self::parse_node_id(node_id)?;
+        let attributes = self.read_attributes(vec![node_id]).await?;
+        attributes.into_iter().next().ok_or_else(|| {
+            DriverError::Protocol("OPC UA server returned no attributes for the node".to_string())
+        })
     }
 
-    fn discover_tags_recursive<'a>(
-        &'a self,
-        node_id: &'a str,
-        discovered: &'a mut Vec<String>,
-        depth: usize,
-        max_depth: usize,
-    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = OpcDriverResult<()>> + 'a + Send>> {
-        Box::pin(async move {
-            if depth > max_depth {
-                return Ok(());
-            }
+    /// Read any one attribute of a node, e.g. its DataType for diagnostics. The
+    /// quality and timestamps come from the server's answer, so an attribute the
+    /// node doesn't have reads as `Bad`. Node ids and qualified names are
+    /// returned as strings.
+    pub async fn read_attribute(&self, node_id: &str, attribute: AttributeId) -> DriverResult<TagValue> {
+        let mut values = self.read_attribute_values(node_id, &[attribute]).await?;
+        Ok(values.remove(0))
+    }
 
-            let children = self.browse_node(node_id).await?;
-            for child in children {
-                // Try to construct a potential node ID - this is simplified
-                let child_node_id = format!("ns=2;s={}", child);
-                
-                // Check if this looks like a data variable by trying to read it
-                if self.is_data_variable(&child_node_id).await.unwrap_or(false) {
-                    discovered.push(child);
-                }
+    /// Read several attributes of a node in one request, in the order given.
+    pub async fn read_attribute_values(
+        &self,
+        node_id: &str,
+        attributes: &[AttributeId],
+    ) -> DriverResult<Vec<TagValue>> {
+        let node_id = Self::parse_node_id(node_id)?;
+        let session = self.session()?;
 
-                // Recurse for non-data variables
-                if depth < max_depth && !self.is_data_variable(&child_node_id).await.unwrap_or(true) {
-                    let _ = self.discover_tags_recursive(&child_node_id, discovered, depth + 1, max_depth).await;
-                }
-            }
-            Ok(())
-        })
+        let read_ids: Vec<ReadValueId> = attributes
+            .iter()
+            .map(|attribute| ReadValueId {
+                node_id: node_id.clone(),
+                attribute_id: *attribute as u32,
+                index_range: Default::default(),
+                data_encoding: QualifiedName::null(),
+            })
+            .collect();
+        let data_values = self
+            .read_values(&session, &read_ids, TimestampsToReturn::Both)
+            .await?;
+        Ok((0..attributes.len())
+            .map(|i| match data_values.get(i) {
+                // Servers leave out the status of attributes other than Value when it is Good
+                Some(dv) => Self::data_value_to_tag_value(&DataValue {
+                    status: Some(dv.status.unwrap_or(StatusCode::Good)),
+                    ..dv.clone()
+                }),
+                None => TagValue::bad(Quality::Bad),
+            })
+            .collect())
     }
 
-    async fn is_data_variable(&self, node_id: &str) -> OpcDriverResult<bool> {
-        let session = {
-            let guard = self.session.lock().unwrap();
-            guard.clone().ok_or("not connected")?
-        };
+    /// Send one read request, giving up after the configured `read_timeout_ms`
+    /// so a stuck session can't hang a poll or an API request.
+    async fn read_values(
+        &self,
+        session: &Session,
+        read_ids: &[ReadValueId],
+        timestamps: TimestampsToReturn,
+    ) -> DriverResult<Vec<DataValue>> {
+        timeout(self.config.read_timeout(), session.read(read_ids, timestamps, 0.0))
+            .await
+            .map_err(|_| DriverError::Timeout)?
+            .map_err(|e| DriverError::Protocol(format!("read error: {e:?}")))
+    }
 
-        let node_id = Self::parse_node_id(node_id)?;
-        let read_id = ReadValueId {
-            node_id,
-            attribute_id: AttributeId::Value as u32,
-            index_range: Default::default(),
-            data_encoding: QualifiedName::null(),
+    /// Read the attributes of many nodes, batched like value reads. Attributes a
+    /// node doesn't have (e.g. the DataType of an object) are left empty.
+    async fn read_attributes(&self, node_ids: Vec<NodeId>) -> DriverResult<Vec<NodeAttributes>> {
+        let session = self.session()?;
+        let mut attributes = Vec::with_capacity(node_ids.len());
+        // Keep each node's attributes in one request
+        let nodes_per_request = (self.read_batch_size() / NODE_ATTRIBUTES.len()).max(1);
+        for batch in node_ids.chunks(nodes_per_request) {
+            let read_ids: Vec<ReadValueId> = batch
+                .iter()
+                .flat_map(|node_id| {
+                    NODE_ATTRIBUTES.iter().map(|attribute| ReadValueId {
+                        node_id: node_id.clone(),
+                        attribute_id: *attribute as u32,
+                        index_range: Default::default(),
+                        data_encoding: QualifiedName::null(),
+                    })
+                })
+                .collect();
+            let data_values = self
+                .read_values(&session, &read_ids, TimestampsToReturn::Neither)
+                .await?;
+            attributes.extend(Self::node_attributes(batch, &data_values)?);
+        }
+        Ok(attributes)
+    }
+
+    /// Pair the nodes of one attribute read with the values the server returned,
+    /// [`NODE_ATTRIBUTES`] per node. A server that returns a different number of
+    /// values is an error, since the values could not be matched to their nodes.
+    pub fn node_attributes(
+        node_ids: &[NodeId],
+        data_values: &[DataValue],
+    ) -> DriverResult<Vec<NodeAttributes>> {
+        let expected = node_ids.len() * NODE_ATTRIBUTES.len();
+        if data_values.len() != expected {
+            return Err(DriverError::Protocol(format!(
+                "OPC UA server returned {} values for {} attribute reads",
+                data_values.len(),
+                expected
+            )));
+        }
+
+        let text = |dv: &DataValue| match &dv.value {
+            Some(Variant::LocalizedText(text)) if !text.text.is_empty() => {
+                Some(text.text.to_string())
+            }
+            _ => None,
         };
+        Ok(node_ids
+            .iter()
+            .zip(data_values.chunks(NODE_ATTRIBUTES.len()))
+            .map(|(node_id, values)| {
+                let data_type = match values[2].value.as_ref() {
+                    Some(Variant::NodeId(id)) => Some(match id.as_data_type_id() {
+                        Ok(data_type) => format!("{data_type:?}"),
+                        Err(_) => id.to_string(),
+                    }),
+                    _ => None,
+                };
+                let writable = matches!(
+                    values[3].value.as_ref(),
+                    Some(Variant::Byte(level)) if access_level_writable(*level)
+                );
+                NodeAttributes {
+                    node_id: node_id.to_string(),
+                    display_name: text(&values[0]).unwrap_or_default(),
+                    description: text(&values[1]),
+                    data_type,
+                    writable,
+                }
+            })
+            .collect())
+    }
+
+    /// Call a method of an object (e.g. resetting a counter) and return its output arguments.
+    /// Arguments are converted the same way as written tag values.
+    pub async fn call_method(
+        &self,
+        object_id: &str,
+        method_id: &str,
+        args: Vec<ValueVariant>,
+    ) -> DriverResult<Vec<ValueVariant>> {
+        let object_id = Self::parse_node_id(object_id)?;
+        let method_id = Self::parse_node_id(method_id)?;
+        let session = self.session()?;
 
-        match session.read(&[read_id], TimestampsToReturn::Neither, 0.0).await {
-            Ok(values) => Ok(!values.is_empty() && values[0].value.is_some()),
-            Err(_) => Ok(false),
+        let inputs = args
+            .iter()
+            .map(Self::value_to_variant)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| DriverError::InvalidValue(e.to_string()))?;
+        let result = session
+            .call_one((object_id, method_id, Some(inputs)))
+            .await
+            .map_err(|e| DriverError::Protocol(format!("call error: {e:?}")))?;
+        if !result.status_code.is_good() {
+            return Err(DriverError::Protocol(format!(
+                "method call failed: {}",
+                result.status_code
+            )));
         }
+
+        Ok(result
+            .output_arguments
+            .unwrap_or_default()
+            .iter()
+            .map(Self::variant_to_value_variant)
+            .collect())
     }
-}
 
-#[async_trait]
-impl OpcDriver for OpcUaDriver {
-    fn config(&self) -> &OpcDriverConfig {
-        &self.config
+    /// The nodes below `node_id_str` reached through hierarchical references.
+    pub async fn browse_node(&self, node_id_str: &str) -> DriverResult<Vec<BrowseChild>> {
+        self.browse_node_filtered(node_id_str, &BrowseFilter::default()).await
     }
 
-    async fn connect(&self) -> OpcDriverResult<()> {
-        if self.client.lock().unwrap().is_some() {
-            return Ok(());
+    /// The nodes below `node_id_str` that match `filter`.
+    pub async fn browse_node_filtered(
+        &self,
+        node_id_str: &str,
+        filter: &BrowseFilter,
+    ) -> DriverResult<Vec<BrowseChild>> {
+        let node_id = Self::parse_node_id(node_id_str)?;
+        let references = self.browse_references(node_id, filter).await?;
+        Ok(references.iter().map(BrowseChild::from).collect())
+    }
+
+    /// Walk the hierarchy below `root` breadth-first, following hierarchical
+    /// references down to `max_depth` levels. Nodes reachable through more than
+    /// one path are reported once.
+    pub async fn browse_node_recursive(
+        &self,
+        root: &str,
+        max_depth: usize,
+    ) -> DriverResult<Vec<BrowsedNode>> {
+        let root_id = Self::parse_node_id(root)?;
+        let mut visited = HashSet::from([root_id.clone()]);
+        let mut queue = VecDeque::from([(root_id, String::new(), 0)]);
+        let mut nodes = Vec::new();
+
+        while let Some((node_id, path, depth)) = queue.pop_front() {
+            if depth >= max_depth {
+                continue;
+            }
+            for reference in self.browse_references(node_id, &BrowseFilter::default()).await? {
+                let child_id = reference.node_id.node_id;
+                if !visited.insert(child_id.clone()) {
+                    continue;
+                }
+                let browse_name = reference.browse_name.name.to_string();
+                let child_path = if path.is_empty() {
+                    browse_name.clone()
+                } else {
+                    format!("{}/{}", path, browse_name)
+                };
+                nodes.push(BrowsedNode {
+                    node_id: child_id.to_string(),
+                    browse_name,
+                    node_class: format!("{:?}", reference.node_class),
+                    parent_path: path.clone(),
+                });
+                queue.push_back((child_id, child_path, depth + 1));
+            }
+        }
+        Ok(nodes)
+    }
+
+    /// Subscribe to data changes on the given tags.
+    ///
+    /// Creates a subscription publishing at the driver's `scan_rate_ms` with one
+    /// monitored item per address. Each data-change notification is forwarded
+    /// through the returned channel keyed by the tag address. The channel is
+    /// closed when the session drops or the driver disconnects; either that or
+    /// dropping the receiver deletes the subscription on the server.
+    pub async fn subscribe_tags(
+        &self,
+        tags: &[TagRequest],
+    ) -> DriverResult<mpsc::Receiver<(String, TagValue)>> {
+        let session = self.session()?;
+
+        // Keyed by node and index range, so elements of one array are told apart
+        let mut addresses = HashMap::new();
+        let mut items = Vec::with_capacity(tags.len());
+        for t in tags {
+            let NodeAddress { node_id, index_range } = self.resolve(&t.address)?;
+            addresses.insert((node_id.clone(), index_range.to_string()), t.address.clone());
+            items.push(MonitoredItemCreateRequest::new(
+                ReadValueId {
+                    node_id,
+                    attribute_id: AttributeId::Value as u32,
+                    index_range,
+                    data_encoding: QualifiedName::null(),
+                },
+                MonitoringMode::Reporting,
+                MonitoringParameters::default(),
+            ));
         }
 
+        // The notification callback runs synchronously inside the session event
+        // loop, so hand values off to a forwarding task instead of blocking it.
+        let (notify_tx, mut notify_rx) = mpsc::unbounded_channel();
+        let subscription_id = session
+            .create_subscription(
+                Duration::from_millis(self.config.scan_rate_ms),
+                30,
+                10,
+                0,
+                0,
+                true,
+                DataChangeCallback::new(move |dv, item| {
+                    let monitored = item.item_to_monitor();
+                    let key = (monitored.node_id.clone(), monitored.index_range.to_string());
+                    if let Some(address) = addresses.get(&key) {
+                        let dv = Self::select_element(dv, &monitored.index_range);
+                        let _ = notify_tx
+                            .send((address.clone(), Self::data_value_to_tag_value(&dv)));
+                    }
+                }),
+            )
+            .await
+            .map_err(|e| DriverError::Protocol(format!("create subscription error: {e:?}")))?;
+
+        session
+            .create_monitored_items(subscription_id, TimestampsToReturn::Both, items)
+            .await
+            .map_err(|e| DriverError::Protocol(format!("create monitored items error: {e:?}")))?;
+
+        info!(
+            "OPC UA subscription {} created for {} tags on {}",
+            subscription_id,
+            tags.len(),
+            self.config.address
+        );
+
+        let (tx, rx) = mpsc::channel(SUBSCRIPTION_CHANNEL_CAPACITY);
+        let mut alive = self.session_alive.subscribe();
+        tokio::spawn(async move {
+            let session_closed = async move {
+                let _ = alive.wait_for(|alive| !*alive).await;
+            };
+            tokio::pin!(session_closed);
+            loop {
+                let update = tokio::select! {
+                    update = notify_rx.recv() => update,
+                    _ = &mut session_closed => None,
+                };
+                let Some(update) = update else { break };
+                if tx.send(update).await.is_err() {
+                    // Receiver dropped, nobody is listening anymore.
+                    break;
+                }
+            }
+            // The session may outlive this driver when other drivers share it
+            let _ = session.delete_subscription(subscription_id).await;
+        });
+
+        Ok(rx)
+    }
+
+    /// Open a new session to the configured endpoint, retrying as configured.
+    async fn open_session(&self) -> DriverResult<OpenedSession> {
         let cfg = self.config.clone();
-        let max_retries = cfg.connect_retry_attempts.unwrap_or(0);
-        let mut delay = cfg.connect_retry_delay_ms.unwrap_or(0);
-        let backoff = cfg.connect_retry_backoff.unwrap_or(2.0);
+        let delays = self.retry_delays();
         let timeout_ms = cfg.connect_timeout_ms.unwrap_or(5_000);
+        let security_policy = self.security_policy()?;
+        let security_mode = self.security_mode()?;
         let mut attempt = 0;
 
         loop {
             let attempt_fut = async {
-                let mut client = ClientBuilder::new()
-                    .application_name(
-                        cfg.application_name
-                            .as_deref()
-                            .unwrap_or("ForgeIO OPC UA Client"),
-                    )
-                    .application_uri(
-                        cfg.application_uri
-                            .as_deref()
-                            .unwrap_or("urn:forgeio:client"),
-                    )
-                    .session_name(cfg.session_name.as_deref().unwrap_or("ForgeIOSession"))
-                    .trust_server_certs(true)
-                    .create_sample_keypair(true)
-                    .max_message_size(cfg.max_message_size.unwrap_or(0))
-                    .max_chunk_count(cfg.max_chunk_count.unwrap_or(0))
+                let mut client = self
+                    .client_builder()
                     .client()
                     .map_err(|e| format!("failed to build client: {e:?}"))?;
 
                 let endpoint: EndpointDescription = (
                     cfg.address.as_str(),
-                    "None",
-                    MessageSecurityMode::None,
+                    security_policy.to_str(),
+                    security_mode,
                     UserTokenPolicy::anonymous(),
                 )
                     .into();
 
                 let (session, event_loop) = client
-                    .connect_to_matching_endpoint(endpoint, IdentityToken::Anonymous)
+                    .connect_to_matching_endpoint(endpoint, self.identity_token())
                     .await
                     .map_err(|e| format!("failed to connect: {e:?}"))?;
 
-                let mut handle = event_loop.spawn();
+                let (alive_tx, alive) = watch::channel(true);
+                let mut handle = tokio::spawn(async move {
+                    let status = event_loop.run().await;
+                    alive_tx.send_replace(false);
+                    status
+                });
                 tokio::select! {
                     status = &mut handle => {
                         Err(format!("event loop ended: {status:?}"))
                     }
                     _ = session.wait_for_connection() => {
-                        Ok((client, session, handle))
+                        Ok(OpenedSession { client, session, event_loop: handle, alive })
                     }
                 }
             };
 
             match tokio::time::timeout(Duration::from_millis(timeout_ms), attempt_fut).await {
-                Ok(Ok((client, session, handle))) => {
-                    *self.client.lock().unwrap() = Some(client);
-                    *self.session.lock().unwrap() = Some(session);
-                    *self.event_loop.lock().unwrap() = Some(handle);
-                    info!("OPC UA driver connected to {}", self.config.address);
-                    return Ok(());
+                Ok(Ok(opened)) => {
+                    info!("OPC UA session opened to {}", self.config.address);
+                    return Ok(opened);
                 }
-                Ok(Err(e)) if attempt < max_retries => {
+                Ok(Err(e)) if attempt < delays.len() => {
                     warn!(
                         "OPC UA connection attempt {} failed: {}. Retrying in {} ms",
                         attempt + 1,
                         e,
-                        delay
+                        delays[attempt].as_millis()
                     );
                 }
-                Ok(Err(e)) => return Err(e.into()),
-                Err(_) if attempt < max_retries => {
+                Ok(Err(e)) => return Err(DriverError::Protocol(e)),
+                Err(_) if attempt < delays.len() => {
                     warn!(
                         "OPC UA connection attempt {} timed out after {} ms. Retrying in {} ms",
                         attempt + 1,
                         timeout_ms,
-                        delay
+                        delays[attempt].as_millis()
                     );
                 }
                 Err(_) => {
-                    return Err(
-                        format!("connection attempt timed out after {} ms", timeout_ms).into(),
-                    )
+                    warn!(
+                        "OPC UA connection attempt {} timed out after {} ms",
+                        attempt + 1,
+                        timeout_ms
+                    );
+                    return Err(DriverError::Timeout);
                 }
             }
 
-            if delay > 0 {
-                sleep(Duration::from_millis(delay)).await;
-                delay = (delay as f64 * backoff) as u64;
+            if !delays[attempt].is_zero() {
+                sleep(delays[attempt]).await;
             }
             attempt += 1;
         }
     }
 
-    async fn disconnect(&self) -> OpcDriverResult<()> {
-        let session = { self.session.lock().unwrap().take() };
-        if let Some(session) = session {
-            session
-                .disconnect()
-                .await
-                .map_err(|e| format!("disconnect error: {e:?}"))?;
+    /// Discover the data variables exposed below the Objects folder.
+    ///
+    /// Standard namespace 0 objects such as `Server` are skipped since they only
+    /// hold server diagnostics. Each variable found is read once to detect its
+    /// value type.
+    pub async fn discover_tags(&self) -> DriverResult<Vec<DiscoveredTag>> {
+        let mut seen = HashSet::new();
+        let mut variables = Vec::new();
+        for child in self.browse_node_recursive(OBJECTS_FOLDER_ID, 1).await? {
+            // Methods only hold descriptions of their arguments, which are not tags
+            if Self::parse_node_id(&child.node_id)?.namespace == 0
+                || child.node_class == "Method"
+            {
+                continue;
+            }
+            let below = self
+                .browse_node_recursive(&child.node_id, DISCOVERY_MAX_DEPTH - 1)
+                .await?;
+            let mut method_paths: Vec<String> = Vec::new();
+            for mut node in std::iter::once(child.clone()).chain(below) {
+                // Parents are always visited before their children
+                if node.node_class == "Method" {
+                    method_paths.push(if node.parent_path.is_empty() {
+                        node.browse_name.clone()
+                    } else {
+                        format!("{}/{}", node.parent_path, node.browse_name)
+                    });
+                }
+                let below_method = method_paths.iter().any(|method| {
+                    node.parent_path == *method
+                        || node.parent_path.starts_with(&format!("{method}/"))
+                });
+                if node.node_class != "Variable"
+                    || below_method
+                    || !seen.insert(node.node_id.clone())
+                {
+                    continue;
+                }
+                if node.node_id != child.node_id {
+                    node.parent_path = if node.parent_path.is_empty() {
+                        child.browse_name.clone()
+                    } else {
+                        format!("{}/{}", child.browse_name, node.parent_path)
+                    };
+                }
+                variables.push(node);
+            }
         }
-        let handle = { self.event_loop.lock().unwrap().take() };
-        if let Some(handle) = handle {
-            let _ = handle.await;
+        if variables.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let requests: Vec<TagRequest> = variables
+            .iter()
+            .map(|node| TagRequest {
+                address: node.node_id.clone(),
+            })
+            .collect();
+        let values = self.read_tags(&requests).await?;
+        let node_ids = variables
+            .iter()
+            .map(|node| Self::parse_node_id(&node.node_id))
+            .collect::<DriverResult<Vec<_>>>()?;
+        let attributes = self.read_attributes(node_ids).await?;
+
+        Ok(variables
+            .into_iter()
+            .zip(attributes)
+            .filter_map(|(node, attributes)| {
+                let data_type = values
+                    .get(&node.node_id)
+                    .map(|v| v.value.type_name())
+                    .unwrap_or("Null");
+                let path = if node.parent_path.is_empty() {
+                    node.browse_name.clone()
+                } else {
+                    format!("{}/{}", node.parent_path, node.browse_name)
+                };
+                // E.g. a node with an empty browse name
+                let path = match TagPath::new(path) {
+                    Ok(path) => path,
+                    Err(e) => {
+                        warn!("Skipping discovered node {}: {}", node.node_id, e);
+                        return None;
+                    }
+                };
+                Some(DiscoveredTag {
+                    node_id: node.node_id,
+                    path,
+                    data_type: data_type.to_string(),
+                    display_name: attributes.display_name,
+                    description: attributes.description,
+                    writable: attributes.writable,
+                })
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl DeviceDriver for OpcUaDriver {
+    fn config(&self) -> &DriverConfig {
+        &self.config
+    }
+
+    async fn connect(&self) -> DriverResult<()> {
+        if self.lease.lock().unwrap().is_some() {
+            return Ok(());
         }
-        *self.client.lock().unwrap() = None;
+
+        let lease = session_pool::acquire(self.session_key()?, || self.open_session()).await?;
+        self.session_alive.send_replace(true);
+        let mut shared_alive = lease.alive.clone();
+        let session_alive = Arc::clone(&self.session_alive);
+        let forwarder = tokio::spawn(async move {
+            let _ = shared_alive.wait_for(|alive| !*alive).await;
+            session_alive.send_replace(false);
+        });
+        *self.lease.lock().unwrap() = Some(lease);
+        *self.alive_forwarder.lock().unwrap() = Some(forwarder);
+        info!("OPC UA driver connected to {}", self.config.address);
         Ok(())
     }
 
-    async fn check_status(&self) -> OpcDriverResult<()> {
-        if let Some(session) = self.session.lock().unwrap().as_ref() {
-            if session.server_session_id() != NodeId::null() {
-                return Ok(());
-            }
+    async fn disconnect(&self) -> DriverResult<()> {
+        self.session_alive.send_replace(false);
+        if let Some(forwarder) = self.alive_forwarder.lock().unwrap().take() {
+            forwarder.abort();
+        }
+        // Other drivers may still be using the session
+        let lease = { self.lease.lock().unwrap().take() };
+        match lease {
+            Some(lease) => session_pool::release(lease).await,
+            None => Ok(()),
         }
-        Err("Disconnected".into())
     }
 
-    async fn read_tags(&self, tags: &[OpcTagRequest]) -> OpcDriverResult<HashMap<String, TagValue>> {
-        let session = {
-            let guard = self.session.lock().unwrap();
-            guard.clone().ok_or("not connected")?
-        };
+    async fn check_status(&self) -> DriverResult<()> {
+        // The event loop ends when the connection drops
+        if !*self.session_alive.borrow() {
+            return Err(DriverError::NotConnected);
+        }
+        match self.session() {
+            Ok(session) if session.server_session_id() != NodeId::null() => Ok(()),
+            _ => Err(DriverError::NotConnected),
+        }
+    }
+
+    async fn read_tags(&self, tags: &[TagRequest]) -> DriverResult<HashMap<String, TagValue>> {
+        let session = self.session()?;
 
-        let mut read_ids = Vec::new();
+        // A malformed address only makes its own tag Bad; the rest are still read
+        let mut result = HashMap::with_capacity(tags.len());
+        let mut read_ids = Vec::with_capacity(tags.len());
+        let mut readable = Vec::with_capacity(tags.len());
         for t in tags {
-            let node_id = Self::parse_node_id(&t.address)?;
-            read_ids.push(ReadValueId {
+            match self.resolve(&t.address) {
+                Ok(NodeAddress { node_id, index_range }) => {
+                    read_ids.push(ReadValueId {
+                        node_id,
+                        attribute_id: AttributeId::Value as u32,
+                        index_range,
+                        data_encoding: QualifiedName::null(),
+                    });
+                    readable.push(t.clone());
+                }
+                Err(e) => {
+                    warn!("Skipping read from {}: {}", self.config.address, e);
+                    result.insert(t.address.clone(), TagValue::bad(Quality::Bad));
+                }
+            }
+        }
+
+        // Large groups are split so no single request exceeds the server's message size
+        let batch_size = self.read_batch_size();
+        for (batch, requests) in read_ids.chunks(batch_size).zip(readable.chunks(batch_size)) {
+            let data_values = self
+                .read_values(&session, batch, TimestampsToReturn::Both)
+                .await?;
+            let data_values: Vec<DataValue> = data_values
+                .into_iter()
+                .zip(batch)
+                .map(|(dv, read_id)| Self::select_element(dv, &read_id.index_range))
+                .collect();
+            result.extend(Self::read_results(requests, &data_values));
+        }
+
+        info!(
+            "OPC UA read {} values from {}",
+            result.len(),
+            self.config.address
+        );
+        Ok(result)
+    }
+
+    async fn write_tags(
+        &self,
+        tags: HashMap<String, TagValue>,
+    ) -> DriverResult<HashMap<String, WriteStatus>> {
+        let session = self.session()?;
+
+        // Addresses that can't be written fail on their own; only session-level
+        // problems fail the whole call
+        let mut result = HashMap::with_capacity(tags.len());
+        let mut requested = Vec::with_capacity(tags.len());
+        let mut write_values = Vec::with_capacity(tags.len());
+        for (address, value) in tags {
+            let node_id = match self.resolve(&address) {
+                Ok(NodeAddress { node_id, index_range }) if !index_range.has_range() => node_id,
+                // Writing array elements isn't supported; the whole array would be replaced
+                Ok(_) => {
+                    let status = WriteStatus::failed(value.value, "array elements are read-only");
+                    result.insert(address, status);
+                    continue;
+                }
+                Err(e) => {
+                    result.insert(address, WriteStatus::failed(value.value, e.to_string()));
+                    continue;
+                }
+            };
+            let variant = match Self::tag_value_to_variant(&value) {
+                Ok(variant) => variant,
+                Err(e) => {
+                    result.insert(address, WriteStatus::failed(value.value, e.to_string()));
+                    continue;
+                }
+            };
+            write_values.push(WriteValue {
                 node_id,
                 attribute_id: AttributeId::Value as u32,
                 index_range: Default::default(),
-                data_encoding: QualifiedName::null(),
+                value: DataValue::value_only(variant),
             });
+            requested.push((address, value));
+        }
+        if write_values.is_empty() {
+            return Ok(result);
         }
 
-        let data_values = session
-            .read(&read_ids, TimestampsToReturn::Both, 0.0)
+        // The server may have made a node read-only since the tag was configured;
+        // those are reported as failed without holding back the rest. Nodes whose
+        // access level can't be read are left for the write to report.
+        let access_levels: Vec<ReadValueId> = write_values
+            .iter()
+            .map(|write| ReadValueId {
+                node_id: write.node_id.clone(),
+                attribute_id: AttributeId::AccessLevel as u32,
+                index_range: Default::default(),
+                data_encoding: QualifiedName::null(),
+            })
+            .collect();
+        // Batched like value reads so a large write stays under the message size
+        let mut levels = Vec::with_capacity(access_levels.len());
+        for batch in access_levels.chunks(self.read_batch_size()) {
+            levels.extend(
+                self.read_values(&session, batch, TimestampsToReturn::Neither)
+                    .await?,
+            );
+        }
+        let access_levels = levels;
+        let mut writable = Vec::with_capacity(requested.len());
+        for (index, (request, write)) in requested.into_iter().zip(write_values).enumerate() {
+            match access_levels.get(index).and_then(|level| level.value.as_ref()) {
+                Some(Variant::Byte(level)) if !access_level_writable(*level) => {
+                    let (address, value) = request;
+                    warn!("OPC UA node '{}' is read-only, not writing it", address);
+                    result.insert(address, WriteStatus::failed(value.value, "read-only"));
+                }
+                _ => writable.push((request, write)),
+            }
+        }
+        if writable.is_empty() {
+            return Ok(result);
+        }
+        let (requested, write_values): (Vec<_>, Vec<_>) = writable.into_iter().unzip();
+
+        let statuses = session
+            .write(&write_values)
             .await
-            .map_err(|e| format!("read error: {e:?}"))?;
+            .map_err(|e| DriverError::Protocol(format!("write error: {e:?}")))?;
 
         info!(
-            "OPC UA read {} values from {}",
-            data_values.len(),
+            "OPC UA wrote {} values to {}",
+            statuses.len(),
             self.config.address
         );
 
-        let mut result = HashMap::new();
-        for (req, dv) in tags.iter().zip(data_values.iter()) {
-            result.insert(req.address.clone(), Self::data_value_to_tag_value(dv));
-        }
+        result.extend(Self::write_results(requested, &statuses));
         Ok(result)
     }
 
-    async fn write_tags(
-        &self,
-        _tags: HashMap<String, TagValue>,
-    ) -> OpcDriverResult<HashMap<String, TagValue>> {
-        Ok(HashMap::new())
-    }
-
     fn as_any(&self) -> &dyn Any {
         self
     }
 }
 
 // Remove the incorrect implementation
-// impl dyn OpcDriver + Send + Sync {
+// impl dyn DeviceDriver + Send + Sync {
 //     pub fn as_any(&self) -> &dyn Any {
 //         self
 //     }
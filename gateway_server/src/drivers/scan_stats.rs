@@ -0,0 +1,58 @@
+use dashmap::DashMap;
+use serde::Serialize;
+use tokio::time::Duration;
+
+/// Timing of a driver's scans, i.e. the reads of its poll groups.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ScanStats {
+    /// Scans finished, successful or not.
+    pub scan_count: u64,
+    /// Duration of the most recent scan.
+    pub last_scan_duration_ms: u64,
+    /// Mean duration over every scan.
+    pub avg_scan_duration_ms: f64,
+    /// Scans that failed as a whole, e.g. on a lost connection or a timeout.
+    pub error_count: u64,
+    /// Failed scans since the last successful one.
+    pub consecutive_errors: u64,
+}
+
+/// Scan statistics of each driver, keyed by device id, recorded by the
+/// [`Poller`](crate::tags::poller::Poller). Drivers without an entry have not
+/// been scanned yet.
+#[derive(Debug, Default)]
+pub struct ScanStatistics {
+    stats: DashMap<String, ScanStats>,
+}
+
+impl ScanStatistics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a scan of `id` that took `duration`.
+    pub fn record(&self, id: &str, duration: Duration, succeeded: bool) {
+        let mut stats = self.stats.entry(id.to_string()).or_default();
+        let duration_ms = duration.as_secs_f64() * 1000.0;
+        stats.scan_count += 1;
+        stats.last_scan_duration_ms = duration.as_millis() as u64;
+        stats.avg_scan_duration_ms +=
+            (duration_ms - stats.avg_scan_duration_ms) / stats.scan_count as f64;
+        if succeeded {
+            stats.consecutive_errors = 0;
+        } else {
+            stats.error_count += 1;
+            stats.consecutive_errors += 1;
+        }
+    }
+
+    /// Statistics of `id`, all zero if it has not been scanned yet.
+    pub fn get(&self, id: &str) -> ScanStats {
+        self.stats.get(id).map(|stats| stats.clone()).unwrap_or_default()
+    }
+
+    /// Forget the statistics of drivers `keep` returns false for.
+    pub fn retain(&self, mut keep: impl FnMut(&str) -> bool) {
+        self.stats.retain(|id, _| keep(id));
+    }
+}
@@ -0,0 +1,152 @@
+use crate::drivers::traits::{DriverError, DriverResult};
+use dashmap::DashMap;
+use opcua::client::{Client, Session};
+use opcua::types::StatusCode;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock};
+use tokio::sync::{watch, Mutex};
+use tokio::task::JoinHandle;
+use tracing::info;
+
+/// Everything two OPC UA drivers must agree on to share a session: the endpoint,
+/// its security settings, how certificates are handled and the identity the
+/// session is activated with.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct SessionKey {
+    pub address: String,
+    pub security_policy: String,
+    pub security_mode: String,
+    pub pki_dir: Option<String>,
+    pub trust_server_certs: bool,
+    pub create_sample_keypair: bool,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+/// A freshly connected session together with what keeps it running.
+pub(crate) struct OpenedSession {
+    pub client: Client,
+    pub session: Arc<Session>,
+    pub event_loop: JoinHandle<StatusCode>,
+    /// Flips to `false` when the event loop ends.
+    pub alive: watch::Receiver<bool>,
+}
+
+struct Entry {
+    id: u64,
+    opened: OpenedSession,
+    users: usize,
+}
+
+impl Entry {
+    fn lease(&self, key: SessionKey) -> Lease {
+        Lease {
+            key,
+            id: self.id,
+            session: Arc::clone(&self.opened.session),
+            alive: self.opened.alive.clone(),
+        }
+    }
+}
+
+/// One driver's use of a pooled session, handed back through [`release`].
+#[derive(Clone)]
+pub(crate) struct Lease {
+    key: SessionKey,
+    id: u64,
+    pub session: Arc<Session>,
+    pub alive: watch::Receiver<bool>,
+}
+
+type Slot = Arc<Mutex<Option<Entry>>>;
+
+/// Sessions in use, one slot per key. Drivers connecting to the same endpoint
+/// wait on the slot's lock so only the first of them opens a session.
+static SESSIONS: LazyLock<DashMap<SessionKey, Slot>> = LazyLock::new(DashMap::new);
+
+/// Distinguishes a session from the one that replaced it after a connection loss,
+/// so late releases of the old session leave the new one's user count alone.
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+fn slot(key: &SessionKey) -> Slot {
+    Arc::clone(SESSIONS.entry(key.clone()).or_default().value())
+}
+
+/// Join the live session for `key`, opening one with `open` when there is none
+/// or the previous one has dropped.
+pub(crate) async fn acquire<F, Fut>(key: SessionKey, open: F) -> DriverResult<Lease>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = DriverResult<OpenedSession>>,
+{
+    let slot = slot(&key);
+    let mut entry = slot.lock().await;
+    if let Some(shared) = entry.as_mut().filter(|e| *e.opened.alive.borrow()) {
+        shared.users += 1;
+        info!(
+            "Sharing OPC UA session to {} between {} drivers",
+            key.address, shared.users
+        );
+        return Ok(shared.lease(key));
+    }
+    // Drivers still holding a dropped session pick up this one when they reconnect
+    if let Some(stale) = entry.take() {
+        stale.opened.event_loop.abort();
+    }
+    let opened = open().await?;
+    let shared = entry.insert(Entry {
+        id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+        opened,
+        users: 1,
+    });
+    Ok(shared.lease(key))
+}
+
+/// Give up a lease. The session is closed once its last user has left.
+pub(crate) async fn release(lease: Lease) -> DriverResult<()> {
+    let slot = slot(&lease.key);
+    let mut entry = slot.lock().await;
+    match entry.as_mut() {
+        Some(shared) if shared.id == lease.id => {
+            shared.users -= 1;
+            if shared.users > 0 {
+                return Ok(());
+            }
+        }
+        _ => return Ok(()),
+    }
+    let Some(Entry { opened, .. }) = entry.take() else {
+        return Ok(());
+    };
+    drop(entry);
+    // Nobody else is waiting on the slot, so forget the endpoint entirely
+    SESSIONS.remove_if(&lease.key, |_, s| Arc::strong_count(s) == 2);
+
+    // A dropped session can't be closed cleanly; tear down the rest anyway so
+    // that a following `connect` starts from scratch.
+    let result = opened
+        .session
+        .disconnect()
+        .await
+        .map_err(|e| DriverError::Protocol(format!("disconnect error: {e:?}")));
+    // Without a clean disconnect the event loop may keep retrying on its own
+    if result.is_err() {
+        opened.event_loop.abort();
+    }
+    let _ = opened.event_loop.await;
+    drop(opened.client);
+    result
+}
+
+/// Number of drivers sharing the session behind `lease`, `0` once it was replaced.
+pub(crate) async fn users(lease: &Lease) -> usize {
+    let Some(slot) = SESSIONS.get(&lease.key).map(|s| Arc::clone(s.value())) else {
+        return 0;
+    };
+    let entry = slot.lock().await;
+    match entry.as_ref() {
+        Some(shared) if shared.id == lease.id => shared.users,
+        _ => 0,
+    }
+}
@@ -1,17 +1,25 @@
-use crate::tags::structures::TagValue;
+use crate::tags::engine::TagEngine;
+use crate::tags::structures::{Quality, TagValue, ValueVariant};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize}; // Added for config
 use std::any::Any;
 use std::collections::HashMap;
-use std::error::Error; // Imported from structures to avoid duplication
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::time::Duration;
 
-/// Configuration for an OPC UA driver
-#[derive(Debug, Clone, Deserialize, Serialize)] // Added Deserialize, Serialize, and Debug
-pub struct OpcDriverConfig {
+/// Configuration for a device and the driver talking to it. The optional client
+/// parameters are currently OPC UA specific.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)] // Added Deserialize, Serialize, and Debug
+pub struct DriverConfig {
     pub id: String,        // Unique identifier for this device instance
     pub name: String,      // User-friendly name
     pub address: String,   // e.g., IP address, COM port, connection string
     pub scan_rate_ms: u64, // How often to poll tags (if applicable)
+    /// Protocol used to talk to this device; selects the driver implementation.
+    #[serde(default = "default_driver_type")]
+    pub driver_type: String,
     // Additional optional OPC UA client parameters
     #[serde(default)]
     pub application_name: Option<String>,
@@ -23,58 +31,260 @@ pub struct OpcDriverConfig {
     pub max_message_size: Option<usize>,
     #[serde(default)]
     pub max_chunk_count: Option<usize>,
+    /// Maximum number of nodes per read request. Derived from `max_message_size` when unset.
+    #[serde(default)]
+    pub read_batch_size: Option<usize>,
     #[serde(default)]
     pub connect_retry_attempts: Option<u32>,
     #[serde(default)]
     pub connect_retry_delay_ms: Option<u64>,
     #[serde(default)]
     pub connect_retry_backoff: Option<f64>,
+    /// Adds a random extra of up to this many ms to each retry delay, so drivers
+    /// that lost their connection together don't all retry at the same moment.
+    #[serde(default)]
+    pub connect_retry_jitter_ms: Option<u64>,
     #[serde(default)]
     pub connect_timeout_ms: Option<u64>,
+    /// OPC UA security policy name or URI (e.g. "Basic256Sha256"). Defaults to "None".
+    #[serde(default)]
+    pub security_policy: Option<String>,
+    /// OPC UA message security mode: "None", "Sign" or "SignAndEncrypt". Defaults to "None".
+    #[serde(default)]
+    pub security_mode: Option<String>,
+    /// Username for a UserName identity token. Used only when `password` is also set.
+    #[serde(default)]
+    pub username: Option<String>,
+    /// Password for a UserName identity token. Never logged.
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Directory holding the client's keypair and the `trusted` and `rejected`
+    /// server certificates. Defaults to `pki` in the working directory.
+    #[serde(default)]
+    pub pki_dir: Option<String>,
+    /// Trust server certificates not yet in `pki_dir/trusted` instead of
+    /// rejecting them. Defaults to the value of `insecure`.
+    #[serde(default)]
+    pub trust_server_certs: Option<bool>,
+    /// Generate a self-signed client keypair when none is found in `pki_dir`.
+    /// Defaults to the value of `insecure`.
+    #[serde(default)]
+    pub create_sample_keypair: Option<bool>,
+    /// Trust any server and generate a keypair as needed, for development
+    /// against test servers.
+    #[serde(default)]
+    pub insecure: bool,
+    /// Maximum number of reads the poller keeps in flight against this device. Defaults to 4.
+    #[serde(default)]
+    pub max_concurrent_reads: Option<usize>,
+    /// How long one read may take, both a poll group's read by the poller and
+    /// each request the driver sends, e.g. for the API. A read that takes longer
+    /// fails with a timeout. Defaults to 10 seconds.
+    #[serde(default)]
+    pub read_timeout_ms: Option<u64>,
+    /// Whether `/api/health` reports the gateway unavailable while this device
+    /// is disconnected.
+    #[serde(default)]
+    pub critical: bool,
+}
+
+fn default_driver_type() -> String {
+    "opcua".to_string()
+}
+
+/// Read timeout for drivers that don't set `read_timeout_ms`.
+const DEFAULT_READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+impl DriverConfig {
+    /// The configured `read_timeout_ms`, or the default.
+    pub fn read_timeout(&self) -> Duration {
+        self.read_timeout_ms.map_or(DEFAULT_READ_TIMEOUT, Duration::from_millis)
+    }
 }
 
 /// Represents a request to read or write a tag
 #[derive(Clone)]
-pub struct OpcTagRequest {
+pub struct TagRequest {
     pub address: String, // Protocol-specific tag address (e.g., "ns=1;s=MyTag", "40001", "Topic/Subtopic")
                          // Potentially add data type hint
 }
 
+/// Outcome of writing one address.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WriteStatus {
+    /// The value that was written.
+    pub value: ValueVariant,
+    /// Good if the device accepted the value, Bad otherwise.
+    pub quality: Quality,
+    /// Why the device rejected the value.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl WriteStatus {
+    pub fn good(value: ValueVariant) -> Self {
+        WriteStatus {
+            value,
+            quality: Quality::Good,
+            error: None,
+        }
+    }
+
+    pub fn failed(value: ValueVariant, error: impl Into<String>) -> Self {
+        WriteStatus {
+            value,
+            quality: Quality::Bad,
+            error: Some(error.into()),
+        }
+    }
+}
+
+/// Errors returned by driver operations.
+#[derive(Debug)]
+pub enum DriverError {
+    /// The driver has no session with its device.
+    NotConnected,
+    /// A tag address is not a valid node id.
+    InvalidNodeId(String),
+    /// A value can't be converted to a type the device accepts.
+    InvalidValue(String),
+    /// The device did not answer in time.
+    Timeout,
+    /// The device rejected a request or the protocol stack failed.
+    Protocol(String),
+    /// The driver configuration is invalid, e.g. an unknown security policy.
+    Config(String),
+    Io(io::Error),
+}
+
+impl fmt::Display for DriverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DriverError::NotConnected => write!(f, "not connected"),
+            DriverError::InvalidNodeId(address) => write!(f, "Invalid NodeId '{}'", address),
+            DriverError::Timeout => write!(f, "timed out"),
+            DriverError::InvalidValue(message)
+            | DriverError::Protocol(message)
+            | DriverError::Config(message) => f.write_str(message),
+            DriverError::Io(e) => write!(f, "I/O error: {}", e),
+        }
+    }
+}
+
+impl DriverError {
+    /// The tag address the error is about, if it concerns a single one rather
+    /// than the connection or the driver as a whole.
+    pub fn address(&self) -> Option<&str> {
+        match self {
+            DriverError::InvalidNodeId(address) => Some(address),
+            _ => None,
+        }
+    }
+}
+
+impl Error for DriverError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DriverError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for DriverError {
+    fn from(e: io::Error) -> Self {
+        DriverError::Io(e)
+    }
+}
+
 // Type alias for results from driver operations
-pub type OpcDriverResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+pub type DriverResult<T> = Result<T, DriverError>;
 
-/// Trait implemented by OPC UA drivers.
+/// Trait implemented by every device driver, whatever its protocol.
 #[async_trait]
-pub trait OpcDriver: Send + Sync {
+pub trait DeviceDriver: Send + Sync {
     /// Get the configuration of this driver instance.
-    fn config(&self) -> &OpcDriverConfig;
+    fn config(&self) -> &DriverConfig;
 
     /// Connect to the underlying device.
-    async fn connect(&self) -> OpcDriverResult<()>;
+    async fn connect(&self) -> DriverResult<()>;
 
     /// Disconnect from the underlying device.
-    async fn disconnect(&self) -> OpcDriverResult<()>;
+    async fn disconnect(&self) -> DriverResult<()>;
 
     /// Check the connection status.
-    async fn check_status(&self) -> OpcDriverResult<()>; // Returns Ok(()) if connected, Err otherwise
+    async fn check_status(&self) -> DriverResult<()>; // Returns Ok(()) if connected, Err otherwise
 
     /// Read a batch of tags.
     /// Takes a list of tag addresses and returns a map of address to TagValue.
-    async fn read_tags(&self, tags: &[OpcTagRequest]) -> OpcDriverResult<HashMap<String, TagValue>>;
+    /// Addresses that can't be read come back Bad; an `Err` means the batch as
+    /// a whole could not be read.
+    async fn read_tags(&self, tags: &[TagRequest]) -> DriverResult<HashMap<String, TagValue>>;
 
     /// Write a batch of tags.
-    /// Takes a map of tag address to the TagValue to write.
-    /// Returns a map of address to TagValue representing the result (e.g., success or error status per tag).
+    /// Takes a map of tag address to the TagValue to write and returns the outcome
+    /// for each address, so some writes can fail while others succeed. An `Err`
+    /// means the batch as a whole could not be written.
     async fn write_tags(
         &self,
         tags: HashMap<String, TagValue>,
-    ) -> OpcDriverResult<HashMap<String, TagValue>>;
+    ) -> DriverResult<HashMap<String, WriteStatus>>;
+
+    /// Read the given tags through this driver, keyed by tag path. Paths that are
+    /// not registered or belong to another driver are skipped, and an address
+    /// shared by several tags is read once.
+    async fn read_paths(
+        &self,
+        engine: &TagEngine,
+        driver_id: &str,
+        tag_paths: &[String],
+    ) -> DriverResult<HashMap<String, TagValue>> {
+        let mut paths_by_address: HashMap<String, Vec<String>> = HashMap::new();
+        for path in tag_paths {
+            match engine.read_tag_full(path) {
+                Some(tag) if tag.driver_id == driver_id => paths_by_address
+                    .entry(tag.driver_address)
+                    .or_default()
+                    .push(path.clone()),
+                _ => {}
+            }
+        }
+        if paths_by_address.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let requests: Vec<TagRequest> = paths_by_address
+            .keys()
+            .map(|address| TagRequest {
+                address: address.clone(),
+            })
+            .collect();
+        let values = self.read_tags(&requests).await?;
+
+        let mut by_path = HashMap::with_capacity(tag_paths.len());
+        for (address, value) in values {
+            for path in paths_by_address.remove(&address).unwrap_or_default() {
+                by_path.insert(path, value.clone());
+            }
+        }
+        Ok(by_path)
+    }
+
+    /// Read every tag registered for this driver in one call, keyed by tag path.
+    async fn read_all(
+        &self,
+        engine: &TagEngine,
+        driver_id: &str,
+    ) -> DriverResult<HashMap<String, TagValue>> {
+        self.read_paths(engine, driver_id, &engine.get_all_tag_paths())
+            .await
+    }
 
     /// Enable downcasting to concrete types
     fn as_any(&self) -> &dyn Any;
 
     // TODO: Add methods for subscription-based updates if the protocol supports it
-    // async fn subscribe_tags(&mut self, tags: &[OpcTagRequest]) -> OpcDriverResult<()>;
-    // async fn unsubscribe_tags(&mut self, tags: &[OpcTagRequest]) -> OpcDriverResult<()>;
+    // async fn subscribe_tags(&mut self, tags: &[TagRequest]) -> DriverResult<()>;
+    // async fn unsubscribe_tags(&mut self, tags: &[TagRequest]) -> DriverResult<()>;
     // Potentially return a stream or use a callback mechanism for subscription updates
 }
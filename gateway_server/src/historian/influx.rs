@@ -0,0 +1,196 @@
+use crate::tags::engine::TagEngine;
+use crate::tags::structures::{TagValue, ValueVariant};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::time::{interval, Duration, MissedTickBehavior};
+use tokio_stream::wrappers::WatchStream;
+use tokio_stream::{StreamExt, StreamMap};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+/// Connection and batching settings for writing tag values to InfluxDB (v2 write API).
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct InfluxConfig {
+    /// Base URL of the InfluxDB server, e.g. `http://localhost:8086`.
+    pub url: String,
+    pub bucket: String,
+    #[serde(default)]
+    pub org: Option<String>,
+    /// API token, sent as `Authorization: Token <token>`.
+    #[serde(default)]
+    pub token: Option<String>,
+    /// Measurement name every point is written to.
+    #[serde(default = "default_measurement")]
+    pub measurement: String,
+    /// Points per write request; a full batch is sent immediately.
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    /// Longest time a point waits in a partial batch before it is sent.
+    #[serde(default = "default_flush_interval_ms")]
+    pub flush_interval_ms: u64,
+}
+
+fn default_measurement() -> String {
+    "forgeio".to_string()
+}
+
+fn default_batch_size() -> usize {
+    500
+}
+
+fn default_flush_interval_ms() -> u64 {
+    1000
+}
+
+/// Streams tag updates from the engine to InfluxDB as line protocol.
+pub struct InfluxWriter {
+    config: InfluxConfig,
+    tag_engine: Arc<TagEngine>,
+    client: reqwest::Client,
+    cancel: CancellationToken,
+}
+
+impl InfluxWriter {
+    pub fn new(config: InfluxConfig, tag_engine: Arc<TagEngine>, cancel: CancellationToken) -> Self {
+        Self {
+            config,
+            tag_engine,
+            client: reqwest::Client::new(),
+            cancel,
+        }
+    }
+
+    /// Forward tag updates until the cancellation token fires, then send what is left.
+    pub async fn run(self) {
+        info!("InfluxDB writer started for bucket '{}'.", self.config.bucket);
+        let mut updates = StreamMap::new();
+        let mut batch = Vec::with_capacity(self.config.batch_size);
+        let mut ticker = interval(Duration::from_millis(self.config.flush_interval_ms.max(1)));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            tokio::select! {
+                biased;
+                _ = self.cancel.cancelled() => break,
+                _ = ticker.tick() => {
+                    self.flush(&mut batch).await;
+                    // Pick up tags registered since the last tick; removed tags end their stream.
+                    self.subscribe_new_tags(&mut updates);
+                }
+                Some((path, value)) = updates.next(), if !updates.is_empty() => {
+                    let driver_id = match self.tag_engine.get_tag_details(&path) {
+                        Some(tag) => tag.driver_id,
+                        None => continue,
+                    };
+                    if let Some(line) =
+                        to_line_protocol(&self.config.measurement, &path, &driver_id, &value)
+                    {
+                        batch.push(line);
+                    }
+                    if batch.len() >= self.config.batch_size {
+                        self.flush(&mut batch).await;
+                    }
+                }
+            }
+        }
+
+        self.flush(&mut batch).await;
+        info!("InfluxDB writer stopped.");
+    }
+
+    fn subscribe_new_tags(&self, updates: &mut StreamMap<String, WatchStream<TagValue>>) {
+        let known: HashSet<String> = updates.keys().cloned().collect();
+        for path in self.tag_engine.get_all_tag_paths() {
+            if !known.contains(&path) {
+                // Only changes: the current value was either written already or is the startup placeholder
+                let stream = WatchStream::from_changes(self.tag_engine.subscribe(&path));
+                updates.insert(path, stream);
+            }
+        }
+    }
+
+    /// Send the batched lines. Failed batches are logged and dropped so memory stays bounded.
+    async fn flush(&self, batch: &mut Vec<String>) {
+        if batch.is_empty() {
+            return;
+        }
+        let body = batch.join("\n");
+        let points = batch.len();
+        batch.clear();
+
+        let url = format!("{}/api/v2/write", self.config.url.trim_end_matches('/'));
+        let mut query = vec![
+            ("bucket", self.config.bucket.as_str()),
+            ("precision", "ms"),
+        ];
+        if let Some(org) = &self.config.org {
+            query.push(("org", org.as_str()));
+        }
+        let mut request = self.client.post(url).query(&query).body(body);
+        if let Some(token) = &self.config.token {
+            request = request.header("Authorization", format!("Token {token}"));
+        }
+
+        match request.send().await.and_then(|r| r.error_for_status()) {
+            Ok(_) => info!("Wrote {} points to InfluxDB.", points),
+            Err(e) => warn!("Dropping {} points, InfluxDB write failed: {}", points, e),
+        }
+    }
+}
+
+/// Format one tag value as an InfluxDB line, tagged by path and driver id, with
+/// a value field, a `quality` field and a millisecond timestamp. InfluxDB fixes
+/// a field's type per measurement, so the value field is named after its type:
+/// `value_f`, `value_i`, `value_u`, `value_b` or `value_s`. Alias tags have no
+/// driver and get no `driver_id` tag, since tag values can't be empty.
+/// Returns `None` for values with no line protocol field type (null and arrays).
+pub fn to_line_protocol(
+    measurement: &str,
+    path: &str,
+    driver_id: &str,
+    value: &TagValue,
+) -> Option<String> {
+    let (name, field) = match &value.value {
+        ValueVariant::Bool(b) => ("value_b", b.to_string()),
+        ValueVariant::Int(i) => ("value_i", format!("{i}i")),
+        ValueVariant::UInt(u) => ("value_u", format!("{u}u")),
+        ValueVariant::Float(f) if f.is_finite() => ("value_f", f.to_string()),
+        ValueVariant::String(s) => ("value_s", quote_field(s)),
+        ValueVariant::DateTime(ms) => ("value_i", format!("{ms}i")),
+        ValueVariant::Float(_) | ValueVariant::Null | ValueVariant::Array(_) => return None,
+    };
+    let mut line = format!(
+        "{},path={}",
+        measurement.replace(',', "\\,").replace(' ', "\\ "),
+        escape_key(path)
+    );
+    if !driver_id.is_empty() {
+        line.push_str(&format!(",driver_id={}", escape_key(driver_id)));
+    }
+    line.push_str(&format!(
+        " {}={},quality={} {}",
+        name,
+        field,
+        quote_field(&format!("{:?}", value.quality)),
+        value.timestamp
+    ));
+    Some(line)
+}
+
+/// Escape a tag key or tag value.
+fn escape_key(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, ',' | '=' | ' ') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Quote a string field value.
+fn quote_field(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -0,0 +1 @@
+pub mod influx; // InfluxDB line protocol writer
@@ -0,0 +1 @@
+pub mod mqtt; // Publishing tag changes to an MQTT broker
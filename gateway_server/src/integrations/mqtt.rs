@@ -0,0 +1,170 @@
+use crate::tags::engine::TagEngine;
+use crate::tags::structures::TagValue;
+use rumqttc::{AsyncClient, MqttOptions, QoS};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::time::{interval, sleep, Duration, MissedTickBehavior};
+use tokio_stream::wrappers::WatchStream;
+use tokio_stream::{StreamExt, StreamMap};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+/// How often newly registered tags are picked up.
+const RESUBSCRIBE_INTERVAL: Duration = Duration::from_secs(1);
+/// Pause before polling the connection again after an error; rumqttc reconnects on the next poll.
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+/// Publishes that may be queued while the broker connection is busy.
+const REQUEST_CHANNEL_CAPACITY: usize = 1024;
+
+/// Broker connection and topic settings for publishing tag changes over MQTT.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct MqttConfig {
+    /// Broker address, e.g. `mqtt://localhost:1883`. The port defaults to 1883.
+    pub broker_url: String,
+    /// Each tag is published to `<base_topic>/<tag path>`.
+    #[serde(default = "default_base_topic")]
+    pub base_topic: String,
+    /// MQTT QoS level (0, 1 or 2).
+    #[serde(default)]
+    pub qos: u8,
+    #[serde(default = "default_client_id")]
+    pub client_id: String,
+}
+
+fn default_base_topic() -> String {
+    "forgeio".to_string()
+}
+
+fn default_client_id() -> String {
+    "forgeio-gateway".to_string()
+}
+
+impl MqttConfig {
+    /// Host and port from `broker_url`, accepting `mqtt://`, `tcp://` or no scheme.
+    pub fn broker_address(&self) -> Result<(String, u16), String> {
+        let address = self
+            .broker_url
+            .strip_prefix("mqtt://")
+            .or_else(|| self.broker_url.strip_prefix("tcp://"))
+            .unwrap_or(&self.broker_url)
+            .trim_end_matches('/');
+        match address.rsplit_once(':') {
+            Some((host, port)) => {
+                let port = port
+                    .parse()
+                    .map_err(|_| format!("Invalid port in MQTT broker URL '{}'", self.broker_url))?;
+                Ok((host.to_string(), port))
+            }
+            None if !address.is_empty() => Ok((address.to_string(), 1883)),
+            None => Err(format!("Invalid MQTT broker URL '{}'", self.broker_url)),
+        }
+    }
+
+    /// Topic a tag's updates are published to.
+    pub fn topic_for(&self, tag_path: &str) -> String {
+        format!("{}/{}", self.base_topic.trim_end_matches('/'), tag_path)
+    }
+}
+
+/// Publishes every stored tag change to the MQTT broker as JSON
+/// (`{"value": ..., "quality": ..., "timestamp": ...}`).
+pub struct MqttPublisher {
+    config: MqttConfig,
+    tag_engine: Arc<TagEngine>,
+    cancel: CancellationToken,
+}
+
+impl MqttPublisher {
+    pub fn new(config: MqttConfig, tag_engine: Arc<TagEngine>, cancel: CancellationToken) -> Self {
+        Self {
+            config,
+            tag_engine,
+            cancel,
+        }
+    }
+
+    /// Publish tag changes until the cancellation token fires.
+    pub async fn run(self) {
+        let (host, port) = match self.config.broker_address() {
+            Ok(address) => address,
+            Err(e) => {
+                error!("MQTT publisher disabled: {}", e);
+                return;
+            }
+        };
+        let qos = match rumqttc::qos(self.config.qos) {
+            Ok(qos) => qos,
+            Err(_) => {
+                error!("MQTT publisher disabled: invalid QoS {}", self.config.qos);
+                return;
+            }
+        };
+
+        let options = MqttOptions::new(self.config.client_id.clone(), host.clone(), port);
+        let (client, mut event_loop) = AsyncClient::new(options, REQUEST_CHANNEL_CAPACITY);
+        // The event loop does the actual network I/O and must be polled continuously
+        let connection_cancel = self.cancel.clone();
+        let connection = tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = connection_cancel.cancelled() => break,
+                    event = event_loop.poll() => {
+                        if let Err(e) = event {
+                            warn!("MQTT connection to {}:{} failed: {}", host, port, e);
+                            tokio::select! {
+                                _ = connection_cancel.cancelled() => break,
+                                _ = sleep(RECONNECT_DELAY) => {}
+                            }
+                        }
+                    }
+                }
+            }
+        });
+        info!("MQTT publisher started, publishing to '{}/...'.", self.config.base_topic);
+
+        let mut updates = StreamMap::new();
+        let mut ticker = interval(RESUBSCRIBE_INTERVAL);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                biased;
+                _ = self.cancel.cancelled() => break,
+                _ = ticker.tick() => self.subscribe_new_tags(&mut updates),
+                Some((path, value)) = updates.next(), if !updates.is_empty() => {
+                    self.publish(&client, qos, &path, &value);
+                }
+            }
+        }
+
+        // The connection task stops on the same token, so don't wait on the request queue
+        let _ = client.try_disconnect();
+        let _ = connection.await;
+        info!("MQTT publisher stopped.");
+    }
+
+    fn subscribe_new_tags(&self, updates: &mut StreamMap<String, WatchStream<TagValue>>) {
+        let known: HashSet<String> = updates.keys().cloned().collect();
+        for path in self.tag_engine.get_all_tag_paths() {
+            if !known.contains(&path) {
+                let stream = WatchStream::from_changes(self.tag_engine.subscribe(&path));
+                updates.insert(path, stream);
+            }
+        }
+    }
+
+    /// Queue a tag change for publishing. Changes are dropped rather than waited on
+    /// while the broker is unreachable and the queue is full.
+    fn publish(&self, client: &AsyncClient, qos: QoS, path: &str, value: &TagValue) {
+        let payload = match serde_json::to_vec(value) {
+            Ok(payload) => payload,
+            Err(e) => {
+                warn!("Failed to encode value of tag '{}': {}", path, e);
+                return;
+            }
+        };
+        if let Err(e) = client.try_publish(self.config.topic_for(path), qos, false, payload) {
+            warn!("Failed to publish tag '{}' to MQTT: {}", path, e);
+        }
+    }
+}
@@ -2,4 +2,7 @@ pub mod drivers;
 pub mod tags;
 pub mod config;
 pub mod api;
+pub mod historian;
+pub mod integrations;
 pub mod logging;
+pub mod alarms;
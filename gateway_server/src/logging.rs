@@ -1,11 +1,59 @@
+use serde::{Deserialize, Serialize};
 use std::io::{self, Write};
-use tokio::sync::mpsc::UnboundedSender;
-use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt};
+use tokio::sync::broadcast;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use tokio::task::JoinHandle;
+use tracing::Subscriber;
+use tracing_subscriber::fmt::writer::MakeWriterExt;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
 
-struct ChannelWriter {
+/// Log level, format and filtering.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct LoggingConfig {
+    /// A level (`error`, `warn`, `info`, `debug`, `trace`) or per-target
+    /// directives such as `info,gateway_server::drivers=debug`.
+    #[serde(default = "default_level")]
+    pub level: String,
+    /// Write one JSON object per event instead of human-readable text.
+    #[serde(default)]
+    pub json: bool,
+}
+
+fn default_level() -> String {
+    "info".to_string()
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: default_level(),
+            json: false,
+        }
+    }
+}
+
+impl LoggingConfig {
+    /// The filter for `level`, or an error naming the bad directive.
+    pub fn filter(&self) -> Result<EnvFilter, String> {
+        EnvFilter::try_new(&self.level).map_err(|e| format!("Invalid log level '{}': {}", self.level, e))
+    }
+}
+
+/// Log lines kept for viewers that fall behind; older lines are skipped for them.
+const LOG_STREAM_CAPACITY: usize = 1024;
+
+/// Sends each formatted event to a channel, e.g. for [`LogStream::forward`].
+pub struct ChannelWriter {
     tx: UnboundedSender<String>,
 }
 
+impl ChannelWriter {
+    pub fn new(tx: UnboundedSender<String>) -> Self {
+        Self { tx }
+    }
+}
+
 impl Write for ChannelWriter {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         let s = String::from_utf8_lossy(buf).to_string();
@@ -18,14 +66,75 @@ impl Write for ChannelWriter {
     }
 }
 
-/// Initialize logging. If a channel is provided, log output is forwarded
-/// to the channel instead of standard output.
-pub fn init_logging(forward: Option<UnboundedSender<String>>) {
-    if let Some(tx) = forward {
-        let layer = fmt::layer().with_writer(move || ChannelWriter { tx: tx.clone() });
-        tracing_subscriber::registry().with(layer).init();
+/// Build a subscriber formatting events as configured and writing them to `writer`.
+pub fn build_subscriber<W>(
+    config: &LoggingConfig,
+    writer: W,
+) -> Result<impl Subscriber + Send + Sync, String>
+where
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let filter = config.filter()?;
+    let layer = if config.json {
+        fmt::layer().json().with_writer(writer).boxed()
     } else {
-        tracing_subscriber::fmt::init();
+        fmt::layer().with_writer(writer).boxed()
+    };
+    Ok(tracing_subscriber::registry().with(layer).with(filter))
+}
+
+/// Initialize logging to standard output. If a channel is provided, log
+/// output is also forwarded to the channel. An invalid level falls back
+/// to the default configuration.
+pub fn init_logging(config: &LoggingConfig, forward: Option<UnboundedSender<String>>) {
+    let config = match config.filter() {
+        Ok(_) => config.clone(),
+        Err(e) => {
+            eprintln!("{e}; using the default logging configuration");
+            LoggingConfig::default()
+        }
+    };
+    let result = if let Some(tx) = forward {
+        let writer = io::stdout.and(move || ChannelWriter::new(tx.clone()));
+        build_subscriber(&config, writer).map(|s| s.init())
+    } else {
+        build_subscriber(&config, io::stdout).map(|s| s.init())
+    };
+    result.expect("logging configuration was checked above");
+}
+
+/// Fans forwarded log lines out to any number of live viewers.
+#[derive(Clone)]
+pub struct LogStream {
+    tx: broadcast::Sender<String>,
+}
+
+impl Default for LogStream {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
+impl LogStream {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(LOG_STREAM_CAPACITY);
+        Self { tx }
+    }
+
+    /// Receive every log line forwarded from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<String> {
+        self.tx.subscribe()
+    }
+
+    /// Pass each line from `rx`, as forwarded by [`init_logging`], on to the
+    /// current viewers until every sender is gone.
+    pub fn forward(&self, mut rx: UnboundedReceiver<String>) -> JoinHandle<()> {
+        let tx = self.tx.clone();
+        tokio::spawn(async move {
+            while let Some(line) = rx.recv().await {
+                // No viewers is not an error
+                let _ = tx.send(line);
+            }
+        })
+    }
+}
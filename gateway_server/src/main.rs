@@ -1,35 +1,65 @@
-use axum::{extract::State, http::StatusCode, response::IntoResponse, routing::get, Json, Router};
+use gateway_server::api::auth::auth_layer;
+use gateway_server::api::frontend::with_frontend;
 use gateway_server::api::rest::{create_api_routes, SharedAppState};
+use gateway_server::api::server::{cancel_on_signal, serve};
 use gateway_server::config::settings::Settings;
-use gateway_server::drivers::opcua::OpcUaDriver;
-use gateway_server::drivers::traits::{OpcDriver, OpcTagRequest};
+use gateway_server::config::reload::{watch_config_file, ConfigReloader};
+use gateway_server::drivers::breaker::CircuitBreakers;
+use gateway_server::drivers::scan_stats::ScanStatistics;
+use gateway_server::drivers::health::{start_drivers, ConnectionStates, HealthMonitor};
+use gateway_server::drivers::DriverMap;
+use gateway_server::historian::influx::InfluxWriter;
+use gateway_server::integrations::mqtt::MqttPublisher;
 use gateway_server::tags::engine::TagEngine;
-use gateway_server::tags::structures::{Quality, Tag, TagMetadata, TagValue};
-use gateway_server::logging::init_logging;
-use serde_json::json;
-use std::collections::HashMap;
-use std::net::SocketAddr;
-use std::path::Path;
+use gateway_server::tags::poller::Poller;
+use gateway_server::logging::{init_logging, LogStream};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{Notify, RwLock};
 use tokio::time::{interval, Duration, Instant};
-use tower_http::services::{ServeDir, ServeFile};
-use tower_http::validate_request::ValidateRequestHeaderLayer;
+use tokio_util::sync::CancellationToken;
 use tracing::{error, info, warn};
 
 // Modules are defined in the accompanying library crate (lib.rs)
 
+/// Last-known tag values, restored at startup and refreshed while running.
+const SNAPSHOT_PATH: &str = "tag_snapshot.json";
+
+/// The built web UI, served for every route the API doesn't handle.
+const WEBUI_DIR: &str = "webui/dist";
+
+/// How long requests in flight may take to finish once shutdown starts.
+const SHUTDOWN_DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(60);
+
 // Potentially other modules like scripting, historian, events etc.
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    init_logging(None);
-    info!("ForgeIO Gateway Server starting...");
     let start_time = Instant::now();
 
     // --- Load Configuration ---
-    let config_path = Path::new("config.toml");
-    let settings = match Settings::load(config_path) {
+    // TOML, YAML or JSON by extension; `config.toml` unless a path is given
+    let config_path = std::env::args()
+        .nth(1)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("config.toml"));
+    let config_path = config_path.as_path();
+    let loaded = Settings::load(config_path);
+    // Log with the configured format, or the defaults if the config can't be used
+    let logging = loaded
+        .as_ref()
+        .map(|settings| settings.logging.clone())
+        .unwrap_or_default();
+    // Also stream the log to viewers of /api/logs/ws
+    let (log_tx, log_rx) = tokio::sync::mpsc::unbounded_channel();
+    init_logging(&logging, Some(log_tx));
+    let logs = LogStream::new();
+    logs.forward(log_rx);
+    info!("ForgeIO Gateway Server starting...");
+
+    let settings = match loaded {
         Ok(s) => s,
         Err(e) => {
             error!(
@@ -39,6 +69,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             std::process::exit(1);
         }
     };
+    // Refuse to serve the API unauthenticated unless that was asked for explicitly
+    let auth = match settings.auth.clone() {
+        Some(auth) => auth,
+        None => {
+            error!(
+                "FATAL: {:?} has no [auth] section. Configure API credentials or set `enabled = false`.",
+                config_path
+            );
+            std::process::exit(1);
+        }
+    };
     info!(
         "Configuration loaded: {} devices, {} tags",
         settings.devices.len(),
@@ -48,245 +89,193 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let settings_arc = Arc::new(RwLock::new(settings.clone()));
 
     // --- Initialize Tag Engine ---
-    let tag_engine = TagEngine::new();
+    // Sized for the configured tags so registering them doesn't grow the maps
+    let tag_engine = TagEngine::with_capacity(settings.tags.len());
     let tag_engine_arc = Arc::new(tag_engine); // Wrap in Arc for sharing
     info!("Tag Engine initialized.");
 
     // --- Initialize Drivers ---
     // Store drivers in a thread-safe way, accessible by ID
-    let mut driver_instances: HashMap<String, Arc<dyn OpcDriver + Send + Sync>> = HashMap::new();
-
-    for driver_config in settings.devices {
-        info!(
-            "Initializing driver: {} ({})",
-            driver_config.name, driver_config.id
-        );
-
-        // TODO: Add a 'driver_type' field to OpcDriverConfig to select the correct driver
-        // For now, assume all are OPC UA if opcua driver exists
-        let driver: Arc<dyn OpcDriver + Send + Sync> = {
-            let driver = Arc::new(
-                OpcUaDriver::new(driver_config.clone())
-                    .map_err(|e| format!("Failed to create OPC UA driver: {}", e))?,
-            );
-            driver
-                .connect()
-                .await
-                .map_err(|e| format!("Failed to connect OPC UA driver: {}", e))?;
-            driver
-        };
-
-        driver_instances.insert(driver_config.id.clone(), driver);
-    }
-    let drivers_arc = Arc::new(driver_instances); // Share the driver map
-    info!("{} drivers initialized and connected.", drivers_arc.len());
+    let drivers_arc: Arc<DriverMap> = Arc::new(DriverMap::new());
+    let connection_states = Arc::new(ConnectionStates::new());
+    let shutdown = CancellationToken::new();
+    // Connect in the background; tags stay Bad until their driver is connected
+    let mut driver_connects = start_drivers(
+        &settings.devices,
+        &drivers_arc,
+        &connection_states,
+        &shutdown,
+    )?;
+    info!("{} drivers initialized, connecting in the background.", drivers_arc.len());
 
     // --- Register Tags ---
-    for tag_config in settings.tags {
-        // Check if the driver for this tag exists and was initialized
-        if drivers_arc.contains_key(&tag_config.driver_id) {
+    let mut tags = Vec::with_capacity(settings.tags.len());
+    let mut tag_paths = HashSet::with_capacity(settings.tags.len());
+    for tag_config in &settings.tags {
+        // Rejected when the config is loaded; if one gets through, the later definition wins
+        if !tag_paths.insert(tag_config.path.as_str()) {
+            warn!("Tag path '{}' is defined more than once, the last definition is used.", tag_config.path);
+        }
+        if let Some(source) = &tag_config.alias_of {
+            info!("Registering tag: {} (Alias of: {})", tag_config.path, source);
+            tags.push(tag_config.to_tag());
+        } else if drivers_arc.contains_key(&tag_config.driver_id) {
+            // The driver for this tag exists and was initialized
             info!(
                 "Registering tag: {} (Driver: {}, Address: {}, Rate: {}ms)",
                 tag_config.path, tag_config.driver_id, tag_config.address, tag_config.poll_rate_ms
             );
 
-            let metadata = TagMetadata {
-                description: Some("Default description".to_string()),
-                eng_unit: Some("unit".to_string()),
-                eng_low: Some(f64::MIN),
-                eng_high: Some(f64::MAX),
-                writable: false, // Ensure all fields are correctly set
-            };
-
-            let initial_tag = Tag {
-                path: tag_config.path.clone(),
-                value: TagValue::bad(Quality::Bad), // Start with Bad quality
-                driver_id: tag_config.driver_id.clone(),
-                driver_address: tag_config.address.clone(),
-                poll_rate_ms: tag_config.poll_rate_ms,
-                metadata, // Basic metadata
-            };
-            tag_engine_arc.register_tag(initial_tag);
+            tags.push(tag_config.to_tag());
         } else {
             warn!("Skipping tag '{}' because its driver '{}' was not found or failed to initialize.",
                 tag_config.path, tag_config.driver_id);
         }
     }
+    tag_engine_arc.register_tags(tags);
     info!("Tags registered in Tag Engine.");
 
-    // --- Start Polling Loop ---
-    let polling_tag_engine = Arc::clone(&tag_engine_arc);
-    let polling_drivers = Arc::clone(&drivers_arc);
-
-    tokio::spawn(async move {
-        info!("Polling task started.");
-        // Group tags by (driver_id, poll_rate_ms)
-        let mut poll_groups: HashMap<(String, u64), Vec<String>> = HashMap::new();
-        for tag_path in polling_tag_engine.get_all_tag_paths() {
-            // We need the full Tag info here, not just the path.
-            // Let's modify TagEngine slightly or fetch details here.
-            // For now, assuming we can get Tag details from the path.
-            // THIS IS A SIMPLIFICATION - requires TagEngine modification
-            if let Some(tag) = polling_tag_engine.get_tag_details(&tag_path) {
-                // Assumed method
-                poll_groups
-                    .entry((tag.driver_id.clone(), tag.poll_rate_ms))
-                    .or_default()
-                    .push(tag_path);
-            }
+    // Show last-known values until the first poll replaces them
+    let snapshot_path = Path::new(SNAPSHOT_PATH);
+    if snapshot_path.exists() {
+        match tag_engine_arc.restore_from(snapshot_path) {
+            Ok(restored) => info!("Restored {} tag values from {:?}.", restored, snapshot_path),
+            Err(e) => warn!("Could not restore tag values from {:?}: {}", snapshot_path, e),
         }
-        info!("Polling groups created: {}", poll_groups.len());
-
-        // Store last poll time for each group
-        let mut last_poll_times: HashMap<(String, u64), Instant> = HashMap::new();
-        let base_interval = Duration::from_millis(100); // Check every 100ms which groups are due
-        let mut tick_interval = interval(base_interval);
+    }
 
-        loop {
-            tick_interval.tick().await;
-            let now = Instant::now();
+    // --- Watch Configuration For Changes ---
+    let config_changed = Arc::new(Notify::new());
+    // Shared with the config API so both apply changes one at a time
+    let reloader = ConfigReloader::new(
+        config_path.to_path_buf(),
+        Arc::clone(&settings_arc),
+        Arc::clone(&tag_engine_arc),
+        Arc::clone(&drivers_arc),
+        Arc::clone(&connection_states),
+        Arc::clone(&config_changed),
+    );
+    // Keep the watcher alive for the lifetime of the server
+    let _config_watcher = match watch_config_file(config_path) {
+        Ok((watcher, events)) => {
+            tokio::spawn(reloader.clone().run(events));
+            info!("Watching {:?} for changes.", config_path);
+            Some(watcher)
+        }
+        Err(e) => {
+            warn!("Config hot reload disabled, could not watch {:?}: {}", config_path, e);
+            None
+        }
+    };
 
-            for ((driver_id, poll_rate_ms), tag_paths) in &poll_groups {
-                let poll_duration = Duration::from_millis(*poll_rate_ms);
-                let last_poll = last_poll_times
-                    .entry((driver_id.clone(), *poll_rate_ms))
-                    .or_insert(Instant::now() - Duration::from_secs(60));
+    // --- Start Polling Loop ---
+    let breakers = Arc::new(CircuitBreakers::default());
+    let scan_stats = Arc::new(ScanStatistics::new());
+    let mut poller = Poller::new(
+        Arc::clone(&tag_engine_arc),
+        Arc::clone(&drivers_arc),
+        Arc::clone(&config_changed),
+        shutdown.clone(),
+    )
+    .with_circuit_breakers(Arc::clone(&breakers))
+    .with_scan_stats(Arc::clone(&scan_stats));
+    if let Some(poll_tick_ms) = settings.poll_tick_ms {
+        poller = poller.with_poll_tick(Duration::from_millis(poll_tick_ms));
+    }
+    let poller_handle = tokio::spawn(poller.run());
 
-                if now.duration_since(*last_poll) >= poll_duration {
-                    // This group is due for polling
-                    info!(
-                        "Polling group: Driver '{}', Rate {}ms, Tags: {}",
-                        driver_id,
-                        poll_rate_ms,
-                        tag_paths.len()
-                    );
+    // --- Start Historian ---
+    if let Some(influx) = settings.influx.clone() {
+        let writer = InfluxWriter::new(influx, Arc::clone(&tag_engine_arc), shutdown.clone());
+        tokio::spawn(writer.run());
+    }
 
-                    if let Some(driver) = polling_drivers.get(driver_id) {
-                        let mut requests = Vec::new();
-                        // Need tag address again - requires TagEngine modification or storing more info
-                        for path in tag_paths {
-                            if let Some(tag) = polling_tag_engine.get_tag_details(path) {
-                                // Assumed method
-                                requests.push(OpcTagRequest {
-                                    address: tag.driver_address,
-                                });
-                            }
-                        }
+    // --- Start MQTT Publisher ---
+    if let Some(mqtt) = settings.mqtt.clone() {
+        let publisher = MqttPublisher::new(mqtt, Arc::clone(&tag_engine_arc), shutdown.clone());
+        tokio::spawn(publisher.run());
+    }
 
-                        if !requests.is_empty() {
-                            match driver.read_tags(&requests).await {
-                                Ok(results) => {
-                                    info!(
-                                        "Read successful for {} tags from driver '{}'",
-                                        results.len(),
-                                        driver_id
-                                    );
-                                    for (address, driver_tag_value) in results {
-                                        if let Some(path) = polling_tag_engine
-                                            .find_path_by_address(driver_id, &address)
-                                        {
-                                            let structures_tag_value =
-                                                TagValue::from(driver_tag_value);
-                                            polling_tag_engine
-                                                .update_tag_value(&path, structures_tag_value);
-                                        }
-                                    }
-                                }
-                                Err(e) => {
-                                    error!(
-                                        "Failed to read tags from driver '{}': {}",
-                                        driver_id, e
-                                    );
-                                    // Optionally mark tags as Bad quality
-                                    for path in tag_paths {
-                                        polling_tag_engine
-                                            .update_tag_value(path, TagValue::bad(Quality::Bad));
-                                    }
-                                }
-                            }
-                        }
-                    } else {
-                        warn!("Driver '{}' not found for polling.", driver_id);
-                        // Mark tags as Bad?
-                    }
-                    // Update last poll time regardless of success/failure to avoid spamming logs on error
-                    *last_poll = now;
+    // --- Snapshot Tag Values Periodically ---
+    {
+        let tag_engine = Arc::clone(&tag_engine_arc);
+        let shutdown = shutdown.clone();
+        tokio::spawn(async move {
+            let mut ticker = interval(SNAPSHOT_INTERVAL);
+            ticker.tick().await; // The first tick completes immediately
+            loop {
+                tokio::select! {
+                    _ = shutdown.cancelled() => break,
+                    _ = ticker.tick() => {}
+                }
+                if let Err(e) = tag_engine.snapshot_to(Path::new(SNAPSHOT_PATH)) {
+                    warn!("Failed to write tag snapshot: {}", e);
                 }
             }
-        }
-    });
+        });
+    }
+
+    // --- Start Driver Health Monitor ---
+    let health_monitor = HealthMonitor::new(
+        Arc::clone(&drivers_arc),
+        Arc::clone(&connection_states),
+        shutdown.clone(),
+    )
+    .with_circuit_breakers(Arc::clone(&breakers));
+    let health_handle = tokio::spawn(health_monitor.run());
+
+    // Stop polling and serving on Ctrl-C or SIGTERM
+    tokio::spawn(cancel_on_signal(shutdown.clone()));
 
     // --- Start API Server ---
     info!("Starting API server...");
     let app_state = SharedAppState {
         tag_engine: Arc::clone(&tag_engine_arc),
-        driver_count: drivers_arc.len(),
         start_time,
         settings: Arc::clone(&settings_arc),
         drivers: Arc::clone(&drivers_arc),
+        connection_states: Arc::clone(&connection_states),
+        breakers,
+        scan_stats,
+        reloader,
+        logs,
     };
     
-    // Create the OPC UA API routes 
-    let opcua_routes = create_api_routes();
-    
-    let app = Router::new()
-        .route("/api/health", get(root))
-        .route("/api/stats", get(stats))
-        .route("/tags", get(get_tags))
-        .route("/api/config", get(get_config).put(update_config))
-        .merge(opcua_routes)
-        .with_state(app_state)
-        .fallback_service(
-            ServeDir::new("webui/dist").not_found_service(ServeFile::new("webui/dist/index.html")),
-        )
-        // Serve frontend and support client-side routing
-        .layer(ValidateRequestHeaderLayer::basic("admin", "admin"));
+    let app = create_api_routes().with_state(app_state);
+    let app = with_frontend(app, Path::new(WEBUI_DIR));
+    let app = match auth_layer(&auth) {
+        Some(layer) => app.layer(layer),
+        None => {
+            warn!("API authentication is disabled by the [auth] section.");
+            app
+        }
+    };
+    // Outside the auth layer: browsers send preflight requests without credentials
+    let app = match &settings.cors {
+        Some(cors) => app.layer(cors.layer()?),
+        None => app,
+    };
 
-    let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
+    // Checked when the settings were loaded
+    let addr = settings.server.socket_addr()?;
     info!("API server listening on {}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    serve(listener, app, shutdown.clone(), SHUTDOWN_DRAIN_TIMEOUT).await?;
 
-    Ok(())
-}
-
-// Simple health check endpoint
-async fn root() -> &'static str {
-    "ForgeIO Gateway Server Running"
-}
-
-async fn get_tags(State(state): State<SharedAppState>) -> impl IntoResponse {
-    let tags = state.tag_engine.get_all_tags().await;
-    Json(json!(tags))
-}
-
-async fn get_config(State(state): State<SharedAppState>) -> impl IntoResponse {
-    let cfg = state.settings.read().await.clone();
-    Json(cfg)
-}
-
-async fn update_config(
-    State(state): State<SharedAppState>,
-    Json(new_cfg): Json<Settings>,
-) -> impl IntoResponse {
-    if let Err(e) = new_cfg.save(Path::new("config.toml")) {
-        return (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({ "error": e.to_string() })),
-        );
+    // Make sure no reconnect is in flight and the poller has disconnected the drivers before exiting
+    shutdown.cancel();
+    while driver_connects.join_next().await.is_some() {}
+    if let Err(e) = health_handle.await {
+        error!("Health monitor task failed: {}", e);
     }
-    let mut cfg_lock = state.settings.write().await;
-    *cfg_lock = new_cfg;
-    (StatusCode::OK, Json(json!({ "status": "ok" })))
-}
+    if let Err(e) = poller_handle.await {
+        error!("Polling task failed: {}", e);
+    }
+    if let Err(e) = tag_engine_arc.snapshot_to(snapshot_path) {
+        error!("Failed to write tag snapshot to {:?}: {}", snapshot_path, e);
+    }
+    info!("ForgeIO Gateway Server stopped.");
 
-async fn stats(State(state): State<SharedAppState>) -> impl IntoResponse {
-    let tag_count = state.tag_engine.get_all_tag_paths().len();
-    let uptime = state.start_time.elapsed().as_secs();
-    Json(json!({
-        "uptime_seconds": uptime,
-        "tag_count": tag_count,
-        "driver_count": state.driver_count,
-    }))
+    Ok(())
 }
@@ -1,58 +1,540 @@
-use crate::tags::structures::{Tag, TagValue};
+use crate::alarms::AlarmEngine;
+use crate::tags::events::{EventChannel, EventReceiver};
+use crate::tags::history::History;
+use crate::tags::path::{TagPath, SEPARATOR};
+use crate::tags::structures::{Quality, QualityEvent, Tag, TagNode, TagStats, TagValue, ValueVariant};
 use dashmap::DashMap; // Using DashMap for concurrent R/W access
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io;
+use std::mem::size_of;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::watch;
+
+/// Restored values older than this are marked [`Quality::Stale`].
+const SNAPSHOT_STALE_AFTER: Duration = Duration::from_secs(60);
+
+/// Quality events buffered per receiver before slow receivers start missing some,
+/// see [`TagEngine::dropped_quality_events`].
+const QUALITY_EVENT_CAPACITY: usize = 256;
 
 /// Manages the state of all tags in the system.
 /// Uses DashMap for thread-safe access.
 #[derive(Debug, Clone)] // Clone provides cheap Arc clones
 pub struct TagEngine {
-    tags: Arc<DashMap<String, Tag>>,
+    tags: Arc<DashMap<TagPath, Tag>>,
+    /// Change notification channels, created lazily by `subscribe`.
+    watchers: Arc<DashMap<String, watch::Sender<TagValue>>>,
+    /// Per-tag update statistics, kept alongside `tags`.
+    stats: Arc<DashMap<String, TagStats>>,
+    /// Recent values of each tag, appended on every stored update.
+    history: Arc<History>,
+    /// Paths of the tags at each `(driver_id, driver_address)`, in registration
+    /// order. Several tags may share an address.
+    addresses: Arc<DashMap<(String, String), Vec<String>>>,
+    /// Paths of the alias tags mirroring each source tag, see
+    /// [`TagMetadata::alias_of`](crate::tags::structures::TagMetadata::alias_of).
+    aliases: Arc<DashMap<String, Vec<String>>>,
+    /// Limit alarms, evaluated on every stored update.
+    alarms: Arc<AlarmEngine>,
+    /// Quality transitions of stored updates, see [`quality_events`](Self::quality_events).
+    quality_events: EventChannel<QualityEvent>,
+    /// Bumped by [`set_poll_rate`](Self::set_poll_rate) so the poller knows to
+    /// regroup the tags.
+    poll_rates_version: Arc<AtomicU64>,
 }
 
 impl TagEngine {
     pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    /// Create an engine with room for `capacity` tags, so registering that many
+    /// doesn't have to grow the maps along the way.
+    pub fn with_capacity(capacity: usize) -> Self {
+        TagEngine {
+            tags: Arc::new(DashMap::with_capacity(capacity)),
+            watchers: Arc::new(DashMap::new()),
+            stats: Arc::new(DashMap::with_capacity(capacity)),
+            history: Arc::new(History::default()),
+            addresses: Arc::new(DashMap::with_capacity(capacity)),
+            aliases: Arc::new(DashMap::new()),
+            alarms: Arc::new(AlarmEngine::new()),
+            quality_events: EventChannel::new(QUALITY_EVENT_CAPACITY),
+            poll_rates_version: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Create an engine that keeps up to `capacity` recent values per tag.
+    pub fn with_history_capacity(capacity: usize) -> Self {
         TagEngine {
-            tags: Arc::new(DashMap::new()),
+            history: Arc::new(History::new(capacity)),
+            ..Self::new()
         }
     }
 
     /// Add or update a tag definition.
     /// (In a real scenario, this might load from config initially).
-    pub fn register_tag(&self, tag: Tag) {
-        self.tags.insert(tag.path.clone(), tag);
+    /// An alias starts out with its source's current value if the source is
+    /// already registered.
+    pub fn register_tag(&self, mut tag: Tag) {
+        let path = tag.path.to_string();
+        let source = tag.metadata.alias_of.clone();
+        if let Some(current) = source.as_deref().and_then(|source| self.read_tag(source)) {
+            tag.value = current;
+        }
+        let value = tag.value.clone();
+        let address = (tag.driver_id.clone(), tag.driver_address.clone());
+        if let Some(previous) = self.tags.insert(tag.path.clone(), tag) {
+            if (&previous.driver_id, &previous.driver_address) != (&address.0, &address.1) {
+                self.unindex_address(&previous);
+            }
+            if previous.metadata.alias_of != source {
+                self.unlink_alias(&previous);
+            }
+        }
+        if let Some(source) = source {
+            let mut aliases = self.aliases.entry(source.into()).or_default();
+            if !aliases.contains(&path) {
+                aliases.push(path.clone());
+            }
+        }
+        let mut paths = self.addresses.entry(address).or_default();
+        if !paths.contains(&path) {
+            paths.push(path.clone());
+        }
+        drop(paths);
+        // Changed limits are applied from the next update on
+        self.alarms.clear_tag(&path);
+        // Re-registering a tag keeps the statistics gathered so far
+        self.stats.entry(path.clone()).or_default();
+        self.notify_watchers(&path, value);
+    }
+
+    /// Register many tags at once, e.g. the whole configuration at startup.
+    /// Same as calling [`register_tag`](Self::register_tag) for each in order;
+    /// pair it with [`with_capacity`](Self::with_capacity) to avoid growing the
+    /// maps while inserting.
+    pub fn register_tags(&self, tags: Vec<Tag>) {
+        for tag in tags {
+            self.register_tag(tag);
+        }
+    }
+
+    /// Remove a tag definition, returning it if it was registered.
+    /// Subscribers of the tag see their channel close.
+    pub fn unregister_tag(&self, tag_path: &str) -> Option<Tag> {
+        self.watchers.remove(tag_path);
+        self.stats.remove(tag_path);
+        self.history.remove(tag_path);
+        self.alarms.clear_tag(tag_path);
+        let (_, tag) = self.tags.remove(tag_path)?;
+        self.unindex_address(&tag);
+        self.unlink_alias(&tag);
+        Some(tag)
+    }
+
+    fn unlink_alias(&self, tag: &Tag) {
+        let Some(source) = tag.metadata.alias_of.as_deref() else {
+            return;
+        };
+        if let Some(mut aliases) = self.aliases.get_mut(source) {
+            aliases.retain(|path| tag.path != path.as_str());
+        }
+        self.aliases.remove_if(source, |_, aliases| aliases.is_empty());
+    }
+
+    fn unindex_address(&self, tag: &Tag) {
+        let key = (tag.driver_id.clone(), tag.driver_address.clone());
+        if let Some(mut paths) = self.addresses.get_mut(&key) {
+            paths.retain(|path| tag.path != path.as_str());
+        }
+        self.addresses.remove_if(&key, |_, paths| paths.is_empty());
+    }
+
+    /// Change how often a tag is polled, taking effect from the poller's next
+    /// tick. Returns `false` if the tag is not registered.
+    pub fn set_poll_rate(&self, tag_path: &str, poll_rate_ms: u64) -> bool {
+        let Some(mut tag) = self.tags.get_mut(tag_path) else {
+            return false;
+        };
+        if tag.poll_rate_ms != poll_rate_ms {
+            tag.poll_rate_ms = poll_rate_ms;
+            self.poll_rates_version.fetch_add(1, Ordering::Release);
+        }
+        true
+    }
+
+    /// Pause or resume polling of a tag, taking effect from the poller's next
+    /// tick. Disabling keeps the tag's last value but marks it
+    /// [`Quality::Stale`]; once enabled again the next poll refreshes it.
+    /// Returns `false` if the tag is not registered.
+    pub fn set_enabled(&self, tag_path: &str, enabled: bool) -> bool {
+        let stale = {
+            let Some(mut tag) = self.tags.get_mut(tag_path) else {
+                return false;
+            };
+            if tag.enabled == enabled {
+                return true;
+            }
+            tag.enabled = enabled;
+            self.poll_rates_version.fetch_add(1, Ordering::Release);
+            (!enabled).then(|| TagValue {
+                quality: Quality::Stale,
+                ..tag.value.clone()
+            })
+        };
+        if let Some(stale) = stale {
+            self.apply_update(tag_path, stale, false);
+        }
+        true
+    }
+
+    /// Changes whenever a poll rate is changed through [`set_poll_rate`](Self::set_poll_rate)
+    /// or a tag is enabled or disabled through [`set_enabled`](Self::set_enabled).
+    pub fn poll_rates_version(&self) -> u64 {
+        self.poll_rates_version.load(Ordering::Acquire)
+    }
+
+    /// Remove all tags.
+    pub fn clear(&self) {
+        self.watchers.clear();
+        self.stats.clear();
+        self.history.clear();
+        self.alarms.clear();
+        self.addresses.clear();
+        self.aliases.clear();
+        self.tags.clear();
+    }
+
+    /// Get the update statistics of a tag.
+    pub fn stats(&self, tag_path: &str) -> Option<TagStats> {
+        self.stats.get(tag_path).map(|stats| stats.clone())
+    }
+
+    /// The newest `limit` values stored for a tag, oldest first.
+    pub fn history(&self, tag_path: &str, limit: usize) -> Vec<TagValue> {
+        self.history.recent(tag_path, limit)
+    }
+
+    /// Remember the most recent error seen while acquiring a tag's value.
+    /// Returns false if the tag is not registered.
+    pub fn record_error(&self, tag_path: &str, error: impl Into<String>) -> bool {
+        match self.stats.get_mut(tag_path) {
+            Some(mut stats) => {
+                stats.last_error = Some(error.into());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Watch a tag for value changes.
+    /// The receiver starts with the current value (or an `Initializing` placeholder
+    /// if the tag is not registered yet) and sees every subsequent update.
+    pub fn subscribe(&self, tag_path: &str) -> watch::Receiver<TagValue> {
+        let current = self
+            .read_tag(tag_path)
+            .unwrap_or_else(|| TagValue::bad(Quality::Initializing));
+        self.watchers
+            .entry(tag_path.to_string())
+            .or_insert_with(|| watch::channel(current).0)
+            .subscribe()
+    }
+
+    fn notify_watchers(&self, tag_path: &str, value: TagValue) {
+        let closed = match self.watchers.get(tag_path) {
+            Some(tx) => {
+                // send_replace never fails, even when every receiver is gone.
+                tx.send_replace(value);
+                tx.is_closed()
+            }
+            None => return,
+        };
+        if closed {
+            self.watchers.remove_if(tag_path, |_, tx| tx.is_closed());
+        }
+    }
+
+    /// Alarms raised by the tags' configured limits.
+    pub fn alarms(&self) -> &AlarmEngine {
+        &self.alarms
+    }
+
+    /// Receive an event whenever an update changes a tag's quality, e.g. from
+    /// Good to Bad and back. Updates that keep the quality send nothing. A
+    /// receiver more than 256 events behind misses the oldest ones; updates are
+    /// never held up by slow receivers.
+    pub fn quality_events(&self) -> EventReceiver<QualityEvent> {
+        self.quality_events.subscribe()
+    }
+
+    /// Quality events missed by receivers that fell behind, summed over all receivers.
+    pub fn dropped_quality_events(&self) -> u64 {
+        self.quality_events.dropped()
     }
 
     /// Get a snapshot of a tag's value.
     pub fn read_tag(&self, tag_path: &str) -> Option<TagValue> {
-        self.tags.get(tag_path).and_then(|tag_ref| Some(tag_ref.value.clone()))
+        self.tags.get(tag_path).map(|tag_ref| tag_ref.value.clone())
     }
 
-    /// Update the value of an existing tag.
+    /// Get a snapshot of a whole tag, value and metadata taken together in a
+    /// single lookup so they can't come from different updates.
+    pub fn read_tag_full(&self, tag_path: &str) -> Option<Tag> {
+        self.tags.get(tag_path).map(|tag_ref| tag_ref.clone())
+    }
+
+    /// Update the value of an existing tag. A Bad `Null` value, as stored for a
+    /// failed read, keeps the value and timestamp of a tag with
+    /// [`hold_last_value`](crate::tags::structures::TagMetadata::hold_last_value)
+    /// and only changes its quality.
     pub fn update_tag_value(&self, tag_path: &str, new_value: TagValue) -> bool {
-        match self.tags.get_mut(tag_path) {
+        self.apply_update(tag_path, new_value, false)
+    }
+
+    /// Update a tag with a raw driver value, scaling it into the tag's
+    /// engineering range first (see [`TagMetadata::scale_raw`](crate::tags::structures::TagMetadata::scale_raw)).
+    pub fn update_tag_value_scaled(&self, tag_path: &str, raw_value: TagValue) -> bool {
+        self.apply_update(tag_path, raw_value, true)
+    }
+
+    /// Update the values of several tags in one call.
+    /// Returns how many of the paths referred to registered tags.
+    pub fn update_tag_values(&self, updates: Vec<(String, TagValue)>) -> usize {
+        let mut updated = 0;
+        for (tag_path, new_value) in updates {
+            if self.apply_update(&tag_path, new_value, false) {
+                updated += 1;
+            }
+        }
+        updated
+    }
+
+    /// Bulk variant of [`update_tag_value_scaled`](Self::update_tag_value_scaled).
+    pub fn update_tag_values_scaled(&self, updates: Vec<(String, TagValue)>) -> usize {
+        let mut updated = 0;
+        for (tag_path, raw_value) in updates {
+            if self.apply_update(&tag_path, raw_value, true) {
+                updated += 1;
+            }
+        }
+        updated
+    }
+
+    fn apply_update(&self, tag_path: &str, new_value: TagValue, scale: bool) -> bool {
+        match self.store_value(tag_path, new_value, scale) {
+            Some(stored) => {
+                if stored {
+                    self.mirror_to_aliases(tag_path);
+                }
+                true
+            }
+            None => false, // Tag not found
+        }
+    }
+
+    /// Copy the value of `source` to its aliases, and on to aliases of those.
+    /// Each tag is written at most once, so a cycle that slipped past config
+    /// validation can't loop forever.
+    fn mirror_to_aliases(&self, source: &str) {
+        if self.aliases.is_empty() {
+            return;
+        }
+        let mut visited = HashSet::from([source.to_string()]);
+        let mut pending = vec![source.to_string()];
+        while let Some(source) = pending.pop() {
+            let Some(aliases) = self.aliases.get(&source).map(|aliases| aliases.clone()) else {
+                continue;
+            };
+            let Some(value) = self.read_tag(&source) else {
+                continue;
+            };
+            for alias in aliases {
+                if visited.insert(alias.clone())
+                    && self.store_value(&alias, value.clone(), false) == Some(true)
+                {
+                    pending.push(alias);
+                }
+            }
+        }
+    }
+
+    /// Store an update of one tag. `None` if the tag is not registered, otherwise
+    /// whether the value was stored rather than dropped by the deadband.
+    fn store_value(&self, tag_path: &str, mut new_value: TagValue, scale: bool) -> Option<bool> {
+        let (notify, transition) = match self.tags.get_mut(tag_path) {
             Some(mut tag_ref) => {
+                if scale {
+                    new_value.value = tag_ref.metadata.scale_raw(new_value.value);
+                }
+                let changed_at = new_value.timestamp;
+                // A failed read only degrades the quality of a tag holding its last value
+                if tag_ref.metadata.hold_last_value
+                    && new_value.quality == Quality::Bad
+                    && new_value.value == ValueVariant::Null
+                {
+                    new_value.value = tag_ref.value.value.clone();
+                    new_value.timestamp = tag_ref.value.timestamp;
+                }
+                // Keep the stored value (and its timestamp) for sub-deadband jitter.
+                if new_value.quality == tag_ref.value.quality
+                    && tag_ref
+                        .metadata
+                        .within_deadband(&tag_ref.value.value, &new_value.value)
+                {
+                    return Some(false);
+                }
+                let notify = self
+                    .watchers
+                    .contains_key(tag_path)
+                    .then(|| new_value.clone());
+                let transition = (new_value.quality != tag_ref.value.quality).then(|| QualityEvent {
+                    path: tag_path.to_string(),
+                    old: tag_ref.value.quality.clone(),
+                    new: new_value.quality.clone(),
+                    timestamp: changed_at,
+                });
+                if let Some(mut stats) = self.stats.get_mut(tag_path) {
+                    stats.update_count += 1;
+                    if new_value.quality == Quality::Good {
+                        stats.last_good_timestamp = new_value.timestamp;
+                    }
+                }
+                self.history.push(tag_path, new_value.clone());
+                self.alarms
+                    .evaluate(tag_path, &tag_ref.metadata.alarms, &tag_ref.value, &new_value);
                 tag_ref.value = new_value;
-                true // Update successful
+                (notify, transition)
             }
-            None => false, // Tag not found
+            None => return None,
+        };
+        if let Some(value) = notify {
+            self.notify_watchers(tag_path, value);
+        }
+        if let Some(event) = transition {
+            self.quality_events.send(event);
         }
+        Some(true)
     }
 
     /// Get a list of all registered tag paths.
     pub fn get_all_tag_paths(&self) -> Vec<String> {
-        self.tags.iter().map(|entry| entry.key().clone()).collect()
+        self.tags.iter().map(|entry| entry.key().to_string()).collect()
     }
 
-    /// Get the details of a tag.
+    /// Get the details of a tag. Same as [`read_tag_full`](Self::read_tag_full).
     pub fn get_tag_details(&self, tag_path: &str) -> Option<Tag> {
-        self.tags.get(tag_path).map(|tag_ref| tag_ref.clone()) // Clone the Tag struct
+        self.read_tag_full(tag_path)
     }
 
-    /// Find the path of a tag by its driver ID and address.
+    /// Find the path of a tag by its driver ID and address. When several tags
+    /// share the address, the one registered first is returned.
     pub fn find_path_by_address(&self, driver_id: &str, address: &str) -> Option<String> {
-        self.tags.iter()
-            .find(|entry| entry.driver_id == driver_id && entry.driver_address == address)
-            .map(|entry| entry.key().clone())
+        let key = (driver_id.to_string(), address.to_string());
+        self.addresses.get(&key).and_then(|paths| paths.first().cloned())
+    }
+
+    /// Resolve a batch of one driver's addresses to tag paths, keyed by address.
+    /// Unknown addresses are left out; shared ones resolve as in
+    /// [`find_path_by_address`](Self::find_path_by_address).
+    pub fn paths_for_addresses(
+        &self,
+        driver_id: &str,
+        addresses: &[String],
+    ) -> HashMap<String, String> {
+        let mut key = (driver_id.to_string(), String::new());
+        let mut paths = HashMap::with_capacity(addresses.len());
+        for address in addresses {
+            address.clone_into(&mut key.1);
+            if let Some(path) = self.addresses.get(&key).and_then(|p| p.first().cloned()) {
+                paths.insert(address.clone(), path);
+            }
+        }
+        paths
+    }
+
+    /// Get the tags whose value is newer than `since` (Unix ms), sorted by path.
+    pub fn changed_since(&self, since: u64) -> Vec<Tag> {
+        let mut tags: Vec<Tag> = self
+            .tags
+            .iter()
+            .filter(|entry| entry.value.timestamp > since)
+            .map(|entry| entry.value().clone())
+            .collect();
+        tags.sort_unstable_by(|a, b| a.path.cmp(&b.path));
+        tags
+    }
+
+    /// Get all tags whose path matches a glob pattern.
+    /// `*` matches any run of characters (including `/`) and `?` matches exactly one,
+    /// so `Plant1/*` selects a subtree and `*/Temperature` a leaf name anywhere.
+    pub fn query_tags(&self, pattern: &str) -> Vec<Tag> {
+        self.tags
+            .iter()
+            .filter(|entry| glob_match(pattern, entry.key().as_str()))
+            .map(|entry| entry.value().clone())
+            .collect()
+    }
+
+    /// List the tags and sub-folders directly below the folder `prefix`, folders
+    /// first and each group sorted by name. An empty prefix lists the root, and a
+    /// trailing `/` is optional. A name used both as a tag and as a folder is listed
+    /// twice.
+    pub fn list_children(&self, prefix: &str) -> Vec<TagNode> {
+        let folder: Vec<&str> = prefix.split(SEPARATOR).filter(|s| !s.is_empty()).collect();
+        let base: String = folder.iter().map(|segment| format!("{segment}{SEPARATOR}")).collect();
+
+        let mut children = BTreeMap::new();
+        for entry in self.tags.iter() {
+            let Some(mut rest) = entry.key().strip_folder(&folder) else {
+                continue;
+            };
+            // The folder itself may be a tag too
+            let Some(name) = rest.next() else {
+                continue;
+            };
+            let is_folder = rest.next().is_some();
+            children
+                .entry((!is_folder, name.to_string()))
+                .or_insert_with(|| TagNode {
+                    name: name.to_string(),
+                    path: format!("{base}{name}"),
+                    is_folder,
+                });
+        }
+        children.into_values().collect()
+    }
+
+    /// Get one page of tags sorted by path, optionally filtered by glob pattern and
+    /// driver. Returns the total number of matching tags along with the page.
+    /// Only the tags on the page are cloned.
+    pub fn query_tags_page(
+        &self,
+        pattern: Option<&str>,
+        driver_id: Option<&str>,
+        offset: usize,
+        limit: usize,
+    ) -> (usize, Vec<Tag>) {
+        let mut paths: Vec<String> = self
+            .tags
+            .iter()
+            .filter(|entry| pattern.is_none_or(|p| glob_match(p, entry.key().as_str())))
+            .filter(|entry| driver_id.is_none_or(|d| entry.driver_id == d))
+            .map(|entry| entry.key().to_string())
+            .collect();
+        paths.sort_unstable();
+
+        let total = paths.len();
+        let page = paths
+            .iter()
+            .skip(offset)
+            .take(limit)
+            .filter_map(|path| self.get_tag_details(path))
+            .collect();
+        (total, page)
     }
 
     /// Get a serializable list of all tags.
@@ -60,9 +542,121 @@ impl TagEngine {
         self.tags.iter().map(|entry| entry.value().clone()).collect()
     }
 
-    // TODO: Add methods for bulk reads/writes if needed
-    // TODO: Add methods for browsing/querying tags
-    // TODO: Integrate with persistence/historian
+    /// Call `f` with every tag without cloning them. The tags are visited in no
+    /// particular order, and `f` must not call back into the engine's tag
+    /// registration or updates.
+    pub fn for_each<F: FnMut(&Tag)>(&self, mut f: F) {
+        for entry in self.tags.iter() {
+            f(entry.value());
+        }
+    }
+
+    /// Rough memory used by the tags, the address index and the value history,
+    /// in bytes. Walks every entry, so it grows with paths, addresses and string
+    /// values; map overhead and allocator slack are not included.
+    pub fn approx_memory_bytes(&self) -> usize {
+        let mut bytes = 0;
+        self.for_each(|tag| {
+            let text = [&tag.driver_id, &tag.driver_address]
+                .into_iter()
+                .chain(&tag.metadata.description)
+                .chain(&tag.metadata.eng_unit);
+            // The path is stored again as the map key
+            bytes += size_of::<TagPath>()
+                + 2 * tag.path.as_str().len()
+                + size_of::<Tag>()
+                + text.map(String::capacity).sum::<usize>()
+                + tag.value.value.heap_bytes();
+        });
+        for entry in self.addresses.iter() {
+            let ((driver_id, address), paths) = entry.pair();
+            bytes += size_of::<((String, String), Vec<String>)>()
+                + driver_id.capacity()
+                + address.capacity()
+                + paths.capacity() * size_of::<String>()
+                + paths.iter().map(String::capacity).sum::<usize>();
+        }
+        bytes + self.history.approx_memory_bytes()
+    }
+
+    /// Count the tags by the quality of their current value.
+    pub fn count_by_quality(&self) -> HashMap<Quality, usize> {
+        let mut counts = HashMap::new();
+        self.for_each(|tag| *counts.entry(tag.value.quality.clone()).or_insert(0) += 1);
+        counts
+    }
+
+    /// Write the current value of every tag to `path` as JSON, keyed by tag path.
+    /// The file is replaced atomically so a crash never leaves a truncated snapshot.
+    pub fn snapshot_to(&self, path: &Path) -> io::Result<()> {
+        let values: BTreeMap<String, TagValue> = self
+            .tags
+            .iter()
+            .map(|entry| (entry.key().to_string(), entry.value.clone()))
+            .collect();
+        let json = serde_json::to_vec_pretty(&values)?;
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, json)?;
+        std::fs::rename(&tmp, path)
+    }
+
+    /// Load values saved by [`snapshot_to`](Self::snapshot_to) into the registered tags.
+    /// Values older than a minute keep their timestamp but get [`Quality::Stale`];
+    /// paths that are no longer registered are ignored. Returns how many tags were restored.
+    pub fn restore_from(&self, path: &Path) -> io::Result<usize> {
+        let values: BTreeMap<String, TagValue> = serde_json::from_slice(&std::fs::read(path)?)?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let stale_before = now.saturating_sub(SNAPSHOT_STALE_AFTER.as_millis() as u64);
+
+        let mut restored = 0;
+        for (tag_path, mut value) in values {
+            if value.timestamp < stale_before {
+                value.quality = Quality::Stale;
+            }
+            match self.tags.get_mut(tag_path.as_str()) {
+                Some(mut tag_ref) => tag_ref.value = value.clone(),
+                None => continue,
+            }
+            self.notify_watchers(&tag_path, value);
+            restored += 1;
+        }
+        Ok(restored)
+    }
+}
+
+/// Match `text` against a glob `pattern` supporting `*` and `?`.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` seen and the text index it is currently matched up to.
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                // Let the last `*` swallow one more character and retry.
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
 }
 
 impl Default for TagEngine {
@@ -0,0 +1,89 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError, error::TryRecvError};
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+
+/// Bounded fan-out of events to any number of receivers. Sending never waits:
+/// each receiver buffers up to `capacity` events, and one that falls further
+/// behind skips the oldest. Skipped events are counted in [`dropped`](Self::dropped)
+/// instead of being buffered without limit.
+#[derive(Debug, Clone)]
+pub struct EventChannel<T> {
+    tx: broadcast::Sender<T>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl<T: Clone + Send + 'static> EventChannel<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            tx: broadcast::channel(capacity.max(1)).0,
+            dropped: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Send an event to the current receivers. Having none is not an error.
+    pub fn send(&self, event: T) {
+        let _ = self.tx.send(event);
+    }
+
+    /// Receive every event sent from now on.
+    pub fn subscribe(&self) -> EventReceiver<T> {
+        EventReceiver {
+            rx: self.tx.subscribe(),
+            dropped: Arc::clone(&self.dropped),
+        }
+    }
+
+    /// Events skipped by receivers that fell behind, summed over all receivers.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// Receiving end of an [`EventChannel`]. Events missed after falling behind are
+/// skipped and counted rather than reported as errors.
+#[derive(Debug)]
+pub struct EventReceiver<T> {
+    rx: broadcast::Receiver<T>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl<T: Clone + Send + 'static> EventReceiver<T> {
+    /// Wait for the next event. Fails only once every sender is gone.
+    pub async fn recv(&mut self) -> Result<T, RecvError> {
+        loop {
+            match self.rx.recv().await {
+                Err(RecvError::Lagged(skipped)) => self.count_dropped(skipped),
+                result => return result,
+            }
+        }
+    }
+
+    /// The next event if one is waiting.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        loop {
+            match self.rx.try_recv() {
+                Err(TryRecvError::Lagged(skipped)) => self.count_dropped(skipped),
+                result => return result,
+            }
+        }
+    }
+
+    /// The events as a stream, e.g. for Server-Sent Events.
+    pub fn into_stream(self) -> impl Stream<Item = T> {
+        let dropped = self.dropped;
+        BroadcastStream::new(self.rx).filter_map(move |event| match event {
+            Ok(event) => Some(event),
+            Err(BroadcastStreamRecvError::Lagged(skipped)) => {
+                dropped.fetch_add(skipped, Ordering::Relaxed);
+                None
+            }
+        })
+    }
+
+    fn count_dropped(&self, skipped: u64) {
+        self.dropped.fetch_add(skipped, Ordering::Relaxed);
+    }
+}
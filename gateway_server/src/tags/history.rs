@@ -0,0 +1,86 @@
+use crate::tags::structures::TagValue;
+use dashmap::DashMap;
+use std::collections::VecDeque;
+use std::mem::size_of;
+
+/// Samples kept per tag when no capacity is given.
+pub const DEFAULT_HISTORY_CAPACITY: usize = 100;
+
+/// Bounded in-memory history of recent values per tag.
+/// Once a tag has `capacity` samples, each new one evicts the oldest.
+#[derive(Debug)]
+pub struct History {
+    capacity: usize,
+    samples: DashMap<String, VecDeque<TagValue>>,
+}
+
+impl History {
+    /// Keep up to `capacity` samples per tag. A capacity of 0 disables history.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            samples: DashMap::new(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Append a sample, evicting the oldest one if the tag's buffer is full.
+    pub fn push(&self, tag_path: &str, value: TagValue) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut samples = self
+            .samples
+            .entry(tag_path.to_string())
+            .or_insert_with(|| VecDeque::with_capacity(self.capacity));
+        if samples.len() == self.capacity {
+            samples.pop_front();
+        }
+        samples.push_back(value);
+    }
+
+    /// The newest `limit` samples of a tag, oldest first.
+    pub fn recent(&self, tag_path: &str, limit: usize) -> Vec<TagValue> {
+        match self.samples.get(tag_path) {
+            Some(samples) => {
+                let skip = samples.len().saturating_sub(limit);
+                samples.iter().skip(skip).cloned().collect()
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// Rough size of the stored samples in bytes, see
+    /// [`TagEngine::approx_memory_bytes`](crate::tags::engine::TagEngine::approx_memory_bytes).
+    pub fn approx_memory_bytes(&self) -> usize {
+        self.samples
+            .iter()
+            .map(|entry| {
+                let samples = entry.value();
+                size_of::<String>()
+                    + entry.key().capacity()
+                    + samples.capacity() * size_of::<TagValue>()
+                    + samples.iter().map(|s| s.value.heap_bytes()).sum::<usize>()
+            })
+            .sum()
+    }
+
+    /// Forget the samples of one tag.
+    pub fn remove(&self, tag_path: &str) {
+        self.samples.remove(tag_path);
+    }
+
+    /// Forget all samples.
+    pub fn clear(&self) {
+        self.samples.clear();
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_CAPACITY)
+    }
+}
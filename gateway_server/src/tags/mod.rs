@@ -1,2 +1,6 @@
 pub mod engine; // The main tag engine logic
 pub mod structures; // Core Tag struct and related types
+pub mod path; // Validated tag paths
+pub mod poller; // Periodic reads from drivers into the engine
+pub mod history; // Recent values per tag
+pub mod events; // Bounded event fan-out to subscribers
@@ -0,0 +1,150 @@
+use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+use std::fmt;
+use std::ops::Deref;
+use std::str::FromStr;
+
+/// Separator between the segments of a tag path.
+pub const SEPARATOR: char = '/';
+
+/// Path of a tag in the tag hierarchy, e.g. `Plant1/Line2/Temperature`. A path
+/// is one or more non-empty segments separated by `/`, with no leading or
+/// trailing separator. Serialized as a plain string.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TagPath(String);
+
+/// Why a string is not a valid [`TagPath`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidTagPath {
+    pub path: String,
+    pub reason: &'static str,
+}
+
+impl fmt::Display for InvalidTagPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid tag path '{}': {}", self.path, self.reason)
+    }
+}
+
+impl std::error::Error for InvalidTagPath {}
+
+impl TagPath {
+    pub fn new(path: impl Into<String>) -> Result<Self, InvalidTagPath> {
+        let path = path.into();
+        let reason = if path.is_empty() {
+            Some("must not be empty")
+        } else if path.contains('\\') {
+            Some("segments must be separated by '/'")
+        } else if path.split(SEPARATOR).any(str::is_empty) {
+            Some("must not have empty segments or a leading or trailing '/'")
+        } else {
+            None
+        };
+        match reason {
+            Some(reason) => Err(InvalidTagPath { path, reason }),
+            None => Ok(Self(path)),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The segments from the root down, e.g. `Plant1`, `Line2`, `Temperature`.
+    pub fn segments(&self) -> impl DoubleEndedIterator<Item = &str> {
+        self.0.split(SEPARATOR)
+    }
+
+    /// The last segment, e.g. `Temperature`.
+    pub fn name(&self) -> &str {
+        self.0.rsplit(SEPARATOR).next().unwrap_or(&self.0)
+    }
+
+    /// The folder holding this path, or `None` for a path at the root.
+    pub fn parent(&self) -> Option<TagPath> {
+        self.0
+            .rsplit_once(SEPARATOR)
+            .map(|(parent, _)| TagPath(parent.to_string()))
+    }
+
+    /// The segments below `folder`, or `None` if the path is not inside it.
+    /// An empty folder is the root, which contains every path.
+    pub fn strip_folder<'a>(&'a self, folder: &[&str]) -> Option<impl Iterator<Item = &'a str>> {
+        let mut segments = self.segments();
+        for expected in folder {
+            if segments.next() != Some(expected) {
+                return None;
+            }
+        }
+        Some(segments)
+    }
+}
+
+impl fmt::Display for TagPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for TagPath {
+    type Err = InvalidTagPath;
+
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        Self::new(path)
+    }
+}
+
+impl TryFrom<String> for TagPath {
+    type Error = InvalidTagPath;
+
+    fn try_from(path: String) -> Result<Self, Self::Error> {
+        Self::new(path)
+    }
+}
+
+impl From<TagPath> for String {
+    fn from(path: TagPath) -> Self {
+        path.0
+    }
+}
+
+/// Lets a `&TagPath` be passed wherever a `&str` path is expected.
+impl Deref for TagPath {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for TagPath {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Lets maps keyed by `TagPath` be looked up with a `&str`.
+impl Borrow<str> for TagPath {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq<str> for TagPath {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for TagPath {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialEq<TagPath> for String {
+    fn eq(&self, other: &TagPath) -> bool {
+        *self == other.0
+    }
+}
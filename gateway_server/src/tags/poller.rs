@@ -0,0 +1,375 @@
+use crate::drivers::breaker::CircuitBreakers;
+use crate::drivers::scan_stats::ScanStatistics;
+use crate::drivers::traits::{DeviceDriver, DriverError};
+use crate::drivers::DriverMap;
+use crate::tags::engine::TagEngine;
+use crate::tags::structures::{Quality, Tag, TagValue};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use tokio::sync::{Notify, Semaphore};
+use tokio::task::JoinSet;
+use tokio::time::{interval, timeout, Duration, Instant, Interval};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+/// How often the poller checks which groups are due when there are no tags.
+const DEFAULT_POLL_TICK: Duration = Duration::from_millis(100);
+/// Bounds of the tick derived from the poll rates. Rates such as 1000 and 1001ms
+/// would otherwise spin the loop every millisecond.
+const MIN_POLL_TICK: Duration = Duration::from_millis(10);
+const MAX_POLL_TICK: Duration = Duration::from_secs(1);
+/// Read limit for drivers that don't set `max_concurrent_reads`.
+const DEFAULT_MAX_CONCURRENT_READS: usize = 4;
+
+/// Tags polled together: (driver_id, poll_rate_ms).
+pub type PollGroupKey = (String, u64);
+/// Tag paths of each poll group.
+pub type PollGroups = HashMap<PollGroupKey, Vec<String>>;
+
+/// Periodically reads tags from their drivers and stores the values in the tag engine.
+/// Tags are polled in groups of the same driver and poll rate. Drivers whose
+/// circuit breaker is open are skipped and their tags stay Bad.
+pub struct Poller {
+    tag_engine: Arc<TagEngine>,
+    drivers: Arc<DriverMap>,
+    /// Signalled when tags were added or removed, so groups are rebuilt.
+    config_changed: Arc<Notify>,
+    cancel: CancellationToken,
+    /// Seed for the per-group start offsets, see [`first_poll_offset`].
+    stagger_seed: u64,
+    /// How often due groups are checked; derived from the poll rates if not set.
+    poll_tick: Option<Duration>,
+    /// Failing drivers are skipped while their breaker is open.
+    breakers: Arc<CircuitBreakers>,
+    /// Duration and outcome of every group read, per driver.
+    scan_stats: Arc<ScanStatistics>,
+}
+
+impl Poller {
+    pub fn new(
+        tag_engine: Arc<TagEngine>,
+        drivers: Arc<DriverMap>,
+        config_changed: Arc<Notify>,
+        cancel: CancellationToken,
+    ) -> Self {
+        Self {
+            tag_engine,
+            drivers,
+            config_changed,
+            cancel,
+            stagger_seed: 0,
+            poll_tick: None,
+            breakers: Arc::new(CircuitBreakers::default()),
+            scan_stats: Arc::new(ScanStatistics::new()),
+        }
+    }
+
+    /// Use a different seed for spreading the first poll of each group.
+    pub fn with_stagger_seed(mut self, seed: u64) -> Self {
+        self.stagger_seed = seed;
+        self
+    }
+
+    /// Check for due groups every `poll_tick` instead of at the tick derived
+    /// from the poll rates (see [`poll_tick_for`]).
+    pub fn with_poll_tick(mut self, poll_tick: Duration) -> Self {
+        self.poll_tick = Some(poll_tick);
+        self
+    }
+
+    /// Record read failures in `breakers`, e.g. to share them with the API.
+    pub fn with_circuit_breakers(mut self, breakers: Arc<CircuitBreakers>) -> Self {
+        self.breakers = breakers;
+        self
+    }
+
+    /// Record scan durations and failures in `scan_stats`, e.g. to share them with the API.
+    pub fn with_scan_stats(mut self, scan_stats: Arc<ScanStatistics>) -> Self {
+        self.scan_stats = scan_stats;
+        self
+    }
+
+    /// Restart `ticks` if the groups call for a different tick.
+    fn retick(&self, poll_groups: &PollGroups, ticks: &mut Interval) {
+        let tick = self.poll_tick.unwrap_or_else(|| poll_tick_for(poll_groups));
+        if ticks.period() != tick {
+            info!("Polling tick set to {:?}.", tick);
+            *ticks = interval(tick);
+        }
+    }
+
+    /// Poll until the cancellation token fires, then disconnect every driver.
+    /// Due groups are read concurrently, at most `max_concurrent_reads` at a time per driver.
+    pub async fn run(self) {
+        info!("Polling task started.");
+        let mut poll_rates_version = self.tag_engine.poll_rates_version();
+        let mut poll_groups = build_poll_groups(&self.tag_engine.get_all_tags().await);
+        info!("Polling groups created: {}", poll_groups.len());
+
+        // Store last poll time for each group
+        let mut last_poll_times: HashMap<PollGroupKey, Instant> = HashMap::new();
+        let mut tick_interval = interval(DEFAULT_POLL_TICK);
+        self.retick(&poll_groups, &mut tick_interval);
+        // Read permits per driver, created on first use
+        let mut read_limits: HashMap<String, Arc<Semaphore>> = HashMap::new();
+        let mut in_flight = JoinSet::new();
+
+        loop {
+            tokio::select! {
+                biased;
+                _ = self.cancel.cancelled() => break,
+                _ = tick_interval.tick() => {}
+                _ = self.config_changed.notified() => {
+                    poll_groups = build_poll_groups(&self.tag_engine.get_all_tags().await);
+                    last_poll_times.retain(|group, _| poll_groups.contains_key(group));
+                    // Drivers may have been replaced with different limits
+                    read_limits.clear();
+                    self.breakers.retain(|id| self.drivers.contains_key(id));
+                    self.scan_stats.retain(|id| self.drivers.contains_key(id));
+                    self.retick(&poll_groups, &mut tick_interval);
+                    info!("Configuration changed, polling groups rebuilt: {}", poll_groups.len());
+                    continue;
+                }
+            }
+            // Reap finished reads
+            while in_flight.try_join_next().is_some() {}
+            // A tag whose rate changed moves to the group of its new rate, and
+            // one enabled or disabled joins or leaves its group
+            let version = self.tag_engine.poll_rates_version();
+            if version != poll_rates_version {
+                poll_rates_version = version;
+                poll_groups = build_poll_groups(&self.tag_engine.get_all_tags().await);
+                last_poll_times.retain(|group, _| poll_groups.contains_key(group));
+                self.retick(&poll_groups, &mut tick_interval);
+                info!("Poll rates changed, polling groups rebuilt: {}", poll_groups.len());
+            }
+            let now = Instant::now();
+
+            // New groups start at a staggered offset instead of all firing at once
+            for group in poll_groups.keys() {
+                last_poll_times
+                    .entry(group.clone())
+                    .or_insert_with(|| initial_last_poll(now, group, self.stagger_seed));
+            }
+
+            for group in groups_due(now, &last_poll_times, &poll_groups) {
+                // Stop between groups rather than starting another read
+                if self.cancel.is_cancelled() {
+                    break;
+                }
+                // Update last poll time regardless of success/failure to avoid spamming logs on error
+                last_poll_times.insert(group.clone(), now);
+
+                let tag_paths = poll_groups[&group].clone();
+                let (driver_id, poll_rate_ms) = group;
+                let driver = match self.drivers.get(&driver_id).map(|d| Arc::clone(d.value())) {
+                    Some(driver) => driver,
+                    None => {
+                        warn!("Driver '{}' not found for polling.", driver_id);
+                        continue;
+                    }
+                };
+                // Open breaker: leave the tags Bad until the cooldown ends
+                if !self.breakers.allow(&driver_id) {
+                    continue;
+                }
+                let limit = read_limits.entry(driver_id.clone()).or_insert_with(|| {
+                    let permits = driver
+                        .config()
+                        .max_concurrent_reads
+                        .unwrap_or(DEFAULT_MAX_CONCURRENT_READS);
+                    Arc::new(Semaphore::new(permits.max(1)))
+                });
+                let limit = Arc::clone(limit);
+                let tag_engine = Arc::clone(&self.tag_engine);
+                let breakers = Arc::clone(&self.breakers);
+                let scan_stats = Arc::clone(&self.scan_stats);
+
+                in_flight.spawn(async move {
+                    // Skip this round if no permit frees up before the group is due again
+                    let wait = Duration::from_millis(poll_rate_ms);
+                    let Ok(Ok(_permit)) = timeout(wait, limit.acquire_owned()).await else {
+                        warn!(
+                            "Skipping poll of driver '{}' at {}ms: too many reads in flight",
+                            driver_id, poll_rate_ms
+                        );
+                        return;
+                    };
+                    let started = Instant::now();
+                    let result = poll_group(
+                        &tag_engine,
+                        driver.as_ref(),
+                        &driver_id,
+                        poll_rate_ms,
+                        &tag_paths,
+                    )
+                    .await;
+                    scan_stats.record(&driver_id, started.elapsed(), result.is_ok());
+                    match result {
+                        Ok(()) => breakers.record_success(&driver_id),
+                        Err(_) => breakers.record_failure(&driver_id),
+                    }
+                });
+            }
+        }
+
+        info!("Polling task stopping, disconnecting drivers.");
+        in_flight.shutdown().await;
+        self.disconnect_all().await;
+    }
+
+    async fn disconnect_all(&self) {
+        let drivers: Vec<_> = self
+            .drivers
+            .iter()
+            .map(|entry| (entry.key().clone(), Arc::clone(entry.value())))
+            .collect();
+        for (id, driver) in drivers {
+            match driver.disconnect().await {
+                Ok(()) => info!("Driver '{}' disconnected.", id),
+                Err(e) => warn!("Error disconnecting driver '{}': {}", id, e),
+            }
+        }
+    }
+}
+
+/// Read one group of tags and store the results, or mark them Bad if the read fails.
+/// A failure about a single address only marks the tags at that address Bad.
+/// Reads taking longer than the driver's `read_timeout_ms` fail with a timeout.
+/// Returns the error if the driver as a whole failed, not just an address.
+async fn poll_group(
+    tag_engine: &TagEngine,
+    driver: &(dyn DeviceDriver + Send + Sync),
+    driver_id: &str,
+    poll_rate_ms: u64,
+    tag_paths: &[String],
+) -> Result<(), DriverError> {
+    info!(
+        "Polling group: Driver '{}', Rate {}ms, Tags: {}",
+        driver_id,
+        poll_rate_ms,
+        tag_paths.len()
+    );
+
+    // A hung read must not hold up the group forever; time it out and mark it Bad
+    let read_timeout = driver.config().read_timeout();
+    let read = timeout(read_timeout, driver.read_paths(tag_engine, driver_id, tag_paths))
+        .await
+        .unwrap_or(Err(DriverError::Timeout));
+    match read {
+        Ok(results) => {
+            info!(
+                "Read successful for {} tags from driver '{}'",
+                results.len(),
+                driver_id
+            );
+            tag_engine.update_tag_values_scaled(results.into_iter().collect());
+            Ok(())
+        }
+        Err(e) => {
+            error!("Failed to read tags from driver '{}': {}", driver_id, e);
+            // An error about one address marks just the tags at that address Bad;
+            // connection errors and the like condemn the whole group
+            let failed: Vec<&String> = match e.address() {
+                Some(address) => tag_paths
+                    .iter()
+                    .filter(|path| {
+                        tag_engine
+                            .read_tag_full(path)
+                            .is_some_and(|tag| tag.driver_address == address)
+                    })
+                    .collect(),
+                _ => tag_paths.iter().collect(),
+            };
+            for path in &failed {
+                tag_engine.record_error(path, e.to_string());
+            }
+            tag_engine.update_tag_values(
+                failed
+                    .into_iter()
+                    .map(|path| (path.clone(), TagValue::bad(Quality::Bad)))
+                    .collect(),
+            );
+            // The device answered, it just didn't like one address
+            match e.address() {
+                Some(_) => Ok(()),
+                None => Err(e),
+            }
+        }
+    }
+}
+
+/// Group tags by (driver_id, poll_rate_ms). Paths within a group are sorted.
+/// Disabled tags are left out, and so are aliases, which the engine updates
+/// along with their source.
+pub fn build_poll_groups(tags: &[Tag]) -> PollGroups {
+    let mut poll_groups = PollGroups::new();
+    for tag in tags
+        .iter()
+        .filter(|tag| tag.enabled && tag.metadata.alias_of.is_none())
+    {
+        poll_groups
+            .entry((tag.driver_id.clone(), tag.poll_rate_ms))
+            .or_default()
+            .push(tag.path.to_string());
+    }
+    for paths in poll_groups.values_mut() {
+        paths.sort_unstable();
+    }
+    poll_groups
+}
+
+/// Tick at which every group comes due on time: the greatest common divisor of
+/// the poll rates, kept between 10ms and 1s. 100ms without groups.
+pub fn poll_tick_for(poll_groups: &PollGroups) -> Duration {
+    fn gcd(a: u64, b: u64) -> u64 {
+        if b == 0 {
+            a
+        } else {
+            gcd(b, a % b)
+        }
+    }
+    match poll_groups.keys().map(|(_, rate)| *rate).reduce(gcd) {
+        Some(rate) => Duration::from_millis(rate).clamp(MIN_POLL_TICK, MAX_POLL_TICK),
+        None => DEFAULT_POLL_TICK,
+    }
+}
+
+/// Whether a group last polled at `last` (never, if `None`) is due again at `now`.
+pub fn is_due(now: Instant, last: Option<Instant>, poll_rate_ms: u64) -> bool {
+    match last {
+        Some(last) => now.saturating_duration_since(last) >= Duration::from_millis(poll_rate_ms),
+        None => true,
+    }
+}
+
+/// Delay before a group's first poll, in `0..poll_rate_ms`. Derived from a hash of
+/// the group and `seed`, so it is stable across runs but differs between groups.
+pub fn first_poll_offset(group: &PollGroupKey, seed: u64) -> Duration {
+    let mut hasher = DefaultHasher::new();
+    seed.hash(&mut hasher);
+    group.hash(&mut hasher);
+    Duration::from_millis(hasher.finish() % group.1.max(1))
+}
+
+/// A "last polled" time that makes the group first come due at
+/// `now + first_poll_offset(group, seed)`.
+pub fn initial_last_poll(now: Instant, group: &PollGroupKey, seed: u64) -> Instant {
+    let until_due = Duration::from_millis(group.1).saturating_sub(first_poll_offset(group, seed));
+    now.checked_sub(until_due).unwrap_or(now)
+}
+
+/// The groups that should be polled at `now`, given when each was last polled.
+pub fn groups_due(
+    now: Instant,
+    last_poll_times: &HashMap<PollGroupKey, Instant>,
+    poll_groups: &PollGroups,
+) -> Vec<PollGroupKey> {
+    poll_groups
+        .keys()
+        .filter(|group| is_due(now, last_poll_times.get(*group).copied(), group.1))
+        .cloned()
+        .collect()
+}
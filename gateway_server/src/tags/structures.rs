@@ -1,21 +1,21 @@
+use std::error::Error;
+use std::fmt;
 use std::time::{SystemTime, UNIX_EPOCH};
+use crate::alarms::AlarmLimits;
+use crate::tags::path::TagPath;
 use serde::{Serialize, Deserialize};
 
 /// Represents the quality of a tag's value.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Quality {
     Good,
     Uncertain,
     Bad,
+    #[default]
     Initializing,
     CommFailure, // Specific bad quality
     ConfigError, // Specific bad quality
-}
-
-impl Default for Quality {
-    fn default() -> Self {
-        Quality::Initializing
-    }
+    Stale, // Last known value restored from a snapshot, not yet re-read
 }
 
 /// Represents the value, quality, and timestamp of a tag.
@@ -39,6 +39,15 @@ impl TagValue {
         }
     }
 
+    // Helper for values that carry their own sample time (e.g. a device timestamp)
+    pub fn with_timestamp(value: ValueVariant, quality: Quality, timestamp: u64) -> Self {
+        TagValue {
+            value,
+            quality,
+            timestamp,
+        }
+    }
+
     // Helper for bad quality
     pub fn bad(reason: Quality) -> Self {
         Self::new(ValueVariant::Null, reason)
@@ -52,16 +61,170 @@ pub enum ValueVariant {
     Bool(bool),
     Int(i64),
     UInt(u64), // Added unsigned int
-    Float(f64),
+    /// NaN and infinities are serialized as strings, see [`non_finite_name`].
+    Float(#[serde(with = "float_repr")] f64),
     String(String),
-    // TODO: Add complex types: Array, Struct/Object
+    Array(Vec<ValueVariant>), // Elements share one scalar type when read from OPC UA
+    DateTime(i64), // Unix timestamp milliseconds
+    // TODO: Add complex types: Struct/Object
+}
+
+impl ValueVariant {
+    /// Name of the variant, e.g. "Float" for `ValueVariant::Float(_)`.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            ValueVariant::Null => "Null",
+            ValueVariant::Bool(_) => "Bool",
+            ValueVariant::Int(_) => "Int",
+            ValueVariant::UInt(_) => "UInt",
+            ValueVariant::Float(_) => "Float",
+            ValueVariant::String(_) => "String",
+            ValueVariant::Array(_) => "Array",
+            ValueVariant::DateTime(_) => "DateTime",
+        }
+    }
+
+    /// Bytes allocated on the heap for the value: string contents and array elements.
+    pub fn heap_bytes(&self) -> usize {
+        match self {
+            ValueVariant::String(s) => s.capacity(),
+            ValueVariant::Array(values) => {
+                values.capacity() * std::mem::size_of::<ValueVariant>()
+                    + values.iter().map(ValueVariant::heap_bytes).sum::<usize>()
+            }
+            _ => 0,
+        }
+    }
+
+    /// Numeric value as `f64`, or `None` for non-numeric variants.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            ValueVariant::Int(v) => Some(*v as f64),
+            ValueVariant::UInt(v) => Some(*v as f64),
+            ValueVariant::Float(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// Convert this value to the type of `target`, e.g. `Int(5)` to `Float(5.0)`
+    /// for a Float tag. Numbers convert between Int, UInt and Float when no
+    /// precision or sign is lost, numeric strings are parsed, and scalars can be
+    /// written as strings. A `Null` target (type not known yet) takes any value
+    /// as is.
+    pub fn coerce_to(&self, target: &ValueVariant) -> Result<ValueVariant, CoerceError> {
+        use ValueVariant::*;
+        let converted = match (self, target) {
+            (value, Null) => Some(value.clone()),
+            (Bool(v), Bool(_)) => Some(Bool(*v)),
+            (Int(v), Int(_)) => Some(Int(*v)),
+            (UInt(v), Int(_)) => i64::try_from(*v).ok().map(Int),
+            (Float(v), Int(_)) => float_to_int(*v).map(Int),
+            (String(s), Int(_)) => s.trim().parse().ok().map(Int),
+            (UInt(v), UInt(_)) => Some(UInt(*v)),
+            (Int(v), UInt(_)) => u64::try_from(*v).ok().map(UInt),
+            (Float(v), UInt(_)) => float_to_int(*v).and_then(|v| u64::try_from(v).ok()).map(UInt),
+            (String(s), UInt(_)) => s.trim().parse().ok().map(UInt),
+            (Float(v), Float(_)) => Some(Float(*v)),
+            (Int(_) | UInt(_), Float(_)) => self.as_f64().map(Float),
+            (String(s), Float(_)) => s.trim().parse().ok().map(Float),
+            (String(s), String(_)) => Some(String(s.clone())),
+            (Bool(v), String(_)) => Some(String(v.to_string())),
+            (Int(v), String(_)) => Some(String(v.to_string())),
+            (UInt(v), String(_)) => Some(String(v.to_string())),
+            (Float(v), String(_)) => Some(String(match non_finite_name(*v) {
+                Some(name) => name.to_string(),
+                None => v.to_string(),
+            })),
+            (DateTime(v) | Int(v), DateTime(_)) => Some(DateTime(*v)),
+            (Array(v), Array(_)) => Some(Array(v.clone())),
+            _ => None,
+        };
+        converted.ok_or_else(|| CoerceError {
+            value: self.clone(),
+            target: target.type_name(),
+        })
+    }
+}
+
+/// JSON has no NaN or infinities, so a non-finite `Float` is written as one of the
+/// strings "NaN", "Infinity" or "-Infinity" instead of a number. `None` for
+/// finite values.
+pub fn non_finite_name(v: f64) -> Option<&'static str> {
+    if v.is_nan() {
+        Some("NaN")
+    } else if v == f64::INFINITY {
+        Some("Infinity")
+    } else if v == f64::NEG_INFINITY {
+        Some("-Infinity")
+    } else {
+        None
+    }
+}
+
+/// Serde of `Float` values: a number, or a string from [`non_finite_name`].
+mod float_repr {
+    use super::non_finite_name;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(v: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        match non_finite_name(*v) {
+            Some(name) => serializer.serialize_str(name),
+            None => serializer.serialize_f64(*v),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Number(f64),
+            Name(String),
+        }
+        match Repr::deserialize(deserializer)? {
+            Repr::Number(v) => Ok(v),
+            Repr::Name(name) => match name.as_str() {
+                "NaN" => Ok(f64::NAN),
+                "Infinity" => Ok(f64::INFINITY),
+                "-Infinity" => Ok(f64::NEG_INFINITY),
+                _ => Err(D::Error::custom(format!("invalid float '{name}'"))),
+            },
+        }
+    }
+}
+
+/// A whole number held in a float, or `None` if it has a fraction or is out of range.
+fn float_to_int(v: f64) -> Option<i64> {
+    // i64::MAX as f64 rounds up to 2^63, which doesn't fit
+    (v.fract() == 0.0 && v >= i64::MIN as f64 && v < i64::MAX as f64).then_some(v as i64)
+}
+
+/// A value that can't be converted to the type a tag holds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoerceError {
+    pub value: ValueVariant,
+    /// Type name of the target, see [`ValueVariant::type_name`].
+    pub target: &'static str,
+}
+
+impl fmt::Display for CoerceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Cannot convert {:?} to {}", self.value, self.target)
+    }
+}
+
+impl Error for CoerceError {}
+
+/// Serde default of the `enabled` flags.
+pub(crate) fn enabled_by_default() -> bool {
+    true
 }
 
 /// Represents a single tag in the system.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Tag {
     /// Unique path identifying the tag (e.g., "Folder/Device/TagName").
-    pub path: String,
+    pub path: TagPath,
     /// Current value, quality, and timestamp.
     pub value: TagValue,
     /// Source driver ID providing this tag's value.
@@ -70,10 +233,47 @@ pub struct Tag {
     pub driver_address: String,
     /// Poll rate in milliseconds.
     pub poll_rate_ms: u64,
+    /// Whether the tag is polled. A disabled tag keeps its last value, marked
+    /// [`Quality::Stale`].
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
     /// Metadata about the tag.
     pub metadata: TagMetadata,
 }
 
+/// An immediate child of a folder in the tag hierarchy, as listed by
+/// [`TagEngine::list_children`](crate::tags::engine::TagEngine::list_children).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TagNode {
+    /// Last path segment, e.g. `Temperature`.
+    pub name: String,
+    /// Full path of the tag or folder, e.g. `Plant1/Temperature`.
+    pub path: String,
+    /// True for a folder containing further tags, false for a tag.
+    pub is_folder: bool,
+}
+
+/// A tag's quality changing from one stored value to the next.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QualityEvent {
+    pub path: String,
+    pub old: Quality,
+    pub new: Quality,
+    /// Timestamp (Unix ms) of the value that changed the quality.
+    pub timestamp: u64,
+}
+
+/// Update statistics the engine keeps for each tag.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TagStats {
+    /// Number of value updates stored since the tag was registered.
+    pub update_count: u64,
+    /// Timestamp (Unix ms) of the last update with Good quality, or 0 if none yet.
+    pub last_good_timestamp: u64,
+    /// Most recent error reported while reading the tag from its driver.
+    pub last_error: Option<String>,
+}
+
 /// Metadata associated with a tag.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)] // Default trait for easy initialization
 pub struct TagMetadata {
@@ -81,6 +281,81 @@ pub struct TagMetadata {
     pub eng_unit: Option<String>,
     pub eng_low: Option<f64>,
     pub eng_high: Option<f64>,
+    /// Raw driver value that maps to `eng_low` (e.g. 0 counts).
+    #[serde(default)]
+    pub raw_low: Option<f64>,
+    /// Raw driver value that maps to `eng_high` (e.g. 4095 counts).
+    #[serde(default)]
+    pub raw_high: Option<f64>,
+    /// Numeric updates within this distance of the stored value are ignored.
+    #[serde(default)]
+    pub deadband: Option<f64>,
     pub writable: bool,
+    /// Keep the last value and its timestamp when a read fails, marking only
+    /// the quality Bad, instead of replacing the value with `Null`.
+    #[serde(default)]
+    pub hold_last_value: bool,
+    /// Limits checked on every stored value, see [`AlarmEngine`](crate::alarms::AlarmEngine).
+    #[serde(default)]
+    pub alarms: AlarmLimits,
+    /// Path of the tag whose value this one mirrors. Aliases are never polled;
+    /// the engine copies every stored value of the source to them.
+    #[serde(default)]
+    pub alias_of: Option<TagPath>,
     // Add other relevant metadata: security, history settings etc.
 }
+
+impl TagMetadata {
+    /// Linearly map a raw driver value into the engineering range, clamping to
+    /// `eng_low..=eng_high`. Values are returned unchanged when any of the four
+    /// bounds is missing or the value is not numeric.
+    pub fn scale_raw(&self, value: ValueVariant) -> ValueVariant {
+        let (Some(raw_low), Some(raw_high), Some(eng_low), Some(eng_high)) =
+            (self.raw_low, self.raw_high, self.eng_low, self.eng_high)
+        else {
+            return value;
+        };
+        if raw_high == raw_low {
+            return value;
+        }
+        let Some(raw) = value.as_f64() else {
+            return value;
+        };
+        let scaled = eng_low + (raw - raw_low) * (eng_high - eng_low) / (raw_high - raw_low);
+        ValueVariant::Float(scaled.clamp(eng_low.min(eng_high), eng_low.max(eng_high)))
+    }
+
+    /// Map an engineering value back to the raw driver value, the inverse of
+    /// [`scale_raw`](Self::scale_raw), so it can be written to the device. The
+    /// result is rounded to an Int when both raw bounds are whole numbers (e.g.
+    /// ADC counts). Values are returned unchanged when scaling is not configured
+    /// or the value is not numeric.
+    pub fn unscale(&self, value: ValueVariant) -> ValueVariant {
+        let (Some(raw_low), Some(raw_high), Some(eng_low), Some(eng_high)) =
+            (self.raw_low, self.raw_high, self.eng_low, self.eng_high)
+        else {
+            return value;
+        };
+        if eng_high == eng_low {
+            return value;
+        }
+        let Some(eng) = value.as_f64() else {
+            return value;
+        };
+        let raw = raw_low + (eng - eng_low) * (raw_high - raw_low) / (eng_high - eng_low);
+        if raw_low.fract() == 0.0 && raw_high.fract() == 0.0 {
+            ValueVariant::Int(raw.round() as i64)
+        } else {
+            ValueVariant::Float(raw)
+        }
+    }
+
+    /// Whether `new` is close enough to `current` to be dropped by the deadband.
+    /// Always false when no deadband is set or either value is non-numeric.
+    pub fn within_deadband(&self, current: &ValueVariant, new: &ValueVariant) -> bool {
+        match (self.deadband, current.as_f64(), new.as_f64()) {
+            (Some(deadband), Some(current), Some(new)) => (new - current).abs() <= deadband,
+            _ => false,
+        }
+    }
+}
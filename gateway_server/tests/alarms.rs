@@ -0,0 +1,129 @@
+use gateway_server::alarms::{AlarmKind, AlarmLimits, Severity};
+use gateway_server::tags::engine::TagEngine;
+use gateway_server::tags::structures::{Quality, Tag, TagMetadata, TagValue, ValueVariant};
+
+fn limited_tag(path: &str) -> Tag {
+    Tag {
+        path: path.parse().unwrap(),
+        value: TagValue::new(ValueVariant::Float(50.0), Quality::Good),
+        driver_id: "drv1".to_string(),
+        driver_address: path.to_string(),
+        poll_rate_ms: 1000,
+        enabled: true,
+        metadata: TagMetadata {
+            alarms: AlarmLimits {
+                high: Some(80.0),
+                low: Some(10.0),
+                rate_of_change: None,
+                severity: Severity::Critical,
+            },
+            ..Default::default()
+        },
+    }
+}
+
+fn set(engine: &TagEngine, path: &str, value: f64) {
+    let value = TagValue::new(ValueVariant::Float(value), Quality::Good);
+    assert!(engine.update_tag_value(path, value));
+}
+
+#[test]
+fn value_above_high_limit_raises_alarm() {
+    let engine = TagEngine::new();
+    engine.register_tag(limited_tag("Tank/Level"));
+    set(&engine, "Tank/Level", 70.0);
+    assert!(engine.alarms().active().is_empty());
+
+    set(&engine, "Tank/Level", 85.0);
+    let alarms = engine.alarms().active();
+    assert_eq!(alarms.len(), 1);
+    assert_eq!(alarms[0].path, "Tank/Level");
+    assert_eq!(alarms[0].kind, AlarmKind::High);
+    assert_eq!(alarms[0].severity, Severity::Critical);
+    assert_eq!(alarms[0].limit, 80.0);
+    assert_eq!(alarms[0].value, 85.0);
+}
+
+#[test]
+fn alarm_stays_active_while_beyond_limit() {
+    let engine = TagEngine::new();
+    engine.register_tag(limited_tag("Tank/Level"));
+    set(&engine, "Tank/Level", 5.0);
+    let raised = engine.alarms().active()[0].clone();
+    assert_eq!(raised.kind, AlarmKind::Low);
+
+    std::thread::sleep(std::time::Duration::from_millis(5));
+    set(&engine, "Tank/Level", 2.0);
+    let alarms = engine.alarms().active();
+    assert_eq!(alarms.len(), 1);
+    // Still the same alarm, following the latest value
+    assert_eq!(alarms[0].since, raised.since);
+    assert_eq!(alarms[0].value, 2.0);
+
+    // Bad values say nothing about the process, so the alarm stays
+    engine.update_tag_value("Tank/Level", TagValue::bad(Quality::Bad));
+    assert_eq!(engine.alarms().active().len(), 1);
+}
+
+#[test]
+fn alarm_clears_on_return_to_normal() {
+    let engine = TagEngine::new();
+    engine.register_tag(limited_tag("Tank/Level"));
+    engine.register_tag(limited_tag("Tank/Pressure"));
+    set(&engine, "Tank/Level", 95.0);
+    set(&engine, "Tank/Pressure", 1.0);
+    assert_eq!(engine.alarms().active().len(), 2);
+
+    set(&engine, "Tank/Level", 80.0);
+    let alarms = engine.alarms().active();
+    assert_eq!(alarms.len(), 1);
+    assert_eq!(alarms[0].path, "Tank/Pressure");
+
+    // Removing the tag drops its alarms too
+    engine.unregister_tag("Tank/Pressure");
+    assert!(engine.alarms().active().is_empty());
+}
+
+fn rate_limited_tag(path: &str) -> Tag {
+    Tag {
+        // Like a freshly configured tag: nothing read yet
+        value: TagValue::bad(Quality::Bad),
+        metadata: TagMetadata {
+            alarms: AlarmLimits {
+                rate_of_change: Some(10.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        ..limited_tag(path)
+    }
+}
+
+fn set_at(engine: &TagEngine, path: &str, value: f64, timestamp: u64) {
+    let value = TagValue::with_timestamp(ValueVariant::Float(value), Quality::Good, timestamp);
+    assert!(engine.update_tag_value(path, value));
+}
+
+#[test]
+fn fast_change_raises_rate_of_change_alarm() {
+    let engine = TagEngine::new();
+    engine.register_tag(rate_limited_tag("Tank/Level"));
+    // The first sample has nothing to compare against
+    set_at(&engine, "Tank/Level", 20.0, 1_000);
+    assert!(engine.alarms().active().is_empty());
+
+    // 30 units in half a second is 60 units/s
+    set_at(&engine, "Tank/Level", 50.0, 1_500);
+    let alarms = engine.alarms().active();
+    assert_eq!(alarms.len(), 1);
+    assert_eq!(alarms[0].kind, AlarmKind::RateOfChange);
+    assert_eq!(alarms[0].limit, 10.0);
+    assert_eq!(alarms[0].value, 60.0);
+    assert_eq!(alarms[0].since, 1_500);
+
+    // Falling just as fast keeps it active; settling down clears it
+    set_at(&engine, "Tank/Level", 20.0, 2_000);
+    assert_eq!(engine.alarms().active().len(), 1);
+    set_at(&engine, "Tank/Level", 21.0, 3_000);
+    assert!(engine.alarms().active().is_empty());
+}
@@ -0,0 +1,160 @@
+use argon2::password_hash::{PasswordHasher, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+use axum::body::Body;
+use axum::http::{header, Method, Request, StatusCode};
+use axum::routing::get;
+use axum::Router;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use gateway_server::api::auth::{auth_layer, AuthConfig};
+use gateway_server::api::cors::CorsConfig;
+use tokio::time::{sleep, Duration, Instant};
+use tower::ServiceExt;
+
+/// Hash with minimal Argon2 costs so the tests stay fast in debug builds.
+fn hash(password: &str) -> String {
+    hash_with(password, Params::new(Params::MIN_M_COST, 1, 1, None).unwrap())
+}
+
+fn hash_with(password: &str, params: Params) -> String {
+    let salt = SaltString::from_b64("Zm9yZ2Vpby10ZXN0").unwrap();
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password(password.as_bytes(), &salt)
+        .unwrap()
+        .to_string()
+}
+
+fn auth_config(enabled: bool) -> AuthConfig {
+    AuthConfig {
+        enabled,
+        username: "operator".into(),
+        password_hash: hash("s3cret"),
+    }
+}
+
+fn app(config: &AuthConfig) -> Router {
+    let app = Router::new().route("/api/health", get(|| async { "ok" }));
+    match auth_layer(config) {
+        Some(layer) => app.layer(layer),
+        None => app,
+    }
+}
+
+async fn status(app: Router, credentials: Option<&str>) -> StatusCode {
+    let mut request = Request::builder().uri("/api/health");
+    if let Some(credentials) = credentials {
+        request = request.header(
+            header::AUTHORIZATION,
+            format!("Basic {}", STANDARD.encode(credentials)),
+        );
+    }
+    app.oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+        .status()
+}
+
+#[tokio::test]
+async fn configured_credentials_are_required() {
+    let app = app(&auth_config(true));
+    assert_eq!(status(app.clone(), Some("operator:s3cret")).await, StatusCode::OK);
+    // Served from the cache of verified headers the second time
+    assert_eq!(status(app.clone(), Some("operator:s3cret")).await, StatusCode::OK);
+    assert_eq!(status(app.clone(), Some("operator:wrong")).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(status(app.clone(), Some("admin:s3cret")).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(status(app.clone(), Some("admin:admin")).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(status(app, None).await, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn credentials_are_checked_however_the_header_is_spaced() {
+    let app = app(&auth_config(true));
+    for header in [
+        format!("Basic {}", STANDARD.encode("operator:s3cret")),
+        format!("Basic   {}  ", STANDARD.encode("operator:s3cret")),
+    ] {
+        let request = Request::builder()
+            .uri("/api/health")
+            .header(header::AUTHORIZATION, header)
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(app.clone().oneshot(request).await.unwrap().status(), StatusCode::OK);
+    }
+}
+
+// Single-threaded runtime: a hash computed on it would stall every other task
+#[tokio::test]
+async fn password_checks_do_not_block_the_runtime() {
+    let config = AuthConfig {
+        password_hash: hash_with("s3cret", Params::default()),
+        ..auth_config(true)
+    };
+    let request = tokio::spawn(async move {
+        let status = status(app(&config), Some("operator:wrong")).await;
+        (status, Instant::now())
+    });
+    sleep(Duration::from_millis(5)).await;
+    let woke = Instant::now();
+
+    let (status, answered) = request.await.unwrap();
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(woke < answered, "the runtime was blocked while the password was checked");
+}
+
+#[tokio::test]
+async fn unauthorized_response_asks_for_basic_auth() {
+    let request = Request::builder().uri("/api/health").body(Body::empty()).unwrap();
+    let response = app(&auth_config(true)).oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert!(response.headers()[header::WWW_AUTHENTICATE]
+        .to_str()
+        .unwrap()
+        .starts_with("Basic"));
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["error"]["kind"], "unauthorized");
+}
+
+#[tokio::test]
+async fn disabled_auth_lets_every_request_through() {
+    assert_eq!(status(app(&auth_config(false)), None).await, StatusCode::OK);
+}
+
+#[test]
+fn enabled_auth_needs_a_valid_hash() {
+    let mut config = auth_config(true);
+    assert_eq!(config.validate(), Ok(()));
+    config.password_hash = "admin".into();
+    assert!(config.validate().is_err());
+    config.enabled = false;
+    assert_eq!(config.validate(), Ok(()));
+}
+
+#[test]
+fn debug_output_hides_the_password_hash() {
+    let config = auth_config(true);
+    let debug = format!("{:?}", config);
+    assert!(!debug.contains(&config.password_hash), "{debug}");
+    assert!(debug.contains("operator"));
+}
+
+#[tokio::test]
+async fn cors_preflight_does_not_need_credentials() {
+    let cors = CorsConfig {
+        allowed_origins: vec!["*".into()],
+        allowed_methods: vec!["GET".into()],
+        allowed_headers: vec!["authorization".into()],
+    };
+    // Same order as main: CORS wraps the auth layer
+    let app = app(&auth_config(true)).layer(cors.layer().unwrap());
+    let request = Request::builder()
+        .uri("/api/health")
+        .method(Method::OPTIONS)
+        .header(header::ORIGIN, "http://localhost:5173")
+        .header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+}
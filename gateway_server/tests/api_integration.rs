@@ -1,13 +1,35 @@
 use axum::body::Body;
 use axum::http::{Method, Request, StatusCode};
+use gateway_server::alarms::Severity;
+use gateway_server::api::cors::CorsConfig;
+use gateway_server::api::frontend::with_frontend;
 use gateway_server::api::rest::{create_api_routes, SharedAppState};
-use gateway_server::config::settings::Settings;
+use gateway_server::api::server::serve;
+use gateway_server::config::reload::ConfigReloader;
+use gateway_server::config::settings::{ServerConfig, Settings, TagConfig};
+use gateway_server::historian::influx::InfluxConfig;
+use gateway_server::logging::{build_subscriber, ChannelWriter, LogStream, LoggingConfig};
 use gateway_server::tags::engine::TagEngine;
 use gateway_server::tags::structures::{Tag, TagValue, ValueVariant, TagMetadata, Quality};
-use std::collections::HashMap;
+use async_trait::async_trait;
+use gateway_server::drivers::traits::{
+    DeviceDriver, DriverConfig, DriverResult, TagRequest, WriteStatus,
+};
+use gateway_server::drivers::breaker::CircuitBreakers;
+use gateway_server::drivers::mock::MockDriver;
+use gateway_server::drivers::scan_stats::ScanStatistics;
+use gateway_server::drivers::health::{ConnectionState, ConnectionStates};
+use gateway_server::drivers::opcua::OpcUaDriver;
+use gateway_server::drivers::DriverMap;
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
 use std::sync::Arc;
-use tokio::sync::RwLock;
-use tokio::time::Instant;
+use tokio::sync::{Notify, RwLock};
+use tokio::time::{Duration, Instant};
 use tower::ServiceExt;
 use axum::Router;
 
@@ -16,11 +38,12 @@ fn create_test_tag_engine() -> Arc<TagEngine> {
     
     // Add some test tags
     let test_tag = Tag {
-        path: "TestDevice/Temperature".to_string(),
+        path: "TestDevice/Temperature".parse().unwrap(),
         value: TagValue::new(ValueVariant::Float(23.5), Quality::Good),
         driver_id: "test_driver".to_string(),
         driver_address: "test_addr".to_string(),
         poll_rate_ms: 1000,
+        enabled: true,
         metadata: TagMetadata::default(),
     };
     engine.register_tag(test_tag);
@@ -29,18 +52,45 @@ fn create_test_tag_engine() -> Arc<TagEngine> {
 }
 
 fn create_test_app_state() -> SharedAppState {
-    let engine = create_test_tag_engine();
     let settings = Settings {
+        server: ServerConfig::default(),
+        auth: None,
+        cors: None,
+        logging: LoggingConfig::default(),
         devices: vec![],
         tags: vec![],
+        influx: None,
+        mqtt: None,
+        poll_tick_ms: None,
     };
-    
+    let config_path = std::env::temp_dir().join(format!("forgeio_api_{}.toml", std::process::id()));
+    create_app_state_with(settings, config_path)
+}
+
+fn create_app_state_with(settings: Settings, config_path: PathBuf) -> SharedAppState {
+    let engine = create_test_tag_engine();
+    let settings = Arc::new(RwLock::new(settings));
+    let drivers = Arc::new(DriverMap::new());
+    let connection_states = Arc::new(ConnectionStates::new());
+    let reloader = ConfigReloader::new(
+        config_path,
+        Arc::clone(&settings),
+        Arc::clone(&engine),
+        Arc::clone(&drivers),
+        Arc::clone(&connection_states),
+        Arc::new(Notify::new()),
+    );
+
     SharedAppState {
         tag_engine: engine,
-        driver_count: 0,
         start_time: Instant::now(),
-        settings: Arc::new(RwLock::new(settings)),
-        drivers: Arc::new(HashMap::new()),
+        settings,
+        drivers,
+        connection_states,
+        breakers: Arc::new(CircuitBreakers::default()),
+        scan_stats: Arc::new(ScanStatistics::new()),
+        reloader,
+        logs: LogStream::new(),
     }
 }
 
@@ -90,3 +140,1798 @@ async fn test_opcua_discover_tags_nonexistent_driver() {
     let response = app.oneshot(request).await.unwrap();
     assert_eq!(response.status(), StatusCode::NOT_FOUND);
 }
+
+#[tokio::test]
+async fn test_tags_pattern_query() {
+    let state = create_test_app_state();
+    state.tag_engine.register_tag(Tag {
+        path: "OtherDevice/Pressure".parse().unwrap(),
+        value: TagValue::new(ValueVariant::Float(1.0), Quality::Good),
+        driver_id: "test_driver".to_string(),
+        driver_address: "other_addr".to_string(),
+        poll_rate_ms: 1000,
+        enabled: true,
+        metadata: TagMetadata::default(),
+    });
+    let app = create_api_routes().with_state(state);
+
+    let request = Request::builder()
+        .uri("/tags?pattern=TestDevice/*")
+        .method(Method::GET)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let page: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let tags = page["items"].as_array().unwrap();
+    assert_eq!(page["total"], 1);
+    assert_eq!(tags.len(), 1);
+    assert_eq!(tags[0]["path"], "TestDevice/Temperature");
+}
+
+#[tokio::test]
+async fn test_update_config_rejects_invalid_settings() {
+    let app = create_test_app();
+
+    // Tag references a device that does not exist
+    let body = serde_json::json!({
+        "devices": [],
+        "tags": [{
+            "path": "Plant/Temperature",
+            "driver_id": "missing",
+            "address": "ns=2;s=Temperature",
+            "poll_rate_ms": 1000
+        }]
+    });
+    let request = Request::builder()
+        .uri("/api/config")
+        .method(Method::PUT)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        body["problems"][0],
+        "Tag 'Plant/Temperature' references unknown device 'missing'"
+    );
+}
+
+#[tokio::test]
+async fn test_update_config_with_malformed_body_names_the_field() {
+    let app = create_test_app();
+
+    let body = serde_json::json!({
+        "devices": [{
+            "id": "opcua1",
+            "name": "Dummy",
+            "address": "opc.tcp://127.0.0.1:4840/",
+            "scan_rate_ms": "fast"
+        }]
+    });
+    let request = Request::builder()
+        .uri("/api/config")
+        .method(Method::PUT)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["path"], "devices[0].scan_rate_ms", "{body}");
+    assert_eq!(body["error"]["kind"], "invalid_json");
+    let message = body["error"]["message"].as_str().unwrap();
+    assert!(message.starts_with("devices[0].scan_rate_ms: invalid type"), "{message}");
+}
+
+#[tokio::test]
+async fn test_update_config_with_invalid_json_is_a_json_error() {
+    let app = create_test_app();
+    let request = Request::builder()
+        .uri("/api/config")
+        .method(Method::PUT)
+        .header("content-type", "application/json")
+        .body(Body::from("{ \"devices\": ["))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["error"]["kind"], "invalid_json", "{body}");
+}
+
+fn temperature_tag_config() -> TagConfig {
+    TagConfig {
+        path: "TestDevice/Temperature".parse().unwrap(),
+        driver_id: "test_driver".to_string(),
+        address: "test_addr".to_string(),
+        poll_rate_ms: 1000,
+        enabled: true,
+        raw_low: None,
+        raw_high: None,
+        eng_low: None,
+        eng_high: None,
+        deadband: None,
+        writable: false,
+        hold_last_value: false,
+        alarm_high: None,
+        alarm_low: None,
+        alarm_rate_of_change: None,
+        alarm_severity: Severity::default(),
+        alias_of: None,
+    }
+}
+
+/// App state matching a saved config file with one running device and tag.
+fn create_reconfigurable_state(name: &str) -> (SharedAppState, PathBuf) {
+    let driver = RecordingDriver::new("test_driver");
+    let settings = Settings {
+        server: ServerConfig::default(),
+        auth: None,
+        cors: None,
+        logging: LoggingConfig::default(),
+        devices: vec![driver.config.clone()],
+        tags: vec![temperature_tag_config()],
+        influx: None,
+        mqtt: None,
+        poll_tick_ms: None,
+    };
+    let path = std::env::temp_dir().join(format!("forgeio_{}_{}.toml", name, std::process::id()));
+    settings.save(&path).unwrap();
+    let state = create_app_state_with(settings, path.clone());
+    state.drivers.insert("test_driver".to_string(), Arc::new(driver));
+    (state, path)
+}
+
+fn put_config(settings: &Settings) -> Request<Body> {
+    Request::builder()
+        .uri("/api/config")
+        .method(Method::PUT)
+        .header("content-type", "application/json")
+        .body(Body::from(serde_json::to_string(settings).unwrap()))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_update_config_applies_new_tags() {
+    let (state, path) = create_reconfigurable_state("update_tags");
+    let mut settings = state.settings.read().await.clone();
+    settings.tags.push(TagConfig {
+        path: "TestDevice/Pressure".parse().unwrap(),
+        address: "pressure_addr".to_string(),
+        ..temperature_tag_config()
+    });
+    let app = create_api_routes().with_state(state.clone());
+
+    let response = app.clone().oneshot(put_config(&settings)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["changes"]["added_tags"], serde_json::json!(["TestDevice/Pressure"]));
+    assert_eq!(body["changes"]["removed_tags"], serde_json::json!([]));
+
+    // Live without a restart, and persisted
+    let (_, page) = get_json(app, "/tags?pattern=TestDevice/*").await;
+    assert_eq!(page["total"], 2);
+    assert_eq!(page["items"][0]["path"], "TestDevice/Pressure");
+    assert_eq!(page["items"][0]["driver_address"], "pressure_addr");
+    assert_eq!(Settings::load(&path).unwrap().tags.len(), 2);
+    assert_eq!(state.settings.read().await.tags, settings.tags);
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn test_update_config_removes_tags() {
+    let (state, path) = create_reconfigurable_state("remove_tags");
+    let mut settings = state.settings.read().await.clone();
+    settings.tags.clear();
+    let app = create_api_routes().with_state(state.clone());
+
+    let response = app.oneshot(put_config(&settings)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(state.tag_engine.read_tag("TestDevice/Temperature").is_none());
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn test_update_config_does_not_wait_for_unreachable_devices() {
+    let (state, path) = create_reconfigurable_state("unreachable");
+    let mut settings = state.settings.read().await.clone();
+    let mut device = RecordingDriver::new("remote").config;
+    device.driver_type = "opcua".to_string();
+    // Nothing listens here, so connecting fails
+    device.address = "opc.tcp://127.0.0.1:4855/".to_string();
+    device.connect_retry_attempts = Some(2);
+    device.connect_retry_delay_ms = Some(500);
+    device.connect_timeout_ms = Some(1000);
+    settings.devices.push(device);
+    settings.tags.push(TagConfig {
+        path: "Remote/Speed".parse().unwrap(),
+        driver_id: "remote".to_string(),
+        ..temperature_tag_config()
+    });
+    let app = create_api_routes().with_state(state.clone());
+
+    let started = Instant::now();
+    let response = app.oneshot(put_config(&settings)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(started.elapsed() < Duration::from_millis(500), "{:?}", started.elapsed());
+    assert_eq!(
+        state.connection_states.get("remote").map(|s| *s),
+        Some(ConnectionState::Connecting)
+    );
+    assert!(state.drivers.contains_key("remote"));
+    assert!(state.tag_engine.read_tag("Remote/Speed").is_some());
+    assert_eq!(state.settings.read().await.devices.len(), 2);
+
+    // Left to the health monitor once the retries are used up
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while state.connection_states.get("remote").map(|s| *s) != Some(ConnectionState::Disconnected) {
+        assert!(Instant::now() < deadline, "the connect attempt never finished");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn test_update_config_rolls_back_when_a_driver_cannot_be_created() {
+    let (state, path) = create_reconfigurable_state("rollback");
+    let original = std::fs::read_to_string(&path).unwrap();
+    let mut settings = state.settings.read().await.clone();
+    // No driver exists for the "mock" type
+    settings.devices.push(RecordingDriver::new("broken").config);
+    settings.tags.push(TagConfig {
+        path: "Broken/Speed".parse().unwrap(),
+        driver_id: "broken".to_string(),
+        ..temperature_tag_config()
+    });
+    let app = create_api_routes().with_state(state.clone());
+
+    let response = app.oneshot(put_config(&settings)).await.unwrap();
+    assert!(response.status().is_server_error(), "{}", response.status());
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert!(body["error"]["message"].as_str().unwrap().contains("'broken'"), "{body}");
+
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), original);
+    assert_eq!(state.settings.read().await.devices.len(), 1);
+    assert!(!state.drivers.contains_key("broken"));
+    assert!(state.tag_engine.read_tag("Broken/Speed").is_none());
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn test_changed_since_returns_only_newer_tags() {
+    let state = create_test_app_state();
+    state.tag_engine.clear();
+    for path in ["Line/A", "Line/B", "Line/C"] {
+        state.tag_engine.register_tag(Tag {
+            path: path.parse().unwrap(),
+            value: TagValue::with_timestamp(ValueVariant::Int(1), Quality::Good, 1_000),
+            driver_id: "test_driver".to_string(),
+            driver_address: path.to_string(),
+            poll_rate_ms: 1000,
+            enabled: true,
+            metadata: TagMetadata::default(),
+        });
+    }
+    for path in ["Line/C", "Line/B"] {
+        let value = TagValue::with_timestamp(ValueVariant::Int(2), Quality::Good, 5_000);
+        assert!(state.tag_engine.update_tag_value(path, value));
+    }
+    let app = create_api_routes().with_state(state);
+
+    let (status, tags) = get_json(app.clone(), "/api/tags/changed-since?ts=2000").await;
+    assert_eq!(status, StatusCode::OK);
+    let paths: Vec<&str> = tags
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|tag| tag["path"].as_str().unwrap())
+        .collect();
+    assert_eq!(paths, vec!["Line/B", "Line/C"]);
+    assert_eq!(tags[0]["value"]["value"], serde_json::json!({ "Int": 2 }));
+
+    // Nothing changed after the newest value
+    let (_, tags) = get_json(app, "/api/tags/changed-since?ts=5000").await;
+    assert_eq!(tags, serde_json::json!([]));
+}
+
+fn patch_tag(path: &str, body: serde_json::Value) -> Request<Body> {
+    Request::builder()
+        .uri(format!("/api/tags/{path}"))
+        .method(Method::PATCH)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_patch_tag_changes_the_poll_rate() {
+    let state = create_test_app_state();
+    let app = create_api_routes().with_state(state.clone());
+
+    let request = patch_tag("TestDevice%2FTemperature", serde_json::json!({ "poll_rate_ms": 250 }));
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let tag: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(tag["poll_rate_ms"], 250);
+    assert_eq!(
+        state.tag_engine.read_tag_full("TestDevice/Temperature").unwrap().poll_rate_ms,
+        250
+    );
+
+    let request = patch_tag("TestDevice%2FTemperature", serde_json::json!({ "poll_rate_ms": 0 }));
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let request = patch_tag("TestDevice%2FMissing", serde_json::json!({ "poll_rate_ms": 250 }));
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_patch_tag_disables_polling() {
+    let state = create_test_app_state();
+    let app = create_api_routes().with_state(state.clone());
+
+    let request = patch_tag("TestDevice%2FTemperature", serde_json::json!({ "enabled": false }));
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let tag: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(tag["enabled"], false);
+    assert_eq!(tag["value"]["quality"], "Stale");
+    // The poll rate is untouched when left out
+    assert_eq!(tag["poll_rate_ms"], 1000);
+
+    let request = patch_tag("TestDevice%2FTemperature", serde_json::json!({ "enabled": true }));
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(state.tag_engine.read_tag_full("TestDevice/Temperature").unwrap().enabled);
+}
+
+#[tokio::test]
+async fn test_get_config_never_returns_the_device_password() {
+    let (state, path) = create_reconfigurable_state("redacted");
+    let mut settings = state.settings.write().await;
+    settings.devices[0].password = Some("hunter2".to_string());
+    settings.save(&path).unwrap();
+    drop(settings);
+    let app = create_api_routes().with_state(state.clone());
+
+    let (status, config) = get_json(app.clone(), "/api/config").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(!config.to_string().contains("hunter2"), "{config}");
+    assert_eq!(config["devices"][0]["password"], "***");
+
+    // Sending the redacted config back keeps the real password
+    let redacted: Settings = serde_json::from_value(config).unwrap();
+    let response = app.oneshot(put_config(&redacted)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let saved = Settings::load(&path).unwrap();
+    assert_eq!(saved.devices[0].password.as_deref(), Some("hunter2"));
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn test_config_round_trip_keeps_environment_overrides_out_of_the_file() {
+    let (state, path) = create_reconfigurable_state("env_overrides");
+    let mut file = Settings::load_file(&path).unwrap();
+    file.influx = Some(InfluxConfig {
+        url: "http://localhost:8086".to_string(),
+        bucket: "plant".to_string(),
+        org: None,
+        token: None,
+        measurement: "forgeio".to_string(),
+        batch_size: 500,
+        flush_interval_ms: 1000,
+    });
+    file.save(&path).unwrap();
+    // As loaded with FORGEIO_SERVER__PORT=8080 and FORGEIO_INFLUX__TOKEN=env-token
+    let mut running = file.clone();
+    running.server.port = 8080;
+    running.influx.as_mut().unwrap().token = Some("env-token".to_string());
+    *state.settings.write().await = running;
+    let app = create_api_routes().with_state(state.clone());
+
+    let (status, config) = get_json(app.clone(), "/api/config").await;
+    assert_eq!(status, StatusCode::OK);
+    let mut config: Settings = serde_json::from_value(config).unwrap();
+    let response = app.clone().oneshot(put_config(&config)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let saved = Settings::load_file(&path).unwrap();
+    assert_eq!(saved.server.port, 3000);
+    assert_eq!(saved.influx.as_ref().unwrap().token, None);
+    assert!(!std::fs::read_to_string(&path).unwrap().contains("env-token"));
+
+    // A value the client changes is saved, even if it was overridden
+    config.server.port = 9000;
+    let response = app.oneshot(put_config(&config)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(Settings::load_file(&path).unwrap().server.port, 9000);
+    let _ = std::fs::remove_file(path);
+}
+
+/// Driver that remembers what it was asked to write. Writes to addresses in
+/// `rejected` fail, all others succeed. Reads return the last value written.
+struct RecordingDriver {
+    config: DriverConfig,
+    connects: AtomicUsize,
+    writes: Mutex<Vec<HashMap<String, TagValue>>>,
+    rejected: HashSet<String>,
+    /// Largest value the device keeps; bigger writes are silently clamped to it.
+    clamp: Option<f64>,
+}
+
+impl RecordingDriver {
+    fn new(id: &str) -> Self {
+        Self {
+            config: DriverConfig {
+                id: id.to_string(),
+                name: id.to_string(),
+                address: "mock://".to_string(),
+                scan_rate_ms: 1000,
+                driver_type: "mock".to_string(),
+                application_name: None,
+                application_uri: None,
+                session_name: None,
+                max_message_size: None,
+                max_chunk_count: None,
+                read_batch_size: None,
+                connect_retry_attempts: None,
+                connect_retry_delay_ms: None,
+                connect_retry_backoff: None,
+                connect_retry_jitter_ms: None,
+                connect_timeout_ms: None,
+                security_policy: None,
+                security_mode: None,
+                username: None,
+                password: None,
+                pki_dir: None,
+                trust_server_certs: None,
+                create_sample_keypair: None,
+                insecure: false,
+                max_concurrent_reads: None,
+                read_timeout_ms: None,
+                critical: false,
+            },
+            connects: AtomicUsize::new(0),
+            writes: Mutex::new(Vec::new()),
+            rejected: HashSet::new(),
+            clamp: None,
+        }
+    }
+}
+
+#[async_trait]
+impl DeviceDriver for RecordingDriver {
+    fn config(&self) -> &DriverConfig {
+        &self.config
+    }
+    async fn connect(&self) -> DriverResult<()> {
+        self.connects.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+    async fn disconnect(&self) -> DriverResult<()> {
+        Ok(())
+    }
+    async fn check_status(&self) -> DriverResult<()> {
+        Ok(())
+    }
+    async fn read_tags(&self, tags: &[TagRequest]) -> DriverResult<HashMap<String, TagValue>> {
+        let writes = self.writes.lock().unwrap();
+        Ok(tags
+            .iter()
+            .filter_map(|t| {
+                let mut value = writes.iter().rev().find_map(|w| w.get(&t.address))?.clone();
+                if let (ValueVariant::Float(v), Some(max)) = (&mut value.value, self.clamp) {
+                    *v = v.min(max);
+                }
+                Some((t.address.clone(), value))
+            })
+            .collect())
+    }
+    async fn write_tags(
+        &self,
+        tags: HashMap<String, TagValue>,
+    ) -> DriverResult<HashMap<String, WriteStatus>> {
+        self.writes.lock().unwrap().push(tags.clone());
+        Ok(tags
+            .into_iter()
+            .map(|(address, value)| {
+                let status = if self.rejected.contains(&address) {
+                    WriteStatus::failed(value.value, "BadNotWritable")
+                } else {
+                    WriteStatus::good(value.value)
+                };
+                (address, status)
+            })
+            .collect())
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+fn write_request(path: &str, value: serde_json::Value) -> Request<Body> {
+    Request::builder()
+        .uri(format!("/api/tags/{}/write", path.replace('/', "%2F")))
+        .method(Method::POST)
+        .header("content-type", "application/json")
+        .body(Body::from(serde_json::json!({ "value": value }).to_string()))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_write_tag_through_driver() {
+    let state = create_test_app_state();
+    let driver = Arc::new(RecordingDriver::new("test_driver"));
+    state.drivers.insert("test_driver".to_string(), driver.clone());
+    let mut tag = state.tag_engine.get_tag_details("TestDevice/Temperature").unwrap();
+    tag.metadata.writable = true;
+    state.tag_engine.register_tag(tag);
+    let app = create_api_routes().with_state(state);
+
+    let response = app
+        .oneshot(write_request("TestDevice/Temperature", serde_json::json!(42.5)))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let results: HashMap<String, WriteStatus> = serde_json::from_slice(&body).unwrap();
+    assert_eq!(results["TestDevice/Temperature"], WriteStatus::good(ValueVariant::Float(42.5)));
+
+    let writes = driver.writes.lock().unwrap();
+    assert_eq!(writes.len(), 1);
+    assert_eq!(writes[0]["test_addr"].value, ValueVariant::Float(42.5));
+}
+
+#[tokio::test]
+async fn test_write_through_mock_driver() {
+    let state = create_test_app_state();
+    let driver = Arc::new(MockDriver::new("test_driver"));
+    state.drivers.insert("test_driver".to_string(), driver.clone());
+    let mut tag = state.tag_engine.get_tag_details("TestDevice/Temperature").unwrap();
+    tag.metadata.writable = true;
+    state.tag_engine.register_tag(tag);
+    let app = create_api_routes().with_state(state);
+    let write = |value: serde_json::Value| {
+        let request = write_request("TestDevice/Temperature", value);
+        let (mut parts, body) = request.into_parts();
+        parts.uri = format!("{}?verify=true", parts.uri).parse().unwrap();
+        Request::from_parts(parts, body)
+    };
+
+    // Accepted, and read back from the device as written
+    let response = app.clone().oneshot(write(serde_json::json!(42.5))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["TestDevice/Temperature"]["quality"], "Good", "{body}");
+    assert_eq!(body["TestDevice/Temperature"]["verify"]["matches"], true, "{body}");
+    assert_eq!(driver.written().len(), 1);
+    assert_eq!(driver.written()[0].1.value, ValueVariant::Float(42.5));
+
+    // Rejected by the device for this address only
+    driver.set_write_error("test_addr", Some("out of range"));
+    let response = app.clone().oneshot(write(serde_json::json!(1000))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["TestDevice/Temperature"]["quality"], "Bad", "{body}");
+    assert_eq!(body["TestDevice/Temperature"]["error"], "out of range", "{body}");
+    driver.set_write_error("test_addr", None);
+
+    // The device is unreachable
+    driver.drop_connection();
+    let response = app.oneshot(write(serde_json::json!(7))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(driver.written().len(), 1);
+}
+
+#[tokio::test]
+async fn test_batch_write_sends_one_request_per_driver() {
+    let state = create_test_app_state();
+    let driver = Arc::new(MockDriver::new("test_driver"));
+    state.drivers.insert("test_driver".to_string(), driver.clone());
+    let mut temperature = state.tag_engine.get_tag_details("TestDevice/Temperature").unwrap();
+    temperature.metadata.writable = true;
+    let setpoint = Tag {
+        path: "TestDevice/Setpoint".parse().unwrap(),
+        driver_address: "sp_addr".to_string(),
+        value: TagValue::new(ValueVariant::Int(0), Quality::Good),
+        ..temperature.clone()
+    };
+    let read_only = Tag {
+        path: "TestDevice/Status".parse().unwrap(),
+        driver_address: "status_addr".to_string(),
+        metadata: TagMetadata::default(),
+        ..temperature.clone()
+    };
+    state.tag_engine.register_tags(vec![temperature, setpoint, read_only]);
+    let app = create_api_routes().with_state(state);
+
+    let request = Request::builder()
+        .uri("/api/tags/write")
+        .method(Method::POST)
+        .header("content-type", "application/json")
+        .body(Body::from(
+            serde_json::json!({ "writes": [
+                { "path": "TestDevice/Temperature", "value": 80 },
+                { "path": "TestDevice/Setpoint", "value": 12 },
+                { "path": "TestDevice/Status", "value": 1 },
+                { "path": "TestDevice/Missing", "value": 1 },
+            ] })
+            .to_string(),
+        ))
+        .unwrap();
+    let (status, results) = send_json(app, request).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(results["TestDevice/Temperature"]["quality"], "Good", "{results}");
+    assert_eq!(results["TestDevice/Temperature"]["value"]["Float"], 80.0, "{results}");
+    assert_eq!(results["TestDevice/Setpoint"]["quality"], "Good", "{results}");
+    assert_eq!(results["TestDevice/Status"]["error"]["kind"], "forbidden", "{results}");
+    assert_eq!(results["TestDevice/Missing"]["error"]["kind"], "not_found", "{results}");
+
+    // Both writes went to the device in a single call
+    assert_eq!(driver.write_count(), 1);
+    let mut written: Vec<_> = driver
+        .written()
+        .into_iter()
+        .map(|(address, value)| (address, value.value))
+        .collect();
+    written.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(
+        written,
+        vec![
+            ("sp_addr".to_string(), ValueVariant::Int(12)),
+            ("test_addr".to_string(), ValueVariant::Float(80.0)),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_batch_write_rejects_repeated_paths_and_shared_addresses() {
+    let state = create_test_app_state();
+    let driver = Arc::new(MockDriver::new("test_driver"));
+    state.drivers.insert("test_driver".to_string(), driver.clone());
+    let mut temperature = state.tag_engine.get_tag_details("TestDevice/Temperature").unwrap();
+    temperature.metadata.writable = true;
+    // A second tag on the temperature's address
+    let copy = Tag {
+        path: "Overview/Temperature".parse().unwrap(),
+        ..temperature.clone()
+    };
+    let setpoint = Tag {
+        path: "TestDevice/Setpoint".parse().unwrap(),
+        driver_address: "sp_addr".to_string(),
+        ..temperature.clone()
+    };
+    state.tag_engine.register_tags(vec![temperature, copy, setpoint]);
+    let app = create_api_routes().with_state(state);
+
+    let request = Request::builder()
+        .uri("/api/tags/write")
+        .method(Method::POST)
+        .header("content-type", "application/json")
+        .body(Body::from(
+            serde_json::json!({ "writes": [
+                { "path": "TestDevice/Setpoint", "value": 1 },
+                { "path": "TestDevice/Setpoint", "value": 2 },
+                { "path": "TestDevice/Temperature", "value": 80 },
+                { "path": "Overview/Temperature", "value": 90 },
+            ] })
+            .to_string(),
+        ))
+        .unwrap();
+    let (status, results) = send_json(app, request).await;
+    assert_eq!(status, StatusCode::OK);
+    for path in ["TestDevice/Setpoint", "TestDevice/Temperature", "Overview/Temperature"] {
+        assert_eq!(results[path]["error"]["kind"], "invalid_value", "{results}");
+    }
+    assert!(results["Overview/Temperature"]["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("shares address 'test_addr'"));
+
+    // Nothing was left to send
+    assert_eq!(driver.write_count(), 0);
+    assert!(driver.written().is_empty());
+}
+
+#[tokio::test]
+async fn test_write_is_coerced_to_the_tag_type() {
+    let state = create_test_app_state();
+    let driver = Arc::new(RecordingDriver::new("test_driver"));
+    state.drivers.insert("test_driver".to_string(), driver.clone());
+    let mut tag = state.tag_engine.get_tag_details("TestDevice/Temperature").unwrap();
+    tag.metadata.writable = true;
+    state.tag_engine.register_tag(tag.clone());
+    state.tag_engine.register_tag(Tag {
+        path: "TestDevice/Running".parse().unwrap(),
+        value: TagValue::new(ValueVariant::Bool(false), Quality::Good),
+        driver_address: "running_addr".to_string(),
+        ..tag
+    });
+    let app = create_api_routes().with_state(state);
+
+    // An integer written to a Float tag reaches the driver as a float
+    let response = app
+        .clone()
+        .oneshot(write_request("TestDevice/Temperature", serde_json::json!(5)))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(driver.writes.lock().unwrap()[0]["test_addr"].value, ValueVariant::Float(5.0));
+
+    // A string can't be written to a Bool tag
+    let response = app
+        .oneshot(write_request("TestDevice/Running", serde_json::json!("yes")))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["error"]["kind"], "invalid_value", "{body}");
+    assert_eq!(driver.writes.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_non_finite_floats_are_valid_json() {
+    let state = create_test_app_state();
+    state.tag_engine.update_tag_value(
+        "TestDevice/Temperature",
+        TagValue::new(ValueVariant::Float(f64::NAN), Quality::Good),
+    );
+    state.tag_engine.register_tag(Tag {
+        path: "TestDevice/Flow".parse().unwrap(),
+        value: TagValue::new(ValueVariant::Float(f64::NEG_INFINITY), Quality::Good),
+        driver_id: "test_driver".to_string(),
+        driver_address: "flow_addr".to_string(),
+        poll_rate_ms: 1000,
+        enabled: true,
+        metadata: TagMetadata { writable: true, ..TagMetadata::default() },
+    });
+    let driver = Arc::new(RecordingDriver::new("test_driver"));
+    state.drivers.insert("test_driver".to_string(), driver.clone());
+    let app = create_api_routes().with_state(state);
+
+    // get_json fails on invalid JSON
+    let (status, page) = get_json(app.clone(), "/tags?pattern=TestDevice/*").await;
+    assert_eq!(status, StatusCode::OK);
+    let value = |path: &str| {
+        let tag = page["items"].as_array().unwrap().iter().find(|t| t["path"] == path).unwrap();
+        tag["value"]["value"].clone()
+    };
+    assert_eq!(value("TestDevice/Temperature"), serde_json::json!({ "Float": "NaN" }));
+    assert_eq!(value("TestDevice/Flow"), serde_json::json!({ "Float": "-Infinity" }));
+
+    // The same names are accepted when writing
+    let (status, _) = send_json(app, write_request("TestDevice/Flow", serde_json::json!("Infinity"))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        driver.writes.lock().unwrap()[0]["flow_addr"].value,
+        ValueVariant::Float(f64::INFINITY)
+    );
+}
+
+#[tokio::test]
+async fn test_verified_write_reports_a_clamped_value() {
+    let state = create_test_app_state();
+    let mut driver = RecordingDriver::new("test_driver");
+    driver.clamp = Some(100.0);
+    state.drivers.insert("test_driver".to_string(), Arc::new(driver));
+    let mut tag = state.tag_engine.get_tag_details("TestDevice/Temperature").unwrap();
+    tag.metadata.writable = true;
+    state.tag_engine.register_tag(tag);
+    let app = create_api_routes().with_state(state);
+
+    let verified_write = |value: f64| {
+        let mut request = write_request("TestDevice/Temperature", serde_json::json!(value));
+        *request.uri_mut() = format!("{}?verify=true", request.uri()).parse().unwrap();
+        request
+    };
+    let (status, body) = send_json(app.clone(), verified_write(80.5)).await;
+    assert_eq!(status, StatusCode::OK);
+    let result = &body["TestDevice/Temperature"];
+    assert_eq!(result["verify"]["matches"], true, "{body}");
+    assert_eq!(result["verify"]["value"], serde_json::json!({ "Float": 80.5 }));
+
+    // The device kept 100 instead of 150
+    let (status, body) = send_json(app.clone(), verified_write(150.0)).await;
+    assert_eq!(status, StatusCode::OK);
+    let result = &body["TestDevice/Temperature"];
+    assert_eq!(result["quality"], "Good");
+    assert_eq!(result["value"], serde_json::json!({ "Float": 150.0 }));
+    assert_eq!(result["verify"]["matches"], false, "{body}");
+    assert_eq!(result["verify"]["value"], serde_json::json!({ "Float": 100.0 }));
+
+    // Without the flag nothing is read back
+    let (_, body) = send_json(app, write_request("TestDevice/Temperature", serde_json::json!(1.0))).await;
+    assert!(body["TestDevice/Temperature"].get("verify").is_none(), "{body}");
+}
+
+#[tokio::test]
+async fn test_write_to_scaled_tag_sends_the_raw_value() {
+    let state = create_test_app_state();
+    let driver = Arc::new(RecordingDriver::new("test_driver"));
+    state.drivers.insert("test_driver".to_string(), driver.clone());
+    let mut tag = state.tag_engine.get_tag_details("TestDevice/Temperature").unwrap();
+    // 0..4000 counts for 0..100 °C
+    tag.metadata = TagMetadata {
+        writable: true,
+        raw_low: Some(0.0),
+        raw_high: Some(4000.0),
+        eng_low: Some(0.0),
+        eng_high: Some(100.0),
+        ..TagMetadata::default()
+    };
+    state.tag_engine.register_tag(tag);
+    let app = create_api_routes().with_state(state);
+
+    let mut request = write_request("TestDevice/Temperature", serde_json::json!(25));
+    *request.uri_mut() = format!("{}?verify=true", request.uri()).parse().unwrap();
+    let (status, body) = send_json(app.clone(), request).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(driver.writes.lock().unwrap()[0]["test_addr"].value, ValueVariant::Int(1000));
+    // Answered and verified in engineering units
+    let result = &body["TestDevice/Temperature"];
+    assert_eq!(result["value"], serde_json::json!({ "Float": 25.0 }), "{body}");
+    assert_eq!(result["verify"]["matches"], true, "{body}");
+    assert_eq!(result["verify"]["value"], serde_json::json!({ "Float": 25.0 }), "{body}");
+
+    let request = Request::builder()
+        .uri("/api/tags/write")
+        .method(Method::POST)
+        .header("content-type", "application/json")
+        .body(Body::from(
+            serde_json::json!({ "writes": [{ "path": "TestDevice/Temperature", "value": 75.5 }] })
+                .to_string(),
+        ))
+        .unwrap();
+    let (status, results) = send_json(app, request).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(driver.writes.lock().unwrap()[1]["test_addr"].value, ValueVariant::Int(3020));
+    assert_eq!(results["TestDevice/Temperature"]["value"]["Float"], 75.5, "{results}");
+}
+
+#[tokio::test]
+async fn test_driver_reports_status_per_written_address() {
+    let mut driver = RecordingDriver::new("test_driver");
+    driver.rejected.insert("locked_addr".to_string());
+    let writes = HashMap::from([
+        ("test_addr".to_string(), TagValue::new(ValueVariant::Int(1), Quality::Good)),
+        ("locked_addr".to_string(), TagValue::new(ValueVariant::Int(2), Quality::Good)),
+    ]);
+
+    let results = driver.write_tags(writes).await.unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results["test_addr"], WriteStatus::good(ValueVariant::Int(1)));
+    assert_eq!(results["locked_addr"].quality, Quality::Bad);
+    assert_eq!(results["locked_addr"].error.as_deref(), Some("BadNotWritable"));
+}
+
+#[tokio::test]
+async fn test_rejected_write_reports_error_status() {
+    let state = create_test_app_state();
+    let mut driver = RecordingDriver::new("test_driver");
+    driver.rejected.insert("test_addr".to_string());
+    state.drivers.insert("test_driver".to_string(), Arc::new(driver));
+    let mut tag = state.tag_engine.get_tag_details("TestDevice/Temperature").unwrap();
+    tag.metadata.writable = true;
+    state.tag_engine.register_tag(tag);
+    let app = create_api_routes().with_state(state);
+
+    let response = app
+        .oneshot(write_request("TestDevice/Temperature", serde_json::json!(7)))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let results: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(results["TestDevice/Temperature"]["quality"], "Bad", "{results}");
+    assert_eq!(results["TestDevice/Temperature"]["error"], "BadNotWritable");
+}
+
+#[tokio::test]
+async fn test_write_read_only_tag_is_forbidden() {
+    let state = create_test_app_state();
+    let driver = Arc::new(RecordingDriver::new("test_driver"));
+    state.drivers.insert("test_driver".to_string(), driver.clone());
+    let app = create_api_routes().with_state(state);
+
+    let response = app
+        .oneshot(write_request("TestDevice/Temperature", serde_json::json!(1)))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(driver.writes.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_write_to_alias_names_its_source() {
+    let state = create_test_app_state();
+    let driver = Arc::new(RecordingDriver::new("test_driver"));
+    state.drivers.insert("test_driver".to_string(), driver.clone());
+    state.tag_engine.register_tag(Tag {
+        path: "Line/Temperature".parse().unwrap(),
+        value: TagValue::new(ValueVariant::Float(0.0), Quality::Good),
+        driver_id: String::new(),
+        driver_address: String::new(),
+        poll_rate_ms: 1000,
+        enabled: true,
+        metadata: TagMetadata {
+            writable: true,
+            alias_of: Some("TestDevice/Temperature".parse().unwrap()),
+            ..TagMetadata::default()
+        },
+    });
+    let app = create_api_routes().with_state(state);
+
+    let request = write_request("Line/Temperature", serde_json::json!(1));
+    let (status, body) = send_json(app.clone(), request).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["kind"], "invalid_value", "{body}");
+    let message = body["error"]["message"].as_str().unwrap();
+    assert!(message.contains("write to 'TestDevice/Temperature'"), "{message}");
+
+    let request = Request::builder()
+        .uri("/api/tags/write")
+        .method(Method::POST)
+        .header("content-type", "application/json")
+        .body(Body::from(
+            serde_json::json!({ "writes": [{ "path": "Line/Temperature", "value": 1 }] }).to_string(),
+        ))
+        .unwrap();
+    let (status, results) = send_json(app, request).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(results["Line/Temperature"]["error"]["kind"], "invalid_value", "{results}");
+    assert!(driver.writes.lock().unwrap().is_empty());
+}
+
+async fn next_event(body: &mut axum::body::BodyDataStream) -> String {
+    use futures::StreamExt;
+
+    let bytes = tokio::time::timeout(std::time::Duration::from_secs(5), body.next())
+        .await
+        .expect("timed out waiting for an event")
+        .unwrap()
+        .unwrap();
+    String::from_utf8(bytes.to_vec()).unwrap()
+}
+
+#[tokio::test]
+async fn test_tag_stream_sends_snapshot_and_updates() {
+    let state = create_test_app_state();
+    let engine = Arc::clone(&state.tag_engine);
+    let app = create_api_routes().with_state(state);
+
+    let request = Request::builder()
+        .uri("/api/tags/stream?pattern=TestDevice/*")
+        .method(Method::GET)
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "text/event-stream");
+
+    let mut body = response.into_body().into_data_stream();
+    let snapshot = next_event(&mut body).await;
+    assert!(snapshot.starts_with("event: tag\n"), "{snapshot}");
+    assert!(snapshot.contains("\"path\":\"TestDevice/Temperature\""));
+    assert!(snapshot.contains("23.5"));
+
+    engine.update_tag_value(
+        "TestDevice/Temperature",
+        TagValue::new(ValueVariant::Float(99.0), Quality::Good),
+    );
+    let update = next_event(&mut body).await;
+    assert!(update.contains("\"path\":\"TestDevice/Temperature\""), "{update}");
+    assert!(update.contains("99.0"));
+}
+
+#[tokio::test]
+async fn test_quality_stream_sends_transitions() {
+    let state = create_test_app_state();
+    let engine = Arc::clone(&state.tag_engine);
+    let app = create_api_routes().with_state(state);
+
+    let request = Request::builder()
+        .uri("/api/events/quality")
+        .method(Method::GET)
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "text/event-stream");
+
+    engine.update_tag_value(
+        "TestDevice/Temperature",
+        TagValue::new(ValueVariant::Float(23.5), Quality::Bad),
+    );
+    let mut body = response.into_body().into_data_stream();
+    let event = next_event(&mut body).await;
+    assert!(event.starts_with("event: quality\n"), "{event}");
+    assert!(event.contains("\"path\":\"TestDevice/Temperature\""), "{event}");
+    assert!(event.contains("\"old\":\"Good\",\"new\":\"Bad\""), "{event}");
+}
+
+#[tokio::test]
+async fn test_large_tag_list_is_gzip_compressed() {
+    let state = create_test_app_state();
+    state.tag_engine.register_tags(
+        (0..2000)
+            .map(|i| Tag {
+                path: format!("Bulk/Tag{i}").parse().unwrap(),
+                value: TagValue::new(ValueVariant::Float(i as f64), Quality::Good),
+                driver_id: "test_driver".to_string(),
+                driver_address: format!("ns=2;i={i}"),
+                poll_rate_ms: 1000,
+                enabled: true,
+                metadata: TagMetadata::default(),
+            })
+            .collect(),
+    );
+    let app = create_api_routes().with_state(state);
+
+    let request = Request::builder()
+        .uri("/tags?pattern=Bulk/*")
+        .method(Method::GET)
+        .header("accept-encoding", "gzip")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-encoding"], "gzip");
+    let compressed = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let mut json = String::new();
+    flate2::read::GzDecoder::new(&compressed[..]).read_to_string(&mut json).unwrap();
+    assert!(compressed.len() * 4 < json.len(), "{} of {} bytes", compressed.len(), json.len());
+    let page: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(page["total"], 2000);
+
+    // Without Accept-Encoding the body is plain JSON
+    let (status, page) = get_json(app, "/tags?pattern=Bulk/*").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(page["total"], 2000);
+}
+
+#[tokio::test]
+async fn test_tag_stream_is_not_compressed() {
+    let state = create_test_app_state();
+    let app = create_api_routes().with_state(state);
+
+    let request = Request::builder()
+        .uri("/api/tags/stream")
+        .method(Method::GET)
+        .header("accept-encoding", "gzip, br")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get("content-encoding").is_none());
+
+    // Events arrive as they are sent rather than buffered by an encoder
+    let mut body = response.into_body().into_data_stream();
+    let snapshot = next_event(&mut body).await;
+    assert!(snapshot.starts_with("event: tag\n"), "{snapshot}");
+}
+
+#[tokio::test]
+async fn test_openapi_document_describes_the_api() {
+    let app = create_api_routes().with_state(create_test_app_state());
+
+    let (status, document) = get_json(app, "/api/openapi.json").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(document["openapi"].as_str().unwrap().starts_with("3."));
+    let browse = &document["paths"]["/api/opcua/browse/{driver_id}"]["get"];
+    assert_eq!(
+        browse["responses"]["200"]["content"]["application/json"]["schema"]["$ref"],
+        "#/components/schemas/BrowseResponse"
+    );
+    let schemas = &document["components"]["schemas"];
+    assert!(schemas["BrowseResponse"]["properties"]["children"].is_object());
+    assert!(schemas["DiscoverResponse"]["properties"]["drivers"].is_object());
+}
+
+#[tokio::test]
+async fn test_every_documented_operation_is_routed() {
+    let app = create_api_routes().with_state(create_test_app_state());
+    let (_, document) = get_json(app.clone(), "/api/openapi.json").await;
+
+    let paths = document["paths"].as_object().unwrap();
+    assert!(paths.len() >= 20, "{} paths", paths.len());
+    for (path, operations) in paths {
+        let uri = path.replace("{path}", "Missing").replace("{driver_id}", "missing");
+        for method in operations.as_object().unwrap().keys() {
+            let request = Request::builder()
+                .uri(&uri)
+                .method(method.to_uppercase().as_str())
+                .body(Body::empty())
+                .unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            let status = response.status();
+            // Only the router's own answers have an empty body
+            let unrouted = match status {
+                StatusCode::NOT_FOUND | StatusCode::METHOD_NOT_ALLOWED => {
+                    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                    body.is_empty()
+                }
+                _ => false,
+            };
+            assert!(!unrouted, "{method} {path} is documented but not routed ({status})");
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_alarms_lists_tags_beyond_configured_limits() {
+    let state = create_test_app_state();
+    let tag = TagConfig {
+        alarm_high: Some(30.0),
+        alarm_severity: Severity::Critical,
+        alias_of: None,
+        ..temperature_tag_config()
+    };
+    state.tag_engine.register_tag(tag.to_tag());
+    state.tag_engine.update_tag_value(
+        &tag.path,
+        TagValue::new(ValueVariant::Float(35.0), Quality::Good),
+    );
+    let app = create_api_routes().with_state(state);
+
+    let request = Request::builder()
+        .uri("/api/alarms")
+        .method(Method::GET)
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let alarms: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(alarms.as_array().unwrap().len(), 1, "{alarms}");
+    assert_eq!(alarms[0]["path"], "TestDevice/Temperature");
+    assert_eq!(alarms[0]["kind"], "High");
+    assert_eq!(alarms[0]["severity"], "Critical");
+    assert_eq!(alarms[0]["value"], 35.0);
+}
+
+async fn get_tag_page(app: Router, query: &str) -> serde_json::Value {
+    let request = Request::builder()
+        .uri(format!("/tags{query}"))
+        .method(Method::GET)
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+fn page_paths(page: &serde_json::Value) -> Vec<&str> {
+    page["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|tag| tag["path"].as_str().unwrap())
+        .collect()
+}
+
+fn create_paged_app() -> Router {
+    let state = create_test_app_state();
+    for i in 0..10 {
+        state.tag_engine.register_tag(Tag {
+            path: format!("Line/Tag{i}").parse().unwrap(),
+            value: TagValue::new(ValueVariant::Int(i), Quality::Good),
+            driver_id: if i % 2 == 0 { "even" } else { "odd" }.to_string(),
+            driver_address: format!("addr{i}"),
+            poll_rate_ms: 1000,
+            enabled: true,
+            metadata: TagMetadata::default(),
+        });
+    }
+    create_api_routes().with_state(state)
+}
+
+#[tokio::test]
+async fn test_tags_pagination_bounds() {
+    let app = create_paged_app();
+
+    let page = get_tag_page(app.clone(), "?pattern=Line/*&offset=2&limit=3").await;
+    assert_eq!(page["total"], 10);
+    assert_eq!(page_paths(&page), vec!["Line/Tag2", "Line/Tag3", "Line/Tag4"]);
+
+    // Limit runs past the end
+    let page = get_tag_page(app.clone(), "?pattern=Line/*&offset=8&limit=5").await;
+    assert_eq!(page_paths(&page), vec!["Line/Tag8", "Line/Tag9"]);
+
+    // Offset past the end
+    let page = get_tag_page(app.clone(), "?offset=100").await;
+    assert_eq!(page["total"], 11);
+    assert!(page_paths(&page).is_empty());
+
+    // No parameters returns everything, sorted by path
+    let page = get_tag_page(app, "").await;
+    assert_eq!(page_paths(&page).len(), 11);
+    assert_eq!(page_paths(&page)[0], "Line/Tag0");
+    assert_eq!(page_paths(&page)[10], "TestDevice/Temperature");
+}
+
+#[tokio::test]
+async fn test_tags_driver_filter() {
+    let app = create_paged_app();
+
+    let page = get_tag_page(app.clone(), "?driver_id=odd").await;
+    assert_eq!(page["total"], 5);
+    assert_eq!(
+        page_paths(&page),
+        vec!["Line/Tag1", "Line/Tag3", "Line/Tag5", "Line/Tag7", "Line/Tag9"]
+    );
+
+    let page = get_tag_page(app, "?driver_id=even&limit=2").await;
+    assert_eq!(page["total"], 5);
+    assert_eq!(page_paths(&page), vec!["Line/Tag0", "Line/Tag2"]);
+}
+
+#[tokio::test]
+async fn test_tag_stats_endpoint() {
+    let state = create_test_app_state();
+    state.tag_engine.update_tag_value(
+        "TestDevice/Temperature",
+        TagValue::new(ValueVariant::Float(24.0), Quality::Good),
+    );
+    let app = create_api_routes().with_state(state);
+
+    let request = Request::builder()
+        .uri("/api/tags/TestDevice%2FTemperature/stats")
+        .method(Method::GET)
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let stats: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(stats["update_count"], 1);
+
+    let request = Request::builder()
+        .uri("/api/tags/Nope/stats")
+        .method(Method::GET)
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_opcua_discover_reports_connection_state() {
+    let state = create_test_app_state();
+    state
+        .drivers
+        .insert("test_driver".to_string(), Arc::new(RecordingDriver::new("test_driver")));
+    state
+        .connection_states
+        .insert("test_driver".to_string(), ConnectionState::Reconnecting);
+    let app = create_api_routes().with_state(state);
+
+    let request = Request::builder()
+        .uri("/api/opcua/discover")
+        .method(Method::GET)
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    // The mock driver reports itself healthy, but the monitor's view wins
+    assert_eq!(body["drivers"][0]["state"], "reconnecting");
+    assert_eq!(body["drivers"][0]["connected"], false);
+}
+
+#[tokio::test]
+async fn test_health_is_unavailable_while_a_critical_driver_is_down() {
+    let state = create_test_app_state();
+    let mut config = MockDriver::new("plc").config().clone();
+    config.critical = true;
+    state.drivers.insert("plc".to_string(), Arc::new(MockDriver::with_config(config)));
+    state.drivers.insert("lab".to_string(), Arc::new(MockDriver::new("lab")));
+    let set_state = |id: &str, connection| state.connection_states.insert(id.to_string(), connection);
+    set_state("plc", ConnectionState::Connected);
+    set_state("lab", ConnectionState::Connected);
+    let app = create_api_routes().with_state(state.clone());
+
+    let (status, health) = get_json(app.clone(), "/api/health").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(health, serde_json::json!({ "status": "ok", "drivers": { "plc": true, "lab": true } }));
+
+    // A non-critical device going away doesn't fail the check
+    set_state("lab", ConnectionState::Disconnected);
+    let (status, health) = get_json(app.clone(), "/api/health").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(health["status"], "degraded");
+    assert_eq!(health["drivers"]["lab"], false);
+
+    set_state("plc", ConnectionState::Reconnecting);
+    let (status, health) = get_json(app, "/api/health").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(health["status"], "unavailable");
+    assert_eq!(health["drivers"]["plc"], false);
+}
+
+fn reconnect_request(driver_id: &str) -> Request<Body> {
+    Request::builder()
+        .uri(format!("/api/drivers/{}/reconnect", driver_id))
+        .method(Method::POST)
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_reconnect_driver() {
+    let state = create_test_app_state();
+    let driver = Arc::new(RecordingDriver::new("test_driver"));
+    state.drivers.insert("test_driver".to_string(), driver.clone());
+    state
+        .connection_states
+        .insert("test_driver".to_string(), ConnectionState::Disconnected);
+    let app = create_api_routes().with_state(state.clone());
+
+    let response = app.clone().oneshot(reconnect_request("test_driver")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["state"], "connected");
+    assert_eq!(body["connected"], true);
+    assert_eq!(driver.connects.load(Ordering::SeqCst), 1);
+    assert_eq!(
+        state.connection_states.get("test_driver").map(|s| *s),
+        Some(ConnectionState::Connected)
+    );
+
+    // Someone else is already reconnecting it
+    state
+        .connection_states
+        .insert("test_driver".to_string(), ConnectionState::Reconnecting);
+    let response = app.clone().oneshot(reconnect_request("test_driver")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    assert_eq!(driver.connects.load(Ordering::SeqCst), 1);
+
+    let response = app.oneshot(reconnect_request("nope")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_driver_stats_endpoint() {
+    let state = create_test_app_state();
+    state
+        .drivers
+        .insert("test_driver".to_string(), Arc::new(RecordingDriver::new("test_driver")));
+    state.scan_stats.record("test_driver", Duration::from_millis(40), true);
+    state.scan_stats.record("test_driver", Duration::from_millis(60), false);
+    let app = create_api_routes().with_state(state);
+
+    let get = |driver_id: &str| {
+        Request::builder()
+            .uri(format!("/api/drivers/{}/stats", driver_id))
+            .body(Body::empty())
+            .unwrap()
+    };
+    let response = app.clone().oneshot(get("test_driver")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["driver_id"], "test_driver");
+    assert_eq!(body["scan_count"], 2);
+    assert_eq!(body["last_scan_duration_ms"], 60);
+    assert_eq!(body["avg_scan_duration_ms"], 50.0);
+    assert_eq!(body["consecutive_errors"], 1);
+
+    let response = app.oneshot(get("nope")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_tag_history_endpoint() {
+    let state = create_test_app_state();
+    for value in [24.0, 24.5, 25.0] {
+        state.tag_engine.update_tag_value(
+            "TestDevice/Temperature",
+            TagValue::new(ValueVariant::Float(value), Quality::Good),
+        );
+    }
+    let app = create_api_routes().with_state(state);
+
+    let request = Request::builder()
+        .uri("/api/tags/TestDevice%2FTemperature/history?limit=2")
+        .method(Method::GET)
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let samples: Vec<TagValue> = serde_json::from_slice(&body).unwrap();
+    let values: Vec<ValueVariant> = samples.into_iter().map(|s| s.value).collect();
+    assert_eq!(values, vec![ValueVariant::Float(24.5), ValueVariant::Float(25.0)]);
+
+    let request = Request::builder()
+        .uri("/api/tags/Nope/history")
+        .method(Method::GET)
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+/// App with an OPC UA driver that was never connected.
+fn create_unconnected_opcua_app() -> Router {
+    let state = create_test_app_state();
+    let mut config = RecordingDriver::new("plc").config;
+    config.address = "opc.tcp://127.0.0.1:4855/".to_string();
+    config.driver_type = "opcua".to_string();
+    state
+        .drivers
+        .insert("plc".to_string(), Arc::new(OpcUaDriver::new(config).unwrap()));
+    create_api_routes().with_state(state)
+}
+
+async fn get_json(app: Router, uri: &str) -> (StatusCode, serde_json::Value) {
+    let request = Request::builder()
+        .uri(uri)
+        .method(Method::GET)
+        .body(Body::empty())
+        .unwrap();
+    send_json(app, request).await
+}
+
+async fn send_json(app: Router, request: Request<Body>) -> (StatusCode, serde_json::Value) {
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn test_tag_tree_endpoint() {
+    let state = create_test_app_state();
+    state.tag_engine.register_tag(Tag {
+        path: "TestDevice/Motor/Speed".parse().unwrap(),
+        value: TagValue::new(ValueVariant::Float(1.0), Quality::Good),
+        driver_id: "test_driver".to_string(),
+        driver_address: "speed_addr".to_string(),
+        poll_rate_ms: 1000,
+        enabled: true,
+        metadata: TagMetadata::default(),
+    });
+    let app = create_api_routes().with_state(state);
+
+    let (status, root) = get_json(app.clone(), "/api/tags/tree").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        root,
+        serde_json::json!([{ "name": "TestDevice", "path": "TestDevice", "is_folder": true }])
+    );
+
+    let (_, device) = get_json(app.clone(), "/api/tags/tree?prefix=TestDevice/").await;
+    assert_eq!(
+        device,
+        serde_json::json!([
+            { "name": "Motor", "path": "TestDevice/Motor", "is_folder": true },
+            { "name": "Temperature", "path": "TestDevice/Temperature", "is_folder": false },
+        ])
+    );
+
+    let (_, motor) = get_json(app, "/api/tags/tree?prefix=TestDevice%2FMotor").await;
+    assert_eq!(motor[0]["path"], "TestDevice/Motor/Speed");
+}
+
+#[tokio::test]
+async fn test_browse_unconnected_driver_is_unavailable() {
+    let app = create_unconnected_opcua_app();
+
+    let (status, body) = get_json(app.clone(), "/api/opcua/browse/plc?node_id=ns%3D0%3Bi%3D85").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["error"]["kind"], "not_connected");
+    assert_eq!(body["error"]["message"], "not connected");
+
+    let (status, body) = get_json(app, "/api/opcua/browse-tree/plc").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["error"]["kind"], "not_connected");
+}
+
+#[tokio::test]
+async fn test_browse_invalid_node_id_is_bad_request() {
+    let app = create_unconnected_opcua_app();
+
+    let (status, body) = get_json(app, "/api/opcua/browse/plc?node_id=not-a-node").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["kind"], "invalid_node_id");
+    assert!(body["error"]["message"].as_str().unwrap().contains("not-a-node"));
+}
+
+#[tokio::test]
+async fn test_read_attributes_checks_the_request() {
+    let app = create_unconnected_opcua_app();
+
+    let (status, body) = get_json(
+        app.clone(),
+        "/api/opcua/attributes/plc?node_id=i%3D85&attributes=Value,Colour",
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["kind"], "invalid_value");
+    assert_eq!(body["error"]["message"], "Unknown attribute 'Colour'");
+
+    let (status, body) = get_json(
+        app.clone(),
+        "/api/opcua/attributes/plc?node_id=i%3D85&attributes=DataType",
+    )
+    .await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["error"]["kind"], "not_connected");
+    assert_eq!(body["node_id"], "i=85");
+
+    let (status, _) = get_json(app, "/api/opcua/attributes/missing?node_id=i%3D85").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_malformed_node_ids_are_rejected_before_the_driver() {
+    let state = create_test_app_state();
+    state.drivers.insert("plc".to_string(), Arc::new(MockDriver::new("plc")));
+    let app = create_api_routes().with_state(state);
+
+    for uri in [
+        "/api/opcua/browse/plc?node_id=ns%3D-1%3Bi%3D0",
+        "/api/opcua/browse-tree/plc?node_id=Objects",
+        "/api/opcua/attributes/plc?node_id=ns%3D2",
+        // Checked even before the driver is looked up
+        "/api/opcua/browse/missing?node_id=ns%3D-1%3Bi%3D0",
+    ] {
+        let (status, body) = get_json(app.clone(), uri).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{uri}: {body}");
+        assert_eq!(body["error"]["kind"], "invalid_node_id", "{uri}: {body}");
+    }
+    let (_, body) = get_json(app.clone(), "/api/opcua/browse/plc?node_id=ns%3D-1%3Bi%3D0").await;
+    assert_eq!(
+        body["error"]["message"],
+        "Invalid NodeId 'ns=-1;i=0': expected [ns=<index>;]<i|s|g|b>=<identifier>, e.g. ns=2;s=Temperature"
+    );
+
+    let call = Request::builder()
+        .uri("/api/opcua/call/plc")
+        .method(Method::POST)
+        .header("content-type", "application/json")
+        .body(Body::from(r#"{"object_id":"i=85","method_id":"Reset"}"#))
+        .unwrap();
+    let (status, body) = send_json(app.clone(), call).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"]["kind"], "invalid_node_id");
+
+    // A well-formed id gets as far as the driver, which isn't an OPC UA one
+    let (_, body) = get_json(app, "/api/opcua/browse/plc?node_id=i%3D85").await;
+    assert_eq!(body["error"]["kind"], "not_opcua");
+}
+
+#[tokio::test]
+async fn test_error_body_is_structured() {
+    let (status, body) = get_json(create_test_app(), "/api/tags/Nope/stats").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["error"]["kind"], "not_found");
+    assert_eq!(body["error"]["message"], "Tag 'Nope' not found");
+}
+
+fn create_cors_app() -> Router {
+    // Default methods and headers
+    let cors: CorsConfig =
+        serde_json::from_value(serde_json::json!({ "allowed_origins": ["http://localhost:5173"] }))
+            .unwrap();
+    create_api_routes()
+        .with_state(create_test_app_state())
+        .layer(cors.layer().unwrap())
+}
+
+fn preflight(origin: &str) -> Request<Body> {
+    preflight_for(origin, "/api/config", "PUT")
+}
+
+fn preflight_for(origin: &str, uri: &str, method: &str) -> Request<Body> {
+    Request::builder()
+        .uri(uri)
+        .method(Method::OPTIONS)
+        .header("Origin", origin)
+        .header("Access-Control-Request-Method", method)
+        .header("Access-Control-Request-Headers", "authorization,content-type")
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_cors_preflight_allows_configured_origin() {
+    let response = create_cors_app()
+        .oneshot(preflight("http://localhost:5173"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let headers = response.headers();
+    assert_eq!(headers["access-control-allow-origin"], "http://localhost:5173");
+    assert!(headers["access-control-allow-methods"].to_str().unwrap().contains("PUT"));
+    assert!(headers["access-control-allow-headers"]
+        .to_str()
+        .unwrap()
+        .contains("authorization"));
+
+    // PATCH /api/tags/:path is allowed by default too
+    let request = preflight_for("http://localhost:5173", "/api/tags/TestDevice%2FTemperature", "PATCH");
+    let response = create_cors_app().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let methods = response.headers()["access-control-allow-methods"].to_str().unwrap();
+    assert!(methods.contains("PATCH"), "{methods}");
+}
+
+#[tokio::test]
+async fn test_cors_rejects_other_origins() {
+    let response = create_cors_app()
+        .oneshot(preflight("http://evil.example"))
+        .await
+        .unwrap();
+    assert!(response.headers().get("access-control-allow-origin").is_none());
+
+    // Without a [cors] section no CORS headers are sent at all
+    let response = create_test_app()
+        .oneshot(preflight("http://localhost:5173"))
+        .await
+        .unwrap();
+    assert!(response.headers().get("access-control-allow-origin").is_none());
+}
+
+#[tokio::test]
+async fn test_cors_headers_on_simple_request() {
+    let request = Request::builder()
+        .uri("/api/opcua/discover")
+        .method(Method::GET)
+        .header("Origin", "http://localhost:5173")
+        .body(Body::empty())
+        .unwrap();
+    let response = create_cors_app().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["access-control-allow-origin"], "http://localhost:5173");
+}
+
+fn call_request(driver_id: &str, body: serde_json::Value) -> Request<Body> {
+    Request::builder()
+        .uri(format!("/api/opcua/call/{driver_id}"))
+        .method(Method::POST)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_call_method_errors() {
+    let app = create_unconnected_opcua_app();
+    let body = serde_json::json!({ "object_id": "i=85", "method_id": "ns=2;s=Reset", "args": [1] });
+
+    let response = app.clone().oneshot(call_request("plc", body.clone())).await.unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    let response = app.clone().oneshot(call_request("nope", body)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let body = serde_json::json!({ "object_id": "i=85", "method_id": "ns=2;s=Reset", "args": [[1]] });
+    let response = app.oneshot(call_request("plc", body)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_log_lines_are_streamed_to_every_websocket_viewer() {
+    use futures::StreamExt;
+    use tokio_tungstenite::connect_async;
+    use tokio_tungstenite::tungstenite::Message;
+
+    let state = create_test_app_state();
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    state.logs.forward(rx);
+    let config = LoggingConfig {
+        level: "info".into(),
+        json: false,
+    };
+    let subscriber = build_subscriber(&config, move || ChannelWriter::new(tx.clone())).unwrap();
+    let _logging = tracing::subscriber::set_default(subscriber);
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}/api/logs/ws", listener.local_addr().unwrap());
+    let app = create_api_routes().with_state(state);
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let (mut first, _) = connect_async(&url).await.unwrap();
+    let (mut second, _) = connect_async(&url).await.unwrap();
+    tracing::info!("line for the viewers");
+
+    for viewer in [&mut first, &mut second] {
+        let line = tokio::time::timeout(std::time::Duration::from_secs(2), async {
+            loop {
+                match viewer.next().await.unwrap().unwrap() {
+                    Message::Text(text) if text.contains("line for the viewers") => break text,
+                    _ => {}
+                }
+            }
+        })
+        .await
+        .expect("log line was not streamed");
+        assert!(line.contains("INFO"), "{line}");
+        assert!(!line.ends_with('\n'));
+    }
+}
+
+#[tokio::test]
+async fn test_api_works_without_the_web_ui() {
+    let missing = std::env::temp_dir().join(format!("forgeio_no_webui_{}", std::process::id()));
+    let app = with_frontend(create_test_app(), &missing);
+
+    let (status, body) = get_json(app.clone(), "/tags").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["total"], 1);
+
+    let request = Request::builder().uri("/").body(Body::empty()).unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert!(String::from_utf8_lossy(&body).contains("ForgeIO Gateway is running"));
+
+    let request = Request::builder().uri("/dashboard").body(Body::empty()).unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_shutdown_lets_requests_in_flight_finish() {
+    use axum::routing::get;
+    use tokio_util::sync::CancellationToken;
+
+    let app = Router::new()
+        .route(
+            "/slow",
+            get(|| async {
+                tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+                "done"
+            }),
+        )
+        // Never finishes, like a tag stream
+        .route("/stream", get(|| async { std::future::pending::<()>().await }));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let shutdown = CancellationToken::new();
+    let server = tokio::spawn(serve(
+        listener,
+        app,
+        shutdown.clone(),
+        std::time::Duration::from_secs(1),
+    ));
+
+    let stream = tokio::spawn(reqwest::get(format!("http://{addr}/stream")));
+    let slow = tokio::spawn(reqwest::get(format!("http://{addr}/slow")));
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    let started = Instant::now();
+    shutdown.cancel();
+
+    let response = slow.await.unwrap().unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.text().await.unwrap(), "done");
+    // The open stream holds the server up only until the drain timeout
+    tokio::time::timeout(std::time::Duration::from_secs(3), server)
+        .await
+        .expect("server did not stop after shutdown")
+        .unwrap()
+        .unwrap();
+    assert!(started.elapsed() >= std::time::Duration::from_millis(900), "{:?}", started.elapsed());
+    stream.abort();
+}
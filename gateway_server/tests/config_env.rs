@@ -0,0 +1,38 @@
+// Kept apart from config_settings.rs: the environment is shared by every test
+// in a binary, so overrides set here would leak into the other loads.
+use gateway_server::config::settings::Settings;
+
+#[test]
+fn environment_overrides_file_values() {
+    let path = std::env::temp_dir().join(format!("forgeio_env_{}.toml", std::process::id()));
+    std::fs::write(
+        &path,
+        r#"
+devices = []
+tags = []
+
+[server]
+host = "127.0.0.1"
+port = 3000
+
+[logging]
+level = "info"
+json = false
+"#,
+    )
+    .unwrap();
+
+    std::env::set_var("FORGEIO_SERVER__PORT", "8080");
+    std::env::set_var("FORGEIO_LOGGING__JSON", "true");
+    let result = Settings::load(&path);
+    std::env::remove_var("FORGEIO_SERVER__PORT");
+    std::env::remove_var("FORGEIO_LOGGING__JSON");
+    std::fs::remove_file(&path).unwrap();
+
+    let settings = result.unwrap();
+    assert_eq!(settings.server.port, 8080);
+    assert!(settings.logging.json);
+    // Values without an override still come from the file
+    assert_eq!(settings.server.host, "127.0.0.1");
+    assert_eq!(settings.logging.level, "info");
+}
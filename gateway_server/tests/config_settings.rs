@@ -0,0 +1,473 @@
+use gateway_server::alarms::Severity;
+use gateway_server::api::cors::CorsConfig;
+use gateway_server::config::settings::{ConfigFormat, ServerConfig, Settings, TagConfig};
+use gateway_server::drivers::traits::DriverConfig;
+use gateway_server::historian::influx::InfluxConfig;
+use gateway_server::logging::LoggingConfig;
+use gateway_server::tags::structures::ValueVariant;
+
+fn device(id: &str) -> DriverConfig {
+    DriverConfig {
+        id: id.into(),
+        name: id.into(),
+        address: "opc.tcp://127.0.0.1:4840/".into(),
+        scan_rate_ms: 1000,
+        driver_type: "opcua".into(),
+        application_name: None,
+        application_uri: None,
+        session_name: None,
+        max_message_size: None,
+        max_chunk_count: None,
+        read_batch_size: None,
+        connect_retry_attempts: None,
+        connect_retry_delay_ms: None,
+        connect_retry_backoff: None,
+        connect_retry_jitter_ms: None,
+        connect_timeout_ms: None,
+        security_policy: None,
+        security_mode: None,
+        username: None,
+        password: None,
+        pki_dir: None,
+        trust_server_certs: None,
+        create_sample_keypair: None,
+        insecure: false,
+        max_concurrent_reads: None,
+        read_timeout_ms: None,
+        critical: false,
+    }
+}
+
+fn tag(path: &str, driver_id: &str) -> TagConfig {
+    TagConfig {
+        path: path.parse().unwrap(),
+        driver_id: driver_id.into(),
+        address: format!("ns=2;s={path}"),
+        poll_rate_ms: 1000,
+        enabled: true,
+        raw_low: None,
+        raw_high: None,
+        eng_low: None,
+        eng_high: None,
+        deadband: None,
+        writable: false,
+        hold_last_value: false,
+        alarm_high: None,
+        alarm_low: None,
+        alarm_rate_of_change: None,
+        alarm_severity: Severity::default(),
+        alias_of: None,
+    }
+}
+
+#[test]
+fn diff_of_identical_settings_is_empty() {
+    let settings = Settings {
+        server: ServerConfig::default(),
+        auth: None,
+        cors: None,
+        logging: LoggingConfig::default(),
+        devices: vec![device("opcua1")],
+        tags: vec![tag("Plant/Temperature", "opcua1")],
+        influx: None,
+        mqtt: None,
+        poll_tick_ms: None,
+    };
+    assert!(settings.diff(&settings.clone()).is_empty());
+}
+
+#[test]
+fn diff_reports_added_removed_and_changed_entries() {
+    let running = Settings {
+        server: ServerConfig::default(),
+        auth: None,
+        cors: None,
+        logging: LoggingConfig::default(),
+        devices: vec![device("opcua1"), device("opcua2")],
+        tags: vec![
+            tag("Plant/Temperature", "opcua1"),
+            tag("Plant/Pressure", "opcua1"),
+            tag("Plant/Flow", "opcua2"),
+        ],
+        influx: None,
+        mqtt: None,
+        poll_tick_ms: None,
+    };
+
+    let mut moved = device("opcua1");
+    moved.address = "opc.tcp://10.0.0.5:4840/".into();
+    let mut slower = tag("Plant/Temperature", "opcua1");
+    slower.poll_rate_ms = 5000;
+    let new = Settings {
+        server: ServerConfig::default(),
+        auth: None,
+        cors: None,
+        logging: LoggingConfig::default(),
+        devices: vec![moved.clone(), device("opcua3")],
+        tags: vec![slower.clone(), tag("Plant/Pressure", "opcua1"), tag("Plant/Level", "opcua3")],
+        influx: None,
+        mqtt: None,
+        poll_tick_ms: None,
+    };
+
+    let diff = running.diff(&new);
+    assert_eq!(diff.added_devices, vec![device("opcua3")]);
+    assert_eq!(diff.removed_devices, vec!["opcua2".to_string()]);
+    assert_eq!(diff.changed_devices, vec![moved]);
+    assert_eq!(diff.added_tags, vec![tag("Plant/Level", "opcua3")]);
+    assert_eq!(diff.removed_tags, vec!["Plant/Flow".to_string()]);
+    assert_eq!(diff.changed_tags, vec![slower]);
+}
+
+fn valid_settings() -> Settings {
+    Settings {
+        server: ServerConfig::default(),
+        auth: None,
+        cors: None,
+        logging: LoggingConfig::default(),
+        devices: vec![device("opcua1"), device("opcua2")],
+        tags: vec![tag("Plant/Temperature", "opcua1"), tag("Plant/Flow", "opcua2")],
+        influx: None,
+        mqtt: None,
+        poll_tick_ms: None,
+    }
+}
+
+#[test]
+fn validate_accepts_consistent_settings() {
+    assert_eq!(valid_settings().validate(), Ok(()));
+}
+
+#[test]
+fn validate_rejects_unknown_driver_id() {
+    let mut settings = valid_settings();
+    settings.tags.push(tag("Plant/Level", "opcua9"));
+    assert_eq!(
+        settings.validate(),
+        Err(vec!["Tag 'Plant/Level' references unknown device 'opcua9'".to_string()])
+    );
+}
+
+#[test]
+fn validate_rejects_duplicate_device_ids() {
+    let mut settings = valid_settings();
+    settings.devices.push(device("opcua1"));
+    assert_eq!(settings.validate(), Err(vec!["Duplicate device id 'opcua1'".to_string()]));
+}
+
+#[test]
+fn validate_rejects_duplicate_tag_paths() {
+    let mut settings = valid_settings();
+    settings.tags.push(tag("Plant/Flow", "opcua1"));
+    assert_eq!(settings.validate(), Err(vec!["Duplicate tag path 'Plant/Flow'".to_string()]));
+}
+
+#[test]
+fn validate_rejects_zero_poll_rate() {
+    let mut settings = valid_settings();
+    settings.tags[0].poll_rate_ms = 0;
+    assert_eq!(
+        settings.validate(),
+        Err(vec!["Tag 'Plant/Temperature' has a poll_rate_ms of 0".to_string()])
+    );
+}
+
+#[test]
+fn configured_scaling_reaches_the_tag() {
+    let mut config = tag("Plant/Level", "opcua1");
+    (config.raw_low, config.raw_high) = (Some(0.0), Some(4095.0));
+    (config.eng_low, config.eng_high) = (Some(0.0), Some(100.0));
+
+    let metadata = config.to_tag().metadata;
+    assert_eq!(metadata.scale_raw(ValueVariant::Int(4095)), ValueVariant::Float(100.0));
+    assert_eq!(metadata.scale_raw(ValueVariant::Int(0)), ValueVariant::Float(0.0));
+    // Without bounds values pass through unscaled
+    let unscaled = tag("Plant/Flow", "opcua1").to_tag().metadata;
+    assert_eq!(unscaled.scale_raw(ValueVariant::Int(4095)), ValueVariant::Int(4095));
+}
+
+#[test]
+fn validate_rejects_equal_raw_bounds() {
+    let mut settings = valid_settings();
+    (settings.tags[0].raw_low, settings.tags[0].raw_high) = (Some(10.0), Some(10.0));
+    assert_eq!(
+        settings.validate(),
+        Err(vec!["Tag 'Plant/Temperature' has equal raw_low and raw_high".to_string()])
+    );
+}
+
+fn alias(path: &str, source: &str) -> TagConfig {
+    TagConfig {
+        driver_id: String::new(),
+        address: String::new(),
+        poll_rate_ms: 0,
+        alias_of: Some(source.parse().unwrap()),
+        ..tag(path, "")
+    }
+}
+
+#[test]
+fn validate_accepts_aliases_without_a_device() {
+    let mut settings = valid_settings();
+    settings.tags.push(alias("Dashboard/Temperature", "Plant/Temperature"));
+    settings.tags.push(alias("Reports/Temperature", "Dashboard/Temperature"));
+    assert_eq!(settings.validate(), Ok(()));
+}
+
+#[test]
+fn validate_rejects_aliases_of_unknown_tags() {
+    let mut settings = valid_settings();
+    settings.tags.push(alias("Dashboard/Level", "Plant/Level"));
+    assert_eq!(
+        settings.validate(),
+        Err(vec!["Alias tag 'Dashboard/Level' references unknown tag 'Plant/Level'".to_string()])
+    );
+}
+
+#[test]
+fn validate_rejects_alias_cycles() {
+    let mut settings = valid_settings();
+    settings.tags.push(alias("Plant/A", "Plant/B"));
+    settings.tags.push(alias("Plant/B", "Plant/C"));
+    settings.tags.push(alias("Plant/C", "Plant/A"));
+    settings.tags.push(alias("Plant/D", "Plant/A"));
+    settings.tags.push(alias("Plant/E", "Plant/E"));
+    assert_eq!(
+        settings.validate(),
+        Err(vec![
+            "Alias cycle: Plant/A -> Plant/B -> Plant/C -> Plant/A".to_string(),
+            "Alias cycle: Plant/E -> Plant/E".to_string(),
+        ])
+    );
+}
+
+#[test]
+fn validate_rejects_zero_poll_tick() {
+    let mut settings = valid_settings();
+    settings.poll_tick_ms = Some(0);
+    assert_eq!(settings.validate(), Err(vec!["poll_tick_ms must be nonzero".to_string()]));
+}
+
+#[test]
+fn validate_reports_every_problem() {
+    let mut settings = valid_settings();
+    settings.devices.push(device("opcua2"));
+    settings.tags.push(tag("Plant/Level", "missing"));
+    settings.tags[0].poll_rate_ms = 0;
+    assert_eq!(settings.validate().unwrap_err().len(), 3);
+}
+
+#[test]
+fn load_rejects_invalid_settings() {
+    let path = std::env::temp_dir().join(format!("forgeio_invalid_{}.toml", std::process::id()));
+    std::fs::write(
+        &path,
+        r#"
+[[devices]]
+id = "opcua1"
+name = "Dummy"
+address = "opc.tcp://127.0.0.1:4840/"
+scan_rate_ms = 1000
+
+[[tags]]
+path = "Plant/Temperature"
+driver_id = "opcua9"
+address = "ns=2;s=Temperature"
+poll_rate_ms = 1000
+"#,
+    )
+    .unwrap();
+
+    let result = Settings::load(&path);
+    std::fs::remove_file(&path).unwrap();
+    let err = result.unwrap_err().to_string();
+    assert!(err.contains("unknown device 'opcua9'"), "{err}");
+}
+
+#[test]
+fn load_rejects_duplicate_tag_paths() {
+    let path = std::env::temp_dir().join(format!("forgeio_duplicate_{}.toml", std::process::id()));
+    std::fs::write(
+        &path,
+        r#"
+[[devices]]
+id = "opcua1"
+name = "Dummy"
+address = "opc.tcp://127.0.0.1:4840/"
+scan_rate_ms = 1000
+
+[[tags]]
+path = "Plant/Temperature"
+driver_id = "opcua1"
+address = "ns=2;s=Temperature"
+poll_rate_ms = 1000
+
+[[tags]]
+path = "Plant/Temperature"
+driver_id = "opcua1"
+address = "ns=2;s=Temperature2"
+poll_rate_ms = 500
+"#,
+    )
+    .unwrap();
+
+    let result = Settings::load(&path);
+    std::fs::remove_file(&path).unwrap();
+    let err = result.unwrap_err().to_string();
+    assert!(err.contains("Duplicate tag path 'Plant/Temperature'"), "{err}");
+}
+
+#[test]
+fn load_rejects_malformed_tag_paths() {
+    let path = std::env::temp_dir().join(format!("forgeio_tag_path_{}.toml", std::process::id()));
+    std::fs::write(
+        &path,
+        r#"
+[[devices]]
+id = "opcua1"
+name = "Dummy"
+address = "opc.tcp://127.0.0.1:4840/"
+scan_rate_ms = 1000
+
+[[tags]]
+path = "Plant//Temperature"
+driver_id = "opcua1"
+address = "ns=2;s=Temperature"
+poll_rate_ms = 1000
+"#,
+    )
+    .unwrap();
+
+    let result = Settings::load(&path);
+    std::fs::remove_file(&path).unwrap();
+    let err = result.unwrap_err().to_string();
+    assert!(err.contains("Invalid tag path 'Plant//Temperature'"), "{err}");
+}
+
+#[test]
+fn influx_section_round_trips_through_save_and_load() {
+    let path = std::env::temp_dir().join(format!("forgeio_influx_{}.toml", std::process::id()));
+    let mut settings = valid_settings();
+    settings.influx = Some(InfluxConfig {
+        url: "http://localhost:8086".into(),
+        bucket: "plant".into(),
+        org: None,
+        token: Some("secret".into()),
+        measurement: "forgeio".into(),
+        batch_size: 100,
+        flush_interval_ms: 500,
+    });
+    settings.save(&path).unwrap();
+    let loaded = Settings::load(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.unwrap().influx, settings.influx);
+}
+
+fn round_trip(extension: &str) {
+    let path = std::env::temp_dir().join(format!(
+        "forgeio_round_trip_{}.{extension}",
+        std::process::id()
+    ));
+    let mut settings = valid_settings();
+    settings.server.port = 8123;
+    settings.tags[0].deadband = Some(0.5);
+    settings.tags[0].alarm_high = Some(90.0);
+    settings.tags[0].alarm_severity = Severity::Critical;
+    settings.devices[0].connect_timeout_ms = Some(2500);
+    settings.save(&path).unwrap();
+    let loaded = Settings::load(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    let loaded = loaded.unwrap_or_else(|e| panic!("{extension}: {e}"));
+    assert_eq!(loaded.server, settings.server);
+    assert!(loaded.diff(&settings).is_empty(), "{extension}: {:?}", loaded.diff(&settings));
+}
+
+#[test]
+fn settings_round_trip_through_toml() {
+    round_trip("toml");
+}
+
+#[test]
+fn settings_round_trip_through_yaml() {
+    round_trip("yaml");
+    round_trip("yml");
+}
+
+#[test]
+fn settings_round_trip_through_json() {
+    round_trip("json");
+}
+
+#[test]
+fn format_follows_extension_and_defaults_to_toml() {
+    let format = |name: &str| ConfigFormat::from_path(std::path::Path::new(name));
+    assert_eq!(format("config.toml"), ConfigFormat::Toml);
+    assert_eq!(format("config.YAML"), ConfigFormat::Yaml);
+    assert_eq!(format("config.json"), ConfigFormat::Json);
+    assert_eq!(format("config.conf"), ConfigFormat::Toml);
+    assert_eq!(format("config"), ConfigFormat::Toml);
+
+    // A file without a known extension is read as TOML
+    let path = std::env::temp_dir().join(format!("forgeio_no_ext_{}", std::process::id()));
+    valid_settings().save(&path).unwrap();
+    let loaded = Settings::load(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.unwrap().tags.len(), 2);
+}
+
+#[test]
+fn server_section_is_read_from_config() {
+    let path = std::env::temp_dir().join(format!("forgeio_server_{}.toml", std::process::id()));
+    std::fs::write(
+        &path,
+        r#"
+devices = []
+
+[server]
+host = "0.0.0.0"
+port = 8080
+"#,
+    )
+    .unwrap();
+
+    let result = Settings::load(&path);
+    std::fs::remove_file(&path).unwrap();
+    let settings = result.unwrap();
+    assert_eq!(
+        settings.server,
+        ServerConfig {
+            host: "0.0.0.0".into(),
+            port: 8080,
+        }
+    );
+    assert_eq!(settings.server.socket_addr().unwrap(), "0.0.0.0:8080".parse().unwrap());
+}
+
+#[test]
+fn server_section_defaults_to_localhost_3000() {
+    assert_eq!(valid_settings().server.socket_addr().unwrap(), "127.0.0.1:3000".parse().unwrap());
+}
+
+#[test]
+fn validate_rejects_unparseable_server_host() {
+    let mut settings = valid_settings();
+    settings.server.host = "not a host".into();
+    let problems = settings.validate().unwrap_err();
+    assert_eq!(problems.len(), 1);
+    assert!(problems[0].starts_with("Invalid server host 'not a host'"), "{problems:?}");
+}
+
+#[test]
+fn validate_rejects_malformed_cors_entries() {
+    let mut settings = valid_settings();
+    settings.cors = Some(CorsConfig {
+        allowed_origins: vec!["http://localhost:5173".into()],
+        allowed_methods: vec!["GET".into(), "NOT A METHOD".into()],
+        allowed_headers: vec![],
+    });
+    assert_eq!(
+        settings.validate(),
+        Err(vec!["Invalid CORS method 'NOT A METHOD'".to_string()])
+    );
+}
@@ -0,0 +1,210 @@
+use async_trait::async_trait;
+use gateway_server::drivers::health::{start_drivers, ConnectionState, ConnectionStates, HealthMonitor};
+use gateway_server::drivers::traits::{
+    DriverError, DeviceDriver, DriverConfig, DriverResult, TagRequest, WriteStatus,
+};
+use gateway_server::drivers::DriverMap;
+use gateway_server::tags::structures::TagValue;
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::time::{sleep, timeout, Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+/// Driver whose session can be dropped from the test and that may refuse to reconnect.
+struct FlakyDriver {
+    config: DriverConfig,
+    connected: AtomicBool,
+    accept_connect: AtomicBool,
+    connects: AtomicUsize,
+}
+
+impl FlakyDriver {
+    fn new(id: &str) -> Self {
+        Self {
+            config: DriverConfig {
+                id: id.to_string(),
+                name: id.to_string(),
+                address: "flaky://".to_string(),
+                scan_rate_ms: 1000,
+                driver_type: "flaky".to_string(),
+                application_name: None,
+                application_uri: None,
+                session_name: None,
+                max_message_size: None,
+                max_chunk_count: None,
+                read_batch_size: None,
+                connect_retry_attempts: None,
+                connect_retry_delay_ms: None,
+                connect_retry_backoff: None,
+                connect_retry_jitter_ms: None,
+                connect_timeout_ms: None,
+                security_policy: None,
+                security_mode: None,
+                username: None,
+                password: None,
+                pki_dir: None,
+                trust_server_certs: None,
+                create_sample_keypair: None,
+                insecure: false,
+                max_concurrent_reads: None,
+                read_timeout_ms: None,
+                critical: false,
+            },
+            connected: AtomicBool::new(true),
+            accept_connect: AtomicBool::new(true),
+            connects: AtomicUsize::new(0),
+        }
+    }
+
+    fn drop_session(&self) {
+        self.connected.store(false, Ordering::SeqCst);
+    }
+}
+
+#[async_trait]
+impl DeviceDriver for FlakyDriver {
+    fn config(&self) -> &DriverConfig {
+        &self.config
+    }
+    async fn connect(&self) -> DriverResult<()> {
+        self.connects.fetch_add(1, Ordering::SeqCst);
+        if !self.accept_connect.load(Ordering::SeqCst) {
+            return Err(DriverError::Timeout);
+        }
+        self.connected.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+    async fn disconnect(&self) -> DriverResult<()> {
+        self.connected.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+    async fn check_status(&self) -> DriverResult<()> {
+        if self.connected.load(Ordering::SeqCst) {
+            Ok(())
+        } else {
+            Err(DriverError::NotConnected)
+        }
+    }
+    async fn read_tags(&self, _tags: &[TagRequest]) -> DriverResult<HashMap<String, TagValue>> {
+        Ok(HashMap::new())
+    }
+    async fn write_tags(
+        &self,
+        tags: HashMap<String, TagValue>,
+    ) -> DriverResult<HashMap<String, WriteStatus>> {
+        Ok(tags
+            .into_iter()
+            .map(|(address, value)| (address, WriteStatus::good(value.value)))
+            .collect())
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+async fn wait_for_state(states: &ConnectionStates, id: &str, expected: ConnectionState) {
+    timeout(Duration::from_secs(2), async {
+        while states.get(id).map(|s| *s) != Some(expected) {
+            sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .unwrap_or_else(|_| panic!("driver '{id}' never became {expected:?}"));
+}
+
+fn start_monitor(driver: Arc<FlakyDriver>) -> (Arc<ConnectionStates>, CancellationToken) {
+    let drivers = Arc::new(DriverMap::new());
+    drivers.insert(driver.config.id.clone(), driver);
+    let states = Arc::new(ConnectionStates::new());
+    let cancel = CancellationToken::new();
+    let monitor = HealthMonitor::new(drivers, Arc::clone(&states), cancel.clone())
+        .with_check_interval(Duration::from_millis(20));
+    tokio::spawn(monitor.run());
+    (states, cancel)
+}
+
+#[tokio::test]
+async fn dropped_session_is_reconnected() {
+    let driver = Arc::new(FlakyDriver::new("plc"));
+    let (states, cancel) = start_monitor(Arc::clone(&driver));
+
+    wait_for_state(&states, "plc", ConnectionState::Connected).await;
+    assert_eq!(driver.connects.load(Ordering::SeqCst), 0);
+
+    driver.drop_session();
+    timeout(Duration::from_secs(2), async {
+        while driver.connects.load(Ordering::SeqCst) == 0 {
+            sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .expect("monitor never tried to reconnect");
+    wait_for_state(&states, "plc", ConnectionState::Connected).await;
+    assert!(driver.check_status().await.is_ok());
+    cancel.cancel();
+}
+
+#[tokio::test]
+async fn cached_state_flips_when_a_drop_is_detected() {
+    let driver = Arc::new(FlakyDriver::new("plc"));
+    driver.accept_connect.store(false, Ordering::SeqCst);
+    let (states, cancel) = start_monitor(Arc::clone(&driver));
+
+    wait_for_state(&states, "plc", ConnectionState::Connected).await;
+    driver.drop_session();
+    wait_for_state(&states, "plc", ConnectionState::Disconnected).await;
+    cancel.cancel();
+}
+
+#[tokio::test]
+async fn failed_reconnect_is_retried_on_next_check() {
+    let driver = Arc::new(FlakyDriver::new("plc"));
+    driver.accept_connect.store(false, Ordering::SeqCst);
+    driver.drop_session();
+    let (states, cancel) = start_monitor(Arc::clone(&driver));
+
+    wait_for_state(&states, "plc", ConnectionState::Disconnected).await;
+
+    // The server comes back
+    driver.accept_connect.store(true, Ordering::SeqCst);
+    wait_for_state(&states, "plc", ConnectionState::Connected).await;
+    assert!(driver.connects.load(Ordering::SeqCst) >= 2);
+    cancel.cancel();
+}
+
+#[tokio::test]
+async fn startup_does_not_wait_for_unreachable_devices() {
+    let mut config = FlakyDriver::new("plc").config;
+    config.driver_type = "opcua".to_string();
+    // Nothing listens here
+    config.address = "opc.tcp://127.0.0.1:4855/".to_string();
+    config.connect_retry_attempts = Some(1);
+    config.connect_retry_delay_ms = Some(100);
+    config.connect_timeout_ms = Some(300);
+    let drivers = Arc::new(DriverMap::new());
+    let states = Arc::new(ConnectionStates::new());
+    let cancel = CancellationToken::new();
+
+    let started = Instant::now();
+    let mut connects = start_drivers(&[config], &drivers, &states, &cancel).unwrap();
+    assert!(started.elapsed() < Duration::from_millis(200), "{:?}", started.elapsed());
+    assert!(drivers.contains_key("plc"));
+    assert_eq!(states.get("plc").map(|s| *s), Some(ConnectionState::Connecting));
+
+    // Left to the health monitor once the retries are used up
+    wait_for_state(&states, "plc", ConnectionState::Disconnected).await;
+    assert!(connects.join_next().await.is_some());
+    assert!(drivers.get("plc").unwrap().check_status().await.is_err());
+}
+
+#[tokio::test]
+async fn startup_fails_on_invalid_driver_config() {
+    let config = FlakyDriver::new("plc").config;
+    let drivers = Arc::new(DriverMap::new());
+    let states = Arc::new(ConnectionStates::new());
+    let error = start_drivers(&[config], &drivers, &states, &CancellationToken::new()).unwrap_err();
+    assert!(error.contains("'plc'"), "{error}");
+    assert!(drivers.is_empty());
+}
@@ -0,0 +1,177 @@
+use axum::extract::{Query, State};
+use axum::http::HeaderMap;
+use axum::routing::post;
+use axum::Router;
+use gateway_server::historian::influx::{to_line_protocol, InfluxConfig, InfluxWriter};
+use gateway_server::tags::engine::TagEngine;
+use gateway_server::tags::structures::{Quality, Tag, TagMetadata, TagValue, ValueVariant};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio::time::{sleep, timeout, Duration};
+use tokio_util::sync::CancellationToken;
+
+/// A write request received by the mock InfluxDB server.
+struct CapturedWrite {
+    query: HashMap<String, String>,
+    authorization: Option<String>,
+    body: String,
+}
+
+/// Serve `/api/v2/write` on an ephemeral port, forwarding every request to the returned channel.
+async fn start_mock_influx() -> (String, mpsc::UnboundedReceiver<CapturedWrite>) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let app = Router::new()
+        .route(
+            "/api/v2/write",
+            post(
+                |State(tx): State<mpsc::UnboundedSender<CapturedWrite>>,
+                 Query(query): Query<HashMap<String, String>>,
+                 headers: HeaderMap,
+                 body: String| async move {
+                    let authorization = headers
+                        .get("authorization")
+                        .map(|v| v.to_str().unwrap().to_string());
+                    let _ = tx.send(CapturedWrite {
+                        query,
+                        authorization,
+                        body,
+                    });
+                    axum::http::StatusCode::NO_CONTENT
+                },
+            ),
+        )
+        .with_state(tx);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, app).await.unwrap();
+    });
+    (url, rx)
+}
+
+fn tag(path: &str, driver_id: &str) -> Tag {
+    Tag {
+        path: path.parse().unwrap(),
+        value: TagValue::bad(Quality::Bad),
+        driver_id: driver_id.to_string(),
+        driver_address: path.to_string(),
+        poll_rate_ms: 1000,
+        enabled: true,
+        metadata: TagMetadata::default(),
+    }
+}
+
+#[test]
+fn line_protocol_escapes_and_types_fields() {
+    let value = TagValue::with_timestamp(ValueVariant::Int(42), Quality::Good, 1_700_000_000_000);
+    assert_eq!(
+        to_line_protocol("forgeio", "Plant 1/Counter", "plc,1", &value).unwrap(),
+        r#"forgeio,path=Plant\ 1/Counter,driver_id=plc\,1 value_i=42i,quality="Good" 1700000000000"#
+    );
+
+    let value = TagValue::with_timestamp(
+        ValueVariant::String("say \"hi\"".into()),
+        Quality::Uncertain,
+        5,
+    );
+    assert_eq!(
+        to_line_protocol("m", "Tag", "d", &value).unwrap(),
+        r#"m,path=Tag,driver_id=d value_s="say \"hi\"",quality="Uncertain" 5"#
+    );
+
+    assert!(to_line_protocol("m", "Tag", "d", &TagValue::bad(Quality::Bad)).is_none());
+}
+
+#[test]
+fn line_protocol_keeps_each_type_in_its_own_field() {
+    let line = |value: ValueVariant, driver_id: &str| {
+        let value = TagValue::with_timestamp(value, Quality::Good, 7);
+        to_line_protocol("forgeio", "Tag", driver_id, &value).unwrap()
+    };
+    // One measurement, so each field name must only ever see one type
+    let batch = [
+        line(ValueVariant::Float(21.5), "plc"),
+        line(ValueVariant::Int(-3), "plc"),
+        line(ValueVariant::UInt(3), "plc"),
+        line(ValueVariant::Bool(false), "plc"),
+        line(ValueVariant::String("on".into()), "plc"),
+        // An alias has no driver
+        line(ValueVariant::Float(1.0), ""),
+    ];
+    assert_eq!(
+        batch,
+        [
+            r#"forgeio,path=Tag,driver_id=plc value_f=21.5,quality="Good" 7"#,
+            r#"forgeio,path=Tag,driver_id=plc value_i=-3i,quality="Good" 7"#,
+            r#"forgeio,path=Tag,driver_id=plc value_u=3u,quality="Good" 7"#,
+            r#"forgeio,path=Tag,driver_id=plc value_b=false,quality="Good" 7"#,
+            r#"forgeio,path=Tag,driver_id=plc value_s="on",quality="Good" 7"#,
+            r#"forgeio,path=Tag value_f=1,quality="Good" 7"#,
+        ]
+    );
+}
+
+#[tokio::test]
+async fn writer_batches_updates_as_line_protocol() {
+    let (url, mut writes) = start_mock_influx().await;
+    let engine = Arc::new(TagEngine::new());
+    engine.register_tag(tag("Plant/Temperature", "opcua1"));
+    engine.register_tag(tag("Plant/Running", "opcua2"));
+
+    let config = InfluxConfig {
+        url,
+        bucket: "plant".into(),
+        org: Some("forge".into()),
+        token: Some("secret".into()),
+        measurement: "forgeio".into(),
+        batch_size: 2,
+        // Long enough that only a full batch triggers the write
+        flush_interval_ms: 60_000,
+    };
+    let cancel = CancellationToken::new();
+    let handle = tokio::spawn(InfluxWriter::new(config, Arc::clone(&engine), cancel.clone()).run());
+    // Give the writer its first tick to subscribe
+    sleep(Duration::from_millis(100)).await;
+
+    engine.update_tag_value(
+        "Plant/Temperature",
+        TagValue::with_timestamp(ValueVariant::Float(21.5), Quality::Good, 1000),
+    );
+    engine.update_tag_value(
+        "Plant/Running",
+        TagValue::with_timestamp(ValueVariant::Bool(true), Quality::Good, 2000),
+    );
+
+    let write = timeout(Duration::from_secs(2), writes.recv())
+        .await
+        .expect("no write after a full batch")
+        .unwrap();
+    assert_eq!(write.query["bucket"], "plant");
+    assert_eq!(write.query["org"], "forge");
+    assert_eq!(write.query["precision"], "ms");
+    assert_eq!(write.authorization.as_deref(), Some("Token secret"));
+    let mut lines: Vec<&str> = write.body.lines().collect();
+    lines.sort();
+    assert_eq!(
+        lines,
+        vec![
+            r#"forgeio,path=Plant/Running,driver_id=opcua2 value_b=true,quality="Good" 2000"#,
+            r#"forgeio,path=Plant/Temperature,driver_id=opcua1 value_f=21.5,quality="Good" 1000"#,
+        ]
+    );
+
+    // A partial batch is sent on shutdown
+    engine.update_tag_value(
+        "Plant/Temperature",
+        TagValue::with_timestamp(ValueVariant::Float(22.0), Quality::Good, 3000),
+    );
+    sleep(Duration::from_millis(50)).await;
+    cancel.cancel();
+    handle.await.unwrap();
+    let write = writes.try_recv().expect("partial batch not flushed on shutdown");
+    assert_eq!(
+        write.body,
+        r#"forgeio,path=Plant/Temperature,driver_id=opcua1 value_f=22,quality="Good" 3000"#
+    );
+}
@@ -0,0 +1,109 @@
+use gateway_server::logging::{build_subscriber, init_logging, LoggingConfig};
+use std::io;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::unbounded_channel;
+use tracing::{debug, info, warn};
+
+/// Collects everything the subscriber writes.
+#[derive(Clone, Default)]
+struct Capture(Arc<Mutex<Vec<u8>>>);
+
+impl Capture {
+    fn output(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+impl io::Write for Capture {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn capture(config: &LoggingConfig, log: impl FnOnce()) -> String {
+    let output = Capture::default();
+    let writer = output.clone();
+    let subscriber = build_subscriber(config, move || writer.clone()).unwrap();
+    tracing::subscriber::with_default(subscriber, log);
+    output.output()
+}
+
+#[test]
+fn level_filters_out_lower_events() {
+    let config = LoggingConfig {
+        level: "warn".into(),
+        json: false,
+    };
+    let output = capture(&config, || {
+        debug!("debug message");
+        info!("info message");
+        warn!("warn message");
+    });
+    assert!(output.contains("warn message"), "{output}");
+    assert!(!output.contains("info message"), "{output}");
+    assert!(!output.contains("debug message"), "{output}");
+}
+
+#[test]
+fn debug_level_includes_debug_events() {
+    let config = LoggingConfig {
+        level: "debug".into(),
+        json: false,
+    };
+    let output = capture(&config, || debug!("debug message"));
+    assert!(output.contains("debug message"), "{output}");
+}
+
+#[test]
+fn json_format_writes_one_object_per_event() {
+    let config = LoggingConfig {
+        level: "info".into(),
+        json: true,
+    };
+    let output = capture(&config, || {
+        info!(tag = "Plant/Temperature", "value stored");
+        debug!("hidden");
+    });
+    let lines: Vec<serde_json::Value> = output
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 1, "{output}");
+    assert_eq!(lines[0]["level"], "INFO");
+    assert_eq!(lines[0]["fields"]["message"], "value stored");
+    assert_eq!(lines[0]["fields"]["tag"], "Plant/Temperature");
+}
+
+#[test]
+fn invalid_level_is_rejected() {
+    let config = LoggingConfig {
+        level: "info,=[".into(),
+        json: false,
+    };
+    assert!(config.filter().is_err());
+    assert!(build_subscriber(&config, io::sink).is_err());
+}
+
+// The only test installing the global subscriber
+#[tokio::test]
+async fn forwarded_logs_honor_level_and_format() {
+    let (tx, mut rx) = unbounded_channel();
+    let config = LoggingConfig {
+        level: "info".into(),
+        json: true,
+    };
+    init_logging(&config, Some(tx));
+
+    debug!("not forwarded");
+    info!("forwarded");
+
+    let line = rx.recv().await.unwrap();
+    let event: serde_json::Value = serde_json::from_str(line.trim()).unwrap();
+    assert_eq!(event["fields"]["message"], "forwarded");
+    assert!(rx.try_recv().is_err());
+}
@@ -0,0 +1,251 @@
+use gateway_server::drivers::breaker::{BreakerState, CircuitBreakers};
+use gateway_server::drivers::health::{ConnectionState, ConnectionStates, HealthMonitor};
+use gateway_server::drivers::mock::MockDriver;
+use gateway_server::drivers::scan_stats::ScanStatistics;
+use gateway_server::drivers::traits::{DeviceDriver, DriverError, TagRequest};
+use gateway_server::drivers::DriverMap;
+use gateway_server::tags::engine::TagEngine;
+use gateway_server::tags::poller::Poller;
+use gateway_server::tags::structures::{Quality, Tag, TagMetadata, TagValue, ValueVariant};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Notify;
+use tokio::time::{sleep, timeout, Duration};
+use tokio_util::sync::CancellationToken;
+
+fn tag(path: &str, address: &str) -> Tag {
+    Tag {
+        path: path.parse().unwrap(),
+        value: TagValue::bad(Quality::Initializing),
+        driver_id: "mock".to_string(),
+        driver_address: address.to_string(),
+        poll_rate_ms: 20,
+        enabled: true,
+        metadata: TagMetadata::default(),
+    }
+}
+
+fn good(value: i64) -> TagValue {
+    TagValue::new(ValueVariant::Int(value), Quality::Good)
+}
+
+/// Whether the tag holds `value` with Good quality.
+fn holds(engine: &TagEngine, path: &str, value: i64) -> bool {
+    engine
+        .read_tag(path)
+        .is_some_and(|tag| tag.quality == Quality::Good && tag.value == ValueVariant::Int(value))
+}
+
+async fn wait_until(what: &str, mut condition: impl FnMut() -> bool) {
+    timeout(Duration::from_secs(2), async {
+        while !condition() {
+            sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .unwrap_or_else(|_| panic!("timed out waiting for {what}"));
+}
+
+fn start_poller(
+    engine: &Arc<TagEngine>,
+    driver: &Arc<MockDriver>,
+    scan_stats: &Arc<ScanStatistics>,
+) -> CancellationToken {
+    let drivers = Arc::new(DriverMap::new());
+    drivers.insert("mock".to_string(), driver.clone());
+    let cancel = CancellationToken::new();
+    let poller = Poller::new(
+        Arc::clone(engine),
+        drivers,
+        Arc::new(Notify::new()),
+        cancel.clone(),
+    )
+    .with_poll_tick(Duration::from_millis(10))
+    .with_scan_stats(Arc::clone(scan_stats));
+    tokio::spawn(poller.run());
+    cancel
+}
+
+#[tokio::test]
+async fn reads_answer_with_programmed_values() {
+    let driver = MockDriver::new("mock");
+    driver.set_value("a", good(1));
+    let requests = ["a", "b"].map(|address| TagRequest {
+        address: address.to_string(),
+    });
+
+    let values = driver.read_tags(&requests).await.unwrap();
+    assert_eq!(values["a"].value, ValueVariant::Int(1));
+    assert_eq!(values["b"].quality, Quality::Bad);
+    assert_eq!(driver.read_count(), 1);
+
+    driver.set_failure(Some(|| DriverError::Timeout));
+    assert!(matches!(driver.read_tags(&requests).await, Err(DriverError::Timeout)));
+    assert!(driver.connect().await.is_err());
+    driver.set_failure(None);
+
+    driver.disconnect().await.unwrap();
+    assert!(matches!(driver.read_tags(&requests).await, Err(DriverError::NotConnected)));
+    driver.connect().await.unwrap();
+    assert!(driver.check_status().await.is_ok());
+    assert_eq!(driver.connect_count(), 2);
+}
+
+#[tokio::test]
+async fn written_values_are_read_back() {
+    let driver = MockDriver::new("mock");
+    driver.set_write_error("locked", Some("access denied"));
+    let writes = HashMap::from([("a".to_string(), good(5)), ("locked".to_string(), good(6))]);
+
+    let results = driver.write_tags(writes).await.unwrap();
+    assert_eq!(results["a"].quality, Quality::Good);
+    assert_eq!(results["locked"].error.as_deref(), Some("access denied"));
+    let written = driver.written();
+    assert_eq!(written.len(), 1);
+    assert_eq!((written[0].0.as_str(), &written[0].1.value), ("a", &ValueVariant::Int(5)));
+
+    let request = [TagRequest { address: "a".to_string() }];
+    assert_eq!(driver.read_tags(&request).await.unwrap()["a"].value, ValueVariant::Int(5));
+}
+
+#[tokio::test]
+async fn poller_stores_mock_values_and_marks_failures_bad() {
+    let engine = Arc::new(TagEngine::new());
+    engine.register_tag(tag("Mock/Level", "level"));
+    let driver = Arc::new(MockDriver::new("mock"));
+    driver.set_value("level", good(42));
+    let scan_stats = Arc::new(ScanStatistics::new());
+    let cancel = start_poller(&engine, &driver, &scan_stats);
+
+    wait_until("the value to be polled", || holds(&engine, "Mock/Level", 42)).await;
+
+    // Values changed on the device show up on a later poll
+    driver.set_value("level", good(43));
+    wait_until("the new value", || holds(&engine, "Mock/Level", 43)).await;
+
+    // A lost connection marks the tag Bad and counts as a failed scan
+    driver.set_failure(Some(|| DriverError::NotConnected));
+    wait_until("the tag to turn Bad", || {
+        engine.read_tag("Mock/Level").unwrap().quality == Quality::Bad
+    })
+    .await;
+    assert_eq!(
+        engine.stats("Mock/Level").unwrap().last_error.as_deref(),
+        Some("not connected")
+    );
+    assert!(scan_stats.get("mock").error_count >= 1);
+
+    driver.set_failure(None);
+    wait_until("the tag to recover", || holds(&engine, "Mock/Level", 43)).await;
+    assert_eq!(scan_stats.get("mock").consecutive_errors, 0);
+    cancel.cancel();
+}
+
+#[tokio::test]
+async fn failed_read_keeps_the_last_value_of_holding_tags() {
+    let engine = Arc::new(TagEngine::new());
+    let mut held = tag("Mock/Held", "level");
+    held.metadata.hold_last_value = true;
+    engine.register_tag(held);
+    engine.register_tag(tag("Mock/Plain", "level"));
+    let driver = Arc::new(MockDriver::new("mock"));
+    driver.set_value("level", good(42));
+    let scan_stats = Arc::new(ScanStatistics::new());
+    let cancel = start_poller(&engine, &driver, &scan_stats);
+
+    wait_until("the value to be polled", || holds(&engine, "Mock/Held", 42)).await;
+    let good_read = engine.read_tag("Mock/Held").unwrap().timestamp;
+
+    driver.set_failure(Some(|| DriverError::Timeout));
+    wait_until("the tags to turn Bad", || {
+        ["Mock/Held", "Mock/Plain"]
+            .iter()
+            .all(|path| engine.read_tag(path).unwrap().quality == Quality::Bad)
+    })
+    .await;
+    let held = engine.read_tag("Mock/Held").unwrap();
+    assert_eq!(held.quality, Quality::Bad);
+    assert_eq!(held.value, ValueVariant::Int(42));
+    assert_eq!(held.timestamp, good_read);
+    assert_eq!(engine.read_tag("Mock/Plain").unwrap().value, ValueVariant::Null);
+    cancel.cancel();
+}
+
+#[tokio::test]
+async fn poller_times_out_slow_reads() {
+    let engine = Arc::new(TagEngine::new());
+    engine.register_tag(tag("Mock/Slow", "slow"));
+    let mut config = MockDriver::new("mock").config().clone();
+    config.read_timeout_ms = Some(30);
+    let driver = Arc::new(MockDriver::with_config(config));
+    driver.set_value("slow", good(1));
+    driver.set_latency(Duration::from_millis(200));
+    let scan_stats = Arc::new(ScanStatistics::new());
+    let cancel = start_poller(&engine, &driver, &scan_stats);
+
+    wait_until("a timed out scan", || scan_stats.get("mock").error_count >= 1).await;
+    let tag = engine.read_tag_full("Mock/Slow").unwrap();
+    assert_eq!(tag.value.quality, Quality::Bad);
+    assert_eq!(engine.stats("Mock/Slow").unwrap().last_error.as_deref(), Some("timed out"));
+
+    driver.set_latency(Duration::ZERO);
+    wait_until("a fast read", || holds(&engine, "Mock/Slow", 1)).await;
+    cancel.cancel();
+}
+
+#[tokio::test]
+async fn health_monitor_reconnects_a_mock_device() {
+    let driver = Arc::new(MockDriver::new("mock"));
+    let drivers = Arc::new(DriverMap::new());
+    drivers.insert("mock".to_string(), driver.clone());
+    let states = Arc::new(ConnectionStates::new());
+    let cancel = CancellationToken::new();
+    let monitor = HealthMonitor::new(drivers, Arc::clone(&states), cancel.clone())
+        .with_check_interval(Duration::from_millis(20));
+    tokio::spawn(monitor.run());
+    let state = || states.get("mock").map(|state| *state);
+
+    wait_until("the device to be seen", || state() == Some(ConnectionState::Connected)).await;
+
+    // The device goes away and refuses connections for a while
+    driver.set_failure(Some(|| DriverError::Timeout));
+    driver.drop_connection();
+    wait_until("the reconnect to fail", || state() == Some(ConnectionState::Disconnected)).await;
+    assert!(!driver.is_connected());
+
+    driver.set_failure(None);
+    wait_until("the device to reconnect", || state() == Some(ConnectionState::Connected)).await;
+    assert!(driver.is_connected());
+    assert!(driver.connect_count() >= 2);
+    cancel.cancel();
+}
+
+#[tokio::test]
+async fn open_breaker_pauses_reconnects_of_a_device_that_is_down() {
+    let driver = Arc::new(MockDriver::new("mock"));
+    driver.set_failure(Some(|| DriverError::Timeout));
+    driver.drop_connection();
+    let drivers = Arc::new(DriverMap::new());
+    drivers.insert("mock".to_string(), driver.clone());
+    let states = Arc::new(ConnectionStates::new());
+    let breakers = Arc::new(CircuitBreakers::new(2, Duration::from_millis(300)));
+    let cancel = CancellationToken::new();
+    let monitor = HealthMonitor::new(drivers, Arc::clone(&states), cancel.clone())
+        .with_check_interval(Duration::from_millis(10))
+        .with_circuit_breakers(Arc::clone(&breakers));
+    tokio::spawn(monitor.run());
+
+    wait_until("the breaker to open", || breakers.state("mock") == BreakerState::Open).await;
+    let attempts = driver.connect_count();
+    assert_eq!(attempts, 2);
+    // No reconnects while the breaker is open, though the device is checked every 10ms
+    sleep(Duration::from_millis(150)).await;
+    assert_eq!(driver.connect_count(), attempts);
+    assert_eq!(states.get("mock").map(|state| *state), Some(ConnectionState::Disconnected));
+
+    // After the cooldown a probe reconnects the device and closes the breaker
+    driver.set_failure(None);
+    wait_until("the device to reconnect", || driver.is_connected()).await;
+    wait_until("the breaker to close", || breakers.state("mock") == BreakerState::Closed).await;
+    cancel.cancel();
+}
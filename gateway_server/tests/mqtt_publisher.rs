@@ -0,0 +1,111 @@
+use bytes::BytesMut;
+use gateway_server::integrations::mqtt::{MqttConfig, MqttPublisher};
+use gateway_server::tags::engine::TagEngine;
+use gateway_server::tags::structures::{Quality, Tag, TagMetadata, TagValue, ValueVariant};
+use rumqttc::mqttbytes::v4::{read, ConnAck, ConnectReturnCode, Packet, Publish};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio::time::{timeout, Duration};
+use tokio_util::sync::CancellationToken;
+
+/// Accept one MQTT client, acknowledge its CONNECT and forward every PUBLISH it sends.
+async fn start_mock_broker() -> (u16, mpsc::UnboundedReceiver<Publish>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut buffer = BytesMut::new();
+        loop {
+            let packet = match read(&mut buffer, 1024 * 1024) {
+                Ok(packet) => packet,
+                // Not a whole packet yet
+                Err(_) => {
+                    if socket.read_buf(&mut buffer).await.unwrap_or(0) == 0 {
+                        return;
+                    }
+                    continue;
+                }
+            };
+            match packet {
+                Packet::Connect(_) => {
+                    let mut out = BytesMut::new();
+                    ConnAck::new(ConnectReturnCode::Success, false)
+                        .write(&mut out)
+                        .unwrap();
+                    socket.write_all(&out).await.unwrap();
+                }
+                Packet::Publish(publish) => {
+                    let _ = tx.send(publish);
+                }
+                Packet::Disconnect => return,
+                _ => {}
+            }
+        }
+    });
+    (port, rx)
+}
+
+#[test]
+fn broker_url_and_topic_parsing() {
+    let mut config = MqttConfig {
+        broker_url: "mqtt://broker.local:1884".into(),
+        base_topic: "forgeio/".into(),
+        qos: 0,
+        client_id: "gw".into(),
+    };
+    assert_eq!(config.broker_address(), Ok(("broker.local".into(), 1884)));
+    assert_eq!(config.topic_for("Plant/Temperature"), "forgeio/Plant/Temperature");
+
+    config.broker_url = "broker.local".into();
+    assert_eq!(config.broker_address(), Ok(("broker.local".into(), 1883)));
+    config.broker_url = "tcp://broker.local:x".into();
+    assert!(config.broker_address().is_err());
+}
+
+#[tokio::test]
+async fn publishes_changed_tags_as_json() {
+    let (port, mut published) = start_mock_broker().await;
+    let engine = Arc::new(TagEngine::new());
+    engine.register_tag(Tag {
+        path: "Plant/Temperature".parse().unwrap(),
+        value: TagValue::bad(Quality::Bad),
+        driver_id: "opcua1".to_string(),
+        driver_address: "ns=2;s=Temperature".to_string(),
+        poll_rate_ms: 1000,
+        enabled: true,
+        metadata: TagMetadata::default(),
+    });
+
+    let config = MqttConfig {
+        broker_url: format!("mqtt://127.0.0.1:{port}"),
+        base_topic: "forgeio".into(),
+        qos: 0,
+        client_id: "forgeio-test".into(),
+    };
+    let cancel = CancellationToken::new();
+    let handle = tokio::spawn(MqttPublisher::new(config, Arc::clone(&engine), cancel.clone()).run());
+    // Let the publisher subscribe to the tag
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let value = TagValue::with_timestamp(ValueVariant::Float(21.5), Quality::Good, 1234);
+    engine.update_tag_value("Plant/Temperature", value.clone());
+
+    let publish = timeout(Duration::from_secs(5), published.recv())
+        .await
+        .expect("nothing published")
+        .unwrap();
+    assert_eq!(publish.topic, "forgeio/Plant/Temperature");
+    let payload: serde_json::Value = serde_json::from_slice(&publish.payload).unwrap();
+    assert_eq!(payload, serde_json::to_value(&value).unwrap());
+    assert_eq!(payload["quality"], "Good");
+    assert_eq!(payload["timestamp"], 1234);
+
+    cancel.cancel();
+    timeout(Duration::from_secs(1), handle)
+        .await
+        .expect("publisher did not stop")
+        .unwrap();
+}
@@ -1,11 +1,21 @@
-use gateway_server::drivers::opcua::OpcUaDriver;
-use gateway_server::drivers::traits::{OpcDriver, OpcDriverConfig};
-use opcua::server::address_space::Variable;
+use gateway_server::drivers::build_driver;
+use gateway_server::drivers::opcua::{
+    access_level_writable, parse_attribute, BrowseChild, BrowseFilter, NodeAddress,
+    NodeAttributes, OpcUaDriver,
+};
+use gateway_server::drivers::traits::{DriverError, DeviceDriver, DriverConfig, TagRequest};
+use gateway_server::tags::structures::{Quality, TagValue, ValueVariant};
+use opcua::server::address_space::{AccessLevel, MethodBuilder, Variable};
 use opcua::server::diagnostics::NamespaceMetadata;
 use opcua::server::node_manager::memory::{simple_node_manager, SimpleNodeManager};
 use opcua::server::{ServerBuilder, ServerHandle};
-use opcua::types::NodeId;
-use tokio::time::{sleep, Duration};
+use opcua::client::IdentityToken;
+use opcua::types::{
+    AttributeId, ByteString, DataTypeId, DataValue, Guid, NodeId, NumericRange, StatusCode, Variant,
+};
+use std::collections::HashMap;
+use std::str::FromStr;
+use tokio::time::{sleep, timeout, Duration};
 
 struct DummyServer {
     handle: ServerHandle,
@@ -13,11 +23,11 @@ struct DummyServer {
 }
 
 impl DummyServer {
-    async fn start() -> Self {
+    async fn start(port: u16) -> Self {
         let namespace_uri = "http://forgeio/dummy/";
         let (server, handle) = ServerBuilder::new_anonymous("Dummy OPC UA Server")
             .host("127.0.0.1")
-            .port(4840)
+            .port(port)
             .with_node_manager(simple_node_manager(
                 NamespaceMetadata {
                     namespace_uri: namespace_uri.to_string(),
@@ -34,21 +44,49 @@ impl DummyServer {
             .unwrap();
         let ns = handle.get_namespace_index(namespace_uri).unwrap();
         {
+            let mut temperature = Variable::new(
+                &NodeId::new(ns, "Temperature"),
+                "Temperature",
+                "Temperature",
+                20f64,
+            );
+            temperature.set_access_level(AccessLevel::CURRENT_READ | AccessLevel::CURRENT_WRITE);
+            temperature
+                .set_user_access_level(AccessLevel::CURRENT_READ | AccessLevel::CURRENT_WRITE);
+
             let mut space = node_manager.address_space().write();
             let _ = space.add_variables(
                 vec![
-                    Variable::new(
-                        &NodeId::new(ns, "Temperature"),
-                        "Temperature",
-                        "Temperature",
-                        20f64,
-                    ),
+                    temperature,
                     Variable::new(&NodeId::new(ns, "Pressure"), "Pressure", "Pressure", 1f64),
                     Variable::new(&NodeId::new(ns, "Counter"), "Counter", "Counter", 0i32),
                 ],
                 &NodeId::objects_folder_id(),
             );
+
+            // Add(a: Int32, b: Int32) -> Int32 on the Objects folder
+            MethodBuilder::new(&NodeId::new(ns, "Add"), "Add", "Add")
+                .component_of(NodeId::objects_folder_id())
+                .executable(true)
+                .user_executable(true)
+                .input_args(
+                    &mut *space,
+                    &NodeId::new(ns, "AddInputs"),
+                    &[("a", DataTypeId::Int32).into(), ("b", DataTypeId::Int32).into()],
+                )
+                .output_args(
+                    &mut *space,
+                    &NodeId::new(ns, "AddOutputs"),
+                    &[("sum", DataTypeId::Int32).into()],
+                )
+                .insert(&mut *space);
         }
+        node_manager
+            .inner()
+            .add_method_callback(NodeId::new(ns, "Add"), |args| match args {
+                [Variant::Int32(a), Variant::Int32(b)] => Ok(vec![Variant::Int32(a + b)]),
+                _ => Err(StatusCode::BadInvalidArgument),
+            });
 
         let task = tokio::spawn(async move {
             server.run().await.unwrap();
@@ -61,39 +99,1062 @@ impl DummyServer {
     }
 }
 
+impl DummyServer {
+    /// Add a variable below the Objects folder of the running server.
+    fn add_variable(&self, name: &'static str, value: impl Into<Variant>) {
+        let ns = self.handle.get_namespace_index("http://forgeio/dummy/").unwrap();
+        self.add_variable_at(NodeId::new(ns, name), name, value);
+    }
+
+    /// Add a variable with the given node id below the Objects folder.
+    fn add_variable_at(&self, node_id: NodeId, name: &str, value: impl Into<Variant>) {
+        self.add(Variable::new(&node_id, name, name, value));
+    }
+
+    /// Add a variable below the Objects folder, e.g. one made writable.
+    fn add(&self, variable: Variable) {
+        let node_manager = self
+            .handle
+            .node_managers()
+            .get_of_type::<SimpleNodeManager>()
+            .unwrap();
+        let _ = node_manager
+            .address_space()
+            .write()
+            .add_variables(vec![variable], &NodeId::objects_folder_id());
+    }
+}
+
 impl Drop for DummyServer {
     fn drop(&mut self) {
         self.handle.cancel();
     }
 }
 
-#[tokio::test(flavor = "multi_thread")]
-async fn browse_tags_from_dummy_server() {
-    let _ = tracing_subscriber::fmt::try_init();
-    let _server = DummyServer::start().await;
-    let config = OpcDriverConfig {
+fn test_config(port: u16) -> DriverConfig {
+    DriverConfig {
         id: "srv".into(),
         name: "srv".into(),
-        address: "opc.tcp://127.0.0.1:4840/".into(),
+        address: format!("opc.tcp://127.0.0.1:{port}/"),
         scan_rate_ms: 1000,
+        driver_type: "opcua".into(),
         application_name: Some("TestClient".into()),
         application_uri: None,
         session_name: Some("TestSession".into()),
         max_message_size: None,
         max_chunk_count: None,
+        read_batch_size: None,
         connect_retry_attempts: Some(10),
         connect_retry_delay_ms: Some(200),
         connect_retry_backoff: Some(1.5),
+        connect_retry_jitter_ms: None,
         connect_timeout_ms: Some(1000),
+        security_policy: None,
+        security_mode: None,
+        username: None,
+        password: None,
+        pki_dir: None,
+        trust_server_certs: None,
+        create_sample_keypair: None,
+        insecure: false,
+        max_concurrent_reads: None,
+        read_timeout_ms: None,
+        critical: false,
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn browse_tags_from_dummy_server() {
+    let _ = tracing_subscriber::fmt::try_init();
+    let _server = DummyServer::start(4840).await;
+    let driver = OpcUaDriver::new(test_config(4840)).unwrap();
+    driver.connect().await.unwrap();
+    driver.check_status().await.unwrap();
+
+    let children = driver.browse_node("ns=0;i=85").await.unwrap();
+    for name in ["Temperature", "Pressure", "Counter"] {
+        let child = children
+            .iter()
+            .find(|c| c.browse_name == name)
+            .unwrap_or_else(|| panic!("{name} not found in {children:?}"));
+        assert_eq!(
+            *child,
+            BrowseChild {
+                browse_name: name.to_string(),
+                node_id: format!("ns=2;s={name}"),
+                node_class: "Variable".to_string(),
+            }
+        );
+    }
+    // Namespace 0 nodes keep their own node ids
+    let server = children.iter().find(|c| c.browse_name == "Server").unwrap();
+    assert_eq!(server.node_id, "i=2253");
+    assert_eq!(server.node_class, "Object");
+
+    driver.disconnect().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn write_and_read_back_temperature() {
+    let _ = tracing_subscriber::fmt::try_init();
+    let _server = DummyServer::start(4841).await;
+    let driver = OpcUaDriver::new(test_config(4841)).unwrap();
+    driver.connect().await.unwrap();
+
+    let address = "ns=2;s=Temperature".to_string();
+    let mut writes = HashMap::new();
+    writes.insert(
+        address.clone(),
+        TagValue::new(ValueVariant::Float(42.5), Quality::Good),
+    );
+    let results = driver.write_tags(writes).await.unwrap();
+    assert_eq!(results[&address].quality, Quality::Good);
+
+    let values = driver
+        .read_tags(&[TagRequest {
+            address: address.clone(),
+        }])
+        .await
+        .unwrap();
+    assert_eq!(values[&address].value, ValueVariant::Float(42.5));
+
+    driver.disconnect().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn subscribe_to_counter() {
+    let _ = tracing_subscriber::fmt::try_init();
+    let _server = DummyServer::start(4842).await;
+    let driver = OpcUaDriver::new(test_config(4842)).unwrap();
+    driver.connect().await.unwrap();
+
+    let address = "ns=2;s=Counter".to_string();
+    let mut updates = driver
+        .subscribe_tags(&[TagRequest {
+            address: address.clone(),
+        }])
+        .await
+        .unwrap();
+
+    // The server reports the current value as soon as the item is monitored.
+    let (updated, value) = timeout(Duration::from_secs(5), updates.recv())
+        .await
+        .expect("no initial notification")
+        .expect("channel closed");
+    assert_eq!(updated, address);
+    assert_eq!(value.value, ValueVariant::Int(0));
+    assert_eq!(value.quality, Quality::Good);
+
+    driver.disconnect().await.unwrap();
+    let closed = timeout(Duration::from_secs(5), updates.recv()).await.unwrap();
+    assert!(closed.is_none());
+}
+
+#[tokio::test]
+async fn unknown_security_settings_are_rejected() {
+    let mut config = test_config(4843);
+    config.security_policy = Some("Basic512".into());
+    let driver = OpcUaDriver::new(config).unwrap();
+    let err = driver.connect().await.unwrap_err();
+    assert!(
+        matches!(&err, DriverError::Config(msg) if msg.contains("Unknown security policy 'Basic512'")),
+        "{err:?}"
+    );
+
+    let mut config = test_config(4843);
+    config.security_mode = Some("EncryptOnly".into());
+    let driver = OpcUaDriver::new(config).unwrap();
+    let err = driver.connect().await.unwrap_err();
+    assert!(
+        matches!(&err, DriverError::Config(msg) if msg.contains("Unknown security mode 'EncryptOnly'")),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn identity_token_follows_credentials() {
+    let driver = OpcUaDriver::new(test_config(4843)).unwrap();
+    assert!(matches!(driver.identity_token(), IdentityToken::Anonymous));
+
+    let mut config = test_config(4843);
+    config.username = Some("operator".into());
+    let driver = OpcUaDriver::new(config.clone()).unwrap();
+    assert!(matches!(driver.identity_token(), IdentityToken::Anonymous));
+
+    config.password = Some("secret".into());
+    let driver = OpcUaDriver::new(config).unwrap();
+    match driver.identity_token() {
+        IdentityToken::UserName(user, password) => {
+            assert_eq!(user, "operator");
+            assert_eq!(password.0, "secret");
+        }
+        other => panic!("expected UserName token, got {other:?}"),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn browse_tree_from_dummy_server() {
+    let _ = tracing_subscriber::fmt::try_init();
+    let _server = DummyServer::start(4844).await;
+    let driver = OpcUaDriver::new(test_config(4844)).unwrap();
+    driver.connect().await.unwrap();
+
+    let nodes = driver.browse_node_recursive("ns=0;i=85", 2).await.unwrap();
+    for name in ["Temperature", "Pressure", "Counter"] {
+        let node = nodes
+            .iter()
+            .find(|n| n.browse_name == name)
+            .unwrap_or_else(|| panic!("{name} not found"));
+        assert_eq!(node.node_id, format!("ns=2;s={name}"));
+        assert_eq!(node.node_class, "Variable");
+        assert_eq!(node.parent_path, "");
+    }
+    // Every node appears once even though the address space has cross links.
+    let mut ids: Vec<_> = nodes.iter().map(|n| n.node_id.clone()).collect();
+    ids.sort();
+    ids.dedup();
+    assert_eq!(ids.len(), nodes.len());
+
+    driver.disconnect().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn discover_tags_from_dummy_server() {
+    let _ = tracing_subscriber::fmt::try_init();
+    let _server = DummyServer::start(4845).await;
+    let driver = OpcUaDriver::new(test_config(4845)).unwrap();
+    driver.connect().await.unwrap();
+
+    let mut tags = driver.discover_tags().await.unwrap();
+    tags.sort_by(|a, b| a.path.cmp(&b.path));
+    let found: Vec<_> = tags
+        .iter()
+        .map(|t| (t.path.as_str(), t.node_id.as_str(), t.data_type.as_str()))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            ("Counter", "ns=2;s=Counter", "Int"),
+            ("Pressure", "ns=2;s=Pressure", "Float"),
+            ("Temperature", "ns=2;s=Temperature", "Float"),
+        ]
+    );
+    let temperature = &tags[2];
+    assert_eq!(temperature.display_name, "Temperature");
+    assert!(temperature.writable);
+    assert!(!tags[1].writable);
+    let tag = temperature.to_tag("srv", 500);
+    assert_eq!(tag.driver_address, "ns=2;s=Temperature");
+    assert!(tag.metadata.writable);
+    assert_eq!(tag.metadata.description.as_deref(), Some("Temperature"));
+
+    driver.disconnect().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn read_is_split_into_batches() {
+    let _ = tracing_subscriber::fmt::try_init();
+    let _server = DummyServer::start(4847).await;
+    let mut config = test_config(4847);
+    config.read_batch_size = Some(2);
+    let driver = OpcUaDriver::new(config).unwrap();
+    driver.connect().await.unwrap();
+
+    let requests: Vec<TagRequest> = ["Temperature", "Pressure", "Counter"]
+        .iter()
+        .map(|name| TagRequest {
+            address: format!("ns=2;s={name}"),
+        })
+        .collect();
+    let values = driver.read_tags(&requests).await.unwrap();
+    assert_eq!(values.len(), 3);
+    assert_eq!(values["ns=2;s=Pressure"].value, ValueVariant::Float(1.0));
+    assert_eq!(values["ns=2;s=Counter"].value, ValueVariant::Int(0));
+    assert!(values.values().all(|v| v.quality == Quality::Good));
+
+    driver.disconnect().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn write_access_check_is_split_into_batches() {
+    let _ = tracing_subscriber::fmt::try_init();
+    let _server = DummyServer::start(4859).await;
+    let mut config = test_config(4859);
+    config.read_batch_size = Some(1);
+    let driver = OpcUaDriver::new(config).unwrap();
+    driver.connect().await.unwrap();
+
+    let writes: HashMap<String, TagValue> = ["Temperature", "Pressure", "Counter"]
+        .iter()
+        .map(|name| {
+            let value = TagValue::new(ValueVariant::Float(2.5), Quality::Good);
+            (format!("ns=2;s={name}"), value)
+        })
+        .collect();
+    let results = driver.write_tags(writes).await.unwrap();
+    assert_eq!(results.len(), 3);
+    assert_eq!(results["ns=2;s=Temperature"].quality, Quality::Good);
+    assert_eq!(results["ns=2;s=Pressure"].error.as_deref(), Some("read-only"));
+    assert_eq!(results["ns=2;s=Counter"].error.as_deref(), Some("read-only"));
+
+    driver.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn operations_without_session_report_not_connected() {
+    let driver = OpcUaDriver::new(test_config(4848)).unwrap();
+    let request = TagRequest {
+        address: "ns=2;s=Temperature".into(),
+    };
+    assert!(matches!(driver.check_status().await, Err(DriverError::NotConnected)));
+    assert!(matches!(driver.read_tags(&[request]).await, Err(DriverError::NotConnected)));
+    assert!(matches!(driver.browse_node("i=85").await, Err(DriverError::NotConnected)));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn invalid_node_id_only_fails_its_own_tag() {
+    let _ = tracing_subscriber::fmt::try_init();
+    let _server = DummyServer::start(4848).await;
+    let driver = OpcUaDriver::new(test_config(4848)).unwrap();
+    driver.connect().await.unwrap();
+
+    let requests = [
+        TagRequest {
+            address: "ns=2;s=Temperature".into(),
+        },
+        TagRequest {
+            address: "not a node id".into(),
+        },
+    ];
+    let values = driver.read_tags(&requests).await.unwrap();
+    assert_eq!(values["ns=2;s=Temperature"].quality, Quality::Good);
+    assert_eq!(values["not a node id"].quality, Quality::Bad);
+
+    let err = driver.node_id("not a node id").unwrap_err();
+    assert!(matches!(&err, DriverError::InvalidNodeId(address) if address == "not a node id"));
+    assert_eq!(err.to_string(), "Invalid NodeId 'not a node id'");
+
+    driver.disconnect().await.unwrap();
+}
+
+#[test]
+fn read_batch_size_follows_config() {
+    let driver = OpcUaDriver::new(test_config(4847)).unwrap();
+    assert_eq!(driver.read_batch_size(), 1000);
+
+    let mut config = test_config(4847);
+    config.max_message_size = Some(65_536);
+    assert_eq!(OpcUaDriver::new(config.clone()).unwrap().read_batch_size(), 256);
+
+    config.read_batch_size = Some(10);
+    assert_eq!(OpcUaDriver::new(config.clone()).unwrap().read_batch_size(), 10);
+
+    config.read_batch_size = Some(0);
+    assert_eq!(OpcUaDriver::new(config).unwrap().read_batch_size(), 1);
+}
+
+#[test]
+fn build_driver_dispatches_on_driver_type() {
+    let driver = build_driver(test_config(4846)).unwrap();
+    assert!(driver.as_any().downcast_ref::<OpcUaDriver>().is_some());
+    assert_eq!(driver.config().id, "srv");
+
+    let mut config = test_config(4846);
+    config.driver_type = "modbus".into();
+    let err = build_driver(config).err().expect("unknown driver type should fail");
+    assert!(
+        matches!(&err, DriverError::Config(msg) if msg.contains("Unknown driver type 'modbus'")),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn integers_beyond_32_bits_are_not_truncated() {
+    let convert = |value| OpcUaDriver::tag_value_to_variant(&TagValue::new(value, Quality::Good));
+    assert_eq!(convert(ValueVariant::Int(-2_000_000_000)).unwrap(), Variant::Int32(-2_000_000_000));
+    assert_eq!(convert(ValueVariant::UInt(u32::MAX as u64)).unwrap(), Variant::UInt32(u32::MAX));
+
+    let err = convert(ValueVariant::Int(5_000_000_000)).unwrap_err();
+    assert_eq!(err.to_string(), "Cannot convert Int(5000000000) to Int32");
+    assert!(convert(ValueVariant::UInt(5_000_000_000)).is_err());
+    let elements = vec![ValueVariant::Int(1), ValueVariant::Int(i64::MIN)];
+    assert!(convert(ValueVariant::Array(elements)).is_err());
+}
+
+#[test]
+fn array_value_is_written_as_an_opc_ua_array() {
+    let ints = ValueVariant::Array(vec![ValueVariant::Int(1), ValueVariant::Int(-2)]);
+    let variant = OpcUaDriver::tag_value_to_variant(&TagValue::new(ints, Quality::Good)).unwrap();
+    assert_eq!(variant, Variant::from(vec![1i32, -2]));
+
+    // Without one element type there is no OPC UA array to write
+    for value in [
+        ValueVariant::Array(vec![ValueVariant::Int(1), ValueVariant::Float(2.5)]),
+        ValueVariant::Array(vec![]),
+    ] {
+        assert!(OpcUaDriver::tag_value_to_variant(&TagValue::new(value, Quality::Good)).is_err());
+    }
+}
+
+#[test]
+fn array_variant_converts_element_wise() {
+    let dv = DataValue {
+        value: Some(Variant::from(vec![1i32, -2, 3])),
+        status: Some(StatusCode::Good),
+        ..Default::default()
+    };
+    let tv = OpcUaDriver::data_value_to_tag_value(&dv);
+    assert_eq!(tv.quality, Quality::Good);
+    assert_eq!(
+        tv.value,
+        ValueVariant::Array(vec![ValueVariant::Int(1), ValueVariant::Int(-2), ValueVariant::Int(3)])
+    );
+
+    // Serialized through the REST layer the elements form a JSON array
+    assert_eq!(
+        serde_json::to_value(&tv.value).unwrap(),
+        serde_json::json!({ "Array": [{ "Int": 1 }, { "Int": -2 }, { "Int": 3 }] })
+    );
+}
+
+#[test]
+fn short_write_response_fails_the_remaining_writes() {
+    let requested: Vec<(String, TagValue)> = ["ns=2;s=A", "ns=2;s=B", "ns=2;s=C"]
+        .into_iter()
+        .map(|address| {
+            (address.to_string(), TagValue::new(ValueVariant::Int(1), Quality::Good))
+        })
+        .collect();
+    // The server only answered the first two writes
+    let statuses = [StatusCode::Good, StatusCode::BadTypeMismatch];
+
+    let results = OpcUaDriver::write_results(requested, &statuses);
+    assert_eq!(results.len(), 3);
+    assert_eq!(results["ns=2;s=A"].quality, Quality::Good);
+    assert_eq!(results["ns=2;s=B"].quality, Quality::Bad);
+    assert_eq!(results["ns=2;s=C"].quality, Quality::Bad);
+    assert_eq!(results["ns=2;s=C"].error.as_deref(), Some("no status returned by the server"));
+}
+
+#[test]
+fn short_attribute_response_is_an_error() {
+    let node_ids = [NodeId::new(2, "A"), NodeId::new(2, "B")];
+    let attributes = |name: &str, access_level: u8| {
+        vec![
+            DataValue::value_only(Variant::from(opcua::types::LocalizedText::from(name))),
+            DataValue::null(),
+            DataValue::value_only(Variant::from(NodeId::from(DataTypeId::Double))),
+            DataValue::value_only(Variant::Byte(access_level)),
+        ]
+    };
+    let mut values = attributes("A", AccessLevel::CURRENT_READ.bits());
+    let read_write = AccessLevel::CURRENT_READ | AccessLevel::CURRENT_WRITE;
+    values.extend(attributes("B", read_write.bits()));
+
+    let nodes = OpcUaDriver::node_attributes(&node_ids, &values).unwrap();
+    assert_eq!(nodes.len(), 2);
+    assert_eq!(nodes[1].display_name, "B");
+    assert_eq!(nodes[1].data_type.as_deref(), Some("Double"));
+    assert!(!nodes[0].writable);
+    assert!(nodes[1].writable);
+
+    // Without the first node's DisplayName every later attribute would shift
+    // onto the wrong node
+    let error = OpcUaDriver::node_attributes(&node_ids, &values[1..]).unwrap_err();
+    assert!(matches!(error, DriverError::Protocol(_)), "{error}");
+    assert!(OpcUaDriver::node_attributes(&node_ids, &[]).is_err());
+}
+
+#[test]
+fn short_read_response_marks_missing_tags_bad() {
+    let requests: Vec<TagRequest> = ["ns=2;s=A", "ns=2;s=B", "ns=2;s=C"]
+        .into_iter()
+        .map(|address| TagRequest {
+            address: address.to_string(),
+        })
+        .collect();
+    // The server only answered the first read
+    let data_values = vec![DataValue {
+        value: Some(Variant::Int32(7)),
+        status: Some(StatusCode::Good),
+        ..Default::default()
+    }];
+
+    let results = OpcUaDriver::read_results(&requests, &data_values);
+    assert_eq!(results.len(), 3);
+    assert_eq!(results["ns=2;s=A"].value, ValueVariant::Int(7));
+    assert_eq!(results["ns=2;s=A"].quality, Quality::Good);
+    for address in ["ns=2;s=B", "ns=2;s=C"] {
+        assert_eq!(results[address].quality, Quality::Bad, "{address}");
+    }
+
+    // Values beyond the requests are ignored
+    let data_values = vec![data_values[0].clone(); 4];
+    let results = OpcUaDriver::read_results(&requests[..1], &data_values);
+    assert_eq!(results.len(), 1);
+}
+
+#[test]
+fn date_time_round_trips_as_unix_millis() {
+    // 2023-11-14T22:13:20.123Z
+    let millis = 1_700_000_000_123;
+    let tv = TagValue::new(ValueVariant::DateTime(millis), Quality::Good);
+
+    let variant = OpcUaDriver::tag_value_to_variant(&tv).unwrap();
+    assert!(matches!(variant, Variant::DateTime(_)));
+    assert_eq!(variant.to_string(), "2023-11-14T22:13:20.123+00:00");
+
+    let dv = DataValue {
+        value: Some(variant),
+        status: Some(StatusCode::Good),
+        ..Default::default()
+    };
+    let back = OpcUaDriver::data_value_to_tag_value(&dv);
+    assert_eq!(back.value, ValueVariant::DateTime(millis));
+    assert_eq!(
+        serde_json::to_value(&back.value).unwrap(),
+        serde_json::json!({ "DateTime": millis })
+    );
+}
+
+#[test]
+fn source_timestamp_is_preserved() {
+    let source: opcua::types::DateTime = "2024-03-01T12:00:00.250Z".parse().unwrap();
+    let server: opcua::types::DateTime = "2024-03-01T12:00:01Z".parse().unwrap();
+    let mut dv = DataValue {
+        value: Some(Variant::Double(1.5)),
+        status: Some(StatusCode::Good),
+        source_timestamp: Some(source),
+        server_timestamp: Some(server),
+        ..Default::default()
     };
+    assert_eq!(OpcUaDriver::data_value_to_tag_value(&dv).timestamp, 1_709_294_400_250);
+
+    // Falls back to the server timestamp, then to local time
+    dv.source_timestamp = None;
+    assert_eq!(OpcUaDriver::data_value_to_tag_value(&dv).timestamp, 1_709_294_401_000);
+    dv.server_timestamp = None;
+    let local = TagValue::new(ValueVariant::Null, Quality::Good).timestamp;
+    assert!(OpcUaDriver::data_value_to_tag_value(&dv).timestamp >= local);
+}
+
+#[test]
+fn uncertain_status_maps_to_uncertain_quality() {
+    let quality_for = |status: StatusCode| {
+        let dv = DataValue {
+            value: Some(Variant::Double(1.0)),
+            status: Some(status),
+            ..Default::default()
+        };
+        OpcUaDriver::data_value_to_tag_value(&dv).quality
+    };
+    assert_eq!(quality_for(StatusCode::UncertainLastUsableValue), Quality::Uncertain);
+    assert_eq!(quality_for(StatusCode::UncertainSensorNotAccurate), Quality::Uncertain);
+    assert_eq!(quality_for(StatusCode::BadCommunicationError), Quality::Bad);
+    assert_eq!(quality_for(StatusCode::Good), Quality::Good);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn repeated_reads_reuse_cached_node_ids() {
+    let _ = tracing_subscriber::fmt::try_init();
+    let _server = DummyServer::start(4849).await;
+    let driver = OpcUaDriver::new(test_config(4849)).unwrap();
+    driver.connect().await.unwrap();
+
+    let requests: Vec<TagRequest> = ["Temperature", "Pressure", "Counter"]
+        .iter()
+        .map(|name| TagRequest {
+            address: format!("ns=2;s={name}"),
+        })
+        .collect();
+    assert_eq!(driver.cached_node_ids(), 0);
+    for _ in 0..10 {
+        let values = driver.read_tags(&requests).await.unwrap();
+        assert_eq!(values.len(), 3);
+        // Parsed on the first poll only
+        assert_eq!(driver.cached_node_ids(), 3);
+    }
+
+    driver.disconnect().await.unwrap();
+}
+
+#[test]
+fn node_id_cache_is_faster_than_parsing_and_bounded() {
+    let driver = OpcUaDriver::new(test_config(4849)).unwrap();
+    let addresses: Vec<String> = (0..1000)
+        .map(|i| format!("ns=2;s=Line1/Motor{i}/Speed"))
+        .collect();
+
+    let start = std::time::Instant::now();
+    for address in &addresses {
+        driver.node_id(address).unwrap();
+    }
+    let first_pass = start.elapsed();
+    let start = std::time::Instant::now();
+    for _ in 0..10 {
+        for address in &addresses {
+            let expected = NodeId::new(2, address["ns=2;s=".len()..].to_string());
+            assert_eq!(driver.node_id(address).unwrap(), expected);
+        }
+    }
+    let cached_passes = start.elapsed();
+    println!("first pass (parsing): {first_pass:?}, 10 cached passes: {cached_passes:?}");
+    assert_eq!(driver.cached_node_ids(), 1000);
+
+    // Past the cap addresses are still parsed, just not remembered
+    for i in 0..20_000 {
+        driver.node_id(&format!("ns=3;i={i}")).unwrap();
+    }
+    assert_eq!(driver.cached_node_ids(), 10_000);
+    assert!(matches!(driver.node_id("bogus"), Err(DriverError::InvalidNodeId(_))));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn call_method_on_dummy_server() {
+    let _ = tracing_subscriber::fmt::try_init();
+    let _server = DummyServer::start(4850).await;
+    let driver = OpcUaDriver::new(test_config(4850)).unwrap();
+    driver.connect().await.unwrap();
+
+    let outputs = driver
+        .call_method("i=85", "ns=2;s=Add", vec![ValueVariant::Int(2), ValueVariant::Int(3)])
+        .await
+        .unwrap();
+    assert_eq!(outputs, vec![ValueVariant::Int(5)]);
+
+    // Missing an argument
+    let err = driver
+        .call_method("i=85", "ns=2;s=Add", vec![ValueVariant::Int(2)])
+        .await
+        .unwrap_err();
+    assert!(matches!(err, DriverError::Protocol(_)), "{err}");
+
+    driver.disconnect().await.unwrap();
+    assert!(matches!(
+        driver.call_method("i=85", "ns=2;s=Add", vec![]).await,
+        Err(DriverError::NotConnected)
+    ));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn read_temperature_node_attributes() {
+    let _ = tracing_subscriber::fmt::try_init();
+    let _server = DummyServer::start(4851).await;
+    let driver = OpcUaDriver::new(test_config(4851)).unwrap();
+    driver.connect().await.unwrap();
+
+    let attributes = driver.read_node_attributes("ns=2;s=Temperature").await.unwrap();
+    assert_eq!(
+        attributes,
+        NodeAttributes {
+            node_id: "ns=2;s=Temperature".into(),
+            display_name: "Temperature".into(),
+            description: None,
+            data_type: Some("Double".into()),
+            writable: true,
+        }
+    );
+    let pressure = driver.read_node_attributes("ns=2;s=Pressure").await.unwrap();
+    assert!(!pressure.writable);
+
+    // Objects have no data type or access level
+    let objects = driver.read_node_attributes("i=85").await.unwrap();
+    assert_eq!(objects.display_name, "Objects");
+    assert_eq!(objects.data_type, None);
+    assert!(!objects.writable);
+
+    driver.disconnect().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn read_the_data_type_attribute() {
+    let _ = tracing_subscriber::fmt::try_init();
+    let _server = DummyServer::start(4857).await;
+    let driver = OpcUaDriver::new(test_config(4857)).unwrap();
+    driver.connect().await.unwrap();
+
+    let data_type = driver
+        .read_attribute("ns=2;s=Temperature", AttributeId::DataType)
+        .await
+        .unwrap();
+    assert_eq!(data_type.quality, Quality::Good);
+    assert_eq!(data_type.value, ValueVariant::String("i=11".into()));
+
+    let values = driver
+        .read_attribute_values(
+            "ns=2;s=Temperature",
+            &[AttributeId::BrowseName, AttributeId::Value],
+        )
+        .await
+        .unwrap();
+    assert_eq!(values[0].value, ValueVariant::String("Temperature".into()));
+    assert_eq!(values[1].quality, Quality::Good);
+    assert!(matches!(values[1].value, ValueVariant::Float(_)));
+
+    // Objects have no data type
+    let objects = driver.read_attribute("i=85", AttributeId::DataType).await.unwrap();
+    assert_eq!(objects.quality, Quality::Bad);
+
+    assert!(matches!(
+        driver.read_attribute("not-a-node", AttributeId::Value).await,
+        Err(DriverError::InvalidNodeId(_))
+    ));
+    driver.disconnect().await.unwrap();
+}
+
+#[test]
+fn attributes_are_parsed_by_name_or_id() {
+    assert_eq!(parse_attribute("DataType"), Some(AttributeId::DataType));
+    assert_eq!(parse_attribute(" datatype "), Some(AttributeId::DataType));
+    assert_eq!(parse_attribute("13"), Some(AttributeId::Value));
+    assert_eq!(parse_attribute("AccessLevelEx"), Some(AttributeId::AccessLevelEx));
+    assert_eq!(parse_attribute("Colour"), None);
+    assert_eq!(parse_attribute("0"), None);
+}
+
+#[test]
+fn retry_delays_back_off_exactly_without_jitter() {
+    let mut config = test_config(4855);
+    config.connect_retry_attempts = Some(3);
     let driver = OpcUaDriver::new(config).unwrap();
+    let millis = [200, 300, 450].map(Duration::from_millis).to_vec();
+    assert_eq!(driver.retry_delays(), millis);
+    assert_eq!(driver.retry_delays(), millis);
+}
+
+#[test]
+fn retry_jitter_spreads_identical_drivers() {
+    let mut config = test_config(4855);
+    config.connect_retry_attempts = Some(5);
+    config.connect_retry_jitter_ms = Some(1000);
+    let driver = |seed| OpcUaDriver::new(config.clone()).unwrap().with_retry_jitter_seed(seed);
+
+    let first = driver(1).retry_delays();
+    let second = driver(2).retry_delays();
+    assert_ne!(first, second);
+    // Reproducible for a given seed
+    assert_eq!(driver(1).retry_delays(), first);
+
+    // Each delay lies between the plain backoff delay and 1000ms above it
+    let plain = [200, 300, 450, 675, 1012];
+    for delays in [first, second] {
+        for (delay, plain) in delays.iter().zip(plain) {
+            assert!((plain..=plain + 1000).contains(&delay.as_millis()), "{delay:?}");
+        }
+    }
+}
+
+#[test]
+fn access_level_maps_to_writable() {
+    let read = AccessLevel::CURRENT_READ.bits();
+    let write = AccessLevel::CURRENT_WRITE.bits();
+    let history = AccessLevel::HISTORY_READ.bits();
+    assert!(!access_level_writable(0));
+    assert!(!access_level_writable(read));
+    assert!(!access_level_writable(read | history));
+    assert!(access_level_writable(write));
+    assert!(access_level_writable(read | write));
+    assert!(access_level_writable(0xFF));
+    assert!(!access_level_writable(!write));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn write_to_read_only_node_is_rejected() {
+    let _ = tracing_subscriber::fmt::try_init();
+    let _server = DummyServer::start(4852).await;
+    let driver = OpcUaDriver::new(test_config(4852)).unwrap();
     driver.connect().await.unwrap();
-    driver.check_status().await.unwrap();
+    let write = |address: &str| {
+        HashMap::from([(
+            address.to_string(),
+            TagValue::new(ValueVariant::Float(1.5), Quality::Good),
+        )])
+    };
+
+    let results = driver.write_tags(write("ns=2;s=Pressure")).await.unwrap();
+    assert_eq!(results["ns=2;s=Pressure"].quality, Quality::Bad);
+    assert_eq!(results["ns=2;s=Pressure"].error.as_deref(), Some("read-only"));
+
+    // A writable node in the same call is still written
+    let mut writes = write("ns=2;s=Pressure");
+    writes.extend(write("ns=2;s=Temperature"));
+    let results = driver.write_tags(writes).await.unwrap();
+    assert_eq!(results["ns=2;s=Pressure"].error.as_deref(), Some("read-only"));
+    assert_eq!(results["ns=2;s=Temperature"].quality, Quality::Good);
+    let request = [TagRequest { address: "ns=2;s=Temperature".to_string() }];
+    let values = driver.read_tags(&request).await.unwrap();
+    assert_eq!(values["ns=2;s=Temperature"].value, ValueVariant::Float(1.5));
+    // An unknown node is reported by the write itself
+    let results = driver.write_tags(write("ns=2;s=Missing")).await.unwrap();
+    assert_eq!(results["ns=2;s=Missing"].quality, Quality::Bad);
+
+    // An address that isn't a node id fails on its own
+    let mut writes = write("bogus");
+    writes.extend(write("ns=2;s=Temperature"));
+    let results = driver.write_tags(writes).await.unwrap();
+    assert_eq!(results["bogus"].error.as_deref(), Some("Invalid NodeId 'bogus'"));
+    assert_eq!(results["ns=2;s=Temperature"].quality, Quality::Good);
+
+    // A value that doesn't fit the OPC UA type is refused rather than truncated
+    let too_large = HashMap::from([(
+        "ns=2;s=Temperature".to_string(),
+        TagValue::new(ValueVariant::Int(5_000_000_000), Quality::Good),
+    )]);
+    let results = driver.write_tags(too_large).await.unwrap();
+    assert_eq!(results["ns=2;s=Temperature"].quality, Quality::Bad);
+    assert_eq!(
+        results["ns=2;s=Temperature"].error.as_deref(),
+        Some("Cannot convert Int(5000000000) to Int32")
+    );
+
+    driver.disconnect().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn drivers_for_the_same_endpoint_share_one_session() {
+    let _server = DummyServer::start(4853).await;
+    let first = OpcUaDriver::new(test_config(4853)).unwrap();
+    let second = OpcUaDriver::new(DriverConfig {
+        id: "srv2".into(),
+        ..test_config(4853)
+    })
+    .unwrap();
+    let (a, b) = tokio::join!(first.connect(), second.connect());
+    a.unwrap();
+    b.unwrap();
+    assert!(first.shares_session_with(&second));
+    assert_eq!(first.session_users().await, 2);
+
+    // The session stays open until its last user disconnects
+    first.disconnect().await.unwrap();
+    assert!(!first.shares_session_with(&second));
+    assert_eq!(second.session_users().await, 1);
+    second.check_status().await.unwrap();
+    let request = TagRequest {
+        address: "ns=2;s=Temperature".into(),
+    };
+    let values = second.read_tags(std::slice::from_ref(&request)).await.unwrap();
+    assert_eq!(values["ns=2;s=Temperature"].quality, Quality::Good);
+
+    // Reconnecting joins the existing session again
+    first.connect().await.unwrap();
+    assert!(first.shares_session_with(&second));
+    first.disconnect().await.unwrap();
+    second.disconnect().await.unwrap();
+    assert_eq!(second.session_users().await, 0);
+    assert!(matches!(second.check_status().await, Err(DriverError::NotConnected)));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn browse_can_be_limited_to_variables() {
+    let _ = tracing_subscriber::fmt::try_init();
+    let _server = DummyServer::start(4854).await;
+    let driver = OpcUaDriver::new(test_config(4854)).unwrap();
+    driver.connect().await.unwrap();
+
+    // The Objects folder also holds the Server object and the Add method
+    let all: Vec<_> = driver
+        .browse_node("i=85")
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|child| child.browse_name)
+        .collect();
+    assert!(all.contains(&"Server".to_string()), "{all:?}");
+    assert!(all.contains(&"Add".to_string()), "{all:?}");
+
+    let filter = BrowseFilter::parse(None, Some("Variable")).unwrap();
+    let mut variables: Vec<_> = driver
+        .browse_node_filtered("i=85", &filter)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|child| child.browse_name)
+        .collect();
+    variables.sort();
+    assert_eq!(variables, vec!["Counter", "Pressure", "Temperature"]);
+
+    driver.disconnect().await.unwrap();
+}
+
+#[test]
+fn browse_filter_parses_names_and_node_ids() {
+    assert_eq!(BrowseFilter::parse(None, None).unwrap(), BrowseFilter::default());
+    assert_eq!(BrowseFilter::default().node_class_mask, 0);
+
+    let filter = BrowseFilter::parse(Some("Organizes"), Some("Object, Variable")).unwrap();
+    assert_eq!(filter.reference_type, NodeId::new(0, 35u32));
+    assert_eq!(filter.node_class_mask, 0b11);
+    // Reference types without a name are given by node id
+    let filter = BrowseFilter::parse(Some("i=47"), None).unwrap();
+    assert_eq!(filter.reference_type, NodeId::new(0, 47u32));
+
+    assert_eq!(
+        BrowseFilter::parse(Some("Contains"), None).unwrap_err(),
+        "Unknown reference type 'Contains'"
+    );
+    assert_eq!(
+        BrowseFilter::parse(None, Some("Variable,Tag")).unwrap_err(),
+        "Unknown node class 'Tag'"
+    );
+}
+
+#[test]
+fn address_index_range_is_split_from_the_node_id() {
+    let parse = |address| OpcUaDriver::parse_address(address).unwrap();
+    assert_eq!(
+        parse("ns=2;s=Array[3]"),
+        NodeAddress { node_id: NodeId::new(2, "Array"), index_range: NumericRange::Index(3) }
+    );
+    assert_eq!(parse("ns=2;s=Array[0:5]").index_range, NumericRange::Range(0, 5));
+    assert_eq!(parse("ns=2;i=7[1]").node_id, NodeId::new(2, 7u32));
+    assert_eq!(parse("ns=2;s=Array").index_range, NumericRange::None);
+    // Brackets that aren't an index range belong to the node id
+    assert_eq!(parse("ns=2;s=Tank[A]").node_id, NodeId::new(2, "Tank[A]"));
+
+    let err = OpcUaDriver::parse_address("bogus[3]").unwrap_err();
+    assert_eq!(err.address(), Some("bogus[3]"));
+}
+
+#[test]
+fn node_ids_of_every_identifier_type_parse() {
+    let parse = |node_id| OpcUaDriver::parse_node_id(node_id).unwrap();
+    let guid = Guid::from_str("72962b91-fa75-4ae6-8d28-b404dc7daf63").unwrap();
+    let opaque = ByteString::from(vec![0x33, 0xf4, 0x5b, 0x28]);
+
+    assert_eq!(parse("i=85"), NodeId::new(0, 85u32));
+    assert_eq!(parse("ns=2;i=5"), NodeId::new(2, 5u32));
+    assert_eq!(parse("ns=2;s=Line 1;Speed"), NodeId::new(2, "Line 1;Speed"));
+    assert_eq!(parse("ns=2;g=72962b91-fa75-4ae6-8d28-b404dc7daf63"), NodeId::new(2, guid.clone()));
+    assert_eq!(parse("ns=2;g=72962B91-FA75-4AE6-8D28-B404DC7DAF63"), NodeId::new(2, guid.clone()));
+    assert_eq!(parse("ns=2;g={72962b91-fa75-4ae6-8d28-b404dc7daf63}"), NodeId::new(2, guid));
+    assert_eq!(parse("ns=2;b=M/RbKA=="), NodeId::new(2, opaque.clone()));
+    assert_eq!(parse("ns=2;b=M/RbKA"), NodeId::new(2, opaque));
+
+    // Displayed node ids parse back to the same node
+    let displayed = [
+        "i=85",
+        "ns=2;s=Tank",
+        "ns=3;g=72962b91-fa75-4ae6-8d28-b404dc7daf63",
+        "ns=2;b=M/RbKA==",
+    ];
+    for node_id in displayed {
+        assert_eq!(parse(node_id).to_string(), node_id);
+    }
+
+    for invalid in ["ns=2;g=72962b91", "ns=2;b=not base64!", "ns=2;b=", "ns=x;i=1", "Tank"] {
+        let err = OpcUaDriver::parse_node_id(invalid).unwrap_err();
+        assert_eq!(err.address(), Some(invalid));
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn read_guid_and_opaque_node_ids() {
+    let _ = tracing_subscriber::fmt::try_init();
+    let server = DummyServer::start(4858).await;
+    let guid = Guid::from_str("72962b91-fa75-4ae6-8d28-b404dc7daf63").unwrap();
+    server.add_variable_at(NodeId::new(2, guid), "Flow", 12i32);
+    let opaque = ByteString::from(vec![0x33, 0xf4, 0x5b, 0x28]);
+    server.add_variable_at(NodeId::new(2, opaque), "Level", 7i32);
+    let driver = OpcUaDriver::new(test_config(4858)).unwrap();
+    driver.connect().await.unwrap();
+
+    let addresses = ["ns=2;g=72962B91-FA75-4AE6-8D28-B404DC7DAF63", "ns=2;b=M/RbKA"];
+    let requests: Vec<TagRequest> = addresses
+        .into_iter()
+        .map(|address| TagRequest { address: address.to_string() })
+        .collect();
+    let values = driver.read_tags(&requests).await.unwrap();
+
+    // Results are keyed by the address as requested
+    assert_eq!(values[addresses[0]].quality, Quality::Good);
+    assert_eq!(values[addresses[0]].value, ValueVariant::Int(12));
+    assert_eq!(values[addresses[1]].value, ValueVariant::Int(7));
+    driver.disconnect().await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn read_one_element_of_an_array() {
+    let _ = tracing_subscriber::fmt::try_init();
+    let server = DummyServer::start(4856).await;
+    server.add_variable("Levels", vec![10i32, 20, 30, 40]);
+    let driver = OpcUaDriver::new(test_config(4856)).unwrap();
+    driver.connect().await.unwrap();
+
+    let requests: Vec<TagRequest> = ["ns=2;s=Levels[2]", "ns=2;s=Levels[1:2]", "ns=2;s=Levels[9]"]
+        .into_iter()
+        .map(|address| TagRequest { address: address.to_string() })
+        .collect();
+    let values = driver.read_tags(&requests).await.unwrap();
+
+    let element = &values["ns=2;s=Levels[2]"];
+    assert_eq!(element.quality, Quality::Good);
+    assert_eq!(element.value, ValueVariant::Int(30));
+    assert_eq!(
+        values["ns=2;s=Levels[1:2]"].value,
+        ValueVariant::Array(vec![ValueVariant::Int(20), ValueVariant::Int(30)])
+    );
+    // Past the end of the array
+    assert_eq!(values["ns=2;s=Levels[9]"].quality, Quality::Bad);
+
+    // Writing an element would replace the whole array
+    let write = HashMap::from([(
+        "ns=2;s=Levels[2]".to_string(),
+        TagValue::new(ValueVariant::Int(1), Quality::Good),
+    )]);
+    let results = driver.write_tags(write).await.unwrap();
+    assert_eq!(results["ns=2;s=Levels[2]"].quality, Quality::Bad);
+    assert_eq!(results["ns=2;s=Levels[2]"].error.as_deref(), Some("array elements are read-only"));
 
-    let tags = driver.browse_node("ns=0;i=85").await.unwrap();
-    assert!(tags.contains(&"Temperature".to_string()));
-    assert!(tags.contains(&"Pressure".to_string()));
-    assert!(tags.contains(&"Counter".to_string()));
+    // The whole array can be written
+    let node_id = NodeId::new(2, "Setpoints");
+    let mut setpoints = Variable::new(&node_id, "Setpoints", "Setpoints", vec![0i32; 3]);
+    setpoints.set_access_level(AccessLevel::CURRENT_READ | AccessLevel::CURRENT_WRITE);
+    setpoints.set_user_access_level(AccessLevel::CURRENT_READ | AccessLevel::CURRENT_WRITE);
+    server.add(setpoints);
+    let array = ValueVariant::Array([5, 6, 7].map(ValueVariant::Int).to_vec());
+    let write = HashMap::from([(
+        "ns=2;s=Setpoints".to_string(),
+        TagValue::new(array.clone(), Quality::Good),
+    )]);
+    let results = driver.write_tags(write).await.unwrap();
+    assert_eq!(results["ns=2;s=Setpoints"].quality, Quality::Good);
+    let request = [TagRequest { address: "ns=2;s=Setpoints".to_string() }];
+    assert_eq!(driver.read_tags(&request).await.unwrap()["ns=2;s=Setpoints"].value, array);
 
     driver.disconnect().await.unwrap();
 }
+
+/// The client configuration a driver's sessions are built with.
+fn client_config(config: DriverConfig) -> serde_json::Value {
+    let driver = OpcUaDriver::new(config).unwrap();
+    serde_json::to_value(driver.client_builder().config()).unwrap()
+}
+
+#[test]
+fn certificates_are_only_trusted_when_configured() {
+    // Secure unless asked otherwise
+    let client = client_config(test_config(4855));
+    assert_eq!(client["trust_server_certs"], false);
+    assert_eq!(client["create_sample_keypair"], false);
+
+    let mut config = test_config(4855);
+    config.insecure = true;
+    let client = client_config(config.clone());
+    assert_eq!(client["trust_server_certs"], true);
+    assert_eq!(client["create_sample_keypair"], true);
+
+    // Explicit settings win over `insecure`
+    config.trust_server_certs = Some(false);
+    config.pki_dir = Some("/etc/forgeio/pki".to_string());
+    let client = client_config(config);
+    assert_eq!(client["trust_server_certs"], false);
+    assert_eq!(client["create_sample_keypair"], true);
+    assert_eq!(client["pki_dir"], "/etc/forgeio/pki");
+
+    let mut config = test_config(4855);
+    config.create_sample_keypair = Some(true);
+    let client = client_config(config);
+    assert_eq!(client["trust_server_certs"], false);
+    assert_eq!(client["create_sample_keypair"], true);
+}
@@ -1,6 +1,6 @@
 use gateway_server::drivers::opcua::OpcUaDriver;
 use gateway_server::drivers::traits::{DeviceDriver, DriverConfig, TagRequest};
-use tokio::time::{sleep, Duration};
+use tokio::time::Duration;
 use std::sync::Arc;
 
 fn create_test_config(address: &str) -> DriverConfig {
@@ -9,15 +9,29 @@ fn create_test_config(address: &str) -> DriverConfig {
         name: "Test OPC UA Driver".into(),
         address: address.into(),
         scan_rate_ms: 1000,
+        driver_type: "opcua".into(),
         application_name: Some("TestClient".into()),
         application_uri: None,
         session_name: Some("TestSession".into()),
         max_message_size: None,
         max_chunk_count: None,
+        read_batch_size: None,
         connect_retry_attempts: Some(3),
         connect_retry_delay_ms: Some(100),
         connect_retry_backoff: Some(1.5),
+        connect_retry_jitter_ms: None,
         connect_timeout_ms: Some(500),
+        security_policy: None,
+        security_mode: None,
+        username: None,
+        password: None,
+        pki_dir: None,
+        trust_server_certs: None,
+        create_sample_keypair: None,
+        insecure: false,
+        max_concurrent_reads: None,
+        read_timeout_ms: None,
+        critical: false,
     }
 }
 
@@ -57,15 +71,29 @@ async fn test_driver_configuration() {
         name: "Test Name".into(),
         address: "opc.tcp://127.0.0.1:4840/".into(),
         scan_rate_ms: 2000,
+        driver_type: "opcua".into(),
         application_name: Some("CustomApp".into()),
         application_uri: Some("urn:custom:app".into()),
         session_name: Some("CustomSession".into()),
         max_message_size: Some(1000000),
         max_chunk_count: Some(512),
+        read_batch_size: None,
         connect_retry_attempts: Some(10),
         connect_retry_delay_ms: Some(2000),
         connect_retry_backoff: Some(2.5),
+        connect_retry_jitter_ms: None,
         connect_timeout_ms: Some(5000),
+        security_policy: None,
+        security_mode: None,
+        username: None,
+        password: None,
+        pki_dir: None,
+        trust_server_certs: None,
+        create_sample_keypair: None,
+        insecure: false,
+        max_concurrent_reads: None,
+        read_timeout_ms: None,
+        critical: false,
     };
     
     let driver = OpcUaDriver::new(config.clone()).unwrap();
@@ -0,0 +1,694 @@
+use async_trait::async_trait;
+use gateway_server::drivers::traits::{
+    DeviceDriver, DriverConfig, DriverError, DriverResult, TagRequest, WriteStatus,
+};
+use gateway_server::drivers::breaker::{BreakerState, CircuitBreakers};
+use gateway_server::drivers::scan_stats::{ScanStatistics, ScanStats};
+use gateway_server::drivers::DriverMap;
+use gateway_server::tags::engine::TagEngine;
+use gateway_server::tags::poller::{
+    build_poll_groups, first_poll_offset, groups_due, initial_last_poll, is_due, poll_tick_for,
+    Poller,
+};
+use gateway_server::tags::structures::{Quality, Tag, TagMetadata, TagValue, ValueVariant};
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::Notify;
+use tokio::time::{sleep, timeout, Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+/// Driver that answers every read with a constant and records disconnects.
+struct DummyDriver {
+    config: DriverConfig,
+    /// How long each read takes.
+    read_delay: Duration,
+    reads: AtomicUsize,
+    in_flight: AtomicUsize,
+    max_in_flight: AtomicUsize,
+    disconnected: AtomicBool,
+    /// Error every read fails with, if set.
+    failure: Option<fn() -> DriverError>,
+}
+
+impl DummyDriver {
+    fn new(id: &str) -> Self {
+        Self {
+            config: DriverConfig {
+                id: id.to_string(),
+                name: id.to_string(),
+                address: "dummy://".to_string(),
+                scan_rate_ms: 1000,
+                driver_type: "dummy".to_string(),
+                application_name: None,
+                application_uri: None,
+                session_name: None,
+                max_message_size: None,
+                max_chunk_count: None,
+                read_batch_size: None,
+                connect_retry_attempts: None,
+                connect_retry_delay_ms: None,
+                connect_retry_backoff: None,
+                connect_retry_jitter_ms: None,
+                connect_timeout_ms: None,
+                security_policy: None,
+                security_mode: None,
+                username: None,
+                password: None,
+                pki_dir: None,
+                trust_server_certs: None,
+                create_sample_keypair: None,
+                insecure: false,
+                max_concurrent_reads: None,
+                read_timeout_ms: None,
+                critical: false,
+            },
+            read_delay: Duration::ZERO,
+            reads: AtomicUsize::new(0),
+            in_flight: AtomicUsize::new(0),
+            max_in_flight: AtomicUsize::new(0),
+            disconnected: AtomicBool::new(false),
+            failure: None,
+        }
+    }
+}
+
+#[async_trait]
+impl DeviceDriver for DummyDriver {
+    fn config(&self) -> &DriverConfig {
+        &self.config
+    }
+    async fn connect(&self) -> DriverResult<()> {
+        Ok(())
+    }
+    async fn disconnect(&self) -> DriverResult<()> {
+        self.disconnected.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+    async fn check_status(&self) -> DriverResult<()> {
+        Ok(())
+    }
+    async fn read_tags(&self, tags: &[TagRequest]) -> DriverResult<HashMap<String, TagValue>> {
+        self.reads.fetch_add(1, Ordering::SeqCst);
+        let now_in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_in_flight.fetch_max(now_in_flight, Ordering::SeqCst);
+        sleep(self.read_delay).await;
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        if let Some(failure) = self.failure {
+            return Err(failure());
+        }
+        Ok(tags
+            .iter()
+            .map(|t| (t.address.clone(), TagValue::new(ValueVariant::Int(7), Quality::Good)))
+            .collect())
+    }
+    async fn write_tags(
+        &self,
+        tags: HashMap<String, TagValue>,
+    ) -> DriverResult<HashMap<String, WriteStatus>> {
+        Ok(tags
+            .into_iter()
+            .map(|(address, value)| (address, WriteStatus::good(value.value)))
+            .collect())
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[tokio::test]
+async fn poller_stops_promptly_when_cancelled() {
+    let engine = Arc::new(TagEngine::new());
+    engine.register_tag(Tag {
+        path: "Dummy/Value".parse().unwrap(),
+        value: TagValue::bad(Quality::Initializing),
+        driver_id: "dummy".to_string(),
+        driver_address: "addr".to_string(),
+        poll_rate_ms: 50,
+        enabled: true,
+        metadata: TagMetadata::default(),
+    });
+    let driver = Arc::new(DummyDriver::new("dummy"));
+    let drivers = Arc::new(DriverMap::new());
+    drivers.insert("dummy".to_string(), driver.clone());
+
+    let cancel = CancellationToken::new();
+    let poller = Poller::new(
+        Arc::clone(&engine),
+        drivers,
+        Arc::new(Notify::new()),
+        cancel.clone(),
+    );
+    let handle = tokio::spawn(poller.run());
+
+    // Let it poll at least once
+    timeout(Duration::from_secs(2), async {
+        while engine.read_tag("Dummy/Value").unwrap().value != ValueVariant::Int(7) {
+            sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("poller never stored a value read from the driver");
+
+    cancel.cancel();
+    timeout(Duration::from_millis(500), handle)
+        .await
+        .expect("poller did not stop after cancellation")
+        .unwrap();
+    assert!(driver.disconnected.load(Ordering::SeqCst));
+}
+
+fn tag(path: &str, driver_id: &str, poll_rate_ms: u64) -> Tag {
+    Tag {
+        path: path.parse().unwrap(),
+        value: TagValue::bad(Quality::Initializing),
+        driver_id: driver_id.to_string(),
+        driver_address: path.to_string(),
+        poll_rate_ms,
+        enabled: true,
+        metadata: TagMetadata::default(),
+    }
+}
+
+#[test]
+fn poll_groups_bucket_by_driver_and_rate() {
+    let tags = vec![
+        tag("A/2", "drv1", 1000),
+        tag("A/1", "drv1", 1000),
+        tag("A/Slow", "drv1", 5000),
+        tag("B/1", "drv2", 1000),
+    ];
+    let groups = build_poll_groups(&tags);
+
+    assert_eq!(groups.len(), 3);
+    assert_eq!(groups[&("drv1".to_string(), 1000)], vec!["A/1", "A/2"]);
+    assert_eq!(groups[&("drv1".to_string(), 5000)], vec!["A/Slow"]);
+    assert_eq!(groups[&("drv2".to_string(), 1000)], vec!["B/1"]);
+    assert!(build_poll_groups(&[]).is_empty());
+}
+
+#[test]
+fn poll_tick_divides_every_poll_rate() {
+    let tick = |rates: &[u64]| {
+        let tags: Vec<Tag> = rates.iter().map(|rate| tag("A/1", &format!("drv{rate}"), *rate)).collect();
+        poll_tick_for(&build_poll_groups(&tags))
+    };
+    assert_eq!(tick(&[50]), Duration::from_millis(50));
+    assert_eq!(tick(&[250, 1000]), Duration::from_millis(250));
+    assert_eq!(tick(&[1000, 1500]), Duration::from_millis(500));
+    // Kept within bounds
+    assert_eq!(tick(&[1000, 1001]), Duration::from_millis(10));
+    assert_eq!(tick(&[60_000]), Duration::from_secs(1));
+    assert_eq!(tick(&[]), Duration::from_millis(100));
+}
+
+#[test]
+fn due_time_follows_poll_rate() {
+    let start = Instant::now();
+    assert!(is_due(start, None, 1000), "never-polled groups are due immediately");
+    assert!(!is_due(start + Duration::from_millis(999), Some(start), 1000));
+    assert!(is_due(start + Duration::from_millis(1000), Some(start), 1000));
+    // A clock reading before the last poll is treated as not yet due
+    assert!(!is_due(start, Some(start + Duration::from_millis(10)), 1000));
+}
+
+#[test]
+fn groups_due_selects_only_expired_groups() {
+    let tags = vec![tag("Fast", "drv1", 100), tag("Slow", "drv1", 1000), tag("New", "drv2", 100)];
+    let groups = build_poll_groups(&tags);
+    let start = Instant::now();
+    let last = HashMap::from([
+        (("drv1".to_string(), 100), start),
+        (("drv1".to_string(), 1000), start),
+    ]);
+
+    let mut due = groups_due(start + Duration::from_millis(150), &last, &groups);
+    due.sort();
+    assert_eq!(due, vec![("drv1".to_string(), 100), ("drv2".to_string(), 100)]);
+}
+
+#[test]
+fn first_polls_are_staggered_per_group() {
+    let a = ("drv1".to_string(), 1000);
+    let b = ("drv2".to_string(), 1000);
+
+    let offset_a = first_poll_offset(&a, 42);
+    let offset_b = first_poll_offset(&b, 42);
+    assert_ne!(offset_a, offset_b, "groups with the same rate should not fire together");
+    assert!(offset_a < Duration::from_millis(1000) && offset_b < Duration::from_millis(1000));
+    // Deterministic for a given seed
+    assert_eq!(first_poll_offset(&a, 42), offset_a);
+
+    // The group first comes due exactly at its offset
+    let start = Instant::now();
+    let last = initial_last_poll(start, &a, 42);
+    if !offset_a.is_zero() {
+        assert!(!is_due(start + offset_a - Duration::from_millis(1), Some(last), 1000));
+    }
+    assert!(is_due(start + offset_a, Some(last), 1000));
+}
+
+#[tokio::test]
+async fn concurrent_reads_are_bounded_per_driver() {
+    let engine = Arc::new(TagEngine::new());
+    // Six groups on one driver, all due within the first tick
+    for (i, rate) in [100, 110, 120, 130, 140, 150].into_iter().enumerate() {
+        engine.register_tag(tag(&format!("Slow/{i}"), "slow", rate));
+    }
+    let mut driver = DummyDriver::new("slow");
+    driver.config.max_concurrent_reads = Some(2);
+    driver.read_delay = Duration::from_millis(300);
+    let driver = Arc::new(driver);
+    let drivers = Arc::new(DriverMap::new());
+    drivers.insert("slow".to_string(), driver.clone());
+
+    let cancel = CancellationToken::new();
+    let poller = Poller::new(
+        Arc::clone(&engine),
+        drivers,
+        Arc::new(Notify::new()),
+        cancel.clone(),
+    );
+    let handle = tokio::spawn(poller.run());
+
+    sleep(Duration::from_millis(800)).await;
+    cancel.cancel();
+    timeout(Duration::from_secs(1), handle)
+        .await
+        .expect("poller did not stop after cancellation")
+        .unwrap();
+
+    assert!(driver.reads.load(Ordering::SeqCst) >= 2);
+    assert_eq!(driver.max_in_flight.load(Ordering::SeqCst), 2);
+}
+
+fn dummy_tag(path: &str, driver_id: &str, address: &str) -> Tag {
+    Tag {
+        path: path.parse().unwrap(),
+        value: TagValue::bad(Quality::Initializing),
+        driver_id: driver_id.to_string(),
+        driver_address: address.to_string(),
+        poll_rate_ms: 1000,
+        enabled: true,
+        metadata: TagMetadata::default(),
+    }
+}
+
+#[tokio::test]
+async fn read_all_reads_the_drivers_tags_in_one_call() {
+    let engine = TagEngine::new();
+    engine.register_tag(dummy_tag("Line1/Speed", "dummy", "ns=2;s=Speed"));
+    engine.register_tag(dummy_tag("Line1/Temperature", "dummy", "ns=2;s=Temperature"));
+    // Two paths for the same address are both filled from a single read of it
+    engine.register_tag(dummy_tag("Overview/Temperature", "dummy", "ns=2;s=Temperature"));
+    engine.register_tag(dummy_tag("Line2/Speed", "other", "ns=2;s=Speed"));
+    let driver = DummyDriver::new("dummy");
+
+    let values = driver.read_all(&engine, "dummy").await.unwrap();
+    assert_eq!(driver.reads.load(Ordering::SeqCst), 1);
+    let mut paths: Vec<_> = values.keys().cloned().collect();
+    paths.sort();
+    assert_eq!(paths, vec!["Line1/Speed", "Line1/Temperature", "Overview/Temperature"]);
+    assert!(values.values().all(|v| v.value == ValueVariant::Int(7)));
+
+    // Nothing registered for the driver: no read at all
+    let values = driver.read_all(&engine, "missing").await.unwrap();
+    assert!(values.is_empty());
+    assert_eq!(driver.reads.load(Ordering::SeqCst), 1);
+}
+
+/// Run a poller over one group holding a tag at "good" and one at "bad" until
+/// the driver has been read, returning the engine.
+async fn poll_failing_driver(failure: fn() -> DriverError) -> Arc<TagEngine> {
+    let engine = Arc::new(TagEngine::new());
+    engine.register_tag(dummy_tag("Line1/Good", "failing", "good"));
+    engine.register_tag(dummy_tag("Line1/Bad", "failing", "bad"));
+    let mut driver = DummyDriver::new("failing");
+    driver.failure = Some(failure);
+    let driver = Arc::new(driver);
+    let drivers = Arc::new(DriverMap::new());
+    drivers.insert("failing".to_string(), driver.clone());
+
+    let cancel = CancellationToken::new();
+    let poller = Poller::new(
+        Arc::clone(&engine),
+        drivers,
+        Arc::new(Notify::new()),
+        cancel.clone(),
+    );
+    let handle = tokio::spawn(poller.run());
+    timeout(Duration::from_secs(3), async {
+        while engine.stats("Line1/Bad").unwrap().last_error.is_none() {
+            sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("poller never read from the driver");
+    cancel.cancel();
+    handle.await.unwrap();
+    engine
+}
+
+#[tokio::test]
+async fn error_about_one_address_only_marks_its_tags_bad() {
+    let engine = poll_failing_driver(|| DriverError::InvalidNodeId("bad".to_string())).await;
+
+    assert_eq!(engine.read_tag("Line1/Bad").unwrap().quality, Quality::Bad);
+    assert_eq!(
+        engine.stats("Line1/Bad").unwrap().last_error.as_deref(),
+        Some("Invalid NodeId 'bad'")
+    );
+    // The other tag keeps its value and has no error recorded
+    assert_eq!(engine.read_tag("Line1/Good").unwrap().quality, Quality::Initializing);
+    assert!(engine.stats("Line1/Good").unwrap().last_error.is_none());
+}
+
+#[tokio::test]
+async fn connection_error_marks_the_whole_group_bad() {
+    let engine = poll_failing_driver(|| DriverError::NotConnected).await;
+
+    for path in ["Line1/Good", "Line1/Bad"] {
+        assert_eq!(engine.read_tag(path).unwrap().quality, Quality::Bad);
+        assert_eq!(
+            engine.stats(path).unwrap().last_error.as_deref(),
+            Some("not connected")
+        );
+    }
+}
+
+#[tokio::test]
+async fn breaker_half_opens_after_the_cooldown() {
+    let breakers = CircuitBreakers::new(3, Duration::from_millis(100));
+    for _ in 0..2 {
+        assert!(breakers.allow("drv"));
+        breakers.record_failure("drv");
+    }
+    assert_eq!(breakers.state("drv"), BreakerState::Closed);
+    breakers.record_failure("drv");
+    assert_eq!(breakers.state("drv"), BreakerState::Open);
+    assert!(!breakers.allow("drv"));
+    assert!(breakers.allow("other"));
+
+    // One probe after the cooldown; a failed probe reopens the breaker
+    sleep(Duration::from_millis(120)).await;
+    assert!(breakers.allow("drv"));
+    assert_eq!(breakers.state("drv"), BreakerState::HalfOpen);
+    assert!(!breakers.allow("drv"));
+    breakers.record_failure("drv");
+    assert_eq!(breakers.state("drv"), BreakerState::Open);
+    assert!(!breakers.allow("drv"));
+
+    // A successful probe closes it
+    sleep(Duration::from_millis(120)).await;
+    assert!(breakers.allow("drv"));
+    breakers.record_success("drv");
+    assert_eq!(breakers.state("drv"), BreakerState::Closed);
+    assert!(breakers.allow("drv"));
+}
+
+#[tokio::test]
+async fn poller_stops_reading_a_driver_whose_breaker_is_open() {
+    let engine = Arc::new(TagEngine::new());
+    engine.register_tag(tag("Down/Value", "down", 20));
+    let mut driver = DummyDriver::new("down");
+    driver.failure = Some(|| DriverError::NotConnected);
+    let driver = Arc::new(driver);
+    let drivers = Arc::new(DriverMap::new());
+    drivers.insert("down".to_string(), driver.clone());
+    let breakers = Arc::new(CircuitBreakers::new(3, Duration::from_millis(500)));
+
+    let cancel = CancellationToken::new();
+    let poller = Poller::new(
+        Arc::clone(&engine),
+        drivers,
+        Arc::new(Notify::new()),
+        cancel.clone(),
+    )
+    .with_poll_tick(Duration::from_millis(10))
+    .with_circuit_breakers(Arc::clone(&breakers));
+    let handle = tokio::spawn(poller.run());
+
+    timeout(Duration::from_secs(2), async {
+        while breakers.state("down") != BreakerState::Open {
+            sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .expect("breaker did not open");
+    let reads = driver.reads.load(Ordering::SeqCst);
+    assert_eq!(reads, 3);
+
+    // Skipped while open, the tag stays Bad
+    sleep(Duration::from_millis(250)).await;
+    assert_eq!(driver.reads.load(Ordering::SeqCst), reads);
+    assert_eq!(engine.read_tag("Down/Value").unwrap().quality, Quality::Bad);
+
+    // Then a single probe, which fails and opens the breaker again
+    timeout(Duration::from_secs(2), async {
+        while driver.reads.load(Ordering::SeqCst) == reads {
+            sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .expect("breaker never let a probe through");
+    sleep(Duration::from_millis(100)).await;
+    assert_eq!(driver.reads.load(Ordering::SeqCst), reads + 1);
+    assert_eq!(breakers.state("down"), BreakerState::Open);
+
+    cancel.cancel();
+    handle.await.unwrap();
+}
+
+#[tokio::test]
+async fn poller_records_scan_durations() {
+    let engine = Arc::new(TagEngine::new());
+    engine.register_tag(tag("Slow/Value", "slow", 100));
+    let mut driver = DummyDriver::new("slow");
+    driver.read_delay = Duration::from_millis(50);
+    let drivers = Arc::new(DriverMap::new());
+    drivers.insert("slow".to_string(), Arc::new(driver));
+    let scan_stats = Arc::new(ScanStatistics::new());
+
+    let cancel = CancellationToken::new();
+    let poller = Poller::new(
+        Arc::clone(&engine),
+        drivers,
+        Arc::new(Notify::new()),
+        cancel.clone(),
+    )
+    .with_poll_tick(Duration::from_millis(10))
+    .with_scan_stats(Arc::clone(&scan_stats));
+    let handle = tokio::spawn(poller.run());
+
+    timeout(Duration::from_secs(2), async {
+        while scan_stats.get("slow").scan_count < 2 {
+            sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("poller did not scan the driver twice");
+    cancel.cancel();
+    handle.await.unwrap();
+
+    let stats = scan_stats.get("slow");
+    assert!((50..250).contains(&stats.last_scan_duration_ms), "{stats:?}");
+    assert!((50.0..250.0).contains(&stats.avg_scan_duration_ms), "{stats:?}");
+    assert_eq!(stats.error_count, 0);
+    assert_eq!(stats.consecutive_errors, 0);
+}
+
+#[test]
+fn scan_stats_count_consecutive_errors() {
+    let stats = ScanStatistics::new();
+    assert_eq!(stats.get("drv"), ScanStats::default());
+
+    stats.record("drv", Duration::from_millis(10), true);
+    stats.record("drv", Duration::from_millis(30), false);
+    stats.record("drv", Duration::from_millis(20), false);
+    let recorded = stats.get("drv");
+    assert_eq!(recorded.scan_count, 3);
+    assert_eq!(recorded.last_scan_duration_ms, 20);
+    assert!((recorded.avg_scan_duration_ms - 20.0).abs() < 1e-9);
+    assert_eq!(recorded.error_count, 2);
+    assert_eq!(recorded.consecutive_errors, 2);
+
+    stats.record("drv", Duration::from_millis(20), true);
+    assert_eq!(stats.get("drv").consecutive_errors, 0);
+    assert_eq!(stats.get("drv").error_count, 2);
+
+    stats.retain(|id| id != "drv");
+    assert_eq!(stats.get("drv").scan_count, 0);
+}
+
+#[tokio::test]
+async fn changed_poll_rate_moves_the_tag_to_another_group() {
+    let engine = TagEngine::new();
+    engine.register_tag(tag("A/1", "drv1", 1000));
+    engine.register_tag(tag("A/2", "drv1", 1000));
+    let version = engine.poll_rates_version();
+
+    assert!(engine.set_poll_rate("A/2", 250));
+    assert!(!engine.set_poll_rate("A/Missing", 250));
+    assert_ne!(engine.poll_rates_version(), version);
+
+    let groups = build_poll_groups(&engine.get_all_tags().await);
+    assert_eq!(groups[&("drv1".to_string(), 1000)], vec!["A/1"]);
+    assert_eq!(groups[&("drv1".to_string(), 250)], vec!["A/2"]);
+}
+
+#[tokio::test]
+async fn disabled_tag_is_left_out_of_the_poll_groups() {
+    let engine = TagEngine::new();
+    engine.register_tag(tag("A/1", "drv1", 1000));
+    engine.register_tag(Tag { enabled: false, ..tag("A/Off", "drv1", 1000) });
+    engine.register_tag(tag("B/1", "drv2", 500));
+    let version = engine.poll_rates_version();
+
+    let groups = build_poll_groups(&engine.get_all_tags().await);
+    assert!(groups.values().flatten().all(|path| path != "A/Off"));
+
+    assert!(engine.set_enabled("B/1", false));
+    assert!(!engine.set_enabled("B/Missing", false));
+    assert_ne!(engine.poll_rates_version(), version);
+    let groups = build_poll_groups(&engine.get_all_tags().await);
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[&("drv1".to_string(), 1000)], vec!["A/1"]);
+
+    assert!(engine.set_enabled("A/Off", true));
+    let groups = build_poll_groups(&engine.get_all_tags().await);
+    assert_eq!(groups[&("drv1".to_string(), 1000)], vec!["A/1", "A/Off"]);
+}
+
+#[tokio::test]
+async fn running_poller_picks_up_a_faster_poll_rate() {
+    let engine = Arc::new(TagEngine::new());
+    // Not due for an hour at its configured rate
+    engine.register_tag(tag("Dummy/Value", "dummy", 3_600_000));
+    let driver = Arc::new(DummyDriver::new("dummy"));
+    let drivers = Arc::new(DriverMap::new());
+    drivers.insert("dummy".to_string(), driver.clone());
+
+    let cancel = CancellationToken::new();
+    let poller = Poller::new(
+        Arc::clone(&engine),
+        drivers,
+        Arc::new(Notify::new()),
+        cancel.clone(),
+    )
+    .with_stagger_seed(1);
+    let handle = tokio::spawn(poller.run());
+
+    sleep(Duration::from_millis(300)).await;
+    assert_eq!(driver.reads.load(Ordering::SeqCst), 0);
+
+    engine.set_poll_rate("Dummy/Value", 50);
+    timeout(Duration::from_secs(2), async {
+        while driver.reads.load(Ordering::SeqCst) < 2 {
+            sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("poller did not switch to the new rate");
+
+    cancel.cancel();
+    handle.await.unwrap();
+}
+
+#[tokio::test]
+async fn hung_read_times_out_and_marks_the_group_bad() {
+    let engine = Arc::new(TagEngine::new());
+    engine.register_tag(dummy_tag("Slow/Value", "slow", "addr"));
+    let mut driver = DummyDriver::new("slow");
+    driver.read_delay = Duration::from_secs(30);
+    driver.config.read_timeout_ms = Some(100);
+    let drivers = Arc::new(DriverMap::new());
+    drivers.insert("slow".to_string(), Arc::new(driver));
+
+    let cancel = CancellationToken::new();
+    let poller = Poller::new(
+        Arc::clone(&engine),
+        drivers,
+        Arc::new(Notify::new()),
+        cancel.clone(),
+    );
+    let handle = tokio::spawn(poller.run());
+
+    // Well before the read itself would have finished
+    timeout(Duration::from_secs(3), async {
+        while engine.stats("Slow/Value").unwrap().last_error.is_none() {
+            sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("read was not timed out");
+    assert_eq!(engine.read_tag("Slow/Value").unwrap().quality, Quality::Bad);
+    assert_eq!(
+        engine.stats("Slow/Value").unwrap().last_error.as_deref(),
+        Some("timed out")
+    );
+
+    cancel.cancel();
+    handle.await.unwrap();
+}
+
+#[tokio::test]
+async fn slow_driver_does_not_delay_other_drivers() {
+    let engine = Arc::new(TagEngine::new());
+    engine.register_tag(tag("Slow/Value", "slow", 100));
+    engine.register_tag(tag("Fast/Value", "fast", 100));
+    let mut slow = DummyDriver::new("slow");
+    slow.read_delay = Duration::from_secs(5);
+    let slow = Arc::new(slow);
+    let fast = Arc::new(DummyDriver::new("fast"));
+    let drivers = Arc::new(DriverMap::new());
+    drivers.insert("slow".to_string(), slow.clone());
+    drivers.insert("fast".to_string(), fast.clone());
+
+    let cancel = CancellationToken::new();
+    let poller = Poller::new(
+        Arc::clone(&engine),
+        drivers,
+        Arc::new(Notify::new()),
+        cancel.clone(),
+    );
+    let handle = tokio::spawn(poller.run());
+
+    // The fast driver keeps its rate while the slow driver's reads hang
+    sleep(Duration::from_millis(1000)).await;
+    assert_eq!(engine.read_tag("Slow/Value").unwrap().quality, Quality::Initializing);
+    assert!(fast.reads.load(Ordering::SeqCst) >= 5, "{:?}", fast.reads);
+    assert_eq!(engine.read_tag("Fast/Value").unwrap().value, ValueVariant::Int(7));
+
+    cancel.cancel();
+    timeout(Duration::from_secs(1), handle)
+        .await
+        .expect("poller did not stop after cancellation")
+        .unwrap();
+}
+
+#[tokio::test]
+async fn fast_tag_is_polled_at_its_rate_with_a_lower_tick() {
+    let engine = Arc::new(TagEngine::new());
+    engine.register_tag(tag("Fast/Value", "fast", 50));
+    let driver = Arc::new(DummyDriver::new("fast"));
+    let drivers = Arc::new(DriverMap::new());
+    drivers.insert("fast".to_string(), driver.clone());
+
+    let cancel = CancellationToken::new();
+    let poller = Poller::new(
+        Arc::clone(&engine),
+        drivers,
+        Arc::new(Notify::new()),
+        cancel.clone(),
+    )
+    .with_poll_tick(Duration::from_millis(10));
+    let handle = tokio::spawn(poller.run());
+
+    // About 20 polls at 50ms; a 100ms tick would allow only 10
+    sleep(Duration::from_millis(1000)).await;
+    cancel.cancel();
+    handle.await.unwrap();
+    let reads = driver.reads.load(Ordering::SeqCst);
+    assert!((15..=22).contains(&reads), "{reads} reads");
+}
@@ -1,13 +1,16 @@
 use gateway_server::tags::engine::TagEngine;
+use gateway_server::tags::events::EventChannel;
 use gateway_server::tags::structures::{Tag, TagValue, ValueVariant, TagMetadata, Quality};
+use tokio_stream::StreamExt;
 
 fn sample_tag(path: &str, driver_id: &str, address: &str) -> Tag {
     Tag {
-        path: path.to_string(),
+        path: path.parse().unwrap(),
         value: TagValue::new(ValueVariant::Int(0), Quality::Good),
         driver_id: driver_id.to_string(),
         driver_address: address.to_string(),
         poll_rate_ms: 1000,
+        enabled: true,
         metadata: TagMetadata::default(),
     }
 }
@@ -22,6 +25,21 @@ fn register_and_read_tag() {
     assert_eq!(read, tag.value);
 }
 
+#[test]
+fn pre_sized_engine_registers_the_expected_count() {
+    let engine = TagEngine::with_capacity(500);
+    let tags = (0..500)
+        .map(|i| sample_tag(&format!("Device/Tag{i}"), "drv1", &format!("addr{i}")))
+        .collect();
+    engine.register_tags(tags);
+    assert_eq!(engine.get_all_tag_paths().len(), 500);
+    assert_eq!(engine.find_path_by_address("drv1", "addr499"), Some("Device/Tag499".to_string()));
+
+    // The capacity is only a hint
+    engine.register_tag(sample_tag("Device/Extra", "drv1", "extra"));
+    assert_eq!(engine.get_all_tag_paths().len(), 501);
+}
+
 #[test]
 fn update_tag_value() {
     let engine = TagEngine::new();
@@ -47,8 +65,42 @@ fn list_paths_and_find_by_address() {
     paths.sort();
     assert_eq!(paths, vec![tag1.path.clone(), tag2.path.clone()]);
 
-    assert_eq!(engine.find_path_by_address("drv1", "a1"), Some(tag1.path));
-    assert_eq!(engine.find_path_by_address("drv1", "a2"), Some(tag2.path));
+    assert_eq!(engine.find_path_by_address("drv1", "a1"), Some(tag1.path.to_string()));
+    assert_eq!(engine.find_path_by_address("drv1", "a2"), Some(tag2.path.to_string()));
+}
+
+#[test]
+fn find_by_address_follows_reregistration_and_removal() {
+    let engine = TagEngine::new();
+    engine.register_tag(sample_tag("Device/TagA", "drv1", "a1"));
+    // Moved to another address
+    engine.register_tag(sample_tag("Device/TagA", "drv1", "a2"));
+    assert_eq!(engine.find_path_by_address("drv1", "a1"), None);
+    assert_eq!(engine.find_path_by_address("drv1", "a2"), Some("Device/TagA".to_string()));
+
+    // A second tag on the same address is still found once the first is gone
+    engine.register_tag(sample_tag("Overview/TagA", "drv1", "a2"));
+    engine.unregister_tag("Device/TagA");
+    assert_eq!(engine.find_path_by_address("drv1", "a2"), Some("Overview/TagA".to_string()));
+    engine.unregister_tag("Overview/TagA");
+    assert_eq!(engine.find_path_by_address("drv1", "a2"), None);
+    assert_eq!(engine.find_path_by_address("drv2", "a2"), None);
+}
+
+#[test]
+fn paths_for_addresses_resolves_a_batch() {
+    let engine = TagEngine::new();
+    engine.register_tag(sample_tag("Device/TagA", "drv1", "a1"));
+    engine.register_tag(sample_tag("Device/TagB", "drv1", "a2"));
+    engine.register_tag(sample_tag("Other/TagA", "drv2", "a1"));
+
+    let addresses = vec!["a1".to_string(), "missing".to_string(), "a2".to_string()];
+    let paths = engine.paths_for_addresses("drv1", &addresses);
+    assert_eq!(paths.len(), 2);
+    assert_eq!(paths["a1"], "Device/TagA");
+    assert_eq!(paths["a2"], "Device/TagB");
+    assert!(!paths.contains_key("missing"));
+    assert!(engine.paths_for_addresses("drv3", &addresses).is_empty());
 }
 
 #[test]
@@ -64,3 +116,495 @@ fn get_tag_details_and_all_tags() {
     assert_eq!(all.len(), 1);
     assert_eq!(all[0].path, tag.path);
 }
+
+#[test]
+fn read_tag_full_returns_value_and_metadata() {
+    let engine = TagEngine::new();
+    let mut tag = sample_tag("Device/TagF", "drv3", "addrF");
+    tag.metadata.eng_unit = Some("degC".into());
+    engine.register_tag(tag.clone());
+    let value = TagValue::new(ValueVariant::Int(7), Quality::Good);
+    engine.update_tag_value(&tag.path, value.clone());
+
+    let full = engine.read_tag_full(&tag.path).expect("tag should exist");
+    assert_eq!(full.value, value);
+    assert_eq!(full.driver_id, "drv3");
+    assert_eq!(full.driver_address, "addrF");
+    assert_eq!(full.metadata.eng_unit.as_deref(), Some("degC"));
+    assert!(engine.read_tag_full("Device/Missing").is_none());
+}
+
+#[test]
+fn bulk_update_tag_values() {
+    let engine = TagEngine::new();
+    for i in 0..1000 {
+        engine.register_tag(sample_tag(&format!("Bulk/Tag{i}"), "drv1", &format!("b{i}")));
+    }
+
+    let mut updates: Vec<_> = (0..1000)
+        .map(|i| {
+            (
+                format!("Bulk/Tag{i}"),
+                TagValue::new(ValueVariant::Int(i), Quality::Good),
+            )
+        })
+        .collect();
+    updates.push((
+        "Bulk/Missing".to_string(),
+        TagValue::new(ValueVariant::Int(-1), Quality::Good),
+    ));
+
+    assert_eq!(engine.update_tag_values(updates), 1000);
+    assert_eq!(engine.read_tag("Bulk/Tag0").unwrap().value, ValueVariant::Int(0));
+    assert_eq!(engine.read_tag("Bulk/Tag999").unwrap().value, ValueVariant::Int(999));
+    assert!(engine.read_tag("Bulk/Missing").is_none());
+}
+
+#[test]
+fn unregister_tag_removes_path_and_address() {
+    let engine = TagEngine::new();
+    let tag1 = sample_tag("Device/TagD", "drv1", "d1");
+    let tag2 = sample_tag("Device/TagE", "drv1", "d2");
+    engine.register_tag(tag1.clone());
+    engine.register_tag(tag2.clone());
+
+    let removed = engine.unregister_tag(&tag1.path).expect("tag should be removed");
+    assert_eq!(removed.path, tag1.path);
+    assert!(engine.unregister_tag(&tag1.path).is_none());
+
+    assert_eq!(engine.get_all_tag_paths(), vec![tag2.path.clone()]);
+    assert_eq!(engine.find_path_by_address("drv1", "d1"), None);
+    assert_eq!(engine.find_path_by_address("drv1", "d2"), Some(tag2.path.to_string()));
+}
+
+#[test]
+fn clear_removes_all_tags() {
+    let engine = TagEngine::new();
+    engine.register_tag(sample_tag("Device/TagF", "drv1", "f1"));
+    engine.register_tag(sample_tag("Device/TagG", "drv2", "g1"));
+
+    engine.clear();
+    assert!(engine.get_all_tag_paths().is_empty());
+    assert_eq!(engine.find_path_by_address("drv1", "f1"), None);
+    assert_eq!(engine.find_path_by_address("drv2", "g1"), None);
+}
+
+#[test]
+fn subscribe_observes_updates() {
+    let engine = TagEngine::new();
+    let tag = sample_tag("Device/TagH", "drv1", "h1");
+    engine.register_tag(tag.clone());
+
+    let mut rx = engine.subscribe(&tag.path);
+    assert_eq!(*rx.borrow_and_update(), tag.value);
+
+    let new_value = TagValue::new(ValueVariant::Int(7), Quality::Good);
+    engine.update_tag_value(&tag.path, new_value.clone());
+    assert!(rx.has_changed().unwrap());
+    assert_eq!(*rx.borrow_and_update(), new_value);
+
+    let bulk_value = TagValue::new(ValueVariant::Int(8), Quality::Good);
+    engine.update_tag_values(vec![(tag.path.to_string(), bulk_value.clone())]);
+    assert_eq!(*rx.borrow_and_update(), bulk_value);
+
+    // Updates keep flowing once every receiver is gone.
+    drop(rx);
+    assert!(engine.update_tag_value(&tag.path, TagValue::new(ValueVariant::Int(9), Quality::Good)));
+    assert_eq!(engine.read_tag(&tag.path).unwrap().value, ValueVariant::Int(9));
+}
+
+#[test]
+fn quality_transitions_fire_events() {
+    let engine = TagEngine::new();
+    let tag = sample_tag("Device/TagQ", "drv1", "addrQ");
+    engine.register_tag(tag.clone());
+    let mut events = engine.quality_events();
+
+    for quality in [Quality::Good, Quality::Bad, Quality::Bad, Quality::Good, Quality::Good] {
+        engine.update_tag_value(&tag.path, TagValue::new(ValueVariant::Int(1), quality));
+    }
+
+    let first = events.try_recv().unwrap();
+    assert_eq!(first.path, "Device/TagQ");
+    assert_eq!((first.old, first.new), (Quality::Good, Quality::Bad));
+    let second = events.try_recv().unwrap();
+    assert_eq!((second.old, second.new), (Quality::Bad, Quality::Good));
+    assert!(events.try_recv().is_err());
+}
+
+#[test]
+fn slow_quality_event_receiver_misses_events_without_blocking_updates() {
+    let engine = TagEngine::new();
+    let tag = sample_tag("Device/TagQ", "drv1", "addrQ");
+    engine.register_tag(tag.clone());
+    let mut events = engine.quality_events();
+
+    // Far more transitions than a receiver buffers, none of them received yet
+    let qualities = [Quality::Bad, Quality::Good];
+    for i in 0..1000 {
+        let value = TagValue::new(ValueVariant::Int(i), qualities[i as usize % 2].clone());
+        assert!(engine.update_tag_value(&tag.path, value));
+    }
+    assert_eq!(engine.dropped_quality_events(), 0);
+
+    // The receiver skips to the newest buffered events and the miss is counted
+    let mut received = 0;
+    while let Ok(event) = events.try_recv() {
+        assert_ne!(event.old, event.new);
+        received += 1;
+    }
+    assert_eq!(received, 256);
+    assert_eq!(engine.dropped_quality_events(), 1000 - 256);
+}
+
+#[tokio::test]
+async fn event_channel_counts_what_each_receiver_missed() {
+    let channel = EventChannel::new(4);
+    let mut slow = channel.subscribe();
+    let mut fast = channel.subscribe();
+    for i in 0..10 {
+        channel.send(i);
+        assert_eq!(fast.recv().await.unwrap(), i);
+    }
+    assert_eq!(slow.recv().await.unwrap(), 6);
+    assert_eq!(channel.dropped(), 6);
+
+    // Streams count what they skip too
+    let stream = channel.subscribe().into_stream();
+    for i in 10..20 {
+        channel.send(i);
+    }
+    let received: Vec<i32> = stream.take(4).collect().await;
+    assert_eq!(received, vec![16, 17, 18, 19]);
+    assert_eq!(channel.dropped(), 12);
+}
+
+fn queried_paths(engine: &TagEngine, pattern: &str) -> Vec<String> {
+    let mut paths: Vec<_> = engine.query_tags(pattern).into_iter().map(|t| t.path.into()).collect();
+    paths.sort();
+    paths
+}
+
+#[test]
+fn query_tags_with_wildcards() {
+    let engine = TagEngine::new();
+    for path in [
+        "Plant1/Temperature",
+        "Plant1/Pressure",
+        "Plant1/Line2/Temperature",
+        "Plant2/Temperature",
+        "Plant2/Flow",
+    ] {
+        engine.register_tag(sample_tag(path, "drv1", path));
+    }
+
+    // Prefix
+    assert_eq!(
+        queried_paths(&engine, "Plant1/*"),
+        vec!["Plant1/Line2/Temperature", "Plant1/Pressure", "Plant1/Temperature"]
+    );
+    // Suffix
+    assert_eq!(
+        queried_paths(&engine, "*/Temperature"),
+        vec!["Plant1/Line2/Temperature", "Plant1/Temperature", "Plant2/Temperature"]
+    );
+    // Mid-path
+    assert_eq!(
+        queried_paths(&engine, "Plant?/T*e"),
+        vec!["Plant1/Temperature", "Plant2/Temperature"]
+    );
+    // Exact and no match
+    assert_eq!(queried_paths(&engine, "Plant2/Flow"), vec!["Plant2/Flow"]);
+    assert!(queried_paths(&engine, "Plant3/*").is_empty());
+}
+
+fn tree_engine() -> TagEngine {
+    let engine = TagEngine::new();
+    for path in [
+        "Plant1/Temperature",
+        "Plant1/Pressure",
+        "Plant1/Line2/Temperature",
+        "Plant1/Line2/Motor/Speed",
+        "Plant2/Flow",
+        "Standalone",
+    ] {
+        engine.register_tag(sample_tag(path, "drv1", path));
+    }
+    engine
+}
+
+fn children(engine: &TagEngine, prefix: &str) -> Vec<(String, bool)> {
+    engine
+        .list_children(prefix)
+        .into_iter()
+        .map(|node| (node.name, node.is_folder))
+        .collect()
+}
+
+#[test]
+fn list_children_at_root() {
+    let engine = tree_engine();
+    let root = engine.list_children("");
+    let names: Vec<_> = root.iter().map(|n| (n.name.as_str(), n.is_folder)).collect();
+    assert_eq!(names, vec![("Plant1", true), ("Plant2", true), ("Standalone", false)]);
+    assert_eq!(root[0].path, "Plant1");
+    assert_eq!(root[2].path, "Standalone");
+    assert_eq!(children(&engine, "/"), children(&engine, ""));
+}
+
+#[test]
+fn list_children_of_nested_folders() {
+    let engine = tree_engine();
+    let plant1 = engine.list_children("Plant1/");
+    assert_eq!(
+        plant1.iter().map(|n| (n.name.as_str(), n.is_folder)).collect::<Vec<_>>(),
+        vec![("Line2", true), ("Pressure", false), ("Temperature", false)]
+    );
+    assert_eq!(plant1[0].path, "Plant1/Line2");
+    assert_eq!(plant1[2].path, "Plant1/Temperature");
+    // The trailing separator is optional
+    assert_eq!(children(&engine, "Plant1"), children(&engine, "Plant1/"));
+
+    assert_eq!(
+        children(&engine, "Plant1/Line2"),
+        vec![("Motor".to_string(), true), ("Temperature".to_string(), false)]
+    );
+    let motor = engine.list_children("Plant1/Line2/Motor/");
+    assert_eq!(motor.len(), 1);
+    assert_eq!(motor[0].path, "Plant1/Line2/Motor/Speed");
+
+    // Partial segments, tags and unknown folders have no children
+    assert!(engine.list_children("Plant").is_empty());
+    assert!(engine.list_children("Plant2/Flow").is_empty());
+    assert!(engine.list_children("Plant3/").is_empty());
+}
+
+fn scaled_tag(path: &str) -> Tag {
+    let mut tag = sample_tag(path, "drv1", path);
+    // 4-20 mA style: raw counts 0..4000 map to 0..100 %
+    tag.metadata.raw_low = Some(0.0);
+    tag.metadata.raw_high = Some(4000.0);
+    tag.metadata.eng_low = Some(0.0);
+    tag.metadata.eng_high = Some(100.0);
+    tag
+}
+
+#[test]
+fn scaled_update_maps_raw_into_engineering_range() {
+    let engine = TagEngine::new();
+    engine.register_tag(scaled_tag("Tank/Level"));
+
+    assert!(engine.update_tag_value_scaled(
+        "Tank/Level",
+        TagValue::new(ValueVariant::Int(1000), Quality::Good)
+    ));
+    assert_eq!(engine.read_tag("Tank/Level").unwrap().value, ValueVariant::Float(25.0));
+
+    engine.update_tag_values_scaled(vec![(
+        "Tank/Level".to_string(),
+        TagValue::new(ValueVariant::Float(3000.0), Quality::Good),
+    )]);
+    assert_eq!(engine.read_tag("Tank/Level").unwrap().value, ValueVariant::Float(75.0));
+}
+
+#[test]
+fn scaled_update_clamps_out_of_range_values() {
+    let engine = TagEngine::new();
+    engine.register_tag(scaled_tag("Tank/Level"));
+
+    engine.update_tag_value_scaled("Tank/Level", TagValue::new(ValueVariant::Int(5000), Quality::Good));
+    assert_eq!(engine.read_tag("Tank/Level").unwrap().value, ValueVariant::Float(100.0));
+
+    engine.update_tag_value_scaled("Tank/Level", TagValue::new(ValueVariant::Int(-200), Quality::Good));
+    assert_eq!(engine.read_tag("Tank/Level").unwrap().value, ValueVariant::Float(0.0));
+}
+
+#[test]
+fn scaled_update_passes_through_without_bounds_or_numbers() {
+    let engine = TagEngine::new();
+    engine.register_tag(sample_tag("Raw/Counts", "drv1", "addr"));
+    engine.register_tag(scaled_tag("Tank/Mode"));
+
+    engine.update_tag_value_scaled("Raw/Counts", TagValue::new(ValueVariant::Int(1000), Quality::Good));
+    assert_eq!(engine.read_tag("Raw/Counts").unwrap().value, ValueVariant::Int(1000));
+
+    let mode = ValueVariant::String("Auto".to_string());
+    engine.update_tag_value_scaled("Tank/Mode", TagValue::new(mode.clone(), Quality::Good));
+    assert_eq!(engine.read_tag("Tank/Mode").unwrap().value, mode);
+}
+
+#[test]
+fn deadband_ignores_small_changes() {
+    let engine = TagEngine::new();
+    let mut tag = sample_tag("Line/Flow", "drv1", "flow");
+    tag.value = TagValue::new(ValueVariant::Float(10.0), Quality::Good);
+    tag.metadata.deadband = Some(0.5);
+    engine.register_tag(tag);
+    let original = engine.read_tag("Line/Flow").unwrap();
+
+    // Within the deadband: value and timestamp are left alone
+    assert!(engine.update_tag_value("Line/Flow", TagValue::new(ValueVariant::Float(10.4), Quality::Good)));
+    assert_eq!(engine.read_tag("Line/Flow").unwrap(), original);
+
+    // Beyond the deadband: committed
+    engine.update_tag_value("Line/Flow", TagValue::new(ValueVariant::Float(10.6), Quality::Good));
+    assert_eq!(engine.read_tag("Line/Flow").unwrap().value, ValueVariant::Float(10.6));
+
+    // Quality changes always go through
+    engine.update_tag_value("Line/Flow", TagValue::new(ValueVariant::Float(10.7), Quality::Uncertain));
+    assert_eq!(engine.read_tag("Line/Flow").unwrap().quality, Quality::Uncertain);
+
+    // Non-numeric values always update
+    engine.update_tag_value("Line/Flow", TagValue::new(ValueVariant::String("off".into()), Quality::Uncertain));
+    assert_eq!(engine.read_tag("Line/Flow").unwrap().value, ValueVariant::String("off".into()));
+}
+
+#[test]
+fn stats_count_updates_and_track_last_good() {
+    let engine = TagEngine::new();
+    engine.register_tag(sample_tag("Pump/Speed", "drv1", "speed"));
+    assert_eq!(engine.stats("Pump/Speed").unwrap().update_count, 0);
+    assert!(engine.stats("Missing").is_none());
+
+    let first = TagValue::new(ValueVariant::Int(10), Quality::Good);
+    engine.update_tag_value("Pump/Speed", first.clone());
+    engine.update_tag_value("Pump/Speed", TagValue::bad(Quality::CommFailure));
+    assert!(engine.record_error("Pump/Speed", "connection reset"));
+
+    let stats = engine.stats("Pump/Speed").unwrap();
+    assert_eq!(stats.update_count, 2);
+    assert_eq!(stats.last_good_timestamp, first.timestamp);
+    assert_eq!(stats.last_error.as_deref(), Some("connection reset"));
+
+    engine.unregister_tag("Pump/Speed");
+    assert!(engine.stats("Pump/Speed").is_none());
+}
+
+#[test]
+fn snapshot_round_trip_restores_values() {
+    let file = std::env::temp_dir().join(format!("forgeio_snapshot_{}.json", std::process::id()));
+    let engine = TagEngine::new();
+    engine.register_tag(sample_tag("Plant/Temperature", "drv", "t"));
+    engine.register_tag(sample_tag("Plant/Pressure", "drv", "p"));
+    engine.register_tag(sample_tag("Plant/Level", "drv", "l"));
+    let temperature = TagValue::new(ValueVariant::Float(21.5), Quality::Good);
+    engine.update_tag_value("Plant/Temperature", temperature.clone());
+    // An hour-old sample
+    let pressure =
+        TagValue::with_timestamp(ValueVariant::Int(3), Quality::Good, temperature.timestamp - 3_600_000);
+    engine.update_tag_value("Plant/Pressure", pressure.clone());
+    engine.snapshot_to(&file).unwrap();
+
+    // A fresh engine, as after a restart; Level was removed from the config meanwhile
+    let restarted = TagEngine::new();
+    restarted.register_tag(sample_tag("Plant/Temperature", "drv", "t"));
+    restarted.register_tag(sample_tag("Plant/Pressure", "drv", "p"));
+    let restored = restarted.restore_from(&file).unwrap();
+    std::fs::remove_file(&file).unwrap();
+
+    assert_eq!(restored, 2);
+    assert_eq!(restarted.read_tag("Plant/Temperature").unwrap(), temperature);
+    let old = restarted.read_tag("Plant/Pressure").unwrap();
+    assert_eq!(old.value, ValueVariant::Int(3));
+    assert_eq!(old.timestamp, pressure.timestamp);
+    assert_eq!(old.quality, Quality::Stale);
+    assert!(restarted.read_tag("Plant/Level").is_none());
+}
+
+#[test]
+fn restore_from_missing_file_fails() {
+    let engine = TagEngine::new();
+    let err = engine
+        .restore_from(std::path::Path::new("/nonexistent/forgeio_snapshot.json"))
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+}
+
+#[test]
+fn history_evicts_oldest_samples_beyond_capacity() {
+    let engine = TagEngine::with_history_capacity(3);
+    engine.register_tag(sample_tag("Plant/Counter", "drv", "c"));
+    for i in 1..=5 {
+        engine.update_tag_value("Plant/Counter", TagValue::new(ValueVariant::Int(i), Quality::Good));
+    }
+
+    let values: Vec<ValueVariant> = engine
+        .history("Plant/Counter", usize::MAX)
+        .into_iter()
+        .map(|sample| sample.value)
+        .collect();
+    assert_eq!(values, vec![ValueVariant::Int(3), ValueVariant::Int(4), ValueVariant::Int(5)]);
+
+    let newest = engine.history("Plant/Counter", 1);
+    assert_eq!(newest.len(), 1);
+    assert_eq!(newest[0].value, ValueVariant::Int(5));
+
+    engine.unregister_tag("Plant/Counter");
+    assert!(engine.history("Plant/Counter", usize::MAX).is_empty());
+}
+
+fn alias_tag(path: &str, source: &str) -> Tag {
+    Tag {
+        value: TagValue::bad(Quality::Bad),
+        driver_id: String::new(),
+        driver_address: String::new(),
+        metadata: TagMetadata {
+            alias_of: Some(source.parse().unwrap()),
+            ..TagMetadata::default()
+        },
+        ..sample_tag(path, "", "")
+    }
+}
+
+#[test]
+fn source_updates_propagate_to_aliases() {
+    let engine = TagEngine::new();
+    engine.register_tag(sample_tag("Plant/Temperature", "drv1", "temp"));
+    engine.register_tag(alias_tag("Dashboard/Temperature", "Plant/Temperature"));
+    engine.register_tag(alias_tag("Reports/Temperature", "Plant/Temperature"));
+
+    // Aliases start out with the source's current value
+    assert_eq!(engine.read_tag("Dashboard/Temperature").unwrap().value, ValueVariant::Int(0));
+
+    let value = TagValue::new(ValueVariant::Float(21.5), Quality::Good);
+    assert!(engine.update_tag_value("Plant/Temperature", value.clone()));
+    assert_eq!(engine.read_tag("Dashboard/Temperature"), Some(value.clone()));
+    assert_eq!(engine.read_tag("Reports/Temperature"), Some(value));
+    assert_eq!(engine.stats("Reports/Temperature").unwrap().update_count, 1);
+
+    // A removed alias no longer follows its source
+    engine.unregister_tag("Reports/Temperature");
+    engine.register_tag(sample_tag("Reports/Temperature", "drv1", "other"));
+    let value = TagValue::new(ValueVariant::Float(22.0), Quality::Good);
+    engine.update_tag_value("Plant/Temperature", value.clone());
+    assert_eq!(engine.read_tag("Dashboard/Temperature"), Some(value));
+    assert_eq!(engine.read_tag("Reports/Temperature").unwrap().value, ValueVariant::Int(0));
+}
+
+#[test]
+fn alias_cycles_do_not_loop() {
+    let engine = TagEngine::new();
+    engine.register_tag(alias_tag("Plant/A", "Plant/B"));
+    engine.register_tag(alias_tag("Plant/B", "Plant/A"));
+
+    let value = TagValue::new(ValueVariant::Int(7), Quality::Good);
+    assert!(engine.update_tag_value("Plant/A", value.clone()));
+    assert_eq!(engine.read_tag("Plant/B"), Some(value));
+}
+
+#[test]
+fn disabled_tag_keeps_its_last_value_as_stale() {
+    let engine = TagEngine::new();
+    engine.register_tag(sample_tag("Dev/Paused", "drv", "addr"));
+    engine.update_tag_value("Dev/Paused", TagValue::new(ValueVariant::Int(7), Quality::Good));
+
+    assert!(engine.set_enabled("Dev/Paused", false));
+    let value = engine.read_tag("Dev/Paused").unwrap();
+    assert_eq!(value.value, ValueVariant::Int(7));
+    assert_eq!(value.quality, Quality::Stale);
+    assert!(!engine.read_tag_full("Dev/Paused").unwrap().enabled);
+
+    assert!(engine.set_enabled("Dev/Paused", true));
+    assert!(engine.read_tag_full("Dev/Paused").unwrap().enabled);
+    assert!(!engine.set_enabled("Dev/Missing", false));
+}
@@ -7,11 +7,12 @@ use tokio::time::timeout;
 
 fn sample_tag(path: &str, driver_id: &str, address: &str) -> Tag {
     Tag {
-        path: path.to_string(),
+        path: path.parse().unwrap(),
         value: TagValue::new(ValueVariant::Int(0), Quality::Good),
         driver_id: driver_id.to_string(),
         driver_address: address.to_string(),
         poll_rate_ms: 1000,
+        enabled: true,
         metadata: TagMetadata::default(),
     }
 }
@@ -26,6 +27,7 @@ fn test_duplicate_tag_registration() {
     engine.register_tag(tag2.clone()); // Should overwrite the first one
     
     let read = engine.read_tag("Device/Tag1").expect("tag should exist");
+    assert_eq!(read, tag2.value);
     // Should have the second tag's driver_id since it overwrote the first
     let details = engine.get_tag_details("Device/Tag1").expect("details should exist");
     assert_eq!(details.driver_id, "drv2");
@@ -37,29 +39,32 @@ fn test_tag_value_types() {
     
     // Test different value types
     let bool_tag = Tag {
-        path: "Test/Bool".to_string(),
+        path: "Test/Bool".parse().unwrap(),
         value: TagValue::new(ValueVariant::Bool(true), Quality::Good),
         driver_id: "test".to_string(),
         driver_address: "bool_addr".to_string(),
         poll_rate_ms: 1000,
+        enabled: true,
         metadata: TagMetadata::default(),
     };
     
     let float_tag = Tag {
-        path: "Test/Float".to_string(),
-        value: TagValue::new(ValueVariant::Float(3.14159), Quality::Good),
+        path: "Test/Float".parse().unwrap(),
+        value: TagValue::new(ValueVariant::Float(21.75), Quality::Good),
         driver_id: "test".to_string(),
         driver_address: "float_addr".to_string(),
         poll_rate_ms: 1000,
+        enabled: true,
         metadata: TagMetadata::default(),
     };
     
     let string_tag = Tag {
-        path: "Test/String".to_string(),
+        path: "Test/String".parse().unwrap(),
         value: TagValue::new(ValueVariant::String("Hello World".to_string()), Quality::Good),
         driver_id: "test".to_string(),
         driver_address: "string_addr".to_string(),
         poll_rate_ms: 1000,
+        enabled: true,
         metadata: TagMetadata::default(),
     };
     
@@ -73,15 +78,75 @@ fn test_tag_value_types() {
     let string_read = engine.read_tag("Test/String").unwrap();
     
     assert_eq!(bool_read.value, ValueVariant::Bool(true));
-    assert_eq!(float_read.value, ValueVariant::Float(3.14159));
+    assert_eq!(float_read.value, ValueVariant::Float(21.75));
     assert_eq!(string_read.value, ValueVariant::String("Hello World".to_string()));
 }
 
+#[test]
+fn test_non_finite_float_serialization() {
+    for (value, json) in [
+        (f64::NAN, r#"{"Float":"NaN"}"#),
+        (f64::INFINITY, r#"{"Float":"Infinity"}"#),
+        (f64::NEG_INFINITY, r#"{"Float":"-Infinity"}"#),
+        (1.5, r#"{"Float":1.5}"#),
+    ] {
+        let serialized = serde_json::to_string(&ValueVariant::Float(value)).unwrap();
+        assert_eq!(serialized, json);
+        let ValueVariant::Float(back) = serde_json::from_str(&serialized).unwrap() else {
+            panic!("{serialized} did not deserialize to a Float");
+        };
+        assert!(back == value || (back.is_nan() && value.is_nan()), "{serialized}");
+    }
+    assert!(serde_json::from_str::<ValueVariant>(r#"{"Float":"many"}"#).is_err());
+
+    // Coercion to and from strings uses the same names
+    let text = ValueVariant::String(String::new());
+    assert_eq!(
+        ValueVariant::Float(f64::NEG_INFINITY).coerce_to(&text),
+        Ok(ValueVariant::String("-Infinity".into()))
+    );
+    assert_eq!(
+        ValueVariant::String("Infinity".into()).coerce_to(&ValueVariant::Float(0.0)),
+        Ok(ValueVariant::Float(f64::INFINITY))
+    );
+    assert!(ValueVariant::Float(f64::NAN).coerce_to(&ValueVariant::Int(0)).is_err());
+}
+
+#[test]
+fn test_value_coercion() {
+    let float = ValueVariant::Float(0.0);
+    assert_eq!(ValueVariant::Int(5).coerce_to(&float), Ok(ValueVariant::Float(5.0)));
+    assert_eq!(
+        ValueVariant::String(" 2.5 ".into()).coerce_to(&float),
+        Ok(ValueVariant::Float(2.5))
+    );
+    let int = ValueVariant::Int(0);
+    assert_eq!(ValueVariant::Float(3.0).coerce_to(&int), Ok(ValueVariant::Int(3)));
+    assert!(ValueVariant::Float(3.5).coerce_to(&int).is_err());
+    assert!(ValueVariant::Int(-1).coerce_to(&ValueVariant::UInt(0)).is_err());
+    assert_eq!(
+        ValueVariant::Bool(true).coerce_to(&ValueVariant::String(String::new())),
+        Ok(ValueVariant::String("true".into()))
+    );
+    // A tag that was never read takes any type
+    assert_eq!(
+        ValueVariant::Bool(true).coerce_to(&ValueVariant::Null),
+        Ok(ValueVariant::Bool(true))
+    );
+
+    let err = ValueVariant::String("yes".into())
+        .coerce_to(&ValueVariant::Bool(false))
+        .unwrap_err();
+    assert_eq!(err.target, "Bool");
+    assert_eq!(err.to_string(), "Cannot convert String(\"yes\") to Bool");
+    assert!(ValueVariant::String("abc".into()).coerce_to(&float).is_err());
+}
+
 #[test]
 fn test_quality_levels() {
     let engine = TagEngine::new();
     
-    let qualities = vec![
+    let qualities = [
         Quality::Good,
         Quality::Uncertain,
         Quality::Bad,
@@ -92,11 +157,12 @@ fn test_quality_levels() {
     
     for (i, quality) in qualities.iter().enumerate() {
         let tag = Tag {
-            path: format!("Test/Quality{}", i),
+            path: format!("Test/Quality{}", i).parse().unwrap(),
             value: TagValue::new(ValueVariant::Int(i as i64), quality.clone()),
             driver_id: "test".to_string(),
             driver_address: format!("addr{}", i),
             poll_rate_ms: 1000,
+            enabled: true,
             metadata: TagMetadata::default(),
         };
         
@@ -129,7 +195,7 @@ fn test_concurrent_access() {
                 engine_clone.update_tag_value(&tag_path, new_value);
                 
                 // Try to read the tag
-                if let Some(_) = engine_clone.read_tag(&tag_path) {
+                if engine_clone.read_tag(&tag_path).is_some() {
                     // Successfully read
                 }
                 
@@ -154,14 +220,14 @@ fn test_concurrent_access() {
 
 #[test]
 fn test_large_number_of_tags() {
-    let engine = TagEngine::new();
-    let tag_count = 10000000;
+    let tag_count = 100_000;
+    let engine = TagEngine::with_capacity(tag_count);
     
     // Register a large number of tags
-    for i in 0..tag_count {
-        let tag = sample_tag(&format!("Load/Tag{:05}", i), "load_test", &format!("addr{}", i));
-        engine.register_tag(tag);
-    }
+    let tags = (0..tag_count)
+        .map(|i| sample_tag(&format!("Load/Tag{:05}", i), "load_test", &format!("addr{}", i)))
+        .collect();
+    engine.register_tags(tags);
     
     // Verify we can read all tags
     let all_paths = engine.get_all_tag_paths();
@@ -201,15 +267,22 @@ fn test_tag_metadata() {
         eng_unit: Some("°C".to_string()),
         eng_low: Some(-40.0),
         eng_high: Some(120.0),
+        raw_low: None,
+        raw_high: None,
+        deadband: None,
         writable: false,
+        hold_last_value: false,
+        alarms: Default::default(),
+        alias_of: None,
     };
     
     let tag = Tag {
-        path: "Plant/Temperature".to_string(),
+        path: "Plant/Temperature".parse().unwrap(),
         value: TagValue::new(ValueVariant::Float(25.5), Quality::Good),
         driver_id: "modbus1".to_string(),
         driver_address: "40001".to_string(),
         poll_rate_ms: 2000,
+        enabled: true,
         metadata,
     };
     
@@ -220,7 +293,7 @@ fn test_tag_metadata() {
     assert_eq!(details.metadata.eng_unit, Some("°C".to_string()));
     assert_eq!(details.metadata.eng_low, Some(-40.0));
     assert_eq!(details.metadata.eng_high, Some(120.0));
-    assert_eq!(details.metadata.writable, false);
+    assert!(!details.metadata.writable);
 }
 
 #[test]
@@ -262,3 +335,46 @@ fn test_timestamp_functionality() {
     assert!(updated_timestamp > initial_timestamp);
     assert_eq!(updated_read.value, ValueVariant::Int(100));
 }
+
+#[test]
+fn test_count_by_quality() {
+    let engine = TagEngine::new();
+    for i in 0..5 {
+        engine.register_tag(sample_tag(&format!("Device/Good{i}"), "drv1", &format!("good{i}")));
+    }
+    for i in 0..2 {
+        let mut tag = sample_tag(&format!("Device/Bad{i}"), "drv1", &format!("bad{i}"));
+        tag.value.quality = Quality::Bad;
+        engine.register_tag(tag);
+    }
+
+    let counts = engine.count_by_quality();
+    assert_eq!(counts.get(&Quality::Good), Some(&5));
+    assert_eq!(counts.get(&Quality::Bad), Some(&2));
+    assert_eq!(counts.get(&Quality::Uncertain), None);
+
+    let mut visited = 0;
+    engine.for_each(|tag| {
+        assert!(tag.path.starts_with("Device/"));
+        visited += 1;
+    });
+    assert_eq!(visited, 7);
+}
+
+#[test]
+fn test_memory_grows_with_string_values() {
+    let engine = TagEngine::new();
+    let empty = engine.approx_memory_bytes();
+    engine.register_tag(sample_tag("Device/Small", "drv1", "small"));
+    let one_tag = engine.approx_memory_bytes();
+    assert!(one_tag > empty);
+
+    for i in 0..10 {
+        let mut tag = sample_tag(&format!("Device/Text{i}"), "drv1", &format!("text{i}"));
+        tag.value = TagValue::new(ValueVariant::String("x".repeat(10_000)), Quality::Good);
+        engine.register_tag(tag);
+    }
+    let with_strings = engine.approx_memory_bytes();
+    assert!(with_strings >= one_tag + 10 * 10_000);
+    assert!(with_strings < one_tag + 20 * 10_000);
+}
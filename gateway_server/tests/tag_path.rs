@@ -0,0 +1,81 @@
+use gateway_server::tags::path::TagPath;
+use std::collections::HashMap;
+
+#[test]
+fn valid_paths_parse() {
+    for path in ["Temperature", "Plant1/Temperature", "Plant 1/Line-2/Motor.Speed"] {
+        let parsed: TagPath = path.parse().unwrap();
+        assert_eq!(parsed.as_str(), path);
+        assert_eq!(parsed.to_string(), path);
+    }
+}
+
+#[test]
+fn malformed_paths_are_rejected() {
+    for (path, reason) in [
+        ("", "must not be empty"),
+        ("/Plant1/Temperature", "must not have empty segments or a leading or trailing '/'"),
+        ("Plant1/Temperature/", "must not have empty segments or a leading or trailing '/'"),
+        ("Plant1//Temperature", "must not have empty segments or a leading or trailing '/'"),
+        ("Plant1\\Temperature", "segments must be separated by '/'"),
+    ] {
+        let err = TagPath::new(path).unwrap_err();
+        assert_eq!(err.path, path);
+        assert_eq!(err.reason, reason, "{path}");
+    }
+    assert_eq!(
+        TagPath::new("").unwrap_err().to_string(),
+        "Invalid tag path '': must not be empty"
+    );
+}
+
+#[test]
+fn segments_name_and_parent() {
+    let path: TagPath = "Plant1/Line2/Temperature".parse().unwrap();
+    assert_eq!(
+        path.segments().collect::<Vec<_>>(),
+        vec!["Plant1", "Line2", "Temperature"]
+    );
+    assert_eq!(path.name(), "Temperature");
+
+    let parent = path.parent().unwrap();
+    assert_eq!(parent, "Plant1/Line2");
+    assert_eq!(parent.parent().unwrap(), "Plant1");
+    assert_eq!(parent.parent().unwrap().parent(), None);
+
+    let root: TagPath = "Temperature".parse().unwrap();
+    assert_eq!(root.name(), "Temperature");
+    assert_eq!(root.segments().count(), 1);
+    assert_eq!(root.parent(), None);
+}
+
+#[test]
+fn strip_folder_yields_the_segments_below_it() {
+    let path: TagPath = "Plant1/Line2/Temperature".parse().unwrap();
+    let below = |folder: &[&str]| path.strip_folder(folder).map(|rest| rest.collect::<Vec<_>>());
+    assert_eq!(below(&[]), Some(vec!["Plant1", "Line2", "Temperature"]));
+    assert_eq!(below(&["Plant1"]), Some(vec!["Line2", "Temperature"]));
+    assert_eq!(below(&["Plant1", "Line2", "Temperature"]), Some(vec![]));
+    // Whole segments only
+    assert_eq!(below(&["Plant"]), None);
+    assert_eq!(below(&["Line2"]), None);
+}
+
+#[test]
+fn serializes_as_a_plain_string() {
+    let path: TagPath = "Plant1/Temperature".parse().unwrap();
+    assert_eq!(serde_json::to_string(&path).unwrap(), r#""Plant1/Temperature""#);
+    let back: TagPath = serde_json::from_str(r#""Plant1/Temperature""#).unwrap();
+    assert_eq!(back, path);
+
+    let err = serde_json::from_str::<TagPath>(r#""Plant1/""#).unwrap_err();
+    assert!(err.to_string().contains("Invalid tag path 'Plant1/'"), "{err}");
+}
+
+#[test]
+fn maps_keyed_by_path_are_looked_up_by_str() {
+    let mut values = HashMap::new();
+    values.insert("Plant1/Temperature".parse::<TagPath>().unwrap(), 21.5);
+    assert_eq!(values.get("Plant1/Temperature"), Some(&21.5));
+    assert_eq!(values.get("Plant1"), None);
+}
@@ -1,8 +1,10 @@
+use gateway_server::alarms::Severity;
 use gateway_server::drivers::opcua::OpcUaDriver;
-use gateway_server::drivers::traits::{DeviceDriver, DriverConfig, TagRequest};
+use gateway_server::drivers::traits::{DriverConfig, TagRequest};
 use gateway_server::tags::engine::TagEngine;
 use gateway_server::tags::structures::{Tag, TagValue, ValueVariant, TagMetadata, Quality};
-use gateway_server::config::settings::{Settings, TagConfig};
+use gateway_server::logging::LoggingConfig;
+use gateway_server::config::settings::{ServerConfig, Settings, TagConfig};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::time::{sleep, Duration};
@@ -32,7 +34,7 @@ impl TagEngineFixture {
         let value_type = match index % 4 {
             0 => ValueVariant::Int(index as i64),
             1 => ValueVariant::Float(index as f64 * 1.5),
-            2 => ValueVariant::Bool(index % 2 == 0),
+            2 => ValueVariant::Bool(index.is_multiple_of(2)),
             3 => ValueVariant::String(format!("Value_{}", index)),
             _ => ValueVariant::Int(0),
         };
@@ -48,11 +50,12 @@ impl TagEngineFixture {
         };
         
         Tag {
-            path: format!("TestDevice{}/Tag{:04}", index / 100, index % 100),
+            path: format!("TestDevice{}/Tag{:04}", index / 100, index % 100).parse().unwrap(),
             value: TagValue::new(value_type, quality),
             driver_id: format!("driver_{}", index % 5),
             driver_address: format!("addr_{}", index),
             poll_rate_ms: 1000 + (index as u64 % 5) * 500, // Vary poll rates
+            enabled: true,
             metadata: TagMetadata {
                 description: Some(format!("Test tag number {}", index)),
                 eng_unit: match index % 3 {
@@ -63,7 +66,13 @@ impl TagEngineFixture {
                 },
                 eng_low: Some((index as f64) * -10.0),
                 eng_high: Some((index as f64) * 10.0),
-                writable: index % 3 == 0,
+                raw_low: None,
+                raw_high: None,
+                deadband: None,
+                writable: index.is_multiple_of(3),
+                hold_last_value: false,
+                alarms: Default::default(),
+                alias_of: None,
             },
         }
     }
@@ -99,15 +108,29 @@ impl OpcUaDriverFixture {
             name: format!("Test Driver {}", id),
             address: address.to_string(),
             scan_rate_ms: 1000,
+            driver_type: "opcua".to_string(),
             application_name: Some(format!("TestApp_{}", id)),
             application_uri: Some(format!("urn:test:app:{}", id)),
             session_name: Some(format!("TestSession_{}", id)),
             max_message_size: Some(16777216),
             max_chunk_count: Some(1024),
+            read_batch_size: None,
             connect_retry_attempts: Some(3),
             connect_retry_delay_ms: Some(500),
             connect_retry_backoff: Some(2.0),
+            connect_retry_jitter_ms: None,
             connect_timeout_ms: Some(3000),
+            security_policy: None,
+            security_mode: None,
+            username: None,
+            password: None,
+            pki_dir: None,
+            trust_server_certs: None,
+            create_sample_keypair: None,
+            insecure: false,
+            max_concurrent_reads: None,
+            read_timeout_ms: None,
+            critical: false,
         }
     }
     
@@ -117,15 +140,29 @@ impl OpcUaDriverFixture {
             name: format!("Fast Fail Driver {}", id),
             address: address.to_string(),
             scan_rate_ms: 1000,
+            driver_type: "opcua".to_string(),
             application_name: Some(format!("TestApp_{}", id)),
             application_uri: None,
             session_name: Some(format!("TestSession_{}", id)),
             max_message_size: None,
             max_chunk_count: None,
+            read_batch_size: None,
             connect_retry_attempts: Some(1), // Only one attempt
             connect_retry_delay_ms: Some(100), // Short delay
             connect_retry_backoff: Some(1.0), // No backoff
+            connect_retry_jitter_ms: None,
             connect_timeout_ms: Some(500), // Short timeout
+            security_policy: None,
+            security_mode: None,
+            username: None,
+            password: None,
+            pki_dir: None,
+            trust_server_certs: None,
+            create_sample_keypair: None,
+            insecure: false,
+            max_concurrent_reads: None,
+            read_timeout_ms: None,
+            critical: false,
         }
     }
     
@@ -153,61 +190,151 @@ impl SystemConfigFixture {
                 name: "Primary OPC UA Server".to_string(),
                 address: "opc.tcp://127.0.0.1:4840/".to_string(),
                 scan_rate_ms: 1000,
+                driver_type: "opcua".to_string(),
                 application_name: Some("ForgeIO Client 1".to_string()),
                 application_uri: Some("urn:forgeio:client1".to_string()),
                 session_name: Some("ForgeIOSession1".to_string()),
                 max_message_size: Some(16777216),
                 max_chunk_count: Some(1024),
+                read_batch_size: None,
                 connect_retry_attempts: Some(5),
                 connect_retry_delay_ms: Some(1000),
                 connect_retry_backoff: Some(2.0),
+                connect_retry_jitter_ms: None,
                 connect_timeout_ms: Some(5000),
+                security_policy: None,
+                security_mode: None,
+                username: None,
+                password: None,
+                pki_dir: None,
+                trust_server_certs: None,
+                create_sample_keypair: None,
+                insecure: false,
+                max_concurrent_reads: None,
+                read_timeout_ms: None,
+                critical: false,
             },
             DriverConfig {
                 id: "opcua2".to_string(),
                 name: "Secondary OPC UA Server".to_string(),
                 address: "opc.tcp://127.0.0.1:4841/".to_string(),
                 scan_rate_ms: 2000,
+                driver_type: "opcua".to_string(),
                 application_name: Some("ForgeIO Client 2".to_string()),
                 application_uri: Some("urn:forgeio:client2".to_string()),
                 session_name: Some("ForgeIOSession2".to_string()),
                 max_message_size: Some(8388608),
                 max_chunk_count: Some(512),
+                read_batch_size: None,
                 connect_retry_attempts: Some(3),
                 connect_retry_delay_ms: Some(2000),
                 connect_retry_backoff: Some(1.5),
+                connect_retry_jitter_ms: None,
                 connect_timeout_ms: Some(3000),
+                security_policy: None,
+                security_mode: None,
+                username: None,
+                password: None,
+                pki_dir: None,
+                trust_server_certs: None,
+                create_sample_keypair: None,
+                insecure: false,
+                max_concurrent_reads: None,
+                read_timeout_ms: None,
+                critical: false,
             },
         ];
         
         let tags = vec![
             TagConfig {
-                path: "Plant1/Temperature".to_string(),
+                path: "Plant1/Temperature".parse().unwrap(),
                 driver_id: "opcua1".to_string(),
                 address: "ns=2;s=Temperature".to_string(),
                 poll_rate_ms: 1000,
+                enabled: true,
+                raw_low: None,
+                raw_high: None,
+                eng_low: None,
+                eng_high: None,
+                deadband: None,
+                writable: false,
+                hold_last_value: false,
+                alarm_high: None,
+                alarm_low: None,
+                alarm_rate_of_change: None,
+                alarm_severity: Severity::default(),
+                alias_of: None,
             },
             TagConfig {
-                path: "Plant1/Pressure".to_string(),
+                path: "Plant1/Pressure".parse().unwrap(),
                 driver_id: "opcua1".to_string(),
                 address: "ns=2;s=Pressure".to_string(),
                 poll_rate_ms: 1000,
+                enabled: true,
+                raw_low: None,
+                raw_high: None,
+                eng_low: None,
+                eng_high: None,
+                deadband: None,
+                writable: false,
+                hold_last_value: false,
+                alarm_high: None,
+                alarm_low: None,
+                alarm_rate_of_change: None,
+                alarm_severity: Severity::default(),
+                alias_of: None,
             },
             TagConfig {
-                path: "Plant2/Flow".to_string(),
+                path: "Plant2/Flow".parse().unwrap(),
                 driver_id: "opcua2".to_string(),
                 address: "ns=2;s=Flow".to_string(),
                 poll_rate_ms: 2000,
+                enabled: true,
+                raw_low: None,
+                raw_high: None,
+                eng_low: None,
+                eng_high: None,
+                deadband: None,
+                writable: false,
+                hold_last_value: false,
+                alarm_high: None,
+                alarm_low: None,
+                alarm_rate_of_change: None,
+                alarm_severity: Severity::default(),
+                alias_of: None,
             },
             TagConfig {
-                path: "Plant2/Level".to_string(),
+                path: "Plant2/Level".parse().unwrap(),
                 driver_id: "opcua2".to_string(),
                 address: "ns=2;s=Level".to_string(),
                 poll_rate_ms: 2000,
+                enabled: true,
+                raw_low: None,
+                raw_high: None,
+                eng_low: None,
+                eng_high: None,
+                deadband: None,
+                writable: false,
+                hold_last_value: false,
+                alarm_high: None,
+                alarm_low: None,
+                alarm_rate_of_change: None,
+                alarm_severity: Severity::default(),
+                alias_of: None,
             },
         ];
         
-        Settings { devices, tags }
+        Settings {
+            server: ServerConfig::default(),
+            auth: None,
+            cors: None,
+            logging: LoggingConfig::default(),
+            devices,
+            tags,
+            influx: None,
+            mqtt: None,
+            poll_tick_ms: None,
+        }
     }
     
     pub fn create_stress_test_config(device_count: usize, tags_per_device: usize) -> Settings {
@@ -220,30 +347,67 @@ impl SystemConfigFixture {
                 name: format!("Stress Test Device {}", device_idx),
                 address: format!("opc.tcp://127.0.0.1:{}/", 4840 + device_idx),
                 scan_rate_ms: 1000,
+                driver_type: "opcua".to_string(),
                 application_name: Some(format!("StressApp_{}", device_idx)),
                 application_uri: Some(format!("urn:stress:app:{}", device_idx)),
                 session_name: Some(format!("StressSession_{}", device_idx)),
                 max_message_size: Some(16777216),
                 max_chunk_count: Some(1024),
+                read_batch_size: None,
                 connect_retry_attempts: Some(2),
                 connect_retry_delay_ms: Some(500),
                 connect_retry_backoff: Some(1.5),
+                connect_retry_jitter_ms: None,
                 connect_timeout_ms: Some(2000),
+                security_policy: None,
+                security_mode: None,
+                username: None,
+                password: None,
+                pki_dir: None,
+                trust_server_certs: None,
+                create_sample_keypair: None,
+                insecure: false,
+                max_concurrent_reads: None,
+                read_timeout_ms: None,
+                critical: false,
             };
             devices.push(device);
             
             for tag_idx in 0..tags_per_device {
                 let tag = TagConfig {
-                    path: format!("Device{}/Tag{:04}", device_idx, tag_idx),
+                    path: format!("Device{}/Tag{:04}", device_idx, tag_idx).parse().unwrap(),
                     driver_id: format!("device_{}", device_idx),
                     address: format!("ns=2;s=Tag{}", tag_idx),
                     poll_rate_ms: 1000 + (tag_idx as u64 % 3) * 500,
+                    enabled: true,
+                    raw_low: None,
+                    raw_high: None,
+                    eng_low: None,
+                    eng_high: None,
+                    deadband: None,
+                    writable: false,
+                    hold_last_value: false,
+                    alarm_high: None,
+                    alarm_low: None,
+                    alarm_rate_of_change: None,
+                    alarm_severity: Severity::default(),
+                    alias_of: None,
                 };
                 tags.push(tag);
             }
         }
         
-        Settings { devices, tags }
+        Settings {
+            server: ServerConfig::default(),
+            auth: None,
+            cors: None,
+            logging: LoggingConfig::default(),
+            devices,
+            tags,
+            influx: None,
+            mqtt: None,
+            poll_tick_ms: None,
+        }
     }
 }
 
@@ -302,7 +466,7 @@ pub mod test_ops {
     pub fn generate_load_tags(count: usize, base_path: &str) -> Vec<Tag> {
         (0..count)
             .map(|i| Tag {
-                path: format!("{}/LoadTag{:06}", base_path, i),
+                path: format!("{}/LoadTag{:06}", base_path, i).parse().unwrap(),
                 value: TagValue::new(
                     ValueVariant::Float(i as f64 * 0.1),
                     if i % 10 == 0 { Quality::Bad } else { Quality::Good }
@@ -310,12 +474,19 @@ pub mod test_ops {
                 driver_id: format!("load_driver_{}", i % 5),
                 driver_address: format!("load_addr_{}", i),
                 poll_rate_ms: 1000 + (i as u64 % 10) * 100,
+                enabled: true,
                 metadata: TagMetadata {
                     description: Some(format!("Load test tag {}", i)),
                     eng_unit: Some("test".to_string()),
                     eng_low: Some(0.0),
                     eng_high: Some(100.0),
+                    raw_low: None,
+                    raw_high: None,
+                    deadband: None,
                     writable: i % 4 == 0,
+                    hold_last_value: false,
+                    alarms: Default::default(),
+                    alias_of: None,
                 },
             })
             .collect()
@@ -4,6 +4,7 @@ interface Stats {
   uptime_seconds: number
   tag_count: number
   driver_count: number
+  connections: Record<string, string>
 }
 
 export default function Dashboard() {
@@ -14,12 +15,10 @@ export default function Dashboard() {
   const headers = { Authorization: 'Basic ' + btoa('admin:admin') }
 
   useEffect(() => {
+    // Answers 503 with the same body while a critical driver is down
     fetch('/api/health', { headers })
-      .then(res => {
-        if (!res.ok) throw new Error('Failed to fetch health status')
-        return res.text()
-      })
-      .then(setHealth)
+      .then(res => res.json())
+      .then(data => setHealth(`Gateway status: ${data.status}`))
       .catch(err => setError(err.message))
 
     fetch('/api/stats', { headers })
@@ -39,7 +38,16 @@ export default function Dashboard() {
       {stats && (
         <ul>
           <li>Uptime: {stats.uptime_seconds}s</li>
-          <li>Drivers: {stats.driver_count}</li>
+          <li>
+            Drivers: {stats.driver_count}
+            <ul>
+              {Object.entries(stats.connections).map(([id, state]) => (
+                <li key={id}>
+                  {id}: {state}
+                </li>
+              ))}
+            </ul>
+          </li>
           <li>Tags: {stats.tag_count}</li>
         </ul>
       )}
@@ -9,10 +9,21 @@ interface OpcUaDriver {
   driver_type: string
 }
 
+interface ApiError {
+  kind: string
+  message: string
+}
+
+interface BrowseChild {
+  browse_name: string
+  node_id: string
+  node_class: string
+}
+
 interface BrowseResult {
   node_id: string
-  children: string[]
-  error: string | null
+  children: BrowseChild[]
+  error: ApiError | null
 }
 
 export default function OpcUaBrowserPage() {
@@ -49,14 +60,14 @@ export default function OpcUaBrowserPage() {
     
     try {
       const response = await fetch(`/api/opcua/browse/${selectedDriver}?node_id=${encodeURIComponent(nodeId)}`, { headers })
-      if (!response.ok) throw new Error('Failed to browse node')
-      
       const result: BrowseResult = await response.json()
+      if (!response.ok) throw new Error(result.error?.message ?? 'Failed to browse node')
+
       setBrowseResult(result)
       setCurrentNode(nodeId)
       
       if (result.error) {
-        setError(result.error)
+        setError(result.error.message)
       }
     } catch (err: any) {
       setError(err.message)
@@ -65,12 +76,9 @@ export default function OpcUaBrowserPage() {
     }
   }
 
-  const navigateToChild = (childName: string) => {
-    // This is a simplified approach - in reality we'd need to construct proper node IDs
-    // For now, we'll just try to browse common node patterns
-    const childNodeId = `ns=2;s=${childName}`
-    setBrowseHistory([...browseHistory, childNodeId])
-    browseNode(childNodeId)
+  const navigateToChild = (child: BrowseChild) => {
+    setBrowseHistory([...browseHistory, child.node_id])
+    browseNode(child.node_id)
   }
 
   const navigateBack = () => {
@@ -139,13 +147,17 @@ export default function OpcUaBrowserPage() {
                   <thead>
                     <tr>
                       <th>Name</th>
+                      <th>Node ID</th>
+                      <th>Class</th>
                       <th>Actions</th>
                     </tr>
                   </thead>
                   <tbody>
-                    {browseResult.children.map((child, index) => (
-                      <tr key={index}>
-                        <td>{child}</td>
+                    {browseResult.children.map(child => (
+                      <tr key={child.node_id}>
+                        <td>{child.browse_name}</td>
+                        <td><code>{child.node_id}</code></td>
+                        <td>{child.node_class}</td>
                         <td>
                           <button onClick={() => navigateToChild(child)}>
                             Browse
@@ -1,12 +1,15 @@
 import { useEffect, useState } from 'react'
 import './App.css'
 
-function displayValue(val: unknown) {
+function displayValue(val: unknown): string {
+  if (Array.isArray(val)) {
+    return `[${val.map(displayValue).join(', ')}]`
+  }
   if (val && typeof val === 'object') {
     const [type, v] = Object.entries(val)[0]
-    return `${v}`
+    return displayValue(v)
   }
-  return val
+  return `${val}`
 }
 
 export default function TagsPage() {
@@ -21,7 +24,7 @@ export default function TagsPage() {
         if (!res.ok) throw new Error('Failed to fetch tags')
         return res.json()
       })
-      .then(setTags)
+      .then(page => setTags(page.items))
       .catch(err => setError(err.message))
   }, [])
 